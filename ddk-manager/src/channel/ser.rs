@@ -4,10 +4,12 @@ use super::offered_channel::OfferedChannel;
 use super::party_points::PartyBasePoints;
 use super::signed_channel::{SignedChannel, SignedChannelState};
 use super::{ClosedChannel, ClosedPunishedChannel, ClosingChannel, FailedAccept, FailedSign};
+use crate::manager::CET_NSEQUENCE;
 
 use dlc_messages::ser_impls::{
     read_ecdsa_adaptor_signature, read_string, write_ecdsa_adaptor_signature, write_string,
 };
+use lightning::io::Read;
 use lightning::ln::msgs::DecodeError;
 use lightning::util::ser::{Readable, Writeable, Writer};
 
@@ -27,26 +29,83 @@ impl_dlc_writeable!(AcceptedChannel, {
     (accept_buffer_adaptor_signature, {cb_writeable, write_ecdsa_adaptor_signature, read_ecdsa_adaptor_signature}),
     (counter_party, writeable)
 });
-impl_dlc_writeable!(SignedChannel, {
-    (channel_id, writeable),
-    (counter_party, writeable),
-    (temporary_channel_id, writeable),
-    (fund_output_index, usize),
-    (own_points, writeable),
-    (own_params, { cb_writeable, dlc_messages::ser_impls::party_params::write, dlc_messages::ser_impls::party_params::read }),
-    (own_per_update_point, writeable),
-    (counter_points, writeable),
-    (counter_per_update_point, writeable),
-    (counter_params, { cb_writeable, dlc_messages::ser_impls::party_params::write, dlc_messages::ser_impls::party_params::read }),
-    (state, writeable),
-    (update_idx, writeable),
-    (fund_tx, writeable),
-    (fund_script_pubkey, writeable),
-    (roll_back_state, option),
-    (own_per_update_seed, writeable),
-    (counter_party_commitment_secrets, writeable),
-    (fee_rate_per_vb, writeable)
-});
+/// Writes the fields of a [`SignedChannel`] that channels stored before the CET nsequence was
+/// kept with them also have.
+fn write_signed_channel_fields<W: Writer>(
+    channel: &SignedChannel,
+    w: &mut W,
+) -> Result<(), lightning::io::Error> {
+    field_write!(w, channel.channel_id, writeable);
+    field_write!(w, channel.counter_party, writeable);
+    field_write!(w, channel.temporary_channel_id, writeable);
+    field_write!(w, channel.fund_output_index, usize);
+    field_write!(w, channel.own_points, writeable);
+    field_write!(w, channel.own_params, { cb_writeable, dlc_messages::ser_impls::party_params::write, dlc_messages::ser_impls::party_params::read });
+    field_write!(w, channel.own_per_update_point, writeable);
+    field_write!(w, channel.counter_points, writeable);
+    field_write!(w, channel.counter_per_update_point, writeable);
+    field_write!(w, channel.counter_params, { cb_writeable, dlc_messages::ser_impls::party_params::write, dlc_messages::ser_impls::party_params::read });
+    field_write!(w, channel.state, writeable);
+    field_write!(w, channel.update_idx, writeable);
+    field_write!(w, channel.fund_tx, writeable);
+    field_write!(w, channel.fund_script_pubkey, writeable);
+    field_write!(w, channel.roll_back_state, option);
+    field_write!(w, channel.own_per_update_seed, writeable);
+    field_write!(w, channel.counter_party_commitment_secrets, writeable);
+    field_write!(w, channel.fee_rate_per_vb, writeable);
+    Ok(())
+}
+
+/// Reads the fields written by [`write_signed_channel_fields`], with the CET nsequence set to
+/// [`CET_NSEQUENCE`], the only value used before it was stored.
+fn read_signed_channel_fields<R: Read>(r: &mut R) -> Result<SignedChannel, DecodeError> {
+    Ok(SignedChannel {
+        channel_id: field_read!(r, writeable),
+        counter_party: field_read!(r, writeable),
+        temporary_channel_id: field_read!(r, writeable),
+        fund_output_index: field_read!(r, usize),
+        own_points: field_read!(r, writeable),
+        own_params: field_read!(r, { cb_writeable, dlc_messages::ser_impls::party_params::write, dlc_messages::ser_impls::party_params::read }),
+        own_per_update_point: field_read!(r, writeable),
+        counter_points: field_read!(r, writeable),
+        counter_per_update_point: field_read!(r, writeable),
+        counter_params: field_read!(r, { cb_writeable, dlc_messages::ser_impls::party_params::write, dlc_messages::ser_impls::party_params::read }),
+        state: field_read!(r, writeable),
+        update_idx: field_read!(r, writeable),
+        fund_tx: field_read!(r, writeable),
+        fund_script_pubkey: field_read!(r, writeable),
+        roll_back_state: field_read!(r, option),
+        own_per_update_seed: field_read!(r, writeable),
+        counter_party_commitment_secrets: field_read!(r, writeable),
+        fee_rate_per_vb: field_read!(r, writeable),
+        cet_nsequence: CET_NSEQUENCE,
+    })
+}
+
+/// Reads a CET nsequence written after a channel, falling back to [`CET_NSEQUENCE`] when the
+/// channel was stored without one.
+fn read_trailing_cet_nsequence<R: Read>(r: &mut R) -> Result<u32, DecodeError> {
+    match u32::read(r) {
+        Ok(cet_nsequence) => Ok(cet_nsequence),
+        Err(DecodeError::ShortRead) => Ok(CET_NSEQUENCE),
+        Err(e) => Err(e),
+    }
+}
+
+impl Writeable for SignedChannel {
+    fn write<W: Writer>(&self, w: &mut W) -> Result<(), lightning::io::Error> {
+        write_signed_channel_fields(self, w)?;
+        self.cet_nsequence.write(w)
+    }
+}
+
+impl Readable for SignedChannel {
+    fn read<R: Read>(r: &mut R) -> Result<Self, DecodeError> {
+        let mut channel = read_signed_channel_fields(r)?;
+        channel.cet_nsequence = read_trailing_cet_nsequence(r)?;
+        Ok(channel)
+    }
+}
 
 impl_dlc_writeable_enum!(
     SignedChannelState,;
@@ -68,15 +127,78 @@ impl_dlc_writeable_enum!(
 impl_dlc_writeable!(FailedAccept, {(temporary_channel_id, writeable), (error_message, {cb_writeable, write_string, read_string}), (accept_message, writeable), (counter_party, writeable)});
 impl_dlc_writeable!(FailedSign, {(channel_id, writeable), (error_message, {cb_writeable, write_string, read_string}), (sign_message, writeable), (counter_party, writeable)});
 
-impl_dlc_writeable!(ClosingChannel, {
-    (channel_id, writeable),
-    (counter_party, writeable),
-    (temporary_channel_id, writeable),
-    (rollback_state, option),
-    (buffer_transaction, writeable),
-    (contract_id, writeable),
-    (is_closer, writeable)
+// The rollback state keeps the layout it had before the CET nsequence was stored, which is
+// written after the other fields instead.
+impl Writeable for ClosingChannel {
+    fn write<W: Writer>(&self, w: &mut W) -> Result<(), lightning::io::Error> {
+        field_write!(w, self.channel_id, writeable);
+        field_write!(w, self.counter_party, writeable);
+        field_write!(w, self.temporary_channel_id, writeable);
+        field_write!(w, self.rollback_state, {option_cb, write_signed_channel_fields, read_signed_channel_fields});
+        field_write!(w, self.buffer_transaction, writeable);
+        field_write!(w, self.contract_id, writeable);
+        field_write!(w, self.is_closer, writeable);
+        if let Some(rollback_state) = &self.rollback_state {
+            rollback_state.cet_nsequence.write(w)?;
+        }
+        Ok(())
+    }
+}
 
-});
+impl Readable for ClosingChannel {
+    fn read<R: Read>(r: &mut R) -> Result<Self, DecodeError> {
+        let mut channel = ClosingChannel {
+            channel_id: field_read!(r, writeable),
+            counter_party: field_read!(r, writeable),
+            temporary_channel_id: field_read!(r, writeable),
+            rollback_state: field_read!(r, {option_cb, write_signed_channel_fields, read_signed_channel_fields}),
+            buffer_transaction: field_read!(r, writeable),
+            contract_id: field_read!(r, writeable),
+            is_closer: field_read!(r, writeable),
+        };
+        if let Some(rollback_state) = channel.rollback_state.as_mut() {
+            rollback_state.cet_nsequence = read_trailing_cet_nsequence(r)?;
+        }
+        Ok(channel)
+    }
+}
 impl_dlc_writeable!(ClosedChannel, {(channel_id, writeable), (counter_party, writeable), (temporary_channel_id, writeable)});
 impl_dlc_writeable!(ClosedPunishedChannel, {(channel_id, writeable), (counter_party, writeable), (temporary_channel_id, writeable), (punish_txid, writeable)});
+
+#[cfg(test)]
+mod tests {
+    use lightning::io::Cursor;
+
+    use super::*;
+
+    #[test]
+    fn signed_channel_stored_without_cet_nsequence_uses_the_default() {
+        let buf = include_bytes!("../../../ddk/tests/data/dlc_storage/SignedChannelEstablished");
+        let signed_channel: SignedChannel = Readable::read(&mut Cursor::new(&buf)).unwrap();
+        assert_eq!(signed_channel.cet_nsequence, CET_NSEQUENCE);
+    }
+
+    #[test]
+    fn signed_channel_keeps_its_cet_nsequence() {
+        let buf = include_bytes!("../../../ddk/tests/data/dlc_storage/SignedChannelEstablished");
+        let mut signed_channel: SignedChannel = Readable::read(&mut Cursor::new(&buf)).unwrap();
+        signed_channel.cet_nsequence = 144;
+
+        let serialized = signed_channel.encode();
+        let read: SignedChannel = Readable::read(&mut Cursor::new(&serialized)).unwrap();
+        assert_eq!(read.cet_nsequence, 144);
+
+        let closing = ClosingChannel {
+            counter_party: signed_channel.counter_party,
+            temporary_channel_id: signed_channel.temporary_channel_id,
+            channel_id: signed_channel.channel_id,
+            buffer_transaction: signed_channel.fund_tx.clone(),
+            contract_id: signed_channel.channel_id,
+            is_closer: true,
+            rollback_state: Some(signed_channel),
+        };
+        let serialized = closing.encode();
+        let read: ClosingChannel = Readable::read(&mut Cursor::new(&serialized)).unwrap();
+        assert_eq!(read.rollback_state.unwrap().cet_nsequence, 144);
+    }
+}
@@ -335,6 +335,7 @@ typed_enum!(
             keys_id: KeysId,
         },
     },
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
     /// Enum automatically generated associating a number to each signed channel
     /// state.
     SignedChannelStateType,
@@ -421,4 +422,6 @@ pub struct SignedChannel {
     pub counter_party_commitment_secrets: CounterpartyCommitmentSecrets,
     /// The current fee rate to be used to create transactions.
     pub fee_rate_per_vb: u64,
+    /// The relative locktime of the CETs of the channel, agreed on when it was offered.
+    pub cet_nsequence: u32,
 }
@@ -91,7 +91,7 @@ where
     T::Target: Time,
 {
     let id = get_new_temporary_id();
-    let keys_id = signer_provider.derive_signer_key_id(true, id);
+    let keys_id = signer_provider.derive_signer_key_id(true, id)?;
     let signer = signer_provider.derive_contract_signer(keys_id)?;
     let (offer_params, funding_inputs_info) = crate::utils::get_party_params(
         secp,
@@ -438,6 +438,7 @@ where
             .accepted_contract
             .offered_contract
             .fee_rate_per_vb,
+        cet_nsequence,
     };
 
     let sign_channel = SignChannel {
@@ -509,6 +510,15 @@ where
         },
     );
 
+    // The CETs spend the buffer transaction with the nsequence of the offer.
+    let cet_nsequence = accepted_contract
+        .dlc_transactions
+        .cets
+        .first()
+        .and_then(|cet| cet.input.first())
+        .map(|input| input.sequence.to_consensus_u32())
+        .ok_or_else(|| Error::InvalidState("Accepted contract has no CET.".to_string()))?;
+
     let signed_channel = SignedChannel {
         counter_party: signed_contract
             .accepted_contract
@@ -549,6 +559,7 @@ where
             .accepted_contract
             .offered_contract
             .fee_rate_per_vb,
+        cet_nsequence,
     };
 
     Ok((signed_channel, signed_contract, signed_fund_tx))
@@ -1787,6 +1798,7 @@ pub fn offer_collaborative_close<C: Signing, SP: Deref, T: Deref>(
     counter_payout: u64,
     signer_provider: &SP,
     time: &T,
+    peer_timeout: u64,
 ) -> Result<(CollaborativeCloseOffer, Transaction), Error>
 where
     SP::Target: ContractSignerProvider,
@@ -1835,7 +1847,7 @@ where
         counter_payout,
         offer_signature: close_signature,
         close_tx: close_tx.clone(),
-        timeout: time.unix_time_now() + super::manager::PEER_TIMEOUT,
+        timeout: time.unix_time_now() + peer_timeout,
         keys_id: signed_channel
             .keys_id()
             .ok_or(Error::InvalidState("No keys_id available".to_string()))?,
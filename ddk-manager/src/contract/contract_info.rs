@@ -9,9 +9,7 @@ use bitcoin::{Script, Transaction};
 use dlc::{OracleInfo, Payout};
 use dlc_messages::oracle_msgs::{EventDescriptor, OracleAnnouncement};
 use dlc_trie::{DlcTrie, RangeInfo};
-use secp256k1_zkp::{
-    All, EcdsaAdaptorSignature, Message, PublicKey, Secp256k1, SecretKey, Verification,
-};
+use secp256k1_zkp::{All, EcdsaAdaptorSignature, Message, PublicKey, Secp256k1, Verification};
 use std::ops::Deref;
 
 pub(super) type OracleIndexAndPrefixLength = Vec<(usize, usize)>;
@@ -61,7 +59,7 @@ impl ContractInfo {
         self.oracle_announcements.iter().map(|x| x.into()).collect()
     }
 
-    /// Uses the provided AdaptorInfo and SecretKey to generate the set of
+    /// Uses the provided AdaptorInfo and signer to generate the set of
     /// adaptor signatures for the contract.
     pub fn get_adaptor_signatures<S: Deref>(
         &self,
@@ -75,7 +73,6 @@ impl ContractInfo {
     where
         S::Target: ContractSigner,
     {
-        let fund_privkey = signer.get_secret_key()?;
         match adaptor_info {
             AdaptorInfo::Enum => match &self.contract_descriptor {
                 ContractDescriptor::Enum(e) => e.get_adaptor_signatures(
@@ -83,7 +80,7 @@ impl ContractInfo {
                     &self.get_oracle_infos(),
                     self.threshold,
                     cets,
-                    &fund_privkey,
+                    signer.deref(),
                     funding_script_pubkey,
                     fund_output_value,
                 ),
@@ -91,7 +88,7 @@ impl ContractInfo {
             },
             AdaptorInfo::Numerical(trie) => Ok(trie.sign(
                 secp,
-                &fund_privkey,
+                &signer.get_secret_key()?,
                 funding_script_pubkey,
                 fund_output_value,
                 cets,
@@ -99,7 +96,7 @@ impl ContractInfo {
             )?),
             AdaptorInfo::NumericalWithDifference(trie) => Ok(trie.sign(
                 secp,
-                &fund_privkey,
+                &signer.get_secret_key()?,
                 funding_script_pubkey,
                 fund_output_value,
                 cets,
@@ -238,11 +235,11 @@ impl ContractInfo {
 
     /// Generate the adaptor info and adaptor signatures for the contract.
     #[allow(clippy::too_many_arguments)]
-    pub fn get_adaptor_info(
+    pub fn get_adaptor_info<S: ContractSigner>(
         &self,
         secp: &Secp256k1<All>,
         total_collateral: u64,
-        fund_priv_key: &SecretKey,
+        signer: &S,
         funding_script_pubkey: &Script,
         fund_output_value: u64,
         cets: &[Transaction],
@@ -255,7 +252,7 @@ impl ContractInfo {
                     secp,
                     &oracle_infos,
                     self.threshold,
-                    fund_priv_key,
+                    signer,
                     funding_script_pubkey,
                     fund_output_value,
                     cets,
@@ -264,7 +261,7 @@ impl ContractInfo {
             ContractDescriptor::Numerical(n) => Ok(n.get_adaptor_info(
                 secp,
                 total_collateral,
-                fund_priv_key,
+                &signer.get_secret_key()?,
                 funding_script_pubkey,
                 fund_output_value,
                 self.threshold,
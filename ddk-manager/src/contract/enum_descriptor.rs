@@ -4,15 +4,14 @@ use super::contract_info::OracleIndexAndPrefixLength;
 use super::utils::{get_majority_combination, unordered_equal};
 use super::AdaptorInfo;
 use crate::error::Error;
+use crate::ContractSigner;
 use bitcoin::hashes::Hash;
 use bitcoin::{Script, Transaction};
 use dlc::OracleInfo;
 use dlc::{EnumerationPayout, Payout};
 use dlc_messages::oracle_msgs::EnumEventDescriptor;
 use dlc_trie::{combination_iterator::CombinationIterator, RangeInfo};
-use secp256k1_zkp::{
-    All, EcdsaAdaptorSignature, Message, PublicKey, Secp256k1, SecretKey, Verification,
-};
+use secp256k1_zkp::{All, EcdsaAdaptorSignature, Message, PublicKey, Secp256k1, Verification};
 #[cfg(feature = "use-serde")]
 use serde::{Deserialize, Serialize};
 
@@ -176,12 +175,12 @@ impl EnumDescriptor {
 
     /// Generate the set of adaptor signatures and return the adaptor info.
     #[allow(clippy::too_many_arguments)]
-    pub fn get_adaptor_info(
+    pub fn get_adaptor_info<S: ContractSigner>(
         &self,
         secp: &Secp256k1<All>,
         oracle_infos: &[OracleInfo],
         threshold: usize,
-        fund_privkey: &SecretKey,
+        signer: &S,
         funding_script_pubkey: &Script,
         fund_output_value: u64,
         cets: &[Transaction],
@@ -191,7 +190,7 @@ impl EnumDescriptor {
             oracle_infos,
             threshold,
             cets,
-            fund_privkey,
+            signer,
             funding_script_pubkey,
             fund_output_value,
         )?;
@@ -201,34 +200,35 @@ impl EnumDescriptor {
 
     /// Generate the set of adaptor signatures.
     #[allow(clippy::too_many_arguments)]
-    pub fn get_adaptor_signatures(
+    pub fn get_adaptor_signatures<S: ContractSigner>(
         &self,
         secp: &Secp256k1<All>,
         oracle_infos: &[OracleInfo],
         threshold: usize,
         cets: &[Transaction],
-        fund_privkey: &SecretKey,
+        signer: &S,
         funding_script_pubkey: &Script,
         fund_output_value: u64,
     ) -> Result<Vec<EcdsaAdaptorSignature>, Error> {
-        let mut adaptor_sigs = Vec::new();
+        let mut adaptor_points = Vec::new();
         let mut callback =
             |adaptor_point: &PublicKey, cet_index: usize| -> Result<(), dlc::Error> {
-                let sig = dlc::create_cet_adaptor_sig_from_point(
-                    secp,
-                    &cets[cet_index],
-                    adaptor_point,
-                    fund_privkey,
-                    funding_script_pubkey,
-                    fund_output_value,
-                )?;
-                adaptor_sigs.push(sig);
+                adaptor_points.push((cet_index, *adaptor_point));
                 Ok(())
             };
 
         self.iter_outcomes(secp, oracle_infos, threshold, &mut callback)?;
 
-        Ok(adaptor_sigs)
+        let inputs = adaptor_points
+            .iter()
+            .map(|(cet_index, adaptor_point)| (&cets[*cet_index], adaptor_point))
+            .collect::<Vec<_>>();
+        signer.create_cet_adaptor_signatures(
+            secp,
+            &inputs,
+            funding_script_pubkey,
+            fund_output_value,
+        )
     }
 
     fn iter_outcomes<C: Verification, F>(
@@ -114,6 +114,24 @@ impl Contract {
         }
     }
 
+    /// Returns the id of the local keys of a contract. Closed contracts no longer
+    /// carry it.
+    pub fn get_keys_id(&self) -> Option<crate::KeysId> {
+        match self {
+            Contract::Offered(o) | Contract::Rejected(o) => Some(o.keys_id),
+            Contract::Accepted(o) => Some(o.offered_contract.keys_id),
+            Contract::Signed(o) | Contract::Confirmed(o) | Contract::Refunded(o) => {
+                Some(o.accepted_contract.offered_contract.keys_id)
+            }
+            Contract::FailedAccept(c) => Some(c.offered_contract.keys_id),
+            Contract::FailedSign(c) => Some(c.accepted_contract.offered_contract.keys_id),
+            Contract::PreClosed(c) => {
+                Some(c.signed_contract.accepted_contract.offered_contract.keys_id)
+            }
+            Contract::Closed(_) => None,
+        }
+    }
+
     /// Returns the public key of the counter party's node.
     pub fn get_counter_party_id(&self) -> PublicKey {
         match self {
@@ -49,7 +49,18 @@ pub struct OfferedContract {
     pub(crate) keys_id: KeysId,
 }
 
+/// Keys id of a received offer that has not been accepted yet. The key of the accepting
+/// party is only derived when the offer is accepted, so that incoming offers do not use
+/// up key indexes.
+pub(crate) const UNASSIGNED_KEYS_ID: KeysId = [0u8; 32];
+
 impl OfferedContract {
+    /// The id of the keys of the local party for this contract. All zero for a received
+    /// offer until it is accepted.
+    pub fn keys_id(&self) -> KeysId {
+        self.keys_id
+    }
+
     /// Validate that the contract info covers all the possible outcomes that
     /// can be attested by the oracle(s).
     pub fn validate(&self) -> Result<(), crate::error::Error> {
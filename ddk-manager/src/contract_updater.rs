@@ -10,9 +10,7 @@ use dlc_messages::{
     oracle_msgs::{OracleAnnouncement, OracleAttestation},
     AcceptDlc, FundingSignature, FundingSignatures, OfferDlc, SignDlc, WitnessElement,
 };
-use secp256k1_zkp::{
-    ecdsa::Signature, All, EcdsaAdaptorSignature, PublicKey, Secp256k1, SecretKey, Signing,
-};
+use secp256k1_zkp::{ecdsa::Signature, All, EcdsaAdaptorSignature, PublicKey, Secp256k1, Signing};
 use std::time::Instant;
 
 use crate::{
@@ -56,7 +54,7 @@ where
     contract_input.validate()?;
 
     let id = crate::utils::get_new_temporary_id();
-    let keys_id = signer_provider.derive_signer_key_id(true, id);
+    let keys_id = signer_provider.derive_signer_key_id(true, id)?;
     let signer = signer_provider.derive_contract_signer(keys_id)?;
     let (party_params, funding_inputs_info) = crate::utils::get_party_params(
         secp,
@@ -136,7 +134,7 @@ where
         offered_contract,
         &accept_params,
         &funding_inputs,
-        &signer.get_secret_key()?,
+        &signer,
         fund_output_value.to_sat(),
         None,
         &dlc_transactions,
@@ -148,12 +146,12 @@ where
 }
 
 #[allow(clippy::too_many_arguments)]
-pub(crate) fn accept_contract_internal(
+pub(crate) fn accept_contract_internal<X: ContractSigner>(
     secp: &Secp256k1<All>,
     offered_contract: &OfferedContract,
     accept_params: &PartyParams,
     funding_inputs: &[FundingInput],
-    adaptor_secret_key: &SecretKey,
+    signer: &X,
     input_value: u64,
     input_script_pubkey: Option<&Script>,
     dlc_transactions: &DlcTransactions,
@@ -168,7 +166,7 @@ pub(crate) fn accept_contract_internal(
     let (adaptor_info, adaptor_sig) = offered_contract.contract_info[0].get_adaptor_info(
         secp,
         offered_contract.total_collateral,
-        adaptor_secret_key,
+        signer,
         input_script_pubkey,
         input_value,
         &dlc_transactions.cets,
@@ -202,7 +200,7 @@ pub(crate) fn accept_contract_internal(
         let (adaptor_info, adaptor_sig) = contract_info.get_adaptor_info(
             secp,
             offered_contract.total_collateral,
-            adaptor_secret_key,
+            signer,
             input_script_pubkey,
             input_value,
             &tmp_cets,
@@ -215,14 +213,8 @@ pub(crate) fn accept_contract_internal(
         adaptor_sigs.extend(adaptor_sig);
     }
 
-    let refund_signature = dlc::util::get_raw_sig_for_tx_input(
-        secp,
-        refund,
-        0,
-        input_script_pubkey,
-        input_value,
-        adaptor_secret_key,
-    )?;
+    let refund_signature =
+        signer.sign_funding_input(secp, refund, 0, input_script_pubkey, input_value)?;
 
     let dlc_transactions = DlcTransactions {
         fund: fund.clone(),
@@ -509,14 +501,8 @@ where
         })
         .collect::<Result<Vec<_>, Error>>()?;
 
-    let offer_refund_signature = dlc::util::get_raw_sig_for_tx_input(
-        secp,
-        refund,
-        0,
-        input_script_pubkey,
-        input_value,
-        &signer.get_secret_key()?,
-    )?;
+    let offer_refund_signature =
+        signer.sign_funding_input(secp, refund, 0, input_script_pubkey, input_value)?;
 
     let dlc_transactions = DlcTransactions {
         fund: fund.clone(),
@@ -732,31 +718,55 @@ where
         )
     };
 
-    let funding_sk = signer.get_secret_key()?;
+    let funding_script_pubkey = &contract
+        .accepted_contract
+        .dlc_transactions
+        .funding_script_pubkey;
+    let fund_output_value = contract
+        .accepted_contract
+        .dlc_transactions
+        .get_fund_output()
+        .value
+        .to_sat();
 
     tracing::info!(contract_id, "Getting signed CET.");
-    dlc::sign_cet(
-        secp,
+    let adaptor_secret = crate::utils::oracle_signatures_to_secret(&sigs)?;
+    let other_sig = adaptor_sigs[range_info.adaptor_index].decrypt(&adaptor_secret)?;
+    let own_sig =
+        signer.sign_funding_input(secp, &cet, 0, funding_script_pubkey, fund_output_value)?;
+    crate::utils::set_funding_input_witness(
         &mut cet,
-        &adaptor_sigs[range_info.adaptor_index],
-        &sigs,
-        &funding_sk,
-        other_pubkey,
-        &contract
-            .accepted_contract
-            .dlc_transactions
-            .funding_script_pubkey,
-        contract
-            .accepted_contract
-            .dlc_transactions
-            .get_fund_output()
-            .value
-            .to_sat(),
-    )?;
+        0,
+        (&own_sig, &signer.get_public_key(secp)?),
+        (&other_sig, other_pubkey),
+        funding_script_pubkey,
+    );
 
     Ok(cet)
 }
 
+/// Returns the refund transaction of the contract with the refund signatures of both
+/// parties stored in the contract. Unlike [`get_signed_refund`] it does not need the
+/// contract signer, the transaction can be kept and broadcast after the refund locktime.
+pub fn get_refund_with_stored_signatures(contract: &SignedContract) -> Transaction {
+    let accepted_contract = &contract.accepted_contract;
+    let mut refund = accepted_contract.dlc_transactions.refund.clone();
+    crate::utils::set_funding_input_witness(
+        &mut refund,
+        0,
+        (
+            &contract.offer_refund_signature,
+            &accepted_contract.offered_contract.offer_params.fund_pubkey,
+        ),
+        (
+            &accepted_contract.accept_refund_signature,
+            &accepted_contract.accept_params.fund_pubkey,
+        ),
+        &accepted_contract.dlc_transactions.funding_script_pubkey,
+    );
+    refund
+}
+
 /// Signs and return the refund transaction to refund the contract.
 pub fn get_signed_refund<C: Signing, S: Deref>(
     secp: &Secp256k1<C>,
@@ -783,17 +793,20 @@ where
         )
     };
 
-    let fund_priv_key = signer.get_secret_key()?;
     let mut refund = accepted_contract.dlc_transactions.refund.clone();
-    dlc::util::sign_multi_sig_input(
+    let own_sig = signer.sign_funding_input(
         secp,
-        &mut refund,
-        other_sig,
-        other_fund_pubkey,
-        &fund_priv_key,
+        &refund,
+        0,
         funding_script_pubkey,
         fund_output_value.to_sat(),
-        0,
     )?;
+    crate::utils::set_funding_input_witness(
+        &mut refund,
+        0,
+        (&own_sig, &signer.get_public_key(secp)?),
+        (other_sig, other_fund_pubkey),
+        funding_script_pubkey,
+    );
     Ok(refund)
 }
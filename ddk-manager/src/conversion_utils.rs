@@ -88,7 +88,7 @@ pub fn get_tx_input_infos(
                 txid: tx.compute_txid(),
                 vout,
             },
-            max_witness_len: 107,
+            max_witness_len: fund_input.max_witness_len as usize,
             redeem_script: fund_input.redeem_script.clone(),
             serial_id: fund_input.input_serial_id,
         });
@@ -23,6 +23,8 @@ pub enum Error {
     StorageError(String),
     /// The oracle component encountered an error.
     OracleError(String),
+    /// An external signer failed to sign.
+    SignerError(String),
     /// An error occurred in the DLC library.
     DlcError(dlc::Error),
     /// An error occurred in the Secp library.
@@ -42,6 +44,7 @@ impl fmt::Display for Error {
             Error::StorageError(ref s) => write!(f, "Storage error {}", s),
             Error::DlcError(ref e) => write!(f, "Dlc error {}", e),
             Error::OracleError(ref s) => write!(f, "Oracle error {}", s),
+            Error::SignerError(ref s) => write!(f, "Signer error {}", s),
             Error::SecpError(_) => write!(f, "Secp error"),
         }
     }
@@ -96,6 +99,7 @@ impl std::error::Error for Error {
             Error::BlockchainError(_) => None,
             Error::StorageError(_) => None,
             Error::OracleError(_) => None,
+            Error::SignerError(_) => None,
             Error::DlcError(e) => Some(e),
             Error::SecpError(e) => Some(e),
         }
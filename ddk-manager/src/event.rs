@@ -0,0 +1,158 @@
+//! # Events emitted by the [`crate::manager::Manager`] when contracts and channels
+//! change state.
+
+use crate::channel::signed_channel::SignedChannelStateType;
+use crate::channel::Channel;
+use crate::contract::Contract;
+use crate::{ChannelId, ContractId};
+use bitcoin::Txid;
+use secp256k1_zkp::PublicKey;
+
+/// A state change of a contract or channel handled by the manager.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Event {
+    /// A contract offer was received from a counter party.
+    OfferReceived {
+        /// Temporary id of the offered contract.
+        contract_id: ContractId,
+        /// The node that sent the offer.
+        counter_party: PublicKey,
+    },
+    /// The counter party accepted a contract we offered.
+    Accepted {
+        /// Id of the accepted contract.
+        contract_id: ContractId,
+        /// The node that accepted the offer.
+        counter_party: PublicKey,
+    },
+    /// The contract was signed and its funding transaction can be broadcast.
+    Signed {
+        /// Id of the signed contract.
+        contract_id: ContractId,
+    },
+    /// The funding transaction of the contract reached the required confirmations.
+    FundingConfirmed {
+        /// Id of the confirmed contract.
+        contract_id: ContractId,
+    },
+    /// Enough oracle attestations were found to close the contract.
+    AttestationSeen {
+        /// Id of the contract the attestations are for.
+        contract_id: ContractId,
+    },
+    /// A CET closing the contract was broadcast.
+    PreClosed {
+        /// Id of the contract.
+        contract_id: ContractId,
+        /// Transaction id of the broadcast CET.
+        cet_txid: Txid,
+    },
+    /// The transaction closing the contract reached the required confirmations.
+    Closed {
+        /// Id of the closed contract.
+        contract_id: ContractId,
+    },
+    /// The refund transaction of the contract was broadcast.
+    Refunded {
+        /// Id of the refunded contract.
+        contract_id: ContractId,
+    },
+    /// The counter party rejected a contract or channel offer.
+    RejectReceived {
+        /// Id of the rejected contract or channel.
+        id: [u8; 32],
+        /// The node that sent the reject.
+        counter_party: PublicKey,
+    },
+    /// A channel offer was received from a counter party.
+    ChannelOfferReceived {
+        /// Temporary id of the offered channel.
+        channel_id: ChannelId,
+        /// The node that sent the offer.
+        counter_party: PublicKey,
+    },
+    /// A channel offer was accepted, by us or by the counter party.
+    ChannelAccepted {
+        /// Id of the accepted channel.
+        channel_id: ChannelId,
+        /// The other party of the channel.
+        counter_party: PublicKey,
+    },
+    /// A signed channel moved to a new state, e.g. it was established, settled or renewed.
+    ChannelUpdated {
+        /// Id of the channel.
+        channel_id: ChannelId,
+        /// The new state of the channel.
+        state: SignedChannelStateType,
+    },
+    /// A buffer transaction was broadcast to force close the channel.
+    ChannelClosing {
+        /// Id of the channel.
+        channel_id: ChannelId,
+    },
+    /// The channel was closed collaboratively or by either party.
+    ChannelClosed {
+        /// Id of the channel.
+        channel_id: ChannelId,
+    },
+    /// The counter party broadcast a revoked state and the punishment transaction was
+    /// broadcast.
+    ChannelPunished {
+        /// Id of the channel.
+        channel_id: ChannelId,
+    },
+    /// The channel offer was rejected or failed validation before the channel was signed.
+    ChannelCancelled {
+        /// Id of the channel.
+        channel_id: ChannelId,
+    },
+}
+
+impl Event {
+    /// The event for a contract that was updated to the given state, if any.
+    pub(crate) fn from_contract(contract: &Contract) -> Option<Event> {
+        let contract_id = contract.get_id();
+        match contract {
+            Contract::Signed(_) => Some(Event::Signed { contract_id }),
+            Contract::Confirmed(_) => Some(Event::FundingConfirmed { contract_id }),
+            Contract::PreClosed(c) => Some(Event::PreClosed {
+                contract_id,
+                cet_txid: c.signed_cet.compute_txid(),
+            }),
+            Contract::Closed(_) => Some(Event::Closed { contract_id }),
+            Contract::Refunded(_) => Some(Event::Refunded { contract_id }),
+            _ => None,
+        }
+    }
+
+    /// The event for a channel that was stored in the given state, if any.
+    pub(crate) fn from_channel(channel: &Channel) -> Option<Event> {
+        let channel_id = channel.get_id();
+        match channel {
+            Channel::Offered(_) => None,
+            Channel::Accepted(c) => Some(Event::ChannelAccepted {
+                channel_id,
+                counter_party: c.counter_party,
+            }),
+            Channel::Signed(c) => Some(Event::ChannelUpdated {
+                channel_id,
+                state: c.state.get_type(),
+            }),
+            Channel::Closing(_) => Some(Event::ChannelClosing { channel_id }),
+            Channel::Closed(_) | Channel::CounterClosed(_) | Channel::CollaborativelyClosed(_) => {
+                Some(Event::ChannelClosed { channel_id })
+            }
+            Channel::ClosedPunished(_) => Some(Event::ChannelPunished { channel_id }),
+            Channel::Cancelled(_) | Channel::FailedAccept(_) | Channel::FailedSign(_) => {
+                Some(Event::ChannelCancelled { channel_id })
+            }
+        }
+    }
+}
+
+/// Receives the [`Event`]s emitted by the manager.
+pub trait EventHandler: Send + Sync {
+    /// Called for each event. Must not block.
+    fn handle_event(&self, event: Event);
+}
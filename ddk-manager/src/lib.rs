@@ -22,25 +22,29 @@ pub mod contract;
 pub mod contract_updater;
 mod conversion_utils;
 pub mod error;
+pub mod event;
 pub mod manager;
 pub mod payout_curve;
 mod utils;
 
 use bitcoin::psbt::Psbt;
-use bitcoin::{Address, Block, OutPoint, ScriptBuf, Transaction, TxOut, Txid};
+use bitcoin::{Address, Block, OutPoint, Script, ScriptBuf, Transaction, TxOut, Txid};
 use chain_monitor::ChainMonitor;
 use channel::offered_channel::OfferedChannel;
 use channel::signed_channel::{SignedChannel, SignedChannelStateType};
 use channel::Channel;
 use contract::PreClosedContract;
-use contract::{offered_contract::OfferedContract, signed_contract::SignedContract, Contract};
+use contract::{
+    accepted_contract::AcceptedContract, offered_contract::OfferedContract,
+    signed_contract::SignedContract, Contract,
+};
 use dlc_messages::impl_dlc_writeable;
 use dlc_messages::oracle_msgs::{OracleAnnouncement, OracleAttestation};
 use dlc_messages::ser_impls::{read_address, write_address};
 use error::Error;
 use lightning::ln::msgs::DecodeError;
 use lightning::util::ser::{Readable, Writeable, Writer};
-use secp256k1_zkp::{PublicKey, SecretKey, Signing};
+use secp256k1_zkp::{ecdsa::Signature, EcdsaAdaptorSignature, PublicKey, SecretKey, Signing};
 use secp256k1_zkp::{Secp256k1, XOnlyPublicKey};
 use std::collections::HashMap;
 use std::ops::Deref;
@@ -77,9 +81,47 @@ impl Time for SystemTimeProvider {
 pub trait ContractSigner: Clone {
     /// Get the public key associated with the [`ContractSigner`].
     fn get_public_key<C: Signing>(&self, secp: &Secp256k1<C>) -> Result<PublicKey, Error>;
-    /// Returns the secret key associated with the [`ContractSigner`].
-    // todo: remove this method and add create_adaptor_signature to the trait
+    /// Returns the secret key associated with the [`ContractSigner`]. Signers that keep
+    /// the key elsewhere return an error, numerical contracts and channels need it.
     fn get_secret_key(&self) -> Result<SecretKey, Error>;
+
+    /// Creates the adaptor signatures of CETs spending the funding output, each
+    /// encrypted with the adaptor point it is paired with.
+    fn create_cet_adaptor_signatures<C: Signing>(
+        &self,
+        secp: &Secp256k1<C>,
+        cets: &[(&Transaction, &PublicKey)],
+        funding_script_pubkey: &Script,
+        fund_output_value: u64,
+    ) -> Result<Vec<EcdsaAdaptorSignature>, Error> {
+        Ok(dlc::create_cet_adaptor_sigs_from_points(
+            secp,
+            cets,
+            &self.get_secret_key()?,
+            funding_script_pubkey,
+            fund_output_value,
+        )?)
+    }
+
+    /// Signs input `input_index` of a transaction spending the funding output, e.g.
+    /// the refund transaction or a CET.
+    fn sign_funding_input<C: Signing>(
+        &self,
+        secp: &Secp256k1<C>,
+        tx: &Transaction,
+        input_index: usize,
+        funding_script_pubkey: &Script,
+        fund_output_value: u64,
+    ) -> Result<Signature, Error> {
+        Ok(dlc::util::get_raw_sig_for_tx_input(
+            secp,
+            tx,
+            input_index,
+            funding_script_pubkey,
+            fund_output_value,
+            &self.get_secret_key()?,
+        )?)
+    }
 }
 
 /// Simple sample implementation of [`ContractSigner`].
@@ -121,7 +163,11 @@ pub trait ContractSignerProvider {
     type Signer: ContractSigner;
 
     /// Create a keys id for deriving a `Signer`.
-    fn derive_signer_key_id(&self, is_offer_party: bool, temp_id: [u8; 32]) -> [u8; 32];
+    fn derive_signer_key_id(
+        &self,
+        is_offer_party: bool,
+        temp_id: [u8; 32],
+    ) -> Result<[u8; 32], Error>;
 
     /// Derives the private key material backing a `Signer`.
     fn derive_contract_signer(&self, key_id: [u8; 32]) -> Result<Self::Signer, Error>;
@@ -156,6 +202,16 @@ pub trait Wallet {
     fn sign_psbt_input(&self, psbt: &mut Psbt, input_index: usize) -> Result<(), Error>;
     /// Unlock reserved utxo
     fn unreserve_utxos(&self, outpoints: &[OutPoint]) -> Result<(), Error>;
+    /// Record the contract that reserved utxos fund. Utxos returned by
+    /// [`Wallet::get_utxos_for_amount`] are reserved before the contract exists, and
+    /// the contract id changes once the contract is accepted.
+    fn reserve_utxos(
+        &self,
+        _outpoints: &[OutPoint],
+        _contract_id: &ContractId,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
 }
 
 #[async_trait::async_trait]
@@ -189,6 +245,18 @@ pub trait Storage {
     fn update_contract(&self, contract: &Contract) -> Result<(), Error>;
     /// Returns the set of contracts in offered state.
     fn get_contract_offers(&self) -> Result<Vec<OfferedContract>, Error>;
+    /// Returns the set of contracts in accepted state. The default implementation
+    /// filters the result of [`Storage::get_contracts`].
+    fn get_accepted_contracts(&self) -> Result<Vec<AcceptedContract>, Error> {
+        Ok(self
+            .get_contracts()?
+            .into_iter()
+            .filter_map(|contract| match contract {
+                Contract::Accepted(accepted) => Some(accepted),
+                _ => None,
+            })
+            .collect())
+    }
     /// Returns the set of contracts in signed state.
     fn get_signed_contracts(&self) -> Result<Vec<SignedContract>, Error>;
     /// Returns the set of confirmed contracts.
@@ -239,6 +307,9 @@ pub struct Utxo {
     pub address: Address,
     /// The redeem script for the referenced output.
     pub redeem_script: ScriptBuf,
+    /// The maximum size of the witness spending the referenced output, used to
+    /// compute the fee of the funding transaction.
+    pub max_witness_len: u16,
     /// Whether this Utxo has been reserved (and so should not be used to fund
     /// a DLC).
     pub reserved: bool,
@@ -249,6 +320,7 @@ impl_dlc_writeable!(Utxo, {
     (outpoint, writeable),
     (address, {cb_writeable, write_address, read_address}),
     (redeem_script, writeable),
+    (max_witness_len, writeable),
     (reserved, writeable)
 });
 
@@ -280,7 +352,11 @@ where
 {
     type Signer = X;
 
-    fn derive_signer_key_id(&self, is_offer_party: bool, temp_id: [u8; 32]) -> KeysId {
+    fn derive_signer_key_id(
+        &self,
+        is_offer_party: bool,
+        temp_id: [u8; 32],
+    ) -> Result<KeysId, Error> {
         self.signer_provider
             .derive_signer_key_id(is_offer_party, temp_id)
     }
@@ -10,13 +10,18 @@ use crate::channel::{Channel, ClosedChannel, ClosedPunishedChannel};
 use crate::channel_updater::get_signed_channel_state;
 use crate::channel_updater::verify_signed_channel;
 use crate::contract::{
-    accepted_contract::AcceptedContract, contract_info::ContractInfo,
-    contract_input::ContractInput, contract_input::OracleInput, offered_contract::OfferedContract,
-    signed_contract::SignedContract, AdaptorInfo, ClosedContract, Contract, FailedAcceptContract,
-    FailedSignContract, PreClosedContract,
+    accepted_contract::AcceptedContract,
+    contract_info::ContractInfo,
+    contract_input::ContractInput,
+    contract_input::OracleInput,
+    offered_contract::{OfferedContract, UNASSIGNED_KEYS_ID},
+    signed_contract::SignedContract,
+    AdaptorInfo, ClosedContract, Contract, FailedAcceptContract, FailedSignContract,
+    PreClosedContract,
 };
 use crate::contract_updater::{accept_contract, verify_accepted_and_sign_contract};
 use crate::error::Error;
+use crate::event::{Event, EventHandler};
 use crate::utils::get_object_in_state;
 use crate::{ChannelId, ContractId, ContractSignerProvider};
 use bitcoin::absolute::Height;
@@ -30,7 +35,7 @@ use dlc_messages::channel::{
     SettleOffer, SignChannel,
 };
 use dlc_messages::oracle_msgs::{OracleAnnouncement, OracleAttestation};
-use dlc_messages::{AcceptDlc, Message as DlcMessage, OfferDlc, SignDlc};
+use dlc_messages::{AcceptDlc, FundingInput, Message as DlcMessage, OfferDlc, SignDlc};
 use futures::stream;
 use futures::stream::FuturesUnordered;
 use futures::{StreamExt, TryStreamExt};
@@ -58,6 +63,33 @@ pub const CET_NSEQUENCE: u32 = 288;
 /// is forced closed.
 pub const PEER_TIMEOUT: u64 = 3600;
 
+/// Confirmation depth and timelocks used by a [`Manager`]. The defaults are the module
+/// constants.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ManagerConfig {
+    /// The number of confirmations required before moving the the confirmed state.
+    pub nb_confirmations: u32,
+    /// The delay to set the refund value to. Offers are accepted with a refund delay
+    /// between this value and twice this value.
+    pub refund_delay: u32,
+    /// The nSequence value used for CETs in DLC channels.
+    pub cet_nsequence: u32,
+    /// Timeout in seconds when waiting for a peer's reply, after which a DLC channel
+    /// is forced closed.
+    pub peer_timeout: u64,
+}
+
+impl Default for ManagerConfig {
+    fn default() -> Self {
+        Self {
+            nb_confirmations: NB_CONFIRMATIONS,
+            refund_delay: REFUND_DELAY,
+            cet_nsequence: CET_NSEQUENCE,
+            peer_timeout: PEER_TIMEOUT,
+        }
+    }
+}
+
 type ClosableContractInfo<'a> = Option<(
     &'a ContractInfo,
     &'a AdaptorInfo,
@@ -92,6 +124,8 @@ pub struct Manager<
     chain_monitor: Mutex<ChainMonitor>,
     time: T,
     fee_estimator: F,
+    event_handler: Option<Arc<dyn EventHandler>>,
+    config: ManagerConfig,
 }
 
 macro_rules! get_contract_in_state {
@@ -161,6 +195,7 @@ where
     F::Target: FeeEstimator,
 {
     /// Create a new Manager struct.
+    #[allow(clippy::too_many_arguments)]
     pub async fn new(
         wallet: W,
         signer_provider: SP,
@@ -169,6 +204,7 @@ where
         oracles: HashMap<XOnlyPublicKey, O>,
         time: T,
         fee_estimator: F,
+        config: ManagerConfig,
     ) -> Result<Self, Error> {
         let init_height = blockchain.get_blockchain_height().await?;
         let chain_monitor = Mutex::new(
@@ -189,6 +225,8 @@ where
             time,
             fee_estimator,
             chain_monitor,
+            event_handler: None,
+            config,
         })
     }
 
@@ -197,6 +235,49 @@ where
         &self.store
     }
 
+    /// Get the confirmation depth and timelocks the Manager uses.
+    pub fn config(&self) -> &ManagerConfig {
+        &self.config
+    }
+
+    /// Set the handler that receives an [`Event`] for every contract and channel
+    /// state change. Channel events are emitted when the stored state of a channel
+    /// changes, contract events for the contract lifecycle outside of channels.
+    pub fn set_event_handler(&mut self, event_handler: Arc<dyn EventHandler>) {
+        self.event_handler = Some(event_handler);
+    }
+
+    fn emit(&self, event: Event) {
+        if let Some(handler) = &self.event_handler {
+            handler.handle_event(event);
+        }
+    }
+
+    fn emit_contract_update(&self, contract: &Contract) {
+        if let Some(event) = Event::from_contract(contract) {
+            self.emit(event);
+        }
+    }
+
+    /// Store the channel (and contract) and emit an event if the channel changed state.
+    fn upsert_channel(&self, channel: Channel, contract: Option<Contract>) -> Result<(), Error> {
+        let event = Event::from_channel(&channel);
+        let changed = match (&event, &self.event_handler) {
+            (Some(_), Some(_)) => {
+                let previous = self.store.get_channel(&channel.get_id())?;
+                previous.as_ref().and_then(Event::from_channel) != event
+            }
+            _ => false,
+        };
+
+        self.store.upsert_channel(channel, contract)?;
+
+        if let (true, Some(event)) = (changed, event) {
+            self.emit(event);
+        }
+        Ok(())
+    }
+
     /// Function called to pass a DlcMessage to the Manager.
     pub async fn on_dlc_message(
         &self,
@@ -215,6 +296,10 @@ where
             }
             DlcMessage::OfferChannel(o) => {
                 self.on_offer_channel(o, counter_party)?;
+                self.emit(Event::ChannelOfferReceived {
+                    channel_id: o.temporary_channel_id,
+                    counter_party,
+                });
                 Ok(None)
             }
             DlcMessage::AcceptChannel(a) => Ok(Some(DlcMessage::SignChannel(
@@ -262,6 +347,10 @@ where
             }
             DlcMessage::Reject(r) => {
                 self.on_reject(r, &counter_party)?;
+                self.emit(Event::RejectReceived {
+                    id: r.channel_id,
+                    counter_party,
+                });
                 Ok(None)
             }
         }
@@ -298,7 +387,7 @@ where
             &self.secp,
             contract_input,
             oracle_announcements,
-            REFUND_DELAY,
+            self.config.refund_delay,
             &counter_party,
             &self.wallet,
             &self.blockchain,
@@ -307,9 +396,15 @@ where
         )
         .await?;
 
-        offered_contract.validate()?;
+        let funding_outpoints = get_funding_outpoints(&offered_contract.funding_inputs)?;
+        if let Err(e) = offered_contract.validate() {
+            self.wallet.unreserve_utxos(&funding_outpoints)?;
+            return Err(e);
+        }
 
         self.store.create_contract(&offered_contract)?;
+        self.wallet
+            .reserve_utxos(&funding_outpoints, &offered_contract.id)?;
 
         Ok(offer_msg)
     }
@@ -319,10 +414,19 @@ where
         &self,
         contract_id: &ContractId,
     ) -> Result<(ContractId, PublicKey, AcceptDlc), Error> {
-        let offered_contract =
+        let mut offered_contract =
             get_contract_in_state!(self, contract_id, Offered, None as Option<PublicKey>)?;
 
+        if offered_contract.is_offer_party {
+            return Err(Error::InvalidState(
+                "Cannot accept contract offered by us.".to_string(),
+            ));
+        }
+
         let counter_party = offered_contract.counter_party;
+        offered_contract.keys_id = self
+            .signer_provider
+            .derive_signer_key_id(false, offered_contract.id)?;
 
         let (accepted_contract, accept_msg) = accept_contract(
             &self.secp,
@@ -339,6 +443,10 @@ where
         ))?;
 
         let contract_id = accepted_contract.get_contract_id();
+        self.wallet.reserve_utxos(
+            &get_funding_outpoints(&accepted_contract.funding_inputs)?,
+            &contract_id,
+        )?;
 
         self.store
             .update_contract(&Contract::Accepted(accepted_contract))?;
@@ -346,6 +454,58 @@ where
         Ok((contract_id, counter_party, accept_msg))
     }
 
+    /// Function to call to reject a DLC for which an offer was received. Returns the
+    /// [`Reject`] message to be sent as well as the public key of the offering node.
+    pub fn reject_contract_offer(
+        &self,
+        contract_id: &ContractId,
+    ) -> Result<(Reject, PublicKey), Error> {
+        let offered_contract =
+            get_contract_in_state!(self, contract_id, Offered, None as Option<PublicKey>)?;
+
+        if offered_contract.is_offer_party {
+            return Err(Error::InvalidState(
+                "Cannot reject contract offered by us.".to_string(),
+            ));
+        }
+
+        let counter_party = offered_contract.counter_party;
+        self.store
+            .update_contract(&Contract::Rejected(offered_contract))?;
+
+        let msg = Reject {
+            channel_id: *contract_id,
+        };
+        Ok((msg, counter_party))
+    }
+
+    /// Function to call to cancel a DLC offer sent by the local party before it
+    /// was accepted. The UTXOs reserved to fund the offer are released and the
+    /// [`Reject`] message to be sent to the counter party is returned along with
+    /// its public key.
+    pub fn cancel_offer(&self, contract_id: &ContractId) -> Result<(Reject, PublicKey), Error> {
+        let offered_contract =
+            get_contract_in_state!(self, contract_id, Offered, None as Option<PublicKey>)?;
+
+        if !offered_contract.is_offer_party {
+            return Err(Error::InvalidState(
+                "Cannot cancel contract offered by counter party.".to_string(),
+            ));
+        }
+
+        self.wallet
+            .unreserve_utxos(&get_funding_outpoints(&offered_contract.funding_inputs)?)?;
+
+        let counter_party = offered_contract.counter_party;
+        self.store
+            .update_contract(&Contract::Rejected(offered_contract))?;
+
+        let msg = Reject {
+            channel_id: *contract_id,
+        };
+        Ok((msg, counter_party))
+    }
+
     /// Function to update the state of the [`ChainMonitor`] with new
     /// blocks.
     ///
@@ -395,12 +555,16 @@ where
         offered_message: &OfferDlc,
         counter_party: PublicKey,
     ) -> Result<(), Error> {
-        offered_message.validate(&self.secp, REFUND_DELAY, REFUND_DELAY * 2)?;
-        let keys_id = self
-            .signer_provider
-            .derive_signer_key_id(false, offered_message.temporary_contract_id);
-        let contract: OfferedContract =
-            OfferedContract::try_from_offer_dlc(offered_message, counter_party, keys_id)?;
+        offered_message.validate(
+            &self.secp,
+            self.config.refund_delay,
+            self.config.refund_delay.saturating_mul(2),
+        )?;
+        let contract: OfferedContract = OfferedContract::try_from_offer_dlc(
+            offered_message,
+            counter_party,
+            UNASSIGNED_KEYS_ID,
+        )?;
         contract.validate()?;
 
         if self.store.get_contract(&contract.id)?.is_some() {
@@ -410,6 +574,10 @@ where
         }
 
         self.store.create_contract(&contract)?;
+        self.emit(Event::OfferReceived {
+            contract_id: contract.id,
+            counter_party,
+        });
 
         Ok(())
     }
@@ -445,8 +613,17 @@ where
             self.blockchain.get_network()?,
         ))?;
 
-        self.store
-            .update_contract(&Contract::Signed(signed_contract))?;
+        let contract = Contract::Signed(signed_contract);
+        self.store.update_contract(&contract)?;
+        self.wallet.reserve_utxos(
+            &get_funding_outpoints(&offered_contract.funding_inputs)?,
+            &contract.get_id(),
+        )?;
+        self.emit(Event::Accepted {
+            contract_id: contract.get_id(),
+            counter_party: *counter_party,
+        });
+        self.emit_contract_update(&contract);
 
         Ok(DlcMessage::Sign(signed_msg))
     }
@@ -469,8 +646,9 @@ where
             Err(e) => return self.sign_fail_on_error(accepted_contract, sign_message.clone(), e),
         };
 
-        self.store
-            .update_contract(&Contract::Signed(signed_contract))?;
+        let contract = Contract::Signed(signed_contract);
+        self.store.update_contract(&contract)?;
+        self.emit_contract_update(&contract);
 
         self.blockchain.send_transaction(&fund_tx).await?;
 
@@ -501,6 +679,8 @@ where
         e: Error,
     ) -> Result<R, Error> {
         tracing::error!("Error in on_sign {}", e);
+        self.wallet
+            .unreserve_utxos(&get_funding_outpoints(&accepted_contract.funding_inputs)?)?;
         self.store
             .update_contract(&Contract::FailedSign(FailedSignContract {
                 accepted_contract,
@@ -517,6 +697,8 @@ where
         e: Error,
     ) -> Result<R, Error> {
         tracing::error!("Error in on_accept {}", e);
+        self.wallet
+            .unreserve_utxos(&get_funding_outpoints(&offered_contract.funding_inputs)?)?;
         self.store
             .update_contract(&Contract::FailedAccept(FailedAcceptContract {
                 offered_contract,
@@ -537,18 +719,19 @@ where
                     .compute_txid(),
             )
             .await?;
-        if confirmations >= NB_CONFIRMATIONS {
+        if confirmations >= self.config.nb_confirmations {
             tracing::info!(
                 confirmations,
                 contract_id = contract.accepted_contract.get_contract_id_string(),
                 "Marking contract as confirmed."
             );
-            self.store
-                .update_contract(&Contract::Confirmed(contract.clone()))?;
+            let contract = Contract::Confirmed(contract.clone());
+            self.store.update_contract(&contract)?;
+            self.emit_contract_update(&contract);
         } else {
             tracing::info!(
                 confirmations,
-                required = NB_CONFIRMATIONS,
+                required = self.config.nb_confirmations,
                 contract_id = contract.accepted_contract.get_contract_id_string(),
                 "Not enough confirmations to mark contract as confirmed."
             );
@@ -689,6 +872,10 @@ where
                 {
                     Ok(closed_contract) => {
                         self.store.update_contract(&closed_contract)?;
+                        self.emit(Event::AttestationSeen {
+                            contract_id: contract.accepted_contract.get_contract_id(),
+                        });
+                        self.emit_contract_update(&closed_contract);
                         return Ok(());
                     }
                     Err(e) => {
@@ -766,6 +953,7 @@ where
             {
                 Ok(closed_contract) => {
                     self.store.update_contract(&closed_contract)?;
+                    self.emit_contract_update(&closed_contract);
                     Ok(closed_contract)
                 }
                 Err(e) => {
@@ -803,7 +991,7 @@ where
             .blockchain
             .get_transaction_confirmations(&broadcasted_txid)
             .await?;
-        if confirmations >= NB_CONFIRMATIONS {
+        if confirmations >= self.config.nb_confirmations {
             let closed_contract = ClosedContract {
                 attestations: contract.attestations.clone(),
                 signed_cet: Some(contract.signed_cet.clone()),
@@ -823,8 +1011,9 @@ where
                     .accepted_contract
                     .compute_pnl(&contract.signed_cet),
             };
-            self.store
-                .update_contract(&Contract::Closed(closed_contract))?;
+            let contract = Contract::Closed(closed_contract);
+            self.store.update_contract(&contract)?;
+            self.emit_contract_update(&contract);
         }
 
         Ok(())
@@ -859,7 +1048,7 @@ where
             };
 
             return Ok(Contract::PreClosed(preclosed_contract));
-        } else if confirmations < NB_CONFIRMATIONS {
+        } else if confirmations < self.config.nb_confirmations {
             let preclosed_contract = PreClosedContract {
                 signed_contract: contract.clone(),
                 attestations: Some(attestations),
@@ -905,8 +1094,9 @@ where
                 self.blockchain.send_transaction(&refund).await?;
             }
 
-            self.store
-                .update_contract(&Contract::Refunded(contract.clone()))?;
+            let contract = Contract::Refunded(contract.clone());
+            self.store.update_contract(&contract)?;
+            self.emit_contract_update(&contract);
         }
 
         Ok(())
@@ -943,10 +1133,11 @@ where
         {
             let refunded = Contract::Refunded(contract.clone());
             self.store.update_contract(&refunded)?;
+            self.emit_contract_update(&refunded);
             return Ok(refunded);
         }
 
-        let contract = if confirmations < NB_CONFIRMATIONS {
+        let contract = if confirmations < self.config.nb_confirmations {
             Contract::PreClosed(PreClosedContract {
                 signed_contract: contract.clone(),
                 attestations: None, // todo in some cases we can get the attestations from the closing tx
@@ -964,6 +1155,7 @@ where
         };
 
         self.store.update_contract(&contract)?;
+        self.emit_contract_update(&contract);
 
         Ok(contract)
     }
@@ -994,8 +1186,8 @@ where
             contract_input,
             &counter_party,
             &oracle_announcements,
-            CET_NSEQUENCE,
-            REFUND_DELAY,
+            self.config.cet_nsequence,
+            self.config.refund_delay,
             &self.wallet,
             &self.signer_provider,
             &self.blockchain,
@@ -1004,9 +1196,14 @@ where
         )
         .await?;
 
-        let msg = offered_channel.get_offer_channel_msg(&offered_contract);
+        let msg =
+            offered_channel.get_offer_channel_msg(&offered_contract, self.config.cet_nsequence);
+        self.wallet.reserve_utxos(
+            &get_funding_outpoints(&offered_contract.funding_inputs)?,
+            &offered_contract.id,
+        )?;
 
-        self.store.upsert_channel(
+        self.upsert_channel(
             Channel::Offered(offered_channel),
             Some(Contract::Offered(offered_contract)),
         )?;
@@ -1034,7 +1231,7 @@ where
         )?;
 
         let counterparty = offered_channel.counter_party;
-        self.store.upsert_channel(
+        self.upsert_channel(
             Channel::Cancelled(offered_channel),
             Some(Contract::Rejected(offered_contract)),
         )?;
@@ -1061,12 +1258,15 @@ where
             ));
         }
 
-        let offered_contract = get_contract_in_state!(
+        let mut offered_contract = get_contract_in_state!(
             self,
             &offered_channel.offered_contract_id,
             Offered,
             None as Option<PublicKey>
         )?;
+        offered_contract.keys_id = self
+            .signer_provider
+            .derive_signer_key_id(false, offered_contract.id)?;
 
         let (accepted_channel, accepted_contract, accept_channel) =
             crate::channel_updater::accept_channel_offer(
@@ -1087,8 +1287,12 @@ where
         let channel_id = accepted_channel.channel_id;
         let contract_id = accepted_contract.get_contract_id();
         let counter_party = accepted_contract.offered_contract.counter_party;
+        self.wallet.reserve_utxos(
+            &get_funding_outpoints(&accepted_contract.funding_inputs)?,
+            &contract_id,
+        )?;
 
-        self.store.upsert_channel(
+        self.upsert_channel(
             Channel::Accepted(accepted_channel),
             Some(Contract::Accepted(accepted_contract)),
         )?;
@@ -1118,15 +1322,14 @@ where
             &self.secp,
             &mut signed_channel,
             counter_payout,
-            PEER_TIMEOUT,
+            self.config.peer_timeout,
             &self.signer_provider,
             &self.time,
         )?;
 
         let counter_party = signed_channel.counter_party;
 
-        self.store
-            .upsert_channel(Channel::Signed(signed_channel), None)?;
+        self.upsert_channel(Channel::Signed(signed_channel), None)?;
 
         Ok((msg, counter_party))
     }
@@ -1140,12 +1343,13 @@ where
         let mut signed_channel =
             get_channel_in_state!(self, channel_id, Signed, None as Option<PublicKey>)?;
 
+        let cet_nsequence = signed_channel.cet_nsequence;
         let msg = crate::channel_updater::settle_channel_accept(
             &self.secp,
             &mut signed_channel,
-            CET_NSEQUENCE,
+            cet_nsequence,
             0,
-            PEER_TIMEOUT,
+            self.config.peer_timeout,
             &self.signer_provider,
             &self.time,
             &self.chain_monitor,
@@ -1153,8 +1357,7 @@ where
 
         let counter_party = signed_channel.counter_party;
 
-        self.store
-            .upsert_channel(Channel::Signed(signed_channel), None)?;
+        self.upsert_channel(Channel::Signed(signed_channel), None)?;
 
         Ok((msg, counter_party))
     }
@@ -1173,22 +1376,23 @@ where
 
         let oracle_announcements = self.oracle_announcements(contract_input).await?;
 
+        let cet_nsequence = signed_channel.cet_nsequence;
         let (msg, offered_contract) = crate::channel_updater::renew_offer(
             &self.secp,
             &mut signed_channel,
             contract_input,
             oracle_announcements,
             counter_payout,
-            REFUND_DELAY,
-            PEER_TIMEOUT,
-            CET_NSEQUENCE,
+            self.config.refund_delay,
+            self.config.peer_timeout,
+            cet_nsequence,
             &self.signer_provider,
             &self.time,
         )?;
 
         let counter_party = offered_contract.counter_party;
 
-        self.store.upsert_channel(
+        self.upsert_channel(
             Channel::Signed(signed_channel),
             Some(Contract::Offered(offered_contract)),
         )?;
@@ -1216,19 +1420,20 @@ where
             None as Option<PublicKey>
         )?;
 
+        let cet_nsequence = signed_channel.cet_nsequence;
         let (accepted_contract, msg) = crate::channel_updater::accept_channel_renewal(
             &self.secp,
             &mut signed_channel,
             &offered_contract,
-            CET_NSEQUENCE,
-            PEER_TIMEOUT,
+            cet_nsequence,
+            self.config.peer_timeout,
             &self.signer_provider,
             &self.time,
         )?;
 
         let counter_party = signed_channel.counter_party;
 
-        self.store.upsert_channel(
+        self.upsert_channel(
             Channel::Signed(signed_channel),
             Some(Contract::Accepted(accepted_contract)),
         )?;
@@ -1259,7 +1464,7 @@ where
 
         let counter_party = signed_channel.counter_party;
 
-        self.store.upsert_channel(
+        self.upsert_channel(
             Channel::Signed(signed_channel),
             Some(Contract::Rejected(offered_contract)),
         )?;
@@ -1281,8 +1486,7 @@ where
 
         let counter_party = signed_channel.counter_party;
 
-        self.store
-            .upsert_channel(Channel::Signed(signed_channel), None)?;
+        self.upsert_channel(Channel::Signed(signed_channel), None)?;
 
         Ok((msg, counter_party))
     }
@@ -1305,6 +1509,7 @@ where
             counter_payout,
             &self.signer_provider,
             &self.time,
+            self.config.peer_timeout,
         )?;
 
         self.chain_monitor.lock().unwrap().add_tx(
@@ -1315,8 +1520,7 @@ where
             },
         );
 
-        self.store
-            .upsert_channel(Channel::Signed(signed_channel), None)?;
+        self.upsert_channel(Channel::Signed(signed_channel), None)?;
         self.store
             .persist_chain_monitor(&self.chain_monitor.lock().unwrap())?;
 
@@ -1356,11 +1560,12 @@ where
 
         self.blockchain.send_transaction(&close_tx).await?;
 
-        self.store.upsert_channel(closed_channel, None)?;
+        self.upsert_channel(closed_channel, None)?;
 
         if let Some(closed_contract) = closed_contract {
-            self.store
-                .update_contract(&Contract::Closed(closed_contract))?;
+            let contract = Contract::Closed(closed_contract);
+            self.store.update_contract(&contract)?;
+            self.emit_contract_update(&contract);
         }
 
         Ok(())
@@ -1382,7 +1587,7 @@ where
             .blockchain
             .get_transaction_confirmations(&buffer_tx.compute_txid())
             .await?
-            >= CET_NSEQUENCE
+            >= signed_channel.cet_nsequence
         {
             tracing::info!(
                 "Buffer transaction for contract {} has enough confirmations to spend from it",
@@ -1424,8 +1629,7 @@ where
                 .unwrap()
                 .cleanup_channel(signed_channel.channel_id);
 
-            self.store
-                .upsert_channel(closed_channel, Some(closed_contract))?;
+            self.upsert_channel(closed_channel, Some(closed_contract))?;
         }
 
         Ok(())
@@ -1438,17 +1642,14 @@ where
     ) -> Result<(), Error> {
         offer_channel.validate(
             &self.secp,
-            REFUND_DELAY,
-            REFUND_DELAY * 2,
-            CET_NSEQUENCE,
-            CET_NSEQUENCE * 2,
+            self.config.refund_delay,
+            self.config.refund_delay.saturating_mul(2),
+            self.config.cet_nsequence,
+            self.config.cet_nsequence.saturating_mul(2),
         )?;
 
-        let keys_id = self
-            .signer_provider
-            .derive_signer_key_id(false, offer_channel.temporary_contract_id);
         let (channel, contract) =
-            OfferedChannel::from_offer_channel(offer_channel, counter_party, keys_id)?;
+            OfferedChannel::from_offer_channel(offer_channel, counter_party, UNASSIGNED_KEYS_ID)?;
 
         contract.validate()?;
 
@@ -1462,8 +1663,7 @@ where
             ));
         }
 
-        self.store
-            .upsert_channel(Channel::Offered(channel), Some(Contract::Offered(contract)))?;
+        self.upsert_channel(Channel::Offered(channel), Some(Contract::Offered(contract)))?;
 
         Ok(())
     }
@@ -1492,8 +1692,7 @@ where
                 &offered_channel,
                 &offered_contract,
                 accept_channel,
-                //TODO(tibo): this should be parameterizable.
-                CET_NSEQUENCE,
+                offered_channel.cet_nsequence,
                 &self.wallet,
                 &self.signer_provider,
                 &self.chain_monitor,
@@ -1508,8 +1707,7 @@ where
                         accept_message: accept_channel.clone(),
                         counter_party: *peer_id,
                     };
-                    self.store
-                        .upsert_channel(Channel::FailedAccept(channel), None)?;
+                    self.upsert_channel(Channel::FailedAccept(channel), None)?;
                     return Err(e);
                 }
             }
@@ -1538,7 +1736,7 @@ where
             unreachable!();
         }
 
-        self.store.upsert_channel(
+        self.upsert_channel(
             Channel::Signed(signed_channel),
             Some(Contract::Signed(signed_contract)),
         )?;
@@ -1582,8 +1780,7 @@ where
                         sign_message: sign_channel.clone(),
                         counter_party: *peer_id,
                     };
-                    self.store
-                        .upsert_channel(Channel::FailedSign(channel), None)?;
+                    self.upsert_channel(Channel::FailedSign(channel), None)?;
                     return Err(e);
                 }
             }
@@ -1606,7 +1803,7 @@ where
 
         self.blockchain.send_transaction(&signed_fund_tx).await?;
 
-        self.store.upsert_channel(
+        self.upsert_channel(
             Channel::Signed(signed_channel),
             Some(Contract::Signed(signed_contract)),
         )?;
@@ -1632,8 +1829,7 @@ where
 
         crate::channel_updater::on_settle_offer(&mut signed_channel, settle_offer)?;
 
-        self.store
-            .upsert_channel(Channel::Signed(signed_channel), None)?;
+        self.upsert_channel(Channel::Signed(signed_channel), None)?;
 
         Ok(None)
     }
@@ -1646,20 +1842,20 @@ where
         let mut signed_channel =
             get_channel_in_state!(self, &settle_accept.channel_id, Signed, Some(*peer_id))?;
 
+        let cet_nsequence = signed_channel.cet_nsequence;
         let msg = crate::channel_updater::settle_channel_confirm(
             &self.secp,
             &mut signed_channel,
             settle_accept,
-            CET_NSEQUENCE,
+            cet_nsequence,
             0,
-            PEER_TIMEOUT,
+            self.config.peer_timeout,
             &self.signer_provider,
             &self.time,
             &self.chain_monitor,
         )?;
 
-        self.store
-            .upsert_channel(Channel::Signed(signed_channel), None)?;
+        self.upsert_channel(Channel::Signed(signed_channel), None)?;
 
         Ok(msg)
     }
@@ -1712,8 +1908,7 @@ where
             true,
         )?);
 
-        self.store
-            .upsert_channel(Channel::Signed(signed_channel), Some(closed_contract))?;
+        self.upsert_channel(Channel::Signed(signed_channel), Some(closed_contract))?;
         self.store
             .persist_chain_monitor(&self.chain_monitor.lock().unwrap())?;
 
@@ -1766,8 +1961,7 @@ where
             own_payout,
             true,
         )?);
-        self.store
-            .upsert_channel(Channel::Signed(signed_channel), Some(closed_contract))?;
+        self.upsert_channel(Channel::Signed(signed_channel), Some(closed_contract))?;
         self.store
             .persist_chain_monitor(&self.chain_monitor.lock().unwrap())?;
 
@@ -1794,13 +1988,12 @@ where
         let offered_contract = crate::channel_updater::on_renew_offer(
             &mut signed_channel,
             renew_offer,
-            PEER_TIMEOUT,
+            self.config.peer_timeout,
             &self.time,
         )?;
 
         self.store.create_contract(&offered_contract)?;
-        self.store
-            .upsert_channel(Channel::Signed(signed_channel), None)?;
+        self.upsert_channel(Channel::Signed(signed_channel), None)?;
 
         Ok(None)
     }
@@ -1821,20 +2014,21 @@ where
         let offered_contract =
             get_contract_in_state!(self, &offered_contract_id, Offered, Some(*peer_id))?;
 
+        let cet_nsequence = signed_channel.cet_nsequence;
         let (signed_contract, msg) = crate::channel_updater::verify_renew_accept_and_confirm(
             &self.secp,
             renew_accept,
             &mut signed_channel,
             &offered_contract,
-            CET_NSEQUENCE,
-            PEER_TIMEOUT,
+            cet_nsequence,
+            self.config.peer_timeout,
             &self.wallet,
             &self.signer_provider,
             &self.time,
         )?;
 
         // Directly confirmed as we're in a channel the fund tx is already confirmed.
-        self.store.upsert_channel(
+        self.upsert_channel(
             Channel::Signed(signed_channel),
             Some(Contract::Confirmed(signed_contract)),
         )?;
@@ -1911,7 +2105,7 @@ where
             &mut signed_channel,
             &accepted_contract,
             renew_confirm,
-            PEER_TIMEOUT,
+            self.config.peer_timeout,
             &self.time,
             &self.wallet,
             &self.signer_provider,
@@ -1927,7 +2121,7 @@ where
         );
 
         // Directly confirmed as we're in a channel the fund tx is already confirmed.
-        self.store.upsert_channel(
+        self.upsert_channel(
             Channel::Signed(signed_channel),
             Some(Contract::Confirmed(signed_contract)),
         )?;
@@ -2025,8 +2219,7 @@ where
             },
         );
 
-        self.store
-            .upsert_channel(Channel::Signed(signed_channel), None)?;
+        self.upsert_channel(Channel::Signed(signed_channel), None)?;
         self.store
             .persist_chain_monitor(&self.chain_monitor.lock().unwrap())?;
 
@@ -2051,8 +2244,7 @@ where
             renew_revoke,
         )?;
 
-        self.store
-            .upsert_channel(Channel::Signed(signed_channel), None)
+        self.upsert_channel(Channel::Signed(signed_channel), None)
     }
 
     fn on_collaborative_close_offer(
@@ -2066,12 +2258,11 @@ where
         crate::channel_updater::on_collaborative_close_offer(
             &mut signed_channel,
             close_offer,
-            PEER_TIMEOUT,
+            self.config.peer_timeout,
             &self.time,
         )?;
 
-        self.store
-            .upsert_channel(Channel::Signed(signed_channel), None)?;
+        self.upsert_channel(Channel::Signed(signed_channel), None)?;
 
         Ok(())
     }
@@ -2096,24 +2287,12 @@ where
                         Offered,
                         None as Option<PublicKey>
                     )?;
-                    let utxos = offered_contract
-                        .funding_inputs
-                        .iter()
-                        .map(|funding_input| {
-                            let txid = Transaction::consensus_decode(
-                                &mut funding_input.prev_tx.as_slice(),
-                            )
-                            .expect("Transaction Decode Error")
-                            .compute_txid();
-                            let vout = funding_input.prev_tx_vout;
-                            OutPoint { txid, vout }
-                        })
-                        .collect::<Vec<_>>();
-
-                    self.wallet.unreserve_utxos(&utxos)?;
+                    self.wallet.unreserve_utxos(&get_funding_outpoints(
+                        &offered_contract.funding_inputs,
+                    )?)?;
 
                     // remove rejected channel, since nothing has been confirmed on chain yet.
-                    self.store.upsert_channel(
+                    self.upsert_channel(
                         Channel::Cancelled(offered_channel),
                         Some(Contract::Rejected(offered_contract)),
                     )?;
@@ -2137,8 +2316,7 @@ where
 
                     crate::channel_updater::on_reject(&mut signed_channel)?;
 
-                    self.store
-                        .upsert_channel(Channel::Signed(signed_channel), contract)?;
+                    self.upsert_channel(Channel::Signed(signed_channel), contract)?;
                 }
                 channel => {
                     return Err(Error::InvalidState(format!(
@@ -2147,9 +2325,11 @@ where
                     )))
                 }
             }
+        } else if let Some(contract) = self.get_contract_by_temporary_id(&reject.channel_id)? {
+            self.on_contract_reject(contract, counter_party)?;
         } else {
             tracing::warn!(
-                "Couldn't find rejected dlc channel with id: {}",
+                "Couldn't find rejected dlc channel or contract with id: {}",
                 reject.channel_id.to_lower_hex_string()
             );
         }
@@ -2157,6 +2337,96 @@ where
         Ok(())
     }
 
+    /// Handles a [`Reject`] message referring to a contract offer. Sent by the
+    /// counter party either when it rejects our offer or when it cancels its own.
+    fn on_contract_reject(
+        &self,
+        contract: Contract,
+        counter_party: &PublicKey,
+    ) -> Result<(), Error> {
+        let offered_contract = match contract {
+            Contract::Offered(offered_contract) => offered_contract,
+            // The counter party cancelled its offer after we accepted it but before it
+            // received our accept message.
+            Contract::Accepted(accepted_contract)
+                if !accepted_contract.offered_contract.is_offer_party =>
+            {
+                return self.on_accepted_contract_reject(accepted_contract, counter_party);
+            }
+            contract => {
+                return Err(Error::InvalidState(format!(
+                    "Not in a state adequate to receive a reject message. {:?}",
+                    contract
+                )))
+            }
+        };
+
+        if offered_contract.counter_party != *counter_party {
+            return Err(Error::InvalidParameters(format!(
+                "Peer {:02x?} is not involved with {} {:02x?}.",
+                counter_party,
+                stringify!(Contract),
+                offered_contract.id
+            )));
+        }
+
+        if offered_contract.is_offer_party {
+            self.wallet
+                .unreserve_utxos(&get_funding_outpoints(&offered_contract.funding_inputs)?)?;
+        }
+
+        tracing::info!(
+            contract_id = offered_contract.id.to_lower_hex_string(),
+            "Contract offer was rejected."
+        );
+
+        self.store
+            .update_contract(&Contract::Rejected(offered_contract))
+    }
+
+    fn on_accepted_contract_reject(
+        &self,
+        accepted_contract: AcceptedContract,
+        counter_party: &PublicKey,
+    ) -> Result<(), Error> {
+        let offered_contract = &accepted_contract.offered_contract;
+        if offered_contract.counter_party != *counter_party {
+            return Err(Error::InvalidParameters(format!(
+                "Peer {:02x?} is not involved with {} {:02x?}.",
+                counter_party,
+                stringify!(Contract),
+                offered_contract.id
+            )));
+        }
+
+        self.wallet
+            .unreserve_utxos(&get_funding_outpoints(&accepted_contract.funding_inputs)?)?;
+
+        tracing::info!(
+            contract_id = offered_contract.id.to_lower_hex_string(),
+            "Accepted contract offer was cancelled by the counter party."
+        );
+
+        self.store
+            .delete_contract(&accepted_contract.get_contract_id())?;
+        self.store
+            .update_contract(&Contract::Rejected(accepted_contract.offered_contract))
+    }
+
+    /// Find the contract a [`Reject`] refers to. Offers are rejected by their temporary
+    /// id, which is no longer the storage key once we accepted the offer.
+    fn get_contract_by_temporary_id(&self, id: &ContractId) -> Result<Option<Contract>, Error> {
+        if let Some(contract) = self.store.get_contract(id)? {
+            return Ok(Some(contract));
+        }
+        Ok(self
+            .store
+            .get_accepted_contracts()?
+            .into_iter()
+            .find(|a| a.offered_contract.id == *id)
+            .map(Contract::Accepted))
+    }
+
     async fn channel_checks(&self) -> Result<(), Error> {
         let established_closing_channels = self
             .store
@@ -2230,8 +2500,7 @@ where
 
                     signed_channel.roll_back_state = Some(state);
 
-                    self.store
-                        .upsert_channel(Channel::Signed(signed_channel), None)?;
+                    self.upsert_channel(Channel::Signed(signed_channel), None)?;
 
                     false
                 }
@@ -2353,7 +2622,7 @@ where
                                 &counter_revocation_sk,
                                 &tx,
                                 &self.wallet.get_new_address()?,
-                                CET_NSEQUENCE,
+                                signed_channel.cet_nsequence,
                                 0,
                                 fee_rate_per_vb,
                                 is_offer,
@@ -2376,7 +2645,7 @@ where
                         .lock()
                         .unwrap()
                         .cleanup_channel(signed_channel.channel_id);
-                    self.store.upsert_channel(closed_channel, None)?;
+                    self.upsert_channel(closed_channel, None)?;
                     true
                 }
                 TxType::CollaborativeClose => {
@@ -2406,7 +2675,7 @@ where
                         .lock()
                         .unwrap()
                         .cleanup_channel(signed_channel.channel_id);
-                    self.store.upsert_channel(closed_channel, None)?;
+                    self.upsert_channel(closed_channel, None)?;
                     true
                 }
                 TxType::SettleTx => {
@@ -2419,7 +2688,7 @@ where
                         .lock()
                         .unwrap()
                         .cleanup_channel(signed_channel.channel_id);
-                    self.store.upsert_channel(closed_channel, None)?;
+                    self.upsert_channel(closed_channel, None)?;
                     true
                 }
                 TxType::Cet => {
@@ -2476,8 +2745,7 @@ where
                         .flatten()
                         .flatten();
 
-                    self.store
-                        .upsert_channel(closed_channel, pre_closed_contract)?;
+                    self.upsert_channel(closed_channel, pre_closed_contract)?;
 
                     true
                 }
@@ -2594,8 +2862,7 @@ where
             .unwrap()
             .remove_tx(&buffer_transaction.compute_txid());
 
-        self.store
-            .upsert_channel(Channel::Signed(signed_channel), None)?;
+        self.upsert_channel(Channel::Signed(signed_channel), None)?;
 
         self.store
             .persist_chain_monitor(&self.chain_monitor.lock().unwrap())?;
@@ -2631,7 +2898,7 @@ where
             .unwrap()
             .cleanup_channel(signed_channel.channel_id);
 
-        self.store.upsert_channel(closed_channel, None)?;
+        self.upsert_channel(closed_channel, None)?;
 
         Ok(())
     }
@@ -2692,3 +2959,23 @@ where
         Ok(announcements)
     }
 }
+
+/// The outpoints spent by the given funding inputs. Fails if the previous transaction of
+/// an input can not be decoded.
+pub fn get_funding_outpoints(funding_inputs: &[FundingInput]) -> Result<Vec<OutPoint>, Error> {
+    funding_inputs
+        .iter()
+        .map(|funding_input| {
+            let txid = Transaction::consensus_decode(&mut funding_input.prev_tx.as_slice())
+                .map_err(|e| {
+                    Error::InvalidParameters(format!(
+                        "Could not decode the previous transaction of a funding input: {}",
+                        e
+                    ))
+                })?
+                .compute_txid();
+            let vout = funding_input.prev_tx_vout;
+            Ok(OutPoint { txid, vout })
+        })
+        .collect()
+}
@@ -1,7 +1,7 @@
 //! #Utils
 use std::ops::Deref;
 
-use bitcoin::{consensus::Encodable, Amount, Txid};
+use bitcoin::{consensus::Encodable, Amount, EcdsaSighashType, Script, Transaction, Txid, Witness};
 use dlc::{PartyParams, TxInputInfo};
 use dlc_messages::{
     oracle_msgs::{OracleAnnouncement, OracleAttestation},
@@ -10,7 +10,7 @@ use dlc_messages::{
 use dlc_trie::RangeInfo;
 #[cfg(not(feature = "fuzztarget"))]
 use secp256k1_zkp::rand::{thread_rng, Rng, RngCore};
-use secp256k1_zkp::{PublicKey, Secp256k1, Signing};
+use secp256k1_zkp::{ecdsa::Signature, PublicKey, Scalar, Secp256k1, SecretKey, Signing};
 
 use crate::{
     channel::party_points::PartyBasePoints,
@@ -124,14 +124,12 @@ where
         prev_tx.consensus_encode(&mut writer)?;
         let prev_tx_vout = utxo.outpoint.vout;
         let sequence = 0xffffffff;
-        // TODO(tibo): this assumes P2WPKH with low R
-        let max_witness_len = 107;
         let funding_input = FundingInput {
             input_serial_id: get_new_serial_id(),
             prev_tx: writer,
             prev_tx_vout,
             sequence,
-            max_witness_len,
+            max_witness_len: utxo.max_witness_len,
             redeem_script: utxo.redeem_script,
         };
         total_input += prev_tx.output[prev_tx_vout as usize].value;
@@ -214,6 +212,48 @@ pub(crate) fn get_latest_maturity_date(
         })
 }
 
+/// Sum of the oracle signature scalars, the secret that decrypts the adaptor
+/// signatures of the attested CET.
+pub(crate) fn oracle_signatures_to_secret(
+    signatures: &[Vec<secp256k1_zkp::schnorr::Signature>],
+) -> Result<SecretKey, Error> {
+    let mut s_values = signatures
+        .iter()
+        .flatten()
+        .map(|sig| SecretKey::from_slice(&sig.as_ref()[32..]));
+    let first = s_values
+        .next()
+        .ok_or_else(|| Error::InvalidParameters("No oracle signatures.".to_string()))??;
+    s_values.try_fold(first, |secret, s| Ok(secret.add_tweak(&Scalar::from(s?))?))
+}
+
+/// Places both signatures of the 2-of-2 funding output on the witness of input
+/// `input_index`, ordered by public key like the funding script.
+pub(crate) fn set_funding_input_witness(
+    tx: &mut Transaction,
+    input_index: usize,
+    own: (&Signature, &PublicKey),
+    other: (&Signature, &PublicKey),
+    funding_script_pubkey: &Script,
+) {
+    let finalize = |sig: &Signature| {
+        let mut sig = sig.serialize_der().to_vec();
+        sig.push(EcdsaSighashType::All as u8);
+        sig
+    };
+    let (first, second) = if own.1 < other.1 {
+        (own.0, other.0)
+    } else {
+        (other.0, own.0)
+    };
+    tx.input[input_index].witness = Witness::from_slice(&[
+        Vec::new(),
+        finalize(first),
+        finalize(second),
+        funding_script_pubkey.to_bytes(),
+    ]);
+}
+
 #[cfg(test)]
 mod tests {
     use std::str::FromStr;
@@ -313,4 +353,97 @@ mod tests {
             oracle_event: OracleEvent { oracle_nonces: vec![xonly_pk], event_maturity_epoch: maturity,event_descriptor: EventDescriptor::EnumEvent(EnumEventDescriptor { outcomes: vec!["1".to_string(), "2".to_string()] }), event_id: "01".to_string() },
         }
     }
+
+    fn funding_spend() -> (Transaction, SecretKey, SecretKey, bitcoin::ScriptBuf) {
+        let secp = Secp256k1::new();
+        let own_sk = SecretKey::new(&mut thread_rng());
+        let other_sk = SecretKey::new(&mut thread_rng());
+        let funding_script_pubkey =
+            dlc::make_funding_redeemscript(&own_sk.public_key(&secp), &other_sk.public_key(&secp));
+        let tx = Transaction {
+            version: bitcoin::transaction::Version::TWO,
+            lock_time: bitcoin::absolute::LockTime::ZERO,
+            input: vec![bitcoin::TxIn::default()],
+            output: vec![bitcoin::TxOut {
+                value: Amount::from_sat(99_000),
+                script_pubkey: bitcoin::ScriptBuf::new(),
+            }],
+        };
+        (tx, own_sk, other_sk, funding_script_pubkey)
+    }
+
+    #[test]
+    fn funding_input_witness_matches_multi_sig_signing() {
+        let secp = Secp256k1::new();
+        let (tx, own_sk, other_sk, script) = funding_spend();
+        let other_sig = other_sk
+            .sign_funding_input(&secp, &tx, 0, &script, 100_000)
+            .unwrap();
+
+        let mut expected = tx.clone();
+        dlc::util::sign_multi_sig_input(
+            &secp,
+            &mut expected,
+            &other_sig,
+            &other_sk.public_key(&secp),
+            &own_sk,
+            &script,
+            100_000,
+            0,
+        )
+        .unwrap();
+
+        let mut signed = tx.clone();
+        let own_sig = own_sk
+            .sign_funding_input(&secp, &tx, 0, &script, 100_000)
+            .unwrap();
+        set_funding_input_witness(
+            &mut signed,
+            0,
+            (&own_sig, &own_sk.public_key(&secp)),
+            (&other_sig, &other_sk.public_key(&secp)),
+            &script,
+        );
+
+        assert_eq!(signed.input[0].witness, expected.input[0].witness);
+    }
+
+    #[test]
+    fn oracle_signatures_decrypt_cet_adaptor_signature() {
+        let secp = Secp256k1::new();
+        let (cet, own_sk, _, script) = funding_spend();
+        let oracle = secp256k1_zkp::Keypair::new(&secp, &mut thread_rng());
+        let mut nonce = [0u8; 32];
+        thread_rng().fill_bytes(&mut nonce);
+        let nonce_pk = secp256k1_zkp::Keypair::from_seckey_slice(&secp, &nonce)
+            .unwrap()
+            .x_only_public_key()
+            .0;
+        let msg = secp256k1_zkp::Message::from_digest([7u8; 32]);
+        let oracle_sig = dlc::secp_utils::schnorrsig_sign_with_nonce(&secp, &msg, &oracle, &nonce);
+        let adaptor_point = dlc::secp_utils::schnorrsig_compute_sig_point(
+            &secp,
+            &oracle.x_only_public_key().0,
+            &nonce_pk,
+            &msg,
+        )
+        .unwrap();
+
+        let adaptor_sigs = own_sk
+            .create_cet_adaptor_signatures(&secp, &[(&cet, &adaptor_point)], &script, 100_000)
+            .unwrap();
+        let secret = oracle_signatures_to_secret(&[vec![oracle_sig]]).unwrap();
+        let sig = adaptor_sigs[0].decrypt(&secret).unwrap();
+
+        dlc::verify_tx_input_sig(
+            &secp,
+            &sig,
+            &cet,
+            0,
+            &script,
+            100_000,
+            &own_sk.public_key(&secp),
+        )
+        .unwrap();
+    }
 }
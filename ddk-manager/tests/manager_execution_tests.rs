@@ -8,7 +8,7 @@ use test_utils::*;
 
 use bitcoincore_rpc::RpcApi;
 use ddk_manager::contract::{numerical_descriptor::DifferenceParams, Contract};
-use ddk_manager::manager::Manager;
+use ddk_manager::manager::{Manager, ManagerConfig};
 use ddk_manager::{Blockchain, Oracle, Storage};
 use dlc_messages::oracle_msgs::OracleAttestation;
 use dlc_messages::{AcceptDlc, OfferDlc, SignDlc};
@@ -671,6 +671,7 @@ async fn manager_execution_test(test_params: TestParams, path: TestPath, manual_
             alice_oracles,
             Arc::clone(&mock_time),
             Arc::clone(&electrs),
+            ManagerConfig::default(),
         )
         .await
         .unwrap(),
@@ -688,6 +689,7 @@ async fn manager_execution_test(test_params: TestParams, path: TestPath, manual_
             bob_oracles,
             Arc::clone(&mock_time),
             Arc::clone(&electrs),
+            ManagerConfig::default(),
         )
         .await
         .unwrap(),
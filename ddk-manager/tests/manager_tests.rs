@@ -5,26 +5,37 @@ mod test_utils;
 use bitcoin::Network;
 use ddk::chain::EsploraClient;
 use ddk::oracle::memory::MemoryOracle;
+use ddk::signer::DdkContractSigner;
 use ddk::storage::memory::MemoryStorage;
 use ddk::wallet::DlcDevKitWallet;
-use ddk_manager::{manager::Manager, CachedContractSignerProvider, Oracle, SimpleSigner};
-use dlc_messages::Message;
+use ddk_manager::contract::accepted_contract::AcceptedContract;
+use ddk_manager::contract::ser::Serializable;
+use ddk_manager::contract::Contract;
+use ddk_manager::event::{Event, EventHandler};
+use ddk_manager::manager::{Manager, ManagerConfig, REFUND_DELAY};
+use ddk_manager::{CachedContractSignerProvider, Oracle, Storage};
+use dlc_messages::channel::{OfferChannel, Reject};
+use dlc_messages::{Message, OfferDlc};
 use secp256k1_zkp::{rand::Fill, PublicKey, XOnlyPublicKey};
 use std::{collections::HashMap, sync::Arc};
 use test_utils::{set_time, MockTime};
 
 type TestManager = Manager<
     Arc<DlcDevKitWallet>,
-    Arc<CachedContractSignerProvider<Arc<DlcDevKitWallet>, SimpleSigner>>,
+    Arc<CachedContractSignerProvider<Arc<DlcDevKitWallet>, DdkContractSigner>>,
     Arc<EsploraClient>,
     Arc<MemoryStorage>,
     Arc<MemoryOracle>,
     Arc<MockTime>,
     Arc<EsploraClient>,
-    SimpleSigner,
+    DdkContractSigner,
 >;
 
 async fn get_manager() -> TestManager {
+    get_manager_with_config(ManagerConfig::default()).await
+}
+
+async fn get_manager_with_config(config: ManagerConfig) -> TestManager {
     let blockchain =
         Arc::new(EsploraClient::new("http://localhost:30000", Network::Regtest).unwrap());
     let store = Arc::new(MemoryStorage::new());
@@ -59,6 +70,7 @@ async fn get_manager() -> TestManager {
         oracles,
         time,
         blockchain,
+        config,
     )
     .await
     .unwrap()
@@ -89,6 +101,24 @@ async fn reject_offer_with_existing_contract_id() {
         .expect_err("To reject the second offer message");
 }
 
+#[tokio::test]
+async fn reject_offer_outside_configured_refund_delay() {
+    let offer_message = Message::Offer(
+        serde_json::from_str(include_str!("../test_inputs/offer_contract.json")).unwrap(),
+    );
+
+    let manager = get_manager_with_config(ManagerConfig {
+        refund_delay: REFUND_DELAY * 2,
+        ..Default::default()
+    })
+    .await;
+
+    manager
+        .on_dlc_message(&offer_message, pubkey())
+        .await
+        .expect_err("To reject an offer with a refund delay below the configured one");
+}
+
 #[tokio::test]
 async fn reject_channel_offer_with_existing_channel_id() {
     let offer_message = Message::OfferChannel(
@@ -107,3 +137,171 @@ async fn reject_channel_offer_with_existing_channel_id() {
         .await
         .expect_err("To reject the second offer message");
 }
+
+#[tokio::test]
+async fn reject_contract_offer_moves_contract_to_rejected() {
+    let offer: OfferDlc =
+        serde_json::from_str(include_str!("../test_inputs/offer_contract.json")).unwrap();
+
+    let manager = get_manager().await;
+
+    manager
+        .on_dlc_message(&Message::Offer(offer.clone()), pubkey())
+        .await
+        .expect("To accept the offer message");
+
+    manager
+        .cancel_offer(&offer.temporary_contract_id)
+        .expect_err("To not cancel an offer from the counter party");
+
+    let (reject, counter_party) = manager
+        .reject_contract_offer(&offer.temporary_contract_id)
+        .expect("To reject the offer");
+
+    assert_eq!(reject.channel_id, offer.temporary_contract_id);
+    assert_eq!(counter_party, pubkey());
+    assert!(matches!(
+        manager
+            .get_store()
+            .get_contract(&offer.temporary_contract_id)
+            .unwrap(),
+        Some(Contract::Rejected(_))
+    ));
+}
+
+#[tokio::test]
+async fn cancelled_offer_is_rejected_by_counter_party() {
+    let offer: OfferDlc =
+        serde_json::from_str(include_str!("../test_inputs/offer_contract.json")).unwrap();
+
+    let manager = get_manager().await;
+
+    manager
+        .on_dlc_message(&Message::Offer(offer.clone()), pubkey())
+        .await
+        .expect("To accept the offer message");
+
+    let reject = Message::Reject(Reject {
+        channel_id: offer.temporary_contract_id,
+    });
+    manager
+        .on_dlc_message(&reject, pubkey())
+        .await
+        .expect("To handle the reject message");
+
+    assert!(matches!(
+        manager
+            .get_store()
+            .get_contract(&offer.temporary_contract_id)
+            .unwrap(),
+        Some(Contract::Rejected(_))
+    ));
+}
+
+#[derive(Default)]
+struct RecordingHandler(std::sync::Mutex<Vec<Event>>);
+
+impl EventHandler for RecordingHandler {
+    fn handle_event(&self, event: Event) {
+        self.0.lock().unwrap().push(event);
+    }
+}
+
+#[tokio::test]
+async fn cancelled_offer_rejects_accepted_contract() {
+    let serialized = include_bytes!("../../ddk/tests/data/dlc_storage/Accepted");
+    let mut accepted =
+        AcceptedContract::deserialize(&mut lightning::io::Cursor::new(&serialized)).unwrap();
+    accepted.offered_contract.is_offer_party = false;
+    accepted.offered_contract.counter_party = pubkey();
+    let temporary_id = accepted.offered_contract.id;
+    let contract_id = accepted.get_contract_id();
+
+    let manager = get_manager().await;
+    manager
+        .get_store()
+        .update_contract(&Contract::Accepted(accepted))
+        .unwrap();
+
+    let reject = Message::Reject(Reject {
+        channel_id: temporary_id,
+    });
+    manager
+        .on_dlc_message(&reject, pubkey())
+        .await
+        .expect("To handle the reject message");
+
+    let store = manager.get_store();
+    assert!(store.get_contract(&contract_id).unwrap().is_none());
+    assert!(matches!(
+        store.get_contract(&temporary_id).unwrap(),
+        Some(Contract::Rejected(_))
+    ));
+}
+
+#[tokio::test]
+async fn offer_and_reject_messages_emit_events() {
+    let offer: OfferDlc =
+        serde_json::from_str(include_str!("../test_inputs/offer_contract.json")).unwrap();
+
+    let mut manager = get_manager().await;
+    let handler = Arc::new(RecordingHandler::default());
+    manager.set_event_handler(handler.clone());
+
+    manager
+        .on_dlc_message(&Message::Offer(offer.clone()), pubkey())
+        .await
+        .expect("To accept the offer message");
+    let reject = Message::Reject(Reject {
+        channel_id: offer.temporary_contract_id,
+    });
+    manager
+        .on_dlc_message(&reject, pubkey())
+        .await
+        .expect("To handle the reject message");
+
+    assert_eq!(
+        *handler.0.lock().unwrap(),
+        vec![
+            Event::OfferReceived {
+                contract_id: offer.temporary_contract_id,
+                counter_party: pubkey(),
+            },
+            Event::RejectReceived {
+                id: offer.temporary_contract_id,
+                counter_party: pubkey(),
+            },
+        ]
+    );
+}
+
+#[tokio::test]
+async fn rejected_channel_offer_emits_channel_events() {
+    let offer: OfferChannel =
+        serde_json::from_str(include_str!("../test_inputs/offer_channel.json")).unwrap();
+
+    let mut manager = get_manager().await;
+    let handler = Arc::new(RecordingHandler::default());
+    manager.set_event_handler(handler.clone());
+
+    manager
+        .on_dlc_message(&Message::OfferChannel(offer.clone()), pubkey())
+        .await
+        .expect("To accept the offer message");
+    manager
+        .reject_channel(&offer.temporary_channel_id)
+        .expect("To reject the channel offer");
+
+    assert_eq!(
+        *handler.0.lock().unwrap(),
+        vec![
+            Event::ChannelOfferReceived {
+                channel_id: offer.temporary_channel_id,
+                counter_party: pubkey(),
+            },
+            Event::ChannelCancelled {
+                channel_id: offer.temporary_channel_id,
+            },
+        ]
+    );
+}
@@ -10,8 +10,12 @@ pub enum CliCommand {
     Offers,
     /// Accept a DLC offer with the contract id string.
     AcceptOffer(Accept),
+    /// Reject a DLC offer with the contract id string.
+    RejectOffer(Reject),
+    /// Cancel a DLC offer sent by the node with the contract id string.
+    CancelOffer(Cancel),
     /// List contracts.
-    Contracts,
+    Contracts(ListContracts),
     #[command(about = "Get the wallet balance.")]
     Balance,
     /// Wallet commands
@@ -29,6 +33,40 @@ pub enum CliCommand {
     },
 }
 
+#[derive(Parser, Clone, Debug)]
+pub struct ListContracts {
+    /// Only contracts in this state, e.g. `confirmed`. Can be repeated.
+    #[arg(long = "state")]
+    pub states: Vec<String>,
+    /// Only contracts with this counterparty.
+    #[arg(long)]
+    pub counterparty: Option<String>,
+    /// Only contracts on this oracle event.
+    #[arg(long)]
+    pub event_id: Option<String>,
+    /// Oracle event maturing at or after this unix time.
+    #[arg(long)]
+    pub maturity_from: Option<u32>,
+    /// Oracle event maturing at or before this unix time.
+    #[arg(long)]
+    pub maturity_to: Option<u32>,
+    /// Created at or after this unix time.
+    #[arg(long)]
+    pub created_from: Option<u64>,
+    /// Created at or before this unix time.
+    #[arg(long)]
+    pub created_to: Option<u64>,
+    /// Newest contracts first.
+    #[arg(long)]
+    pub descending: bool,
+    /// Contracts per page.
+    #[arg(long)]
+    pub limit: Option<u32>,
+    /// Continue from the cursor printed with the previous page.
+    #[arg(long)]
+    pub cursor: Option<String>,
+}
+
 #[derive(Parser, Clone, Debug)]
 pub struct Offer {
     #[arg(help = "Generate a contract automatically with peer.")]
@@ -42,18 +80,39 @@ pub struct Offer {
 pub enum WalletCommand {
     #[command(about = "Generate a new, unused address from the wallet.")]
     NewAddress,
-    #[command(about = "Get the wallet transactions.")]
+    #[command(about = "Get the wallet transactions with their amounts, fees and contracts.")]
     Transactions,
     #[command(about = "Get the wallet utxos.")]
     Utxos,
     #[command(about = "Send a Bitcoin amount to an address")]
     Send {
-        /// Address to send to.
-        address: String,
-        /// Amount in sats to send to.
-        amount: u64,
+        /// Address to send to. Can be left out when sending to `--recipient` outputs or
+        /// only an `--op-return` output.
+        #[arg(requires = "amount")]
+        address: Option<String>,
+        /// Amount in sats to send to the address.
+        amount: Option<u64>,
         /// Fee rate in sats/vbyte
+        #[arg(long)]
         fee_rate: u64,
+        /// Another recipient as `<address>:<amount in sats>`. Can be repeated.
+        #[arg(long = "recipient")]
+        recipients: Vec<String>,
+        /// Spend the utxo `<txid>:<vout>`. Can be repeated.
+        #[arg(long = "include")]
+        include_utxos: Vec<String>,
+        /// Don't spend the utxo `<txid>:<vout>`. Can be repeated.
+        #[arg(long = "exclude")]
+        exclude_utxos: Vec<String>,
+        /// Hex encoded data of an OP_RETURN output.
+        #[arg(long)]
+        op_return: Option<String>,
+        /// Send the change to this address instead of the wallet.
+        #[arg(long)]
+        change_address: Option<String>,
+        /// Build the transaction and show its fee without broadcasting it.
+        #[arg(long)]
+        dry_run: bool,
     },
     #[command(about = "Sync the on-chain wallet.")]
     Sync,
@@ -71,6 +130,18 @@ pub struct Accept {
     pub contract_id: String,
 }
 
+#[derive(Parser, Clone, Debug)]
+pub struct Reject {
+    // The contract id string to reject.
+    pub contract_id: String,
+}
+
+#[derive(Parser, Clone, Debug)]
+pub struct Cancel {
+    // The contract id string to cancel.
+    pub contract_id: String,
+}
+
 #[derive(Parser, Clone, Debug)]
 pub struct Connect {
     #[arg(help = "The public key to connect to.")]
@@ -2,23 +2,23 @@ use crate::cli_opts::{CliCommand, OracleCommand, WalletCommand};
 // use crate::convert::*;
 use crate::ddkrpc::ddk_rpc_client::DdkRpcClient;
 use crate::ddkrpc::{
-    AcceptOfferRequest, ConnectRequest, GetWalletTransactionsRequest, InfoRequest,
-    ListContractsRequest, ListOffersRequest, ListOraclesRequest, ListPeersRequest,
-    ListUtxosRequest, NewAddressRequest, OracleAnnouncementsRequest, SendOfferRequest, SendRequest,
-    WalletBalanceRequest, WalletSyncRequest,
+    AcceptOfferRequest, CancelOfferRequest, ConnectRequest, GetWalletTransactionsRequest,
+    InfoRequest, ListContractsRequest, ListOffersRequest, ListOraclesRequest, ListPeersRequest,
+    ListUtxosRequest, NewAddressRequest, OracleAnnouncementsRequest, Recipient, RejectOfferRequest,
+    SendOfferRequest, SendRequest, WalletBalanceRequest, WalletSyncRequest,
 };
 use anyhow::anyhow;
-use bitcoin::Transaction;
 use chrono::TimeDelta;
 use ddk::json::*;
 use ddk::oracle::kormir::KormirOracleClient;
 use ddk::util;
-use ddk::wallet::LocalOutput;
+use ddk::wallet::{LocalOutput, WalletTransaction};
 use ddk_manager::contract::contract_input::{ContractInput, ContractInputInfo, OracleInput};
 use ddk_manager::contract::enum_descriptor::EnumDescriptor;
 use ddk_manager::contract::offered_contract::OfferedContract;
 use ddk_manager::contract::{Contract, ContractDescriptor};
 use dlc::{EnumerationPayout, Payout};
+use dlc_messages::channel::Reject;
 use dlc_messages::oracle_msgs::{EventDescriptor, OracleAnnouncement};
 use dlc_messages::{AcceptDlc, OfferDlc};
 use inquire::{Select, Text};
@@ -77,13 +77,47 @@ pub async fn cli_command(
             let accept_dlc = serde_json::to_string_pretty(&accept_dlc)?;
             print!("{}", accept_dlc)
         }
-        CliCommand::Contracts => {
-            let contracts = client
-                .list_contracts(ListContractsRequest {})
+        CliCommand::RejectOffer(reject) => {
+            let reject = client
+                .reject_offer(RejectOfferRequest {
+                    contract_id: reject.contract_id,
+                })
+                .await?
+                .into_inner();
+            let reject: Reject = serde_json::from_slice(&reject.reject)?;
+            print!("{}", serde_json::to_string_pretty(&reject)?)
+        }
+        CliCommand::CancelOffer(cancel) => {
+            let cancel = client
+                .cancel_offer(CancelOfferRequest {
+                    contract_id: cancel.contract_id,
+                })
+                .await?
+                .into_inner();
+            let reject: Reject = serde_json::from_slice(&cancel.reject)?;
+            print!("{}", serde_json::to_string_pretty(&reject)?)
+        }
+        CliCommand::Contracts(list) => {
+            let response = client
+                .list_contracts(ListContractsRequest {
+                    states: list.states,
+                    counterparty: list.counterparty.unwrap_or_default(),
+                    event_id: list.event_id.unwrap_or_default(),
+                    maturity_from: list.maturity_from.unwrap_or_default(),
+                    maturity_to: list.maturity_to.unwrap_or_default(),
+                    created_from: list.created_from.unwrap_or_default(),
+                    created_to: list.created_to.unwrap_or_default(),
+                    descending: list.descending,
+                    limit: list.limit.unwrap_or_default(),
+                    cursor: list.cursor.unwrap_or_default(),
+                })
                 .await?
-                .into_inner()
-                .contracts;
-            let contract_values = contracts
+                .into_inner();
+            if !response.next_cursor.is_empty() {
+                eprintln!("Next page: --cursor {}", response.next_cursor);
+            }
+            let contract_values = response
+                .contracts
                 .iter()
                 .map(|c| {
                     let contract: Contract = util::deserialize_contract(c).unwrap();
@@ -118,7 +152,7 @@ pub async fn cli_command(
                     .transactions
                     .iter()
                     .map(|txn| serde_json::from_slice(txn).unwrap())
-                    .collect::<Vec<Transaction>>();
+                    .collect::<Vec<WalletTransaction>>();
                 let txns = serde_json::to_string_pretty(&txns)?;
                 print!("{}", txns)
             }
@@ -139,16 +173,50 @@ pub async fn cli_command(
                 address,
                 amount,
                 fee_rate,
+                recipients,
+                include_utxos,
+                exclude_utxos,
+                op_return,
+                change_address,
+                dry_run,
             } => {
-                let txid = client
+                let recipients = recipients
+                    .iter()
+                    .map(|recipient| {
+                        let (address, amount) = recipient
+                            .split_once(':')
+                            .ok_or(anyhow!("Recipient must be <address>:<amount>."))?;
+                        Ok(Recipient {
+                            address: address.to_string(),
+                            amount: amount.parse()?,
+                        })
+                    })
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+                let op_return = match op_return {
+                    Some(data) => hex::decode(data)?,
+                    None => vec![],
+                };
+                let sent = client
                     .send(SendRequest {
-                        address,
-                        amount,
+                        address: address.unwrap_or_default(),
+                        amount: amount.unwrap_or_default(),
                         fee_rate,
+                        recipients,
+                        include_utxos,
+                        exclude_utxos,
+                        op_return,
+                        change_address: change_address.unwrap_or_default(),
+                        dry_run,
                     })
                     .await?
                     .into_inner();
-                print!("{}", serde_json::to_string_pretty(&txid)?)
+                let sent = serde_json::json!({
+                    "txid": sent.txid,
+                    "psbt": hex::encode(sent.psbt),
+                    "fee": sent.fee,
+                    "broadcast": sent.broadcast,
+                });
+                print!("{}", serde_json::to_string_pretty(&sent)?)
             }
             WalletCommand::Sync => {
                 let _ = client.wallet_sync(WalletSyncRequest {}).await?.into_inner();
@@ -13,6 +13,12 @@ pub struct InfoResponse {
     pub transport: ::prost::alloc::string::String,
     #[prost(string, tag = "3")]
     pub oracle: ::prost::alloc::string::String,
+    #[prost(bool, tag = "4")]
+    pub manager_running: bool,
+    #[prost(uint64, tag = "5")]
+    pub manager_restarts: u64,
+    #[prost(uint64, tag = "6")]
+    pub periodic_check_failures: u64,
 }
 #[derive(serde::Serialize, serde::Deserialize)]
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -62,6 +68,34 @@ pub struct AcceptOfferResponse {
 #[derive(serde::Serialize, serde::Deserialize)]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RejectOfferRequest {
+    #[prost(string, tag = "1")]
+    pub contract_id: ::prost::alloc::string::String,
+}
+#[derive(serde::Serialize, serde::Deserialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RejectOfferResponse {
+    #[prost(bytes = "vec", tag = "1")]
+    pub reject: ::prost::alloc::vec::Vec<u8>,
+}
+#[derive(serde::Serialize, serde::Deserialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CancelOfferRequest {
+    #[prost(string, tag = "1")]
+    pub contract_id: ::prost::alloc::string::String,
+}
+#[derive(serde::Serialize, serde::Deserialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CancelOfferResponse {
+    #[prost(bytes = "vec", tag = "1")]
+    pub reject: ::prost::alloc::vec::Vec<u8>,
+}
+#[derive(serde::Serialize, serde::Deserialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
 pub struct NewAddressRequest {}
 #[derive(serde::Serialize, serde::Deserialize)]
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -128,6 +162,8 @@ pub struct Peer {
     pub pubkey: ::prost::alloc::string::String,
     #[prost(string, tag = "2")]
     pub host: ::prost::alloc::string::String,
+    #[prost(bool, tag = "3")]
+    pub connected: bool,
 }
 #[derive(serde::Serialize, serde::Deserialize)]
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -158,13 +194,42 @@ pub struct ListOraclesResponse {
 #[derive(serde::Serialize, serde::Deserialize)]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
-pub struct ListContractsRequest {}
+pub struct ListContractsRequest {
+    /// Contract states, e.g. `confirmed`. Empty lists every state.
+    #[prost(string, repeated, tag = "1")]
+    pub states: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    #[prost(string, tag = "2")]
+    pub counterparty: ::prost::alloc::string::String,
+    #[prost(string, tag = "3")]
+    pub event_id: ::prost::alloc::string::String,
+    /// Inclusive maturity and creation time bounds in unix seconds, 0 is unbounded.
+    #[prost(uint32, tag = "4")]
+    pub maturity_from: u32,
+    #[prost(uint32, tag = "5")]
+    pub maturity_to: u32,
+    #[prost(uint64, tag = "6")]
+    pub created_from: u64,
+    #[prost(uint64, tag = "7")]
+    pub created_to: u64,
+    /// Newest contracts first.
+    #[prost(bool, tag = "8")]
+    pub descending: bool,
+    /// Contracts per page, 0 returns every contract.
+    #[prost(uint32, tag = "9")]
+    pub limit: u32,
+    /// `next_cursor` of the previous page.
+    #[prost(string, tag = "10")]
+    pub cursor: ::prost::alloc::string::String,
+}
 #[derive(serde::Serialize, serde::Deserialize)]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct ListContractsResponse {
     #[prost(bytes = "vec", repeated, tag = "1")]
     pub contracts: ::prost::alloc::vec::Vec<::prost::alloc::vec::Vec<u8>>,
+    /// Empty on the last page.
+    #[prost(string, tag = "2")]
+    pub next_cursor: ::prost::alloc::string::String,
 }
 #[derive(serde::Serialize, serde::Deserialize)]
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -176,6 +241,27 @@ pub struct SendRequest {
     pub amount: u64,
     #[prost(uint64, tag = "3")]
     pub fee_rate: u64,
+    #[prost(message, repeated, tag = "4")]
+    pub recipients: ::prost::alloc::vec::Vec<Recipient>,
+    #[prost(string, repeated, tag = "5")]
+    pub include_utxos: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    #[prost(string, repeated, tag = "6")]
+    pub exclude_utxos: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    #[prost(bytes = "vec", tag = "7")]
+    pub op_return: ::prost::alloc::vec::Vec<u8>,
+    #[prost(string, tag = "8")]
+    pub change_address: ::prost::alloc::string::String,
+    #[prost(bool, tag = "9")]
+    pub dry_run: bool,
+}
+#[derive(serde::Serialize, serde::Deserialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Recipient {
+    #[prost(string, tag = "1")]
+    pub address: ::prost::alloc::string::String,
+    #[prost(uint64, tag = "2")]
+    pub amount: u64,
 }
 #[derive(serde::Serialize, serde::Deserialize)]
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -183,6 +269,12 @@ pub struct SendRequest {
 pub struct SendResponse {
     #[prost(string, tag = "1")]
     pub txid: ::prost::alloc::string::String,
+    #[prost(bytes = "vec", tag = "2")]
+    pub psbt: ::prost::alloc::vec::Vec<u8>,
+    #[prost(uint64, tag = "3")]
+    pub fee: u64,
+    #[prost(bool, tag = "4")]
+    pub broadcast: bool,
 }
 #[derive(serde::Serialize, serde::Deserialize)]
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -353,6 +445,54 @@ pub mod ddk_rpc_client {
             req.extensions_mut().insert(GrpcMethod::new("ddkrpc.DdkRpc", "AcceptOffer"));
             self.inner.unary(req, path, codec).await
         }
+        pub async fn reject_offer(
+            &mut self,
+            request: impl tonic::IntoRequest<super::RejectOfferRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::RejectOfferResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/ddkrpc.DdkRpc/RejectOffer",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut().insert(GrpcMethod::new("ddkrpc.DdkRpc", "RejectOffer"));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn cancel_offer(
+            &mut self,
+            request: impl tonic::IntoRequest<super::CancelOfferRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::CancelOfferResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/ddkrpc.DdkRpc/CancelOffer",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut().insert(GrpcMethod::new("ddkrpc.DdkRpc", "CancelOffer"));
+            self.inner.unary(req, path, codec).await
+        }
         pub async fn list_offers(
             &mut self,
             request: impl tonic::IntoRequest<super::ListOffersRequest>,
@@ -657,6 +797,20 @@ pub mod ddk_rpc_server {
             tonic::Response<super::AcceptOfferResponse>,
             tonic::Status,
         >;
+        async fn reject_offer(
+            &self,
+            request: tonic::Request<super::RejectOfferRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::RejectOfferResponse>,
+            tonic::Status,
+        >;
+        async fn cancel_offer(
+            &self,
+            request: tonic::Request<super::CancelOfferRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::CancelOfferResponse>,
+            tonic::Status,
+        >;
         async fn list_offers(
             &self,
             request: tonic::Request<super::ListOffersRequest>,
@@ -949,6 +1103,98 @@ pub mod ddk_rpc_server {
                     };
                     Box::pin(fut)
                 }
+                "/ddkrpc.DdkRpc/RejectOffer" => {
+                    #[allow(non_camel_case_types)]
+                    struct RejectOfferSvc<T: DdkRpc>(pub Arc<T>);
+                    impl<
+                        T: DdkRpc,
+                    > tonic::server::UnaryService<super::RejectOfferRequest>
+                    for RejectOfferSvc<T> {
+                        type Response = super::RejectOfferResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::RejectOfferRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as DdkRpc>::reject_offer(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = RejectOfferSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/ddkrpc.DdkRpc/CancelOffer" => {
+                    #[allow(non_camel_case_types)]
+                    struct CancelOfferSvc<T: DdkRpc>(pub Arc<T>);
+                    impl<
+                        T: DdkRpc,
+                    > tonic::server::UnaryService<super::CancelOfferRequest>
+                    for CancelOfferSvc<T> {
+                        type Response = super::CancelOfferResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::CancelOfferRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as DdkRpc>::cancel_offer(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = CancelOfferSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
                 "/ddkrpc.DdkRpc/ListOffers" => {
                     #[allow(non_camel_case_types)]
                     struct ListOffersSvc<T: DdkRpc>(pub Arc<T>);
@@ -5,12 +5,16 @@ pub mod opts;
 mod seed;
 
 use bitcoin::secp256k1::PublicKey;
-use bitcoin::{Address, Amount, FeeRate, Network};
+use bitcoin::{Address, Amount, FeeRate, Network, OutPoint};
 use ddk::builder::Builder;
+use ddk::chain::{BitcoindAuth, ChainSource};
 use ddk::oracle::kormir::KormirOracleClient;
+use ddk::storage::encryption::StorageSecret;
+use ddk::storage::query::{ContractCursor, ContractQuery, ContractState, RangeFilter, SortOrder};
 use ddk::storage::sled::SledStorage;
 use ddk::transport::lightning::LightningTransport;
 use ddk::util::serialize_contract;
+use ddk::wallet::SendBuilder;
 use ddk::DlcDevKit;
 use ddk::{Oracle, Storage, Transport};
 use ddk_manager::contract::contract_input::ContractInput;
@@ -18,13 +22,14 @@ use ddk_manager::Oracle as DlcOracle;
 use ddk_manager::Storage as DlcStorage;
 use ddkrpc::ddk_rpc_server::{DdkRpc, DdkRpcServer};
 use ddkrpc::{
-    AcceptOfferRequest, AcceptOfferResponse, ConnectRequest, ConnectResponse,
-    GetWalletTransactionsRequest, GetWalletTransactionsResponse, ListContractsRequest,
-    ListContractsResponse, ListOffersRequest, ListOffersResponse, ListOraclesRequest,
-    ListOraclesResponse, ListPeersRequest, ListPeersResponse, ListUtxosRequest, ListUtxosResponse,
-    NewAddressRequest, NewAddressResponse, OracleAnnouncementsRequest, OracleAnnouncementsResponse,
-    Peer, SendOfferRequest, SendOfferResponse, SendRequest, SendResponse, WalletBalanceRequest,
-    WalletBalanceResponse, WalletSyncRequest, WalletSyncResponse,
+    AcceptOfferRequest, AcceptOfferResponse, CancelOfferRequest, CancelOfferResponse,
+    ConnectRequest, ConnectResponse, GetWalletTransactionsRequest, GetWalletTransactionsResponse,
+    ListContractsRequest, ListContractsResponse, ListOffersRequest, ListOffersResponse,
+    ListOraclesRequest, ListOraclesResponse, ListPeersRequest, ListPeersResponse, ListUtxosRequest,
+    ListUtxosResponse, NewAddressRequest, NewAddressResponse, OracleAnnouncementsRequest,
+    OracleAnnouncementsResponse, Peer, RejectOfferRequest, RejectOfferResponse, SendOfferRequest,
+    SendOfferResponse, SendRequest, SendResponse, WalletBalanceRequest, WalletBalanceResponse,
+    WalletSyncRequest, WalletSyncResponse,
 };
 use ddkrpc::{InfoRequest, InfoResponse};
 use opts::NodeOpts;
@@ -71,16 +76,41 @@ impl DdkNode {
             opts.listening_port,
         )?);
 
-        let storage = Arc::new(SledStorage::new(
-            storage_path.join("sled_db").to_str().unwrap(),
-        )?);
+        let sled_path = storage_path.join("sled_db");
+        let secret = match &opts.storage_passphrase_file {
+            Some(path) => {
+                let passphrase = std::fs::read_to_string(path)?;
+                Some(StorageSecret::Passphrase(passphrase.trim_end().to_string()))
+            }
+            None if opts.encrypt_storage => Some(StorageSecret::Seed(
+                seed_bytes.private_key.secret_bytes().to_vec(),
+            )),
+            None => None,
+        };
+        let storage = Arc::new(match secret {
+            Some(secret) => SledStorage::new_encrypted(sled_path.to_str().unwrap(), &secret)?,
+            None => SledStorage::new(sled_path.to_str().unwrap())?,
+        });
 
         // let oracle = Arc::new(P2PDOracleClient::new(&oracle_host).await?);
         let oracle = Arc::new(KormirOracleClient::new(&opts.oracle_host, None).await?);
 
         let mut builder = Builder::new();
         builder.set_seed_bytes(seed_bytes.private_key.secret_bytes());
-        builder.set_esplora_host(opts.esplora_host);
+        let chain_source = match opts.bitcoind_host {
+            Some(url) => ChainSource::Bitcoind {
+                url,
+                auth: match (opts.bitcoind_user, opts.bitcoind_pass) {
+                    (Some(user), Some(pass)) => BitcoindAuth::UserPass(user, pass),
+                    (None, None) => BitcoindAuth::None,
+                    _ => anyhow::bail!(
+                        "--bitcoind-user and --bitcoind-pass must be passed together."
+                    ),
+                },
+            },
+            None => ChainSource::esplora(&opts.esplora_host),
+        };
+        builder.set_chain_source(chain_source);
         builder.set_network(network);
         builder.set_transport(transport.clone());
         builder.set_storage(storage.clone());
@@ -97,7 +127,7 @@ impl DdkNode {
                 tokio::signal::ctrl_c()
                     .await
                     .expect("Failed to install Ctrl+C signal handler");
-                let _ = node_stop.stop();
+                let _ = node_stop.stop().await;
             });
 
         server.await?;
@@ -114,10 +144,14 @@ impl DdkRpc for DdkNode {
         let pubkey = self.node.transport.node_id.to_string();
         let transport = self.node.transport.name();
         let oracle = self.node.oracle.name();
+        let health = self.node.manager_health();
         let response = InfoResponse {
             pubkey,
             transport,
             oracle,
+            manager_running: health.running,
+            manager_restarts: health.restarts,
+            periodic_check_failures: health.periodic_check_failures,
         };
         Ok(Response::new(response))
     }
@@ -191,6 +225,61 @@ impl DdkRpc for DdkNode {
         }))
     }
 
+    #[tracing::instrument(skip(self, request), name = "grpc_server")]
+    async fn reject_offer(
+        &self,
+        request: Request<RejectOfferRequest>,
+    ) -> Result<Response<RejectOfferResponse>, Status> {
+        tracing::info!("Request to reject offer.");
+        let mut contract_id = [0u8; 32];
+        let contract_id_bytes = hex::decode(request.into_inner().contract_id)
+            .map_err(|_| Status::new(Code::InvalidArgument, "Contract id is not valid hex."))?;
+        if contract_id_bytes.len() != 32 {
+            return Err(Status::new(
+                Code::InvalidArgument,
+                "Contract id must be 32 bytes.",
+            ));
+        }
+        contract_id.copy_from_slice(&contract_id_bytes);
+        let reject = self
+            .node
+            .reject_dlc_offer(contract_id)
+            .await
+            .map_err(|_| Status::new(Code::Cancelled, "Contract could not be rejected."))?;
+
+        let reject = serde_json::to_vec(&reject)
+            .map_err(|_| Status::new(Code::Cancelled, "Reject is malformed to create bytes."))?;
+
+        Ok(Response::new(RejectOfferResponse { reject }))
+    }
+
+    #[tracing::instrument(skip(self, request), name = "grpc_server")]
+    async fn cancel_offer(
+        &self,
+        request: Request<CancelOfferRequest>,
+    ) -> Result<Response<CancelOfferResponse>, Status> {
+        tracing::info!("Request to cancel offer.");
+        let mut contract_id = [0u8; 32];
+        let contract_id_bytes = hex::decode(request.into_inner().contract_id)
+            .map_err(|_| Status::new(Code::InvalidArgument, "Contract id is not valid hex."))?;
+        if contract_id_bytes.len() != 32 {
+            return Err(Status::new(
+                Code::InvalidArgument,
+                "Contract id must be 32 bytes.",
+            ));
+        }
+        contract_id.copy_from_slice(&contract_id_bytes);
+        let reject =
+            self.node.cancel_dlc_offer(contract_id).await.map_err(|_| {
+                Status::new(Code::Cancelled, "Contract offer could not be cancelled.")
+            })?;
+
+        let reject = serde_json::to_vec(&reject)
+            .map_err(|_| Status::new(Code::Cancelled, "Reject is malformed to create bytes."))?;
+
+        Ok(Response::new(CancelOfferResponse { reject }))
+    }
+
     #[tracing::instrument(skip(self, _request), name = "grpc_server")]
     async fn new_address(
         &self,
@@ -240,7 +329,11 @@ impl DdkRpc for DdkNode {
         _request: Request<GetWalletTransactionsRequest>,
     ) -> Result<Response<GetWalletTransactionsResponse>, Status> {
         tracing::info!("Request for all wallet transactions.");
-        let wallet_transactions = self.node.wallet.get_transactions().unwrap();
+        let wallet_transactions = self
+            .node
+            .wallet
+            .transaction_history()
+            .map_err(|e| Status::new(Code::Internal, e.to_string()))?;
         let transactions: Vec<Vec<u8>> = wallet_transactions
             .iter()
             .map(|t| serde_json::to_vec(&t).unwrap())
@@ -270,16 +363,16 @@ impl DdkRpc for DdkNode {
         _request: Request<ListPeersRequest>,
     ) -> Result<Response<ListPeersResponse>, Status> {
         tracing::info!("List peers request");
-        let peers = self.node.transport.peer_manager.list_peers();
-        let peers = peers
-            .iter()
-            .map(|peer| {
-                let host = match &peer.socket_address {
-                    Some(h) => h.to_string(),
-                    None => "".to_string(),
-                };
-                let pubkey = peer.counterparty_node_id.to_string();
-                Peer { pubkey, host }
+        let peers = self
+            .node
+            .list_peers()
+            .await
+            .map_err(|e| Status::new(Code::Internal, e.to_string()))?
+            .into_iter()
+            .map(|status| Peer {
+                pubkey: status.peer.pubkey,
+                host: status.peer.host,
+                connected: status.connected,
             })
             .collect::<Vec<Peer>>();
 
@@ -293,7 +386,10 @@ impl DdkRpc for DdkNode {
     ) -> Result<Response<ConnectResponse>, Status> {
         let ConnectRequest { pubkey, host } = request.into_inner();
         let pubkey = PublicKey::from_str(&pubkey).unwrap();
-        self.node.transport.connect_outbound(pubkey, &host).await;
+        self.node
+            .connect_peer(pubkey, &host)
+            .await
+            .map_err(|e| Status::new(Code::Unavailable, e.to_string()))?;
         Ok(Response::new(ConnectResponse {}))
     }
 
@@ -308,19 +404,23 @@ impl DdkRpc for DdkNode {
 
     async fn list_contracts(
         &self,
-        _request: Request<ListContractsRequest>,
+        request: Request<ListContractsRequest>,
     ) -> Result<Response<ListContractsResponse>, Status> {
-        let contracts = self
+        let query = contract_query(request.into_inner())
+            .map_err(|e| Status::new(Code::InvalidArgument, e.to_string()))?;
+        let page = self
             .node
             .storage
-            .get_contracts()
+            .query_contracts(&query)
             .map_err(|e| Status::new(Code::Cancelled, e.to_string()))?;
-        let contract_bytes: Vec<Vec<u8>> = contracts
+        let contract_bytes: Vec<Vec<u8>> = page
+            .contracts
             .iter()
             .map(|contract| serialize_contract(contract).unwrap())
             .collect();
         Ok(Response::new(ListContractsResponse {
             contracts: contract_bytes,
+            next_cursor: page.next.map(|c| c.to_string()).unwrap_or_default(),
         }))
     }
 
@@ -329,25 +429,65 @@ impl DdkRpc for DdkNode {
             address,
             amount,
             fee_rate,
+            recipients,
+            include_utxos,
+            exclude_utxos,
+            op_return,
+            change_address,
+            dry_run,
         } = request.into_inner();
-        let address = Address::from_str(&address).unwrap().assume_checked();
-        let amount = Amount::from_sat(amount);
+        let network = self.node.network();
+        let parse_address = |address: &str| {
+            Address::from_str(address)
+                .and_then(|a| a.require_network(network))
+                .map_err(|e| Status::new(Code::InvalidArgument, e.to_string()))
+        };
+        let parse_outpoints = |outpoints: Vec<String>| {
+            outpoints
+                .iter()
+                .map(|o| OutPoint::from_str(o))
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| Status::new(Code::InvalidArgument, e.to_string()))
+        };
         let fee_rate = match FeeRate::from_sat_per_vb(fee_rate) {
             Some(f) => f,
             None => return Err(Status::new(Code::InvalidArgument, "Invalid fee rate.")),
         };
-        let txn = self
+
+        let mut payment = SendBuilder::new(fee_rate)
+            .include_utxos(&parse_outpoints(include_utxos)?)
+            .exclude_utxos(&parse_outpoints(exclude_utxos)?);
+        if !address.is_empty() {
+            payment = payment.add_recipient(parse_address(&address)?, Amount::from_sat(amount));
+        }
+        for recipient in recipients {
+            payment = payment.add_recipient(
+                parse_address(&recipient.address)?,
+                Amount::from_sat(recipient.amount),
+            );
+        }
+        if !op_return.is_empty() {
+            payment = payment.op_return(op_return);
+        }
+        if !change_address.is_empty() {
+            payment = payment.change_address(parse_address(&change_address)?);
+        }
+        if dry_run {
+            payment = payment.dry_run();
+        }
+
+        let sent = self
             .node
             .wallet
-            .send_to_address(address, amount, fee_rate)
-            .await;
-        if let Ok(tx) = txn {
-            Ok(Response::new(SendResponse {
-                txid: tx.to_string(),
-            }))
-        } else {
-            Err(Status::new(Code::Internal, "Transaction sending failed."))
-        }
+            .send(payment)
+            .await
+            .map_err(|e| Status::new(Code::Internal, e.to_string()))?;
+        Ok(Response::new(SendResponse {
+            txid: sent.txid.to_string(),
+            psbt: sent.psbt.serialize(),
+            fee: sent.fee.to_sat(),
+            broadcast: sent.broadcast,
+        }))
     }
 
     async fn oracle_announcements(
@@ -378,3 +518,34 @@ impl DdkRpc for DdkNode {
         Ok(Response::new(WalletSyncResponse {}))
     }
 }
+
+fn contract_query(request: ListContractsRequest) -> anyhow::Result<ContractQuery> {
+    let mut query = ContractQuery::new().states(
+        request
+            .states
+            .iter()
+            .map(|state| ContractState::from_str(state))
+            .collect::<anyhow::Result<Vec<_>>>()?,
+    );
+    if !request.counterparty.is_empty() {
+        query = query.counterparty(PublicKey::from_str(&request.counterparty)?);
+    }
+    if !request.event_id.is_empty() {
+        query = query.event_id(request.event_id);
+    }
+    fn bound<T: Default + PartialEq>(value: T) -> Option<T> {
+        (value != T::default()).then_some(value)
+    }
+    query.maturity = RangeFilter::between(bound(request.maturity_from), bound(request.maturity_to));
+    query.created_at = RangeFilter::between(bound(request.created_from), bound(request.created_to));
+    if request.descending {
+        query = query.order(SortOrder::Descending);
+    }
+    if request.limit > 0 {
+        query = query.limit(request.limit as usize);
+    }
+    if !request.cursor.is_empty() {
+        query = query.after(ContractCursor::from_str(&request.cursor)?);
+    }
+    Ok(query)
+}
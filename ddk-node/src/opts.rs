@@ -37,6 +37,15 @@ pub struct NodeOpts {
     #[arg(default_value = "https://mutinynet.com/api")]
     #[arg(help = "Esplora server to connect to.")]
     pub esplora_host: String,
+    #[arg(long = "bitcoind")]
+    #[arg(help = "Bitcoin Core RPC server to connect to instead of esplora.")]
+    pub bitcoind_host: Option<String>,
+    #[arg(long = "bitcoind-user")]
+    #[arg(help = "RPC user of the Bitcoin Core server.")]
+    pub bitcoind_user: Option<String>,
+    #[arg(long = "bitcoind-pass")]
+    #[arg(help = "RPC password of the Bitcoin Core server.")]
+    pub bitcoind_pass: Option<String>,
     #[arg(long = "oracle")]
     #[arg(default_value = "https://kormir.dlcdevkit.com")]
     #[arg(help = "Kormir oracle to connect to.")]
@@ -46,4 +55,16 @@ pub struct NodeOpts {
     #[arg(default_value = "file")]
     #[arg(value_parser = ["file", "bytes"])]
     pub seed: String,
+    #[arg(long = "encrypt-storage")]
+    #[arg(
+        help = "Encrypt the storage with a key derived from the seed. The seed file is \
+                kept in the same storage directory, so this only protects a copy of the \
+                database made without it. Use --storage-passphrase-file for a key that is \
+                not stored next to the data."
+    )]
+    pub encrypt_storage: bool,
+    #[arg(long = "storage-passphrase-file")]
+    #[arg(help = "Encrypt the storage with a key derived from the passphrase in this file.")]
+    #[arg(conflicts_with = "encrypt_storage")]
+    pub storage_passphrase_file: Option<PathBuf>,
 }
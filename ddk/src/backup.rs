@@ -0,0 +1,554 @@
+//! Export and import of the full node state.
+//!
+//! A [`Backup`] holds everything a [`Storage`] keeps: contracts, channels, the wallet, secret
+//! keys and key indexes, peers, announcements, fee estimates, coin reservations and labels. It
+//! is written as a versioned archive with a checksum, optionally encrypted with a
+//! [`StorageSecret`]. Restoring a backup into another backend migrates the node, see [`migrate`].
+//!
+//! Archive layout:
+//!
+//! ```text
+//! magic (8) | version (u16) | flags (u8) | body | sha256 of everything before (32)
+//! ```
+//!
+//! The body is the JSON backup. Encrypted archives store the length prefixed
+//! [`EncryptionHeader`] before the body and seal the body with the preceding bytes as AAD.
+
+use crate::storage::encryption::{EncryptionError, EncryptionHeader, StorageSecret};
+use crate::storage::query::ContractQuery;
+use crate::transport::PeerInformation;
+use crate::util::{
+    deserialize_channel, deserialize_contract, serialize_channel, serialize_contract,
+};
+use crate::wallet::labels::Label;
+use crate::wallet::UtxoReservation;
+use crate::Storage;
+use bdk_chain::Merge;
+use bdk_wallet::ChangeSet;
+use bitcoin::hashes::{sha256, Hash};
+use bitcoin::secp256k1::SecretKey;
+use ddk_manager::chain_monitor::ChainMonitor;
+use ddk_manager::contract::ser::Serializable;
+use dlc_messages::oracle_msgs::OracleAnnouncement;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const MAGIC: &[u8; 8] = b"DDK\0BKUP";
+/// Version of the archive format.
+const VERSION: u16 = 1;
+const PLAIN: u8 = 0;
+const ENCRYPTED: u8 = 1;
+const PREAMBLE_LEN: usize = MAGIC.len() + 3;
+const CHECKSUM_LEN: usize = 32;
+
+/// Prefix and extension of the files written by a [`BackupSchedule`].
+const FILE_PREFIX: &str = "ddk-";
+const FILE_EXTENSION: &str = ".backup";
+/// Default time between scheduled backups.
+pub const DEFAULT_BACKUP_INTERVAL: Duration = Duration::from_secs(60 * 60);
+/// Default number of scheduled backups kept in the backup directory.
+pub const DEFAULT_BACKUPS_KEPT: usize = 24;
+
+#[derive(thiserror::Error, Debug)]
+pub enum BackupError {
+    #[error("Not a DDK backup.")]
+    NotABackup,
+    #[error("Unsupported backup version {0}.")]
+    UnsupportedVersion(u16),
+    #[error("The backup checksum does not match, the archive is corrupted.")]
+    Checksum,
+    #[error("The backup is encrypted, a secret is required to read it.")]
+    Encrypted,
+    #[error("Backup encryption: {0}")]
+    Encryption(#[from] EncryptionError),
+    #[error("Malformed backup: {0}")]
+    Malformed(#[from] serde_json::Error),
+    #[error("Could not serialize the backup: {0}")]
+    Serialize(serde_json::Error),
+}
+
+/// Snapshot of everything a [`Storage`] keeps.
+///
+/// Contracts and channels are stored in their storage serialization, hex encoded, in the order
+/// they were created. Restored contracts are created at the time of the restore, their creation
+/// order is kept.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Backup {
+    /// Unix time the backup was taken.
+    pub created_at: u64,
+    contracts: Vec<String>,
+    channels: Vec<String>,
+    chain_monitor: Option<String>,
+    wallet: ChangeSet,
+    secret_keys: HashMap<String, SecretKey>,
+    key_indexes: HashMap<u32, u32>,
+    peers: Vec<PeerInformation>,
+    announcements: Vec<OracleAnnouncement>,
+    fee_estimates: Option<HashMap<u16, f64>>,
+    utxo_reservations: Vec<UtxoReservation>,
+    labels: Vec<Label>,
+}
+
+impl Backup {
+    /// Read the full state of the storage.
+    pub fn read<S: Storage>(storage: &S) -> anyhow::Result<Backup> {
+        let mut contracts = Vec::new();
+        let mut query = ContractQuery::new();
+        loop {
+            let page = storage.query_contracts(&query)?;
+            for contract in &page.contracts {
+                contracts.push(hex::encode(serialize_contract(contract)?));
+            }
+            match page.next {
+                Some(next) => query = query.after(next),
+                None => break,
+            }
+        }
+        let channels = storage
+            .list_channels()?
+            .iter()
+            .map(|channel| Ok(hex::encode(serialize_channel(channel)?)))
+            .collect::<anyhow::Result<_>>()?;
+        let chain_monitor = storage
+            .get_chain_monitor()?
+            .map(|monitor| monitor.serialize().map(hex::encode))
+            .transpose()?;
+        let secret_keys = storage
+            .list_secret_keys()?
+            .into_iter()
+            .map(|(key_id, secret_key)| (hex::encode(key_id), secret_key))
+            .collect();
+
+        Ok(Backup {
+            created_at: now().as_secs(),
+            contracts,
+            channels,
+            chain_monitor,
+            wallet: storage.initialize_bdk()?,
+            secret_keys,
+            key_indexes: storage.key_indexes()?,
+            peers: storage.list_peers()?,
+            announcements: storage.get_marketplace_announcements()?,
+            fee_estimates: storage.get_fee_estimates()?,
+            utxo_reservations: storage.list_utxo_reservations()?,
+            labels: storage.list_labels()?,
+        })
+    }
+
+    /// Write the backup into the storage, meant to be an empty one. Stored data with the same
+    /// ids is replaced, key indexes are only ever raised.
+    pub fn restore<S: Storage>(&self, storage: &S) -> anyhow::Result<()> {
+        for (account, next) in &self.key_indexes {
+            storage.advance_key_index(*account, *next)?;
+        }
+        for (key_id, secret_key) in &self.secret_keys {
+            let key_id = <[u8; 32]>::try_from(hex::decode(key_id)?)
+                .map_err(|_| anyhow::anyhow!("Malformed key id {}.", key_id))?;
+            storage.store_secret_key(key_id, *secret_key)?;
+        }
+        if !self.wallet.is_empty() {
+            storage.persist_bdk(&self.wallet)?;
+        }
+        for contract in &self.contracts {
+            storage.update_contract(&deserialize_contract(&hex::decode(contract)?)?)?;
+        }
+        for channel in &self.channels {
+            storage.upsert_channel(deserialize_channel(&hex::decode(channel)?)?, None)?;
+        }
+        if let Some(chain_monitor) = &self.chain_monitor {
+            let chain_monitor = ChainMonitor::deserialize(&mut lightning::io::Cursor::new(
+                hex::decode(chain_monitor)?,
+            ))
+            .map_err(|e| anyhow::anyhow!("Malformed chain monitor: {:?}", e))?;
+            storage.persist_chain_monitor(&chain_monitor)?;
+        }
+        for peer in &self.peers {
+            storage.save_peer(peer.clone())?;
+        }
+        for announcement in &self.announcements {
+            storage.save_announcement(announcement.clone())?;
+        }
+        if let Some(fee_estimates) = &self.fee_estimates {
+            storage.persist_fee_estimates(fee_estimates)?;
+        }
+        storage.reserve_utxos(&self.utxo_reservations)?;
+        storage.save_labels(&self.labels)?;
+        Ok(())
+    }
+
+    /// Number of contracts in the backup.
+    pub fn contract_count(&self) -> usize {
+        self.contracts.len()
+    }
+
+    /// Write the backup as an archive, encrypted if a secret is given.
+    pub fn to_archive(&self, secret: Option<&StorageSecret>) -> Result<Vec<u8>, BackupError> {
+        let body = serde_json::to_vec(self).map_err(BackupError::Serialize)?;
+        let mut archive = Vec::with_capacity(PREAMBLE_LEN + body.len() + CHECKSUM_LEN);
+        archive.extend_from_slice(MAGIC);
+        archive.extend_from_slice(&VERSION.to_be_bytes());
+        match secret {
+            Some(secret) => {
+                let (header, cipher) = EncryptionHeader::new(secret);
+                let header = serde_json::to_vec(&header).map_err(BackupError::Serialize)?;
+                archive.push(ENCRYPTED);
+                archive.extend_from_slice(&(header.len() as u32).to_be_bytes());
+                archive.extend_from_slice(&header);
+                let sealed = cipher.seal(&archive, &body);
+                archive.extend_from_slice(&sealed);
+            }
+            None => {
+                archive.push(PLAIN);
+                archive.extend_from_slice(&body);
+            }
+        }
+        let checksum = sha256::Hash::hash(&archive);
+        archive.extend_from_slice(checksum.as_byte_array());
+        Ok(archive)
+    }
+
+    /// Read an archive written by [`Backup::to_archive`]. Encrypted archives need the secret
+    /// they were written with.
+    pub fn from_archive(
+        archive: &[u8],
+        secret: Option<&StorageSecret>,
+    ) -> Result<Backup, BackupError> {
+        if archive.len() < PREAMBLE_LEN + CHECKSUM_LEN || !archive.starts_with(MAGIC) {
+            return Err(BackupError::NotABackup);
+        }
+        let version = u16::from_be_bytes([archive[MAGIC.len()], archive[MAGIC.len() + 1]]);
+        if version != VERSION {
+            return Err(BackupError::UnsupportedVersion(version));
+        }
+        let (content, checksum) = archive.split_at(archive.len() - CHECKSUM_LEN);
+        if sha256::Hash::hash(content).as_byte_array()[..] != checksum[..] {
+            return Err(BackupError::Checksum);
+        }
+
+        let body = match content[PREAMBLE_LEN - 1] {
+            PLAIN => content[PREAMBLE_LEN..].to_vec(),
+            ENCRYPTED => {
+                let secret = secret.ok_or(BackupError::Encrypted)?;
+                let (length, rest) = content[PREAMBLE_LEN..]
+                    .split_first_chunk::<4>()
+                    .ok_or(BackupError::NotABackup)?;
+                let length = u32::from_be_bytes(*length) as usize;
+                if rest.len() < length {
+                    return Err(BackupError::NotABackup);
+                }
+                let header: EncryptionHeader = serde_json::from_slice(&rest[..length])?;
+                let cipher = header.unlock(secret)?;
+                let (aad, sealed) = content.split_at(PREAMBLE_LEN + 4 + length);
+                cipher.open(aad, sealed)?
+            }
+            _ => return Err(BackupError::NotABackup),
+        };
+        Ok(serde_json::from_slice(&body)?)
+    }
+}
+
+/// Archive of the full state of the storage, encrypted if a secret is given.
+pub fn export_backup<S: Storage>(
+    storage: &S,
+    secret: Option<&StorageSecret>,
+) -> anyhow::Result<Vec<u8>> {
+    Ok(Backup::read(storage)?.to_archive(secret)?)
+}
+
+/// Restore an archive written by [`export_backup`] into the storage.
+pub fn import_backup<S: Storage>(
+    storage: &S,
+    archive: &[u8],
+    secret: Option<&StorageSecret>,
+) -> anyhow::Result<Backup> {
+    let backup = Backup::from_archive(archive, secret)?;
+    backup.restore(storage)?;
+    Ok(backup)
+}
+
+/// Copy the full state of one storage into another, for example from sled to a SQL database.
+/// The node must be stopped while migrating.
+pub fn migrate<F: Storage, T: Storage>(from: &F, to: &T) -> anyhow::Result<Backup> {
+    let backup = Backup::read(from)?;
+    backup.restore(to)?;
+    Ok(backup)
+}
+
+/// Periodic backups of a running node to a directory, see
+/// [`crate::builder::Builder::set_backup_schedule`].
+#[derive(Clone, Debug)]
+pub struct BackupSchedule {
+    pub directory: PathBuf,
+    /// Time between backups. The first backup is written when the node starts.
+    pub interval: Duration,
+    /// Number of backups kept, older ones are deleted. Zero keeps every backup.
+    pub keep: usize,
+    /// Encrypt the backups with this secret.
+    pub secret: Option<StorageSecret>,
+}
+
+impl BackupSchedule {
+    /// Backups to `directory` encrypted with `secret` every hour, keeping the last day.
+    pub fn new(directory: impl Into<PathBuf>, secret: StorageSecret) -> BackupSchedule {
+        BackupSchedule {
+            secret: Some(secret),
+            ..BackupSchedule::unencrypted(directory)
+        }
+    }
+
+    /// Unencrypted backups to `directory` every hour, keeping the last day. The backups hold
+    /// the wallet and contract keys in plaintext.
+    pub fn unencrypted(directory: impl Into<PathBuf>) -> BackupSchedule {
+        BackupSchedule {
+            directory: directory.into(),
+            interval: DEFAULT_BACKUP_INTERVAL,
+            keep: DEFAULT_BACKUPS_KEPT,
+            secret: None,
+        }
+    }
+
+    /// Write a backup of the storage to the directory and delete the backups beyond
+    /// [`BackupSchedule::keep`]. Returns the path of the new backup.
+    pub fn write_backup<S: Storage>(&self, storage: &S) -> anyhow::Result<PathBuf> {
+        let archive = export_backup(storage, self.secret.as_ref())?;
+        std::fs::create_dir_all(&self.directory)?;
+        // Millisecond timestamps padded to a fixed width sort in the order they were written.
+        let name = format!("{}{:020}{}", FILE_PREFIX, now().as_millis(), FILE_EXTENSION);
+        let path = self.directory.join(name);
+        let partial = path.with_extension("partial");
+        std::fs::write(&partial, archive)?;
+        std::fs::rename(&partial, &path)?;
+        self.prune()?;
+        Ok(path)
+    }
+
+    /// Backups in the directory, oldest first.
+    pub fn backups(&self) -> anyhow::Result<Vec<PathBuf>> {
+        let mut backups = Vec::new();
+        for entry in std::fs::read_dir(&self.directory)? {
+            let path = entry?.path();
+            if is_backup_file(&path) {
+                backups.push(path);
+            }
+        }
+        backups.sort();
+        Ok(backups)
+    }
+
+    fn prune(&self) -> anyhow::Result<()> {
+        if self.keep == 0 {
+            return Ok(());
+        }
+        let backups = self.backups()?;
+        let excess = backups.len().saturating_sub(self.keep);
+        for path in &backups[..excess] {
+            std::fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+}
+
+fn is_backup_file(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name.starts_with(FILE_PREFIX) && name.ends_with(FILE_EXTENSION))
+}
+
+fn now() -> Duration {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::memory::MemoryStorage;
+    use crate::wallet::labels::LabelType;
+    use crate::KeyStorage;
+    use ddk_manager::channel::Channel;
+    use ddk_manager::contract::Contract;
+    use ddk_manager::Storage as ManagerStorage;
+
+    fn deserialize_object<T: Serializable>(serialized: &[u8]) -> T {
+        T::deserialize(&mut lightning::io::Cursor::new(serialized)).unwrap()
+    }
+
+    fn populated_storage() -> MemoryStorage {
+        let storage = MemoryStorage::new();
+        storage
+            .create_contract(&deserialize_object(include_bytes!(
+                "../tests/data/dlc_storage/Offered"
+            )))
+            .unwrap();
+        storage
+            .update_contract(&Contract::Confirmed(deserialize_object(include_bytes!(
+                "../tests/data/dlc_storage/Confirmed"
+            ))))
+            .unwrap();
+        storage
+            .update_contract(&Contract::PreClosed(deserialize_object(include_bytes!(
+                "../tests/data/dlc_storage/PreClosed"
+            ))))
+            .unwrap();
+        storage
+            .upsert_channel(
+                Channel::Signed(deserialize_object(include_bytes!(
+                    "../tests/data/dlc_storage/SignedChannelEstablished"
+                ))),
+                None,
+            )
+            .unwrap();
+
+        storage
+            .store_secret_key([1; 32], SecretKey::from_slice(&[7; 32]).unwrap())
+            .unwrap();
+        for _ in 0..3 {
+            storage.next_key_index(0).unwrap();
+        }
+        storage.next_key_index(5).unwrap();
+        storage
+            .save_labels(&[Label {
+                label_type: LabelType::Tx,
+                reference: "f91d0a8a78462bc59398f2c5d7a84fcff491c26ba54c4833478b202796c8aafd"
+                    .to_string(),
+                label: Some("funding".to_string()),
+                origin: None,
+                spendable: None,
+            }])
+            .unwrap();
+        let mut estimates = HashMap::new();
+        estimates.insert(6, 2.5);
+        storage.persist_fee_estimates(&estimates).unwrap();
+        storage
+    }
+
+    fn contract_ids<S: Storage>(storage: &S) -> Vec<[u8; 32]> {
+        let mut ids = storage
+            .get_contracts()
+            .unwrap()
+            .iter()
+            .map(|c| c.get_id())
+            .collect::<Vec<_>>();
+        ids.sort();
+        ids
+    }
+
+    fn assert_same_state<A: Storage, B: Storage>(a: &A, b: &B) {
+        assert_eq!(contract_ids(a), contract_ids(b));
+        assert_eq!(
+            a.list_channels().unwrap().len(),
+            b.list_channels().unwrap().len()
+        );
+        assert_eq!(a.list_secret_keys().unwrap(), b.list_secret_keys().unwrap());
+        assert_eq!(a.key_indexes().unwrap(), b.key_indexes().unwrap());
+        assert_eq!(a.list_labels().unwrap(), b.list_labels().unwrap());
+        assert_eq!(
+            a.get_fee_estimates().unwrap(),
+            b.get_fee_estimates().unwrap()
+        );
+    }
+
+    #[test]
+    fn archives_round_trip_plain_and_encrypted() {
+        let storage = populated_storage();
+        let secret = StorageSecret::Passphrase("correct horse".to_string());
+
+        for secret in [None, Some(&secret)] {
+            let archive = export_backup(&storage, secret).unwrap();
+            let restored = MemoryStorage::new();
+            let backup = import_backup(&restored, &archive, secret).unwrap();
+            assert_eq!(backup.contract_count(), 3);
+            assert_same_state(&storage, &restored);
+            assert_eq!(restored.next_key_index(0).unwrap(), 3);
+        }
+    }
+
+    #[test]
+    fn damaged_archives_are_rejected() {
+        let storage = populated_storage();
+        let secret = StorageSecret::Seed(vec![3; 64]);
+        let archive = export_backup(&storage, Some(&secret)).unwrap();
+
+        let mut corrupted = archive.clone();
+        corrupted[archive.len() / 2] ^= 1;
+        assert!(matches!(
+            Backup::from_archive(&corrupted, Some(&secret)),
+            Err(BackupError::Checksum)
+        ));
+        assert!(matches!(
+            Backup::from_archive(&archive, None),
+            Err(BackupError::Encrypted)
+        ));
+        assert!(matches!(
+            Backup::from_archive(&archive, Some(&StorageSecret::Seed(vec![4; 64]))),
+            Err(BackupError::Encryption(EncryptionError::WrongSecret))
+        ));
+        assert!(matches!(
+            Backup::from_archive(b"not a backup", None),
+            Err(BackupError::NotABackup)
+        ));
+
+        let mut future = archive.clone();
+        future[MAGIC.len()..MAGIC.len() + 2].copy_from_slice(&2u16.to_be_bytes());
+        assert!(matches!(
+            Backup::from_archive(&future, Some(&secret)),
+            Err(BackupError::UnsupportedVersion(2))
+        ));
+    }
+
+    #[test]
+    fn schedule_keeps_the_latest_backups() {
+        let directory =
+            std::env::temp_dir().join(format!("ddk-backup-schedule-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+        let storage = populated_storage();
+        let secret = StorageSecret::Seed(vec![3; 64]);
+        let mut schedule = BackupSchedule::new(&directory, secret.clone());
+        schedule.keep = 2;
+
+        let mut written = Vec::new();
+        for _ in 0..3 {
+            written.push(schedule.write_backup(&storage).unwrap());
+            std::thread::sleep(Duration::from_millis(2));
+        }
+        assert_eq!(schedule.backups().unwrap(), written[1..]);
+
+        let archive = std::fs::read(&written[2]).unwrap();
+        let restored = MemoryStorage::new();
+        assert!(import_backup(&restored, &archive, None).is_err());
+        import_backup(&restored, &archive, Some(&secret)).unwrap();
+        assert_same_state(&storage, &restored);
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[cfg(all(feature = "sled", feature = "sqlite"))]
+    #[test]
+    fn migrates_between_backends() {
+        use crate::storage::sled::SledStorage;
+        use crate::storage::sql::SqlStorage;
+
+        let directory =
+            std::env::temp_dir().join(format!("ddk-backup-migrate-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+        std::fs::create_dir_all(&directory).unwrap();
+        let memory = populated_storage();
+        {
+            let sled = SledStorage::new(directory.join("sled").to_str().unwrap()).unwrap();
+            let sql = SqlStorage::sqlite(directory.join("ddk.sqlite")).unwrap();
+            migrate(&memory, &sled).unwrap();
+            migrate(&sled, &sql).unwrap();
+            let migrated = MemoryStorage::new();
+            migrate(&sql, &migrated).unwrap();
+
+            assert_same_state(&memory, &sled);
+            assert_same_state(&memory, &sql);
+            assert_same_state(&memory, &migrated);
+            assert_eq!(sql.next_key_index(0).unwrap(), 3);
+            assert_eq!(sled.next_key_index(5).unwrap(), 1);
+        }
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+}
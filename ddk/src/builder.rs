@@ -1,15 +1,23 @@
-use bitcoin::Network;
-use crossbeam::channel::unbounded;
+use bitcoin::{Network, XOnlyPublicKey};
+use ddk_manager::event::EventHandler;
 use ddk_manager::manager::Manager;
 use ddk_manager::SystemTimeProvider;
 use std::collections::HashMap;
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Mutex, RwLock};
 
-use crate::chain::EsploraClient;
-use crate::ddk::{DlcDevKit, DlcManagerMessage};
-use crate::wallet::DlcDevKitWallet;
+#[cfg(feature = "backup")]
+use crate::backup::BackupSchedule;
+use crate::chain::{ChainSource, FeeEstimates, FeeSource};
+use crate::config::DdkConfig;
+use crate::ddk::{DlcDevKit, DlcManagerMessage, ManagerHealthTracker};
+use crate::event::{EventForwarder, EventHandlers, EVENT_CHANNEL_CAPACITY};
+use crate::recovery::{EmergencyRecorder, EmergencyRecords};
+use crate::signer::ExternalSigner;
+use crate::transport::PeerConnections;
+use crate::wallet::{DlcDevKitWallet, PsbtSigner, WalletConfig};
 use crate::{Oracle, Storage, Transport};
 use thiserror::Error;
+use tokio::sync::{broadcast, mpsc};
 
 const DEFAULT_ESPLORA_HOST: &str = "https://mutinynet.com/api";
 const DEFAULT_NETWORK: Network = Network::Signet;
@@ -21,9 +29,18 @@ pub struct Builder<T, S, O> {
     transport: Option<Arc<T>>,
     storage: Option<Arc<S>>,
     oracle: Option<Arc<O>>,
-    esplora_host: String,
+    additional_oracles: Vec<Arc<O>>,
+    chain_source: ChainSource,
     network: Network,
     seed_bytes: [u8; 32],
+    wallet_config: WalletConfig,
+    psbt_signer: Option<Arc<dyn PsbtSigner>>,
+    external_signer: Option<Arc<dyn ExternalSigner>>,
+    fee_source: Option<Arc<dyn FeeSource>>,
+    config: DdkConfig,
+    #[cfg(feature = "backup")]
+    backup_schedule: Option<BackupSchedule>,
+    emergency_records: Option<EmergencyRecords>,
 }
 
 /// An error that could be thrown while building [`crate::ddk::DlcDevKit`]
@@ -35,11 +52,15 @@ pub enum BuilderError {
     NoStorage,
     #[error("An oracle client was not provided.")]
     NoOracle,
+    #[error("Oracle {0} was added more than once.")]
+    DuplicateOracle(XOnlyPublicKey),
+    #[error("Invalid config: {0}")]
+    InvalidConfig(&'static str),
 }
 /// Defaults when creating a DDK application
 /// Transport, storage, and oracle is set to none.
 ///
-/// esplora_host: <https://mutinynet.com/api>
+/// chain_source: esplora at <https://mutinynet.com/api>
 /// network: Network::Signet
 impl<T: Transport, S: Storage, O: Oracle> Default for Builder<T, S, O> {
     fn default() -> Self {
@@ -48,9 +69,18 @@ impl<T: Transport, S: Storage, O: Oracle> Default for Builder<T, S, O> {
             transport: None,
             storage: None,
             oracle: None,
-            esplora_host: DEFAULT_ESPLORA_HOST.to_string(),
+            additional_oracles: Vec::new(),
+            chain_source: ChainSource::esplora(DEFAULT_ESPLORA_HOST),
             network: DEFAULT_NETWORK,
             seed_bytes: [0u8; 32],
+            wallet_config: WalletConfig::default(),
+            psbt_signer: None,
+            external_signer: None,
+            fee_source: None,
+            config: DdkConfig::default(),
+            #[cfg(feature = "backup")]
+            backup_schedule: None,
+            emergency_records: None,
         }
     }
 }
@@ -92,10 +122,29 @@ impl<T: Transport, S: Storage, O: Oracle> Builder<T, S, O> {
         self
     }
 
+    /// Add an oracle next to the one set with [`Builder::set_oracle`]. Contracts can use
+    /// any of the oracles, multi-oracle contracts need every oracle of their
+    /// [`ddk_manager::contract::contract_input::OracleInput`]. Use
+    /// [`crate::oracle::DynOracle`] to mix oracle implementations.
+    pub fn add_oracle(&mut self, oracle: Arc<O>) -> &mut Self {
+        self.additional_oracles.push(oracle);
+        self
+    }
+
+    /// Set where the wallet and the DLC manager read the chain from. Defaults to the
+    /// mutinynet esplora server.
+    pub fn set_chain_source(&mut self, chain_source: ChainSource) -> &mut Self {
+        self.chain_source = chain_source;
+        self
+    }
+
     /// Set the esplora server to connect to.
+    #[deprecated(
+        since = "0.0.15",
+        note = "use `set_chain_source(ChainSource::esplora(host))`"
+    )]
     pub fn set_esplora_host(&mut self, host: String) -> &mut Self {
-        self.esplora_host = host;
-        self
+        self.set_chain_source(ChainSource::esplora(&host))
     }
 
     /// Set the network DDK connects to.
@@ -110,14 +159,76 @@ impl<T: Transport, S: Storage, O: Oracle> Builder<T, S, O> {
         self
     }
 
+    /// Set the descriptors of the on-chain wallet. Defaults to BIP84 descriptors derived
+    /// from the seed bytes.
+    pub fn set_wallet_config(&mut self, config: WalletConfig) -> &mut Self {
+        self.wallet_config = config;
+        self
+    }
+
+    /// Sign wallet inputs with an external signer. Required for watch-only
+    /// [`WalletConfig::Descriptors`].
+    pub fn set_psbt_signer(&mut self, signer: Arc<dyn PsbtSigner>) -> &mut Self {
+        self.psbt_signer = Some(signer);
+        self
+    }
+
+    /// Sign funding transactions, CETs and refund transactions with keys kept outside
+    /// of DDK. Replaces the signer set with [`Builder::set_psbt_signer`].
+    pub fn set_external_signer(&mut self, signer: Arc<dyn ExternalSigner>) -> &mut Self {
+        self.external_signer = Some(signer);
+        self
+    }
+
+    /// Set where fee rate estimates are fetched from. Defaults to the chain source.
+    pub fn set_fee_source(&mut self, fee_source: Arc<dyn FeeSource>) -> &mut Self {
+        self.fee_source = Some(fee_source);
+        self
+    }
+
+    /// Set the intervals of the background tasks and the confirmation depth, refund delay
+    /// and peer timeout of the DLC manager. Defaults to [`DdkConfig::default`].
+    ///
+    /// [`Builder::finish`] fails if the config does not pass [`DdkConfig::validate`].
+    pub fn set_config(&mut self, config: DdkConfig) -> &mut Self {
+        self.config = config;
+        self
+    }
+
+    /// Write a backup of the storage to a directory at an interval while DDK runs.
+    /// [`Builder::finish`] fails if the interval is zero.
+    #[cfg(feature = "backup")]
+    pub fn set_backup_schedule(&mut self, schedule: BackupSchedule) -> &mut Self {
+        self.backup_schedule = Some(schedule);
+        self
+    }
+
+    /// Keep an emergency record with the signed refund transaction of every signed contract,
+    /// see [`crate::recovery`]. Records of contracts signed earlier are written when DDK is
+    /// built.
+    pub fn set_emergency_records(&mut self, records: EmergencyRecords) -> &mut Self {
+        self.emergency_records = Some(records);
+        self
+    }
+
     /// Builds the `DlcDevKit` instance. Fails if any components are missing.
     pub async fn finish(&self) -> anyhow::Result<DlcDevKit<T, S, O>> {
         tracing::info!(
             network = self.network.to_string(),
-            esplora = self.esplora_host,
+            chain_source = ?self.chain_source,
             "Building DDK."
         );
 
+        self.config.validate()?;
+        #[cfg(feature = "backup")]
+        if self
+            .backup_schedule
+            .as_ref()
+            .is_some_and(|schedule| schedule.interval.is_zero())
+        {
+            return Err(BuilderError::InvalidConfig("backup interval must not be zero").into());
+        }
+
         let transport = self
             .transport
             .as_ref()
@@ -133,53 +244,114 @@ impl<T: Transport, S: Storage, O: Oracle> Builder<T, S, O> {
             .as_ref()
             .map_or_else(|| Err(BuilderError::NoOracle), |o| Ok(o.clone()))?;
 
+        let all_oracles = std::iter::once(oracle.clone())
+            .chain(self.additional_oracles.iter().cloned())
+            .collect::<Vec<_>>();
+        let mut oracles = HashMap::new();
+        for oracle in &all_oracles {
+            if oracles
+                .insert(oracle.get_public_key(), oracle.clone())
+                .is_some()
+            {
+                return Err(BuilderError::DuplicateOracle(oracle.get_public_key()).into());
+            }
+        }
+
         let name = self
             .name
             .clone()
             .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
 
-        let wallet = Arc::new(DlcDevKitWallet::new(
+        let wallet = DlcDevKitWallet::new_with_config(
             &name,
+            &self.wallet_config,
             &self.seed_bytes,
-            &self.esplora_host,
+            &self.chain_source,
             self.network,
             storage.clone(),
-        )?);
+        )?;
+        let chain_client = wallet.chain();
 
-        let mut oracles = HashMap::new();
-        oracles.insert(oracle.get_public_key(), oracle.clone());
+        let fee_source = self
+            .fee_source
+            .clone()
+            .unwrap_or_else(|| chain_client.clone());
+        let fee_estimates = FeeEstimates::new(fee_source, storage.clone())
+            .with_limits(self.config.fee_floor, self.config.fee_ceiling)
+            .with_refresh_interval(self.config.fee_refresh_interval);
 
-        let esplora_client = Arc::new(EsploraClient::new(&self.esplora_host, self.network)?);
+        let mut wallet = wallet
+            .with_fee_estimates(fee_estimates)
+            .with_reservation_timeout(self.config.utxo_reservation_timeout)
+            .with_coin_selection(self.config.coin_selection);
+        if let Some(signer) = &self.psbt_signer {
+            wallet = wallet.with_psbt_signer(signer.clone());
+        }
+        if let Some(signer) = &self.external_signer {
+            wallet = wallet.with_external_signer(signer.clone());
+        }
+        let wallet = Arc::new(wallet);
 
-        let (sender, receiver) = unbounded::<DlcManagerMessage>();
+        let (sender, receiver) = mpsc::unbounded_channel::<DlcManagerMessage>();
         let (stop_signal_sender, stop_signal) = tokio::sync::watch::channel(false);
 
-        let manager = Arc::new(
-            Manager::new(
-                wallet.clone(),
-                wallet.clone(),
-                esplora_client.clone(),
-                storage.clone(),
-                oracles,
-                Arc::new(SystemTimeProvider {}),
-                wallet.clone(),
-            )
-            .await?,
-        );
+        let (events, _) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
+
+        let mut manager = Manager::new(
+            wallet.clone(),
+            wallet.clone(),
+            chain_client.clone(),
+            storage.clone(),
+            oracles,
+            Arc::new(SystemTimeProvider {}),
+            wallet.clone(),
+            self.config.manager,
+        )
+        .await?;
+        let mut handlers: Vec<Arc<dyn EventHandler>> = Vec::new();
+        if let Some(records) = &self.emergency_records {
+            // The records outlive the storage, their keys must not be handed out again.
+            wallet.recover_contract_key_index(records.list()?.iter().map(|r| r.key_id))?;
+            records.sync(storage.as_ref())?;
+            // Records are written before subscribers learn about the contract.
+            handlers.push(Arc::new(EmergencyRecorder {
+                storage: storage.clone(),
+                records: records.clone(),
+                events: events.clone(),
+            }));
+        }
+        handlers.push(Arc::new(EventForwarder(events.clone())));
+        manager.set_event_handler(Arc::new(EventHandlers(handlers)));
+        let manager = Arc::new(manager);
         tracing::info!("Created ddk dlc manager.");
 
+        let peers = Arc::new(PeerConnections::new(
+            transport.clone(),
+            storage.clone(),
+            events.clone(),
+        ));
+
         Ok(DlcDevKit {
             runtime: Arc::new(RwLock::new(None)),
             wallet,
             manager,
             sender: Arc::new(sender),
-            receiver: Arc::new(receiver),
+            receiver: Arc::new(tokio::sync::Mutex::new(receiver)),
             transport,
             storage,
             oracle,
+            oracles: all_oracles,
             network: self.network,
             stop_signal,
             stop_signal_sender,
+            events,
+            peers,
+            tasks: Arc::new(Mutex::new(Vec::new())),
+            health: Arc::new(ManagerHealthTracker::default()),
+            config: self.config,
+            #[cfg(feature = "backup")]
+            backup_schedule: self.backup_schedule.clone(),
+            emergency_records: self.emergency_records.clone(),
         })
     }
 }
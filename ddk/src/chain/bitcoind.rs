@@ -0,0 +1,182 @@
+use super::fees::{fallback_fee_rate, target_blocks};
+use super::FeeSource;
+use crate::error::bitcoind_err_to_manager_err;
+use bdk_esplora::esplora_client::convert_fee_rate;
+use bitcoin::{Block, Network, Transaction, Txid};
+use bitcoincore_rpc::jsonrpc::error::Error as JsonRpcError;
+use bitcoincore_rpc::{Auth, Client, Error as RpcError, RpcApi};
+use ddk_manager::error::Error as ManagerError;
+use lightning::chain::chaininterface::{
+    ConfirmationTarget, FeeEstimator, FEERATE_FLOOR_SATS_PER_KW,
+};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+/// Confirmation targets in blocks that fee estimates are requested for.
+const FEE_ESTIMATE_TARGETS: [u16; 6] = [1, 3, 6, 12, 144, 1008];
+/// `RPC_INVALID_ADDRESS_OR_KEY`, returned for transactions bitcoind doesn't know.
+const RPC_NOT_FOUND: i32 = -5;
+
+/// Bitcoin Core RPC client for getting chain information.
+///
+/// Used by rust-dlc for getting transactions related to DLC contracts.
+/// Used by bdk to sync the wallet block by block. Looking up transactions that are not
+/// in the mempool or the wallet needs `txindex=1`.
+#[derive(Debug)]
+pub struct BitcoindClient {
+    pub client: Arc<Client>,
+    network: Network,
+    fee_estimates: RwLock<HashMap<u16, f64>>,
+}
+
+impl BitcoindClient {
+    pub fn new(url: &str, auth: Auth, network: Network) -> anyhow::Result<BitcoindClient> {
+        let client = Client::new(url, auth)?;
+        Ok(BitcoindClient {
+            client: Arc::new(client),
+            network,
+            fee_estimates: RwLock::new(HashMap::new()),
+        })
+    }
+
+    /// Run a blocking RPC call on the blocking thread pool.
+    pub(crate) async fn call<T, F>(&self, call: F) -> Result<T, RpcError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Client) -> Result<T, RpcError> + Send + 'static,
+    {
+        let client = self.client.clone();
+        tokio::task::spawn_blocking(move || call(&client))
+            .await
+            .map_err(|e| RpcError::ReturnedError(e.to_string()))?
+    }
+
+    /// The transaction with `txid`, `None` if bitcoind doesn't know it.
+    pub(crate) async fn get_tx(&self, txid: Txid) -> Result<Option<Transaction>, RpcError> {
+        match self
+            .call(move |client| client.get_raw_transaction(&txid, None))
+            .await
+        {
+            Ok(tx) => Ok(Some(tx)),
+            Err(e) if is_not_found(&e) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+fn is_not_found(e: &RpcError) -> bool {
+    matches!(e, RpcError::JsonRpc(JsonRpcError::Rpc(e)) if e.code == RPC_NOT_FOUND)
+}
+
+/// Implements the `ddk_manager::Blockchain` interface. Grabs chain related information
+/// regarding DLC transactions.
+#[async_trait::async_trait]
+impl ddk_manager::Blockchain for BitcoindClient {
+    fn get_network(&self) -> Result<Network, ManagerError> {
+        Ok(self.network)
+    }
+
+    async fn get_transaction(&self, tx_id: &Txid) -> Result<Transaction, ManagerError> {
+        tracing::info!(txid = tx_id.to_string(), "Querying for transaction.");
+        let txid = *tx_id;
+        self.call(move |client| client.get_raw_transaction(&txid, None))
+            .await
+            .map_err(bitcoind_err_to_manager_err)
+    }
+
+    async fn send_transaction(&self, transaction: &Transaction) -> Result<(), ManagerError> {
+        let txid = transaction.compute_txid();
+        tracing::info!(
+            txid = txid.to_string(),
+            num_inputs = transaction.input.len(),
+            num_outputs = transaction.output.len(),
+            "Broadcasting transaction."
+        );
+
+        if let Ok(info) = self
+            .call(move |client| client.get_raw_transaction_info(&txid, None))
+            .await
+        {
+            tracing::warn!(txid = txid.to_string(), "Transaction already submitted",);
+            if info.confirmations.is_some_and(|c| c > 0) {
+                return Ok(());
+            }
+        }
+
+        let transaction = transaction.clone();
+        if let Err(e) = self
+            .call(move |client| client.send_raw_transaction(&transaction))
+            .await
+        {
+            tracing::error!(error =? e, "Could not broadcast transaction {}", txid);
+            return Err(bitcoind_err_to_manager_err(e));
+        }
+
+        Ok(())
+    }
+
+    async fn get_block_at_height(&self, height: u64) -> Result<Block, ManagerError> {
+        tracing::info!(height, "Getting block at height.");
+        self.call(move |client| client.get_block(&client.get_block_hash(height)?))
+            .await
+            .map_err(bitcoind_err_to_manager_err)
+    }
+
+    async fn get_blockchain_height(&self) -> Result<u64, ManagerError> {
+        self.call(|client| client.get_block_count())
+            .await
+            .map_err(bitcoind_err_to_manager_err)
+    }
+
+    async fn get_transaction_confirmations(&self, tx_id: &Txid) -> Result<u32, ManagerError> {
+        tracing::info!(
+            txid = tx_id.to_string(),
+            "Getting transaction confirmations."
+        );
+        let txid = *tx_id;
+        match self
+            .call(move |client| client.get_raw_transaction_info(&txid, None))
+            .await
+        {
+            Ok(info) => Ok(info.confirmations.unwrap_or(0)),
+            Err(e) if is_not_found(&e) => Ok(0),
+            Err(e) => Err(bitcoind_err_to_manager_err(e)),
+        }
+    }
+}
+
+/// Fee estimates from `estimatesmartfee`. Targets bitcoind has no estimate for, e.g. on
+/// regtest, are left out. The last estimates are cached for the [`FeeEstimator`]
+/// implementation.
+#[async_trait::async_trait]
+impl FeeSource for BitcoindClient {
+    async fn get_fee_estimates(&self) -> anyhow::Result<HashMap<u16, f64>> {
+        let estimates = self
+            .call(|client| {
+                let mut estimates = HashMap::new();
+                for target in FEE_ESTIMATE_TARGETS {
+                    let estimate = client.estimate_smart_fee(target, None)?;
+                    if let Some(fee_rate) = estimate.fee_rate {
+                        // BTC/kvB to sats/vbyte.
+                        estimates.insert(target, fee_rate.to_sat() as f64 / 1000.0);
+                    }
+                }
+                Ok(estimates)
+            })
+            .await?;
+        *self.fee_estimates.write().unwrap() = estimates.clone();
+        Ok(estimates)
+    }
+}
+
+/// Fee rates from the estimates cached by the last [`FeeSource::get_fee_estimates`] call.
+/// Prefer [`super::FeeEstimates`], which refreshes and persists the estimates.
+impl FeeEstimator for BitcoindClient {
+    fn get_est_sat_per_1000_weight(&self, confirmation_target: ConfirmationTarget) -> u32 {
+        let estimates = self.fee_estimates.read().unwrap();
+        convert_fee_rate(target_blocks(confirmation_target), estimates.clone())
+            .map(|sat_per_vb| (sat_per_vb * 250.0).round() as u32)
+            .unwrap_or_else(|| fallback_fee_rate(confirmation_target))
+            .max(FEERATE_FLOOR_SATS_PER_KW)
+    }
+}
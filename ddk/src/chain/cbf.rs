@@ -0,0 +1,443 @@
+use super::fees::fallback_fee_rate;
+use super::FeeSource;
+use crate::error::cbf_err_to_manager_err;
+use bdk_chain::BlockId;
+use bitcoin::block::Header;
+use bitcoin::{Block, Network, ScriptBuf, Transaction, Txid};
+use ddk_manager::error::Error as ManagerError;
+use kyoto::builder::NodeDefault;
+use kyoto::{
+    Client, ClientError, Event, HeaderCheckpoint, IndexedBlock, NodeBuilder, Requester, SyncUpdate,
+    TrustedPeer,
+};
+use lightning::chain::chaininterface::{
+    ConfirmationTarget, FeeEstimator, FEERATE_FLOOR_SATS_PER_KW,
+};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+use tokio::sync::watch;
+
+/// Time to wait for the node to sync to the tip before a wallet sync or a chain query
+/// fails.
+const SYNC_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// A change of the chain for the wallet, in the order the node found it.
+#[derive(Debug)]
+pub(crate) enum CbfUpdate {
+    /// A block matching the filter of a registered script.
+    Block { height: u32, block: Block },
+    /// Headers of the chain of most work after a sync or a reorg.
+    Headers(Vec<BlockId>),
+}
+
+#[derive(Debug, Default)]
+struct CbfState {
+    tip: u32,
+    scripts: HashSet<ScriptBuf>,
+    /// Transactions paying to a registered script or spending an indexed transaction,
+    /// with the height they confirmed at.
+    transactions: HashMap<Txid, (Transaction, Option<u32>)>,
+    /// Headers of the matched blocks and the indexed transactions in them.
+    blocks: BTreeMap<u32, (Header, Vec<Txid>)>,
+    updates: Vec<CbfUpdate>,
+}
+
+impl CbfState {
+    fn index_block(&mut self, height: u32, block: &Block) {
+        let mut txids = Vec::new();
+        for tx in &block.txdata {
+            let txid = tx.compute_txid();
+            let relevant = self.transactions.contains_key(&txid)
+                || tx
+                    .output
+                    .iter()
+                    .any(|output| self.scripts.contains(&output.script_pubkey))
+                || tx
+                    .input
+                    .iter()
+                    .any(|input| self.transactions.contains_key(&input.previous_output.txid));
+            if relevant {
+                self.transactions.insert(txid, (tx.clone(), Some(height)));
+                txids.push(txid);
+            }
+        }
+        self.blocks.insert(height, (block.header, txids));
+        self.tip = self.tip.max(height);
+    }
+
+    fn disconnect(&mut self, height: u32) {
+        if let Some((_, txids)) = self.blocks.remove(&height) {
+            for txid in txids {
+                if let Some((_, confirmed)) = self.transactions.get_mut(&txid) {
+                    *confirmed = None;
+                }
+            }
+        }
+    }
+}
+
+/// Compact block filter (BIP157/158) light client for getting chain information.
+///
+/// Blocks are downloaded from the peers when their filter matches a registered script,
+/// so the transactions of DLC contracts are never queried from a third party. Used by
+/// rust-dlc for the funding transactions and CETs of contracts, which are registered
+/// with [`ddk_manager::Wallet::import_address`]. Used by bdk to sync the wallet with the
+/// matched blocks.
+///
+/// Only transactions paying to a registered script or spending an indexed transaction
+/// are known to the client. The peers serve no fee estimates, set a [`FeeSource`] with
+/// [`crate::builder::Builder::set_fee_source`].
+#[derive(Debug)]
+pub struct CbfClient {
+    network: Network,
+    requester: Requester,
+    /// The node and its channels until the client is used in a tokio runtime.
+    node: Mutex<Option<(NodeDefault, Client)>>,
+    state: Arc<Mutex<CbfState>>,
+    /// The number of times the node synced to the tip, `None` once it stopped.
+    syncs: Arc<watch::Sender<Option<u64>>>,
+}
+
+impl CbfClient {
+    /// Connect to `peers`, which must serve compact block filters, and keep the headers
+    /// in `data_dir`. Blocks are scanned after the checkpoint below `start_height`, or
+    /// after the most recent checkpoint of `network`.
+    pub fn new(
+        peers: &[SocketAddr],
+        data_dir: &Path,
+        start_height: Option<u32>,
+        network: Network,
+    ) -> anyhow::Result<CbfClient> {
+        if network == Network::Testnet {
+            return Err(anyhow::anyhow!(
+                "Compact block filters are not supported on testnet3."
+            ));
+        }
+        let mut builder = NodeBuilder::new(network)
+            .add_peers(
+                peers
+                    .iter()
+                    .map(|peer| TrustedPeer::from_socket_addr(*peer)),
+            )
+            .data_dir(data_dir);
+        if let Some(height) = start_height {
+            builder = builder.after_checkpoint(HeaderCheckpoint::closest_checkpoint_below_height(
+                height, network,
+            ));
+        }
+        let (node, client) = builder.build()?;
+        Ok(CbfClient {
+            network,
+            requester: client.requester.clone(),
+            node: Mutex::new(Some((node, client))),
+            state: Arc::new(Mutex::new(CbfState::default())),
+            syncs: Arc::new(watch::Sender::new(Some(0))),
+        })
+    }
+
+    /// Run the node and handle its events. Does nothing if it is already running.
+    pub(crate) fn start(&self) {
+        let Some((node, client)) = self.node.lock().unwrap().take() else {
+            return;
+        };
+        tracing::info!(
+            network = self.network.to_string(),
+            "Starting compact block filter node."
+        );
+        let Client {
+            requester: _,
+            mut log_rx,
+            mut info_rx,
+            mut warn_rx,
+            mut event_rx,
+        } = client;
+
+        tokio::spawn(async move {
+            if let Err(e) = node.run().await {
+                tracing::error!(error = e.to_string(), "Compact block filter node stopped.");
+            }
+        });
+        tokio::spawn(async move {
+            while let Some(log) = log_rx.recv().await {
+                tracing::debug!(log, "Compact block filter node.");
+            }
+        });
+        tokio::spawn(async move {
+            while let Some(info) = info_rx.recv().await {
+                tracing::info!(info = info.to_string(), "Compact block filter node.");
+            }
+        });
+        tokio::spawn(async move {
+            while let Some(warning) = warn_rx.recv().await {
+                tracing::warn!(warning = warning.to_string(), "Compact block filter node.");
+            }
+        });
+
+        let state = self.state.clone();
+        let syncs = self.syncs.clone();
+        tokio::spawn(async move {
+            while let Some(event) = event_rx.recv().await {
+                let mut state = state.lock().unwrap();
+                match event {
+                    Event::Block(IndexedBlock { height, block }) => {
+                        tracing::debug!(height, "Block matched a registered script.");
+                        state.index_block(height, &block);
+                        state.updates.push(CbfUpdate::Block { height, block });
+                    }
+                    Event::BlocksDisconnected {
+                        accepted,
+                        disconnected,
+                    } => {
+                        tracing::warn!(
+                            disconnected = disconnected.len(),
+                            "Blocks were reorganized out of the chain."
+                        );
+                        for header in disconnected {
+                            state.disconnect(header.height);
+                        }
+                        let headers = accepted
+                            .iter()
+                            .map(|header| BlockId {
+                                height: header.height,
+                                hash: header.block_hash(),
+                            })
+                            .collect();
+                        state.updates.push(CbfUpdate::Headers(headers));
+                    }
+                    Event::Synced(SyncUpdate {
+                        tip,
+                        recent_history,
+                    }) => {
+                        tracing::debug!(height = tip.height, "Compact block filter node synced.");
+                        state.tip = tip.height;
+                        let headers = recent_history
+                            .iter()
+                            .map(|(height, header)| BlockId {
+                                height: *height,
+                                hash: header.block_hash(),
+                            })
+                            .collect();
+                        state.updates.push(CbfUpdate::Headers(headers));
+                        syncs.send_modify(|syncs| *syncs = syncs.map(|syncs| syncs + 1));
+                    }
+                }
+            }
+            syncs.send_replace(None);
+        });
+    }
+
+    fn state(&self) -> MutexGuard<'_, CbfState> {
+        self.state.lock().unwrap()
+    }
+
+    /// Check the blocks found from now on for `scripts`.
+    pub(crate) fn add_scripts(
+        &self,
+        scripts: impl IntoIterator<Item = ScriptBuf>,
+    ) -> Result<(), ClientError> {
+        let mut state = self.state();
+        for script in scripts {
+            if state.scripts.insert(script.clone()) {
+                self.requester.add_script(script)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Index a transaction found before the node started, e.g. from the wallet.
+    pub(crate) fn insert_transaction(&self, tx: Transaction, height: Option<u32>) {
+        self.state()
+            .transactions
+            .entry(tx.compute_txid())
+            .or_insert((tx, height));
+    }
+
+    /// The number of times the node synced to the tip.
+    pub(crate) fn syncs(&self) -> u64 {
+        self.syncs.borrow().unwrap_or_default()
+    }
+
+    /// Wait until the node synced to the tip more than `syncs` times. Fails if the node
+    /// stopped or did not sync within [`SYNC_TIMEOUT`].
+    pub(crate) async fn wait_for_sync(&self, syncs: u64) -> Result<(), ManagerError> {
+        self.start();
+        let mut subscription = self.syncs.subscribe();
+        let synced = tokio::time::timeout(
+            SYNC_TIMEOUT,
+            subscription.wait_for(|synced| synced.is_none() || *synced > Some(syncs)),
+        )
+        .await
+        .map_err(|_| {
+            ManagerError::BlockchainError(format!(
+                "Compact block filter node did not sync within {} seconds.",
+                SYNC_TIMEOUT.as_secs()
+            ))
+        })?
+        .map_err(|_| cbf_err_to_manager_err(ClientError::SendError))?;
+        let syncs = synced.ok_or_else(|| cbf_err_to_manager_err(ClientError::SendError))?;
+        tracing::debug!(syncs, "Waited for compact block filter sync.");
+        Ok(())
+    }
+
+    /// Check the filters of every block after the start height again, e.g. for scripts
+    /// registered after their transactions confirmed.
+    pub(crate) fn rescan(&self) -> Result<(), ClientError> {
+        self.start();
+        self.requester.rescan()
+    }
+
+    /// The changes of the chain since the last call.
+    pub(crate) fn take_updates(&self) -> Vec<CbfUpdate> {
+        std::mem::take(&mut self.state().updates)
+    }
+
+    /// Transactions broadcast through the client that did not confirm yet.
+    pub(crate) fn unconfirmed_transactions(&self) -> Vec<Transaction> {
+        self.state()
+            .transactions
+            .values()
+            .filter(|(_, height)| height.is_none())
+            .map(|(tx, _)| tx.clone())
+            .collect()
+    }
+
+    pub(crate) fn broadcast(&self, tx: &Transaction) -> Result<(), ClientError> {
+        self.start();
+        self.requester.broadcast_random(tx.clone())?;
+        self.insert_transaction(tx.clone(), None);
+        Ok(())
+    }
+
+    /// A transaction paying to a registered script, spending an indexed transaction or
+    /// broadcast through the client, `None` if the node did not find it. Waits for the
+    /// first sync so that the blocks found so far are indexed.
+    pub(crate) async fn get_tx(&self, txid: Txid) -> Result<Option<Transaction>, ManagerError> {
+        self.wait_for_tip().await?;
+        Ok(self
+            .state()
+            .transactions
+            .get(&txid)
+            .map(|(tx, _)| tx.clone()))
+    }
+
+    /// Wait for the first sync, the tip is unknown until then.
+    async fn wait_for_tip(&self) -> Result<u32, ManagerError> {
+        if self.syncs() == 0 {
+            self.wait_for_sync(0).await?;
+        }
+        Ok(self.state().tip)
+    }
+}
+
+impl Drop for CbfClient {
+    fn drop(&mut self) {
+        let _ = self.requester.shutdown();
+    }
+}
+
+/// Implements the `ddk_manager::Blockchain` interface. Grabs chain related information
+/// regarding DLC transactions.
+#[async_trait::async_trait]
+impl ddk_manager::Blockchain for CbfClient {
+    fn get_network(&self) -> Result<Network, ManagerError> {
+        Ok(self.network)
+    }
+
+    async fn get_transaction(&self, tx_id: &Txid) -> Result<Transaction, ManagerError> {
+        tracing::info!(txid = tx_id.to_string(), "Querying for transaction.");
+        self.start();
+        self.state()
+            .transactions
+            .get(tx_id)
+            .map(|(tx, _)| tx.clone())
+            .ok_or_else(|| {
+                ManagerError::BlockchainError(format!(
+                    "Transaction {} is not in a block matching a registered script.",
+                    tx_id
+                ))
+            })
+    }
+
+    async fn send_transaction(&self, transaction: &Transaction) -> Result<(), ManagerError> {
+        let txid = transaction.compute_txid();
+        tracing::info!(
+            txid = txid.to_string(),
+            num_inputs = transaction.input.len(),
+            num_outputs = transaction.output.len(),
+            "Broadcasting transaction."
+        );
+
+        if let Err(e) = self.broadcast(transaction) {
+            tracing::error!(error =? e, "Could not broadcast transaction {}", txid);
+            return Err(cbf_err_to_manager_err(e));
+        }
+
+        Ok(())
+    }
+
+    /// Blocks that matched no registered script have the header only.
+    async fn get_block_at_height(&self, height: u64) -> Result<Block, ManagerError> {
+        tracing::info!(height, "Getting block at height.");
+        self.wait_for_tip().await?;
+        let height = height as u32;
+        {
+            let state = self.state();
+            if let Some((header, txids)) = state.blocks.get(&height) {
+                let txdata = txids
+                    .iter()
+                    .filter_map(|txid| state.transactions.get(txid))
+                    .map(|(tx, _)| tx.clone())
+                    .collect();
+                return Ok(Block {
+                    header: *header,
+                    txdata,
+                });
+            }
+        }
+        let header = self
+            .requester
+            .get_header(height)
+            .await
+            .map_err(|e| ManagerError::BlockchainError(e.to_string()))?;
+        Ok(Block {
+            header,
+            txdata: Vec::new(),
+        })
+    }
+
+    async fn get_blockchain_height(&self) -> Result<u64, ManagerError> {
+        Ok(self.wait_for_tip().await? as u64)
+    }
+
+    async fn get_transaction_confirmations(&self, tx_id: &Txid) -> Result<u32, ManagerError> {
+        tracing::info!(
+            txid = tx_id.to_string(),
+            "Getting transaction confirmations."
+        );
+        let tip = self.wait_for_tip().await?;
+        match self.state().transactions.get(tx_id) {
+            Some((_, Some(height))) => Ok(tip.saturating_sub(*height) + 1),
+            _ => Ok(0),
+        }
+    }
+}
+
+/// Peers don't serve fee estimates.
+#[async_trait::async_trait]
+impl FeeSource for CbfClient {
+    async fn get_fee_estimates(&self) -> anyhow::Result<HashMap<u16, f64>> {
+        Err(anyhow::anyhow!(
+            "Compact block filter peers serve no fee estimates, set a fee source."
+        ))
+    }
+}
+
+/// Fallback fee rates, peers don't serve fee estimates.
+impl FeeEstimator for CbfClient {
+    fn get_est_sat_per_1000_weight(&self, confirmation_target: ConfirmationTarget) -> u32 {
+        fallback_fee_rate(confirmation_target).max(FEERATE_FLOOR_SATS_PER_KW)
+    }
+}
@@ -1,20 +1,28 @@
+use super::fees::{fallback_fee_rate, target_blocks};
+use super::FeeSource;
 use crate::error::esplora_err_to_manager_err;
 use bdk_esplora::esplora_client::Error as EsploraError;
-use bdk_esplora::esplora_client::{AsyncClient, BlockingClient, Builder};
+use bdk_esplora::esplora_client::{convert_fee_rate, AsyncClient, BlockingClient, Builder};
 use bitcoin::Network;
 use bitcoin::{Transaction, Txid};
 use ddk_manager::error::Error as ManagerError;
-use lightning::chain::chaininterface::{ConfirmationTarget, FeeEstimator};
+use lightning::chain::chaininterface::{
+    ConfirmationTarget, FeeEstimator, FEERATE_FLOOR_SATS_PER_KW,
+};
+use std::collections::HashMap;
+use std::sync::RwLock;
 
 /// Esplora client for getting chain information. Holds both a blocking
 /// and an async client.
 ///
 /// Used by rust-dlc for getting transactions related to DLC contracts.
 /// Used by bdk to sync the wallet and track transaction.
+#[derive(Debug)]
 pub struct EsploraClient {
     pub blocking_client: BlockingClient,
     pub async_client: AsyncClient,
     network: Network,
+    fee_estimates: RwLock<HashMap<u16, f64>>,
 }
 
 impl EsploraClient {
@@ -26,6 +34,7 @@ impl EsploraClient {
             blocking_client,
             async_client,
             network,
+            fee_estimates: RwLock::new(HashMap::new()),
         })
     }
 }
@@ -153,8 +162,25 @@ impl ddk_manager::Blockchain for EsploraClient {
     }
 }
 
+/// Fee estimates from the esplora `/fee-estimates` endpoint. The last estimates are
+/// cached for the [`FeeEstimator`] implementation.
+#[async_trait::async_trait]
+impl FeeSource for EsploraClient {
+    async fn get_fee_estimates(&self) -> anyhow::Result<HashMap<u16, f64>> {
+        let estimates = self.async_client.get_fee_estimates().await?;
+        *self.fee_estimates.write().unwrap() = estimates.clone();
+        Ok(estimates)
+    }
+}
+
+/// Fee rates from the estimates cached by the last [`FeeSource::get_fee_estimates`] call.
+/// Prefer [`super::FeeEstimates`], which refreshes and persists the estimates.
 impl FeeEstimator for EsploraClient {
-    fn get_est_sat_per_1000_weight(&self, _confirmation_target: ConfirmationTarget) -> u32 {
-        1
+    fn get_est_sat_per_1000_weight(&self, confirmation_target: ConfirmationTarget) -> u32 {
+        let estimates = self.fee_estimates.read().unwrap();
+        convert_fee_rate(target_blocks(confirmation_target), estimates.clone())
+            .map(|sat_per_vb| (sat_per_vb * 250.0).round() as u32)
+            .unwrap_or_else(|| fallback_fee_rate(confirmation_target))
+            .max(FEERATE_FLOOR_SATS_PER_KW)
     }
 }
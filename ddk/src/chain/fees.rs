@@ -0,0 +1,260 @@
+use crate::Storage;
+use bdk_esplora::esplora_client::convert_fee_rate;
+use lightning::chain::chaininterface::{
+    ConfirmationTarget, FeeEstimator, FEERATE_FLOOR_SATS_PER_KW,
+};
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Highest fee rate handed out to the manager by default. 400 sats/vbyte.
+pub const DEFAULT_FEE_CEILING: u32 = 100_000;
+/// How often fee estimates are refreshed from the [`FeeSource`] by default.
+pub const DEFAULT_FEE_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+const ALL_TARGETS: [ConfirmationTarget; 8] = [
+    ConfirmationTarget::MaximumFeeEstimate,
+    ConfirmationTarget::UrgentOnChainSweep,
+    ConfirmationTarget::MinAllowedAnchorChannelRemoteFee,
+    ConfirmationTarget::MinAllowedNonAnchorChannelRemoteFee,
+    ConfirmationTarget::AnchorChannelFee,
+    ConfirmationTarget::NonAnchorChannelFee,
+    ConfirmationTarget::ChannelCloseMinimum,
+    ConfirmationTarget::OutputSpendingFee,
+];
+
+/// Provides fee rate estimates for [`FeeEstimates`].
+#[async_trait::async_trait]
+pub trait FeeSource: Debug + Send + Sync + 'static {
+    /// Fee rates in sats/vbyte keyed by the confirmation target in blocks.
+    async fn get_fee_estimates(&self) -> anyhow::Result<HashMap<u16, f64>>;
+}
+
+/// Cached fee rates for every [`ConfirmationTarget`], refreshed from a [`FeeSource`].
+///
+/// The last estimates are persisted to [`crate::Storage`] and loaded on creation so
+/// fee rates are sensible before the first refresh.
+pub struct FeeEstimates {
+    source: Arc<dyn FeeSource>,
+    storage: Arc<dyn Storage>,
+    fees: HashMap<ConfirmationTarget, AtomicU32>,
+    floor: u32,
+    ceiling: u32,
+    refresh_interval: Duration,
+}
+
+impl FeeEstimates {
+    pub fn new(source: Arc<dyn FeeSource>, storage: Arc<dyn Storage>) -> FeeEstimates {
+        let fees = ALL_TARGETS
+            .iter()
+            .map(|target| (*target, AtomicU32::new(fallback_fee_rate(*target))))
+            .collect();
+
+        let fee_estimates = FeeEstimates {
+            source,
+            storage,
+            fees,
+            floor: FEERATE_FLOOR_SATS_PER_KW,
+            ceiling: DEFAULT_FEE_CEILING,
+            refresh_interval: DEFAULT_FEE_REFRESH_INTERVAL,
+        };
+
+        match fee_estimates.storage.get_fee_estimates() {
+            Ok(Some(estimates)) => fee_estimates.apply(&estimates),
+            Ok(None) => tracing::info!("No stored fee estimates. Using fallback fee rates."),
+            Err(e) => tracing::warn!(error =? e, "Could not load stored fee estimates."),
+        }
+
+        fee_estimates
+    }
+
+    /// Set the lowest and highest fee rate (sats per 1000 weight units) that will be
+    /// returned for any confirmation target.
+    pub fn with_limits(mut self, floor: u32, ceiling: u32) -> FeeEstimates {
+        self.floor = floor.max(FEERATE_FLOOR_SATS_PER_KW);
+        self.ceiling = ceiling.max(self.floor);
+        self
+    }
+
+    /// Set how often a running DDK node refreshes the estimates from the fee source.
+    pub fn with_refresh_interval(mut self, refresh_interval: Duration) -> FeeEstimates {
+        self.refresh_interval = refresh_interval;
+        self
+    }
+
+    /// How often the estimates are refreshed from the fee source.
+    pub fn refresh_interval(&self) -> Duration {
+        self.refresh_interval
+    }
+
+    /// Fetch new estimates from the fee source, update the cached fee rates, and persist them.
+    pub async fn update(&self) -> anyhow::Result<()> {
+        let estimates = self.source.get_fee_estimates().await?;
+        if estimates.is_empty() {
+            return Err(anyhow::anyhow!("Fee source returned no estimates."));
+        }
+
+        self.apply(&estimates);
+        self.storage.persist_fee_estimates(&estimates)?;
+        tracing::debug!("Updated fee estimates.");
+        Ok(())
+    }
+
+    /// Use the estimate of the nearest target at or below the blocks of each confirmation
+    /// target. Targets faster than every estimate keep their current fee rate, the estimate
+    /// of a slower target would underpay them.
+    fn apply(&self, estimates: &HashMap<u16, f64>) {
+        for (target, fee) in &self.fees {
+            let blocks = target_blocks(*target);
+            match convert_fee_rate(blocks, estimates.clone()) {
+                Some(sat_per_vb) => {
+                    let sat_per_kw = (sat_per_vb * 250.0).round() as u32;
+                    fee.store(sat_per_kw, Ordering::Release);
+                }
+                None => tracing::debug!(
+                    ?target,
+                    blocks,
+                    "No fee estimate at or below the target. Keeping the current fee rate."
+                ),
+            }
+        }
+    }
+}
+
+impl FeeEstimator for FeeEstimates {
+    fn get_est_sat_per_1000_weight(&self, confirmation_target: ConfirmationTarget) -> u32 {
+        let fee = self
+            .fees
+            .get(&confirmation_target)
+            .map(|fee| fee.load(Ordering::Acquire))
+            .unwrap_or(self.floor);
+
+        fee.clamp(self.floor, self.ceiling)
+    }
+}
+
+/// Number of blocks a transaction for the target is expected to confirm in.
+pub(super) fn target_blocks(target: ConfirmationTarget) -> usize {
+    match target {
+        ConfirmationTarget::MaximumFeeEstimate => 1,
+        ConfirmationTarget::UrgentOnChainSweep => 6,
+        ConfirmationTarget::MinAllowedAnchorChannelRemoteFee => 1008,
+        ConfirmationTarget::MinAllowedNonAnchorChannelRemoteFee => 144,
+        ConfirmationTarget::AnchorChannelFee => 1008,
+        ConfirmationTarget::NonAnchorChannelFee => 12,
+        ConfirmationTarget::ChannelCloseMinimum => 144,
+        ConfirmationTarget::OutputSpendingFee => 12,
+    }
+}
+
+/// Fee rates used until the first estimates are received.
+pub(super) fn fallback_fee_rate(target: ConfirmationTarget) -> u32 {
+    match target {
+        ConfirmationTarget::MaximumFeeEstimate | ConfirmationTarget::UrgentOnChainSweep => 5000,
+        ConfirmationTarget::NonAnchorChannelFee => 2000,
+        _ => FEERATE_FLOOR_SATS_PER_KW,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::memory::MemoryStorage;
+
+    #[derive(Debug)]
+    struct MockFeeSource(HashMap<u16, f64>);
+
+    #[async_trait::async_trait]
+    impl FeeSource for MockFeeSource {
+        async fn get_fee_estimates(&self) -> anyhow::Result<HashMap<u16, f64>> {
+            Ok(self.0.clone())
+        }
+    }
+
+    fn mock_source() -> Arc<MockFeeSource> {
+        let estimates = [(1, 600.0), (6, 20.0), (12, 10.0), (144, 2.0), (1008, 0.5)];
+        Arc::new(MockFeeSource(estimates.into_iter().collect()))
+    }
+
+    #[tokio::test]
+    async fn fee_estimates_are_clamped_and_persisted() {
+        let storage = Arc::new(MemoryStorage::new());
+        let fees = FeeEstimates::new(mock_source(), storage.clone());
+        assert_eq!(
+            fees.get_est_sat_per_1000_weight(ConfirmationTarget::NonAnchorChannelFee),
+            2000
+        );
+
+        fees.update().await.unwrap();
+        assert_eq!(
+            fees.get_est_sat_per_1000_weight(ConfirmationTarget::UrgentOnChainSweep),
+            5000
+        );
+        assert_eq!(
+            fees.get_est_sat_per_1000_weight(ConfirmationTarget::NonAnchorChannelFee),
+            2500
+        );
+        assert_eq!(
+            fees.get_est_sat_per_1000_weight(ConfirmationTarget::MaximumFeeEstimate),
+            DEFAULT_FEE_CEILING
+        );
+        assert_eq!(
+            fees.get_est_sat_per_1000_weight(ConfirmationTarget::AnchorChannelFee),
+            FEERATE_FLOOR_SATS_PER_KW
+        );
+
+        let empty_source = Arc::new(MockFeeSource(HashMap::new()));
+        let reloaded = FeeEstimates::new(empty_source, storage).with_limits(1000, 4000);
+        assert!(reloaded.update().await.is_err());
+        assert_eq!(
+            reloaded.get_est_sat_per_1000_weight(ConfirmationTarget::NonAnchorChannelFee),
+            2500
+        );
+        assert_eq!(
+            reloaded.get_est_sat_per_1000_weight(ConfirmationTarget::UrgentOnChainSweep),
+            4000
+        );
+        assert_eq!(
+            reloaded.get_est_sat_per_1000_weight(ConfirmationTarget::ChannelCloseMinimum),
+            1000
+        );
+    }
+
+    #[tokio::test]
+    async fn missing_targets_use_the_nearest_lower_target() {
+        let estimates = [(3, 30.0), (144, 2.0)];
+        let source = Arc::new(MockFeeSource(estimates.into_iter().collect()));
+        let fees = FeeEstimates::new(source, Arc::new(MemoryStorage::new()));
+        fees.update().await.unwrap();
+        assert_eq!(
+            fees.get_est_sat_per_1000_weight(ConfirmationTarget::NonAnchorChannelFee),
+            7500
+        );
+        assert_eq!(
+            fees.get_est_sat_per_1000_weight(ConfirmationTarget::ChannelCloseMinimum),
+            500
+        );
+        // Nothing at or below one block, the fallback fee rate is kept.
+        assert_eq!(
+            fees.get_est_sat_per_1000_weight(ConfirmationTarget::MaximumFeeEstimate),
+            fallback_fee_rate(ConfirmationTarget::MaximumFeeEstimate)
+        );
+    }
+
+    #[test]
+    fn esplora_fee_estimator_uses_fallback_until_fetched() {
+        let esplora =
+            crate::chain::EsploraClient::new("http://localhost:30000", bitcoin::Network::Regtest)
+                .unwrap();
+        assert_eq!(
+            esplora.get_est_sat_per_1000_weight(ConfirmationTarget::NonAnchorChannelFee),
+            2000
+        );
+        assert_eq!(
+            esplora.get_est_sat_per_1000_weight(ConfirmationTarget::ChannelCloseMinimum),
+            FEERATE_FLOOR_SATS_PER_KW
+        );
+    }
+}
@@ -1,3 +1,19 @@
+#[cfg(feature = "bitcoind")]
+mod bitcoind;
+#[cfg(feature = "cbf")]
+mod cbf;
 mod esplora;
+mod fees;
+mod source;
 
+#[cfg(feature = "bitcoind")]
+pub use bitcoincore_rpc::Auth as BitcoindAuth;
+#[cfg(feature = "bitcoind")]
+pub use bitcoind::BitcoindClient;
+#[cfg(feature = "cbf")]
+pub use cbf::CbfClient;
+#[cfg(feature = "cbf")]
+pub(crate) use cbf::CbfUpdate;
 pub use esplora::EsploraClient;
+pub use fees::{FeeEstimates, FeeSource, DEFAULT_FEE_CEILING, DEFAULT_FEE_REFRESH_INTERVAL};
+pub use source::{ChainClient, ChainSource};
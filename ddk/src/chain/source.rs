@@ -0,0 +1,257 @@
+#[cfg(feature = "bitcoind")]
+use super::BitcoindClient;
+#[cfg(feature = "cbf")]
+use super::CbfClient;
+use super::{EsploraClient, FeeSource};
+use crate::error::WalletError;
+use bitcoin::{Block, Network, Transaction, Txid};
+use ddk_manager::error::Error as ManagerError;
+use ddk_manager::Blockchain;
+use lightning::chain::chaininterface::{ConfirmationTarget, FeeEstimator};
+use std::collections::HashMap;
+#[cfg(feature = "cbf")]
+use std::{net::SocketAddr, path::PathBuf};
+
+/// Where DDK reads the chain from and broadcasts transactions to.
+#[derive(Clone, PartialEq, Eq)]
+pub enum ChainSource {
+    /// An esplora server, e.g. `https://mutinynet.com/api`.
+    Esplora { url: String },
+    /// A Bitcoin Core node, e.g. `http://127.0.0.1:8332`. Set `txindex=1` for DDK to
+    /// look up the transactions of a counterparty.
+    #[cfg(feature = "bitcoind")]
+    Bitcoind {
+        url: String,
+        auth: bitcoincore_rpc::Auth,
+    },
+    /// A compact block filter light client connected to `peers`, e.g. a Bitcoin Core
+    /// node with `blockfilterindex=1` and `peerblockfilters=1`. Headers are kept in
+    /// `data_dir`. Blocks are scanned from the checkpoint below `start_height`, or from
+    /// the most recent checkpoint of the network if it is not set.
+    #[cfg(feature = "cbf")]
+    Cbf {
+        peers: Vec<SocketAddr>,
+        data_dir: PathBuf,
+        start_height: Option<u32>,
+    },
+}
+
+impl ChainSource {
+    pub fn esplora(url: &str) -> ChainSource {
+        ChainSource::Esplora {
+            url: url.to_string(),
+        }
+    }
+}
+
+// The RPC password of a Bitcoin Core node is never printed.
+impl std::fmt::Debug for ChainSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChainSource::Esplora { url } => f.debug_struct("Esplora").field("url", url).finish(),
+            #[cfg(feature = "bitcoind")]
+            ChainSource::Bitcoind { url, auth } => {
+                let auth = match auth {
+                    bitcoincore_rpc::Auth::None => "None".to_string(),
+                    bitcoincore_rpc::Auth::UserPass(user, _) => {
+                        format!("UserPass({}, <redacted>)", user)
+                    }
+                    bitcoincore_rpc::Auth::CookieFile(path) => {
+                        format!("CookieFile({})", path.display())
+                    }
+                };
+                f.debug_struct("Bitcoind")
+                    .field("url", url)
+                    .field("auth", &format_args!("{}", auth))
+                    .finish()
+            }
+            #[cfg(feature = "cbf")]
+            ChainSource::Cbf {
+                peers,
+                data_dir,
+                start_height,
+            } => f
+                .debug_struct("Cbf")
+                .field("peers", peers)
+                .field("data_dir", data_dir)
+                .field("start_height", start_height)
+                .finish(),
+        }
+    }
+}
+
+/// Client for a [`ChainSource`]. Used by the wallet to sync and by rust-dlc for the
+/// transactions of DLC contracts.
+#[derive(Debug)]
+pub enum ChainClient {
+    Esplora(EsploraClient),
+    #[cfg(feature = "bitcoind")]
+    Bitcoind(BitcoindClient),
+    #[cfg(feature = "cbf")]
+    Cbf(CbfClient),
+}
+
+impl ChainClient {
+    pub fn new(source: &ChainSource, network: Network) -> anyhow::Result<ChainClient> {
+        match source {
+            ChainSource::Esplora { url } => {
+                Ok(ChainClient::Esplora(EsploraClient::new(url, network)?))
+            }
+            #[cfg(feature = "bitcoind")]
+            ChainSource::Bitcoind { url, auth } => Ok(ChainClient::Bitcoind(BitcoindClient::new(
+                url,
+                auth.clone(),
+                network,
+            )?)),
+            #[cfg(feature = "cbf")]
+            ChainSource::Cbf {
+                peers,
+                data_dir,
+                start_height,
+            } => Ok(ChainClient::Cbf(CbfClient::new(
+                peers,
+                data_dir,
+                *start_height,
+                network,
+            )?)),
+        }
+    }
+
+    /// The transaction with `txid`, `None` if the chain source doesn't know it.
+    pub(crate) async fn get_tx(&self, txid: Txid) -> Result<Option<Transaction>, WalletError> {
+        match self {
+            ChainClient::Esplora(client) => {
+                Ok(client.async_client.get_tx(&txid).await.map_err(Box::new)?)
+            }
+            #[cfg(feature = "bitcoind")]
+            ChainClient::Bitcoind(client) => Ok(client.get_tx(txid).await?),
+            #[cfg(feature = "cbf")]
+            ChainClient::Cbf(client) => client
+                .get_tx(txid)
+                .await
+                .map_err(|e| WalletError::Blockchain(e.to_string())),
+        }
+    }
+
+    pub(crate) async fn broadcast(&self, tx: &Transaction) -> Result<(), WalletError> {
+        match self {
+            ChainClient::Esplora(client) => Ok(client.async_client.broadcast(tx).await?),
+            #[cfg(feature = "bitcoind")]
+            ChainClient::Bitcoind(client) => {
+                let tx = tx.clone();
+                client
+                    .call(move |client| bitcoincore_rpc::RpcApi::send_raw_transaction(client, &tx))
+                    .await?;
+                Ok(())
+            }
+            #[cfg(feature = "cbf")]
+            ChainClient::Cbf(client) => Ok(client.broadcast(tx)?),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Blockchain for ChainClient {
+    fn get_network(&self) -> Result<Network, ManagerError> {
+        match self {
+            ChainClient::Esplora(client) => client.get_network(),
+            #[cfg(feature = "bitcoind")]
+            ChainClient::Bitcoind(client) => client.get_network(),
+            #[cfg(feature = "cbf")]
+            ChainClient::Cbf(client) => client.get_network(),
+        }
+    }
+
+    async fn get_transaction(&self, tx_id: &Txid) -> Result<Transaction, ManagerError> {
+        match self {
+            ChainClient::Esplora(client) => client.get_transaction(tx_id).await,
+            #[cfg(feature = "bitcoind")]
+            ChainClient::Bitcoind(client) => client.get_transaction(tx_id).await,
+            #[cfg(feature = "cbf")]
+            ChainClient::Cbf(client) => client.get_transaction(tx_id).await,
+        }
+    }
+
+    async fn send_transaction(&self, transaction: &Transaction) -> Result<(), ManagerError> {
+        match self {
+            ChainClient::Esplora(client) => client.send_transaction(transaction).await,
+            #[cfg(feature = "bitcoind")]
+            ChainClient::Bitcoind(client) => client.send_transaction(transaction).await,
+            #[cfg(feature = "cbf")]
+            ChainClient::Cbf(client) => client.send_transaction(transaction).await,
+        }
+    }
+
+    async fn get_block_at_height(&self, height: u64) -> Result<Block, ManagerError> {
+        match self {
+            ChainClient::Esplora(client) => client.get_block_at_height(height).await,
+            #[cfg(feature = "bitcoind")]
+            ChainClient::Bitcoind(client) => client.get_block_at_height(height).await,
+            #[cfg(feature = "cbf")]
+            ChainClient::Cbf(client) => client.get_block_at_height(height).await,
+        }
+    }
+
+    async fn get_blockchain_height(&self) -> Result<u64, ManagerError> {
+        match self {
+            ChainClient::Esplora(client) => client.get_blockchain_height().await,
+            #[cfg(feature = "bitcoind")]
+            ChainClient::Bitcoind(client) => client.get_blockchain_height().await,
+            #[cfg(feature = "cbf")]
+            ChainClient::Cbf(client) => client.get_blockchain_height().await,
+        }
+    }
+
+    async fn get_transaction_confirmations(&self, tx_id: &Txid) -> Result<u32, ManagerError> {
+        match self {
+            ChainClient::Esplora(client) => client.get_transaction_confirmations(tx_id).await,
+            #[cfg(feature = "bitcoind")]
+            ChainClient::Bitcoind(client) => client.get_transaction_confirmations(tx_id).await,
+            #[cfg(feature = "cbf")]
+            ChainClient::Cbf(client) => client.get_transaction_confirmations(tx_id).await,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl FeeSource for ChainClient {
+    async fn get_fee_estimates(&self) -> anyhow::Result<HashMap<u16, f64>> {
+        match self {
+            ChainClient::Esplora(client) => client.get_fee_estimates().await,
+            #[cfg(feature = "bitcoind")]
+            ChainClient::Bitcoind(client) => client.get_fee_estimates().await,
+            #[cfg(feature = "cbf")]
+            ChainClient::Cbf(client) => client.get_fee_estimates().await,
+        }
+    }
+}
+
+impl FeeEstimator for ChainClient {
+    fn get_est_sat_per_1000_weight(&self, confirmation_target: ConfirmationTarget) -> u32 {
+        match self {
+            ChainClient::Esplora(client) => client.get_est_sat_per_1000_weight(confirmation_target),
+            #[cfg(feature = "bitcoind")]
+            ChainClient::Bitcoind(client) => {
+                client.get_est_sat_per_1000_weight(confirmation_target)
+            }
+            #[cfg(feature = "cbf")]
+            ChainClient::Cbf(client) => client.get_est_sat_per_1000_weight(confirmation_target),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "bitcoind"))]
+mod tests {
+    use super::ChainSource;
+
+    #[test]
+    fn debug_hides_the_rpc_password() {
+        let source = ChainSource::Bitcoind {
+            url: "http://127.0.0.1:18443".to_string(),
+            auth: bitcoincore_rpc::Auth::UserPass("ddk".to_string(), "hunter2".to_string()),
+        };
+        let debug = format!("{:?}", source);
+        assert!(debug.contains("ddk"));
+        assert!(!debug.contains("hunter2"));
+    }
+}
@@ -0,0 +1,127 @@
+use crate::builder::BuilderError;
+use crate::chain::{DEFAULT_FEE_CEILING, DEFAULT_FEE_REFRESH_INTERVAL};
+use crate::wallet::{CoinSelectionStrategy, DEFAULT_UTXO_RESERVATION_TIMEOUT};
+use ddk_manager::manager::ManagerConfig;
+use lightning::chain::chaininterface::FEERATE_FLOOR_SATS_PER_KW;
+use std::time::Duration;
+
+/// How often the wallet is synced with the chain.
+pub const DEFAULT_WALLET_SYNC_INTERVAL: Duration = Duration::from_secs(60);
+/// How often the manager checks the chain and oracles for contract and channel updates.
+pub const DEFAULT_PERIODIC_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Intervals of the background tasks and the settings of the DLC manager. Set with
+/// [`crate::builder::Builder::set_config`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DdkConfig {
+    /// How often the wallet is synced with the chain.
+    pub wallet_sync_interval: Duration,
+    /// How often the manager checks the chain and oracles for contract and channel updates.
+    pub periodic_check_interval: Duration,
+    /// Time after which coins reserved for a contract that was never funded are released.
+    pub utxo_reservation_timeout: Duration,
+    /// Algorithm that picks the coins funding contracts and channels.
+    pub coin_selection: CoinSelectionStrategy,
+    /// Time a contract funding transaction, CET or refund may stay unconfirmed before
+    /// its fee is bumped with a child transaction. `None` disables automatic fee bumping.
+    pub fee_bump_deadline: Option<Duration>,
+    /// Lowest fee rate (sats per 1000 weight units) used for DLC and wallet transactions.
+    /// Raised to 253 sats/kw if set lower.
+    pub fee_floor: u32,
+    /// Highest fee rate (sats per 1000 weight units) used for DLC and wallet transactions.
+    pub fee_ceiling: u32,
+    /// How often fee estimates are refreshed from the fee source.
+    pub fee_refresh_interval: Duration,
+    /// Confirmation depth, refund delay and peer timeout of the DLC manager.
+    pub manager: ManagerConfig,
+}
+
+impl Default for DdkConfig {
+    fn default() -> Self {
+        Self {
+            wallet_sync_interval: DEFAULT_WALLET_SYNC_INTERVAL,
+            periodic_check_interval: DEFAULT_PERIODIC_CHECK_INTERVAL,
+            utxo_reservation_timeout: DEFAULT_UTXO_RESERVATION_TIMEOUT,
+            coin_selection: CoinSelectionStrategy::default(),
+            fee_bump_deadline: None,
+            fee_floor: FEERATE_FLOOR_SATS_PER_KW,
+            fee_ceiling: DEFAULT_FEE_CEILING,
+            fee_refresh_interval: DEFAULT_FEE_REFRESH_INTERVAL,
+            manager: ManagerConfig::default(),
+        }
+    }
+}
+
+impl DdkConfig {
+    /// Checks that the intervals are not zero, that the fee ceiling is not below the fee
+    /// floor and that the accepted refund delay and CET nsequence ranges, up to twice the
+    /// configured values, fit in a `u32`.
+    pub fn validate(&self) -> Result<(), BuilderError> {
+        if self.wallet_sync_interval.is_zero() {
+            return Err(BuilderError::InvalidConfig(
+                "wallet sync interval must not be zero",
+            ));
+        }
+        if self.periodic_check_interval.is_zero() {
+            return Err(BuilderError::InvalidConfig(
+                "periodic check interval must not be zero",
+            ));
+        }
+        if self.fee_refresh_interval.is_zero() {
+            return Err(BuilderError::InvalidConfig(
+                "fee refresh interval must not be zero",
+            ));
+        }
+        if self.fee_ceiling < self.fee_floor.max(FEERATE_FLOOR_SATS_PER_KW) {
+            return Err(BuilderError::InvalidConfig(
+                "fee ceiling must not be below the fee floor",
+            ));
+        }
+        if self.manager.refund_delay.checked_mul(2).is_none() {
+            return Err(BuilderError::InvalidConfig(
+                "twice the refund delay must fit in a u32",
+            ));
+        }
+        if self.manager.cet_nsequence.checked_mul(2).is_none() {
+            return Err(BuilderError::InvalidConfig(
+                "twice the CET nsequence must fit in a u32",
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate_rejects_zero_intervals_and_overflowing_ranges() {
+        assert!(DdkConfig::default().validate().is_ok());
+
+        let mut config = DdkConfig::default();
+        config.periodic_check_interval = Duration::ZERO;
+        assert!(config.validate().is_err());
+
+        let mut config = DdkConfig::default();
+        config.wallet_sync_interval = Duration::ZERO;
+        assert!(config.validate().is_err());
+
+        let mut config = DdkConfig::default();
+        config.fee_refresh_interval = Duration::ZERO;
+        assert!(config.validate().is_err());
+
+        let mut config = DdkConfig::default();
+        config.fee_floor = 2000;
+        config.fee_ceiling = 1000;
+        assert!(config.validate().is_err());
+
+        let mut config = DdkConfig::default();
+        config.manager.refund_delay = u32::MAX / 2 + 1;
+        assert!(config.validate().is_err());
+
+        let mut config = DdkConfig::default();
+        config.manager.cet_nsequence = u32::MAX / 2 + 1;
+        assert!(config.validate().is_err());
+    }
+}
@@ -1,4 +1,15 @@
-use crate::chain::EsploraClient;
+#[cfg(feature = "backup")]
+use crate::backup::{self, BackupSchedule};
+use crate::chain::ChainClient;
+use crate::config::DdkConfig;
+use crate::event::DdkEvent;
+use crate::recovery::{self, EmergencyRecords, RefundOutcome};
+use crate::signer::DdkContractSigner;
+#[cfg(feature = "backup")]
+use crate::storage::encryption::StorageSecret;
+use crate::storage::query::{ContractQuery, ContractState};
+use crate::transport::{PeerConnections, PeerStatus};
+use crate::wallet::fee_bump::FeeBumpMonitor;
 use crate::wallet::DlcDevKitWallet;
 #[cfg(feature = "marketplace")]
 use crate::{nostr::marketplace::*, DEFAULT_NOSTR_RELAY};
@@ -6,30 +17,42 @@ use crate::{Oracle, Storage, Transport};
 use anyhow::anyhow;
 use bitcoin::secp256k1::PublicKey;
 use bitcoin::{Amount, Network};
-use crossbeam::channel::{unbounded, Receiver, Sender};
-use ddk_manager::contract::Contract;
+use crossbeam::channel::{unbounded, Sender};
+use ddk_manager::contract::{Contract, ContractDescriptor};
 use ddk_manager::error::Error;
+use ddk_manager::ChannelId;
 use ddk_manager::{
     contract::contract_input::ContractInput, CachedContractSignerProvider, ContractId,
-    SimpleSigner, SystemTimeProvider,
+    SystemTimeProvider,
+};
+use dlc_messages::channel::{
+    AcceptChannel, CollaborativeCloseOffer, OfferChannel, Reject, RenewAccept, RenewOffer,
+    SettleAccept, SettleOffer,
 };
 use dlc_messages::oracle_msgs::OracleAnnouncement;
 use dlc_messages::{AcceptDlc, Message, OfferDlc};
-use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
 use std::time::Duration;
 use tokio::runtime::Runtime;
-use tokio::sync::watch;
+use tokio::sync::{broadcast, mpsc, watch};
+use tokio::task::JoinHandle;
+
+/// Time [`DlcDevKit::stop`] waits for tasks to finish and storage to flush.
+pub const DEFAULT_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(30);
+/// Time the supervisor waits before restarting a manager task that exited.
+const MANAGER_RESTART_DELAY: Duration = Duration::from_secs(1);
 
 /// DlcDevKit type alias for the [ddk_manager::manager::Manager]
 pub type DlcDevKitDlcManager<S, O> = ddk_manager::manager::Manager<
     Arc<DlcDevKitWallet>,
-    Arc<CachedContractSignerProvider<Arc<DlcDevKitWallet>, SimpleSigner>>,
-    Arc<EsploraClient>,
+    Arc<CachedContractSignerProvider<Arc<DlcDevKitWallet>, DdkContractSigner>>,
+    Arc<ChainClient>,
     Arc<S>,
     Arc<O>,
     Arc<SystemTimeProvider>,
     Arc<DlcDevKitWallet>,
-    SimpleSigner,
+    DdkContractSigner,
 >;
 
 #[derive(Debug)]
@@ -42,23 +65,156 @@ pub enum DlcManagerMessage {
         contract_input: ContractInput,
         counter_party: PublicKey,
         oracle_announcements: Vec<OracleAnnouncement>,
-        responder: Sender<OfferDlc>,
+        responder: Sender<Result<OfferDlc, Error>>,
+    },
+    RejectOffer {
+        contract: ContractId,
+        responder: Sender<Result<(Reject, PublicKey), Error>>,
+    },
+    CancelOffer {
+        contract: ContractId,
+        responder: Sender<Result<(Reject, PublicKey), Error>>,
+    },
+    OfferChannel {
+        contract_input: ContractInput,
+        counter_party: PublicKey,
+        responder: Sender<Result<OfferChannel, Error>>,
+    },
+    AcceptChannel {
+        channel: ChannelId,
+        responder: Sender<Result<(AcceptChannel, ChannelId, ContractId, PublicKey), Error>>,
+    },
+    SettleChannel {
+        channel: ChannelId,
+        counter_payout: u64,
+        responder: Sender<Result<(SettleOffer, PublicKey), Error>>,
+    },
+    AcceptSettle {
+        channel: ChannelId,
+        responder: Sender<Result<(SettleAccept, PublicKey), Error>>,
+    },
+    RenewChannel {
+        channel: ChannelId,
+        counter_payout: u64,
+        contract_input: ContractInput,
+        responder: Sender<Result<(RenewOffer, PublicKey), Error>>,
+    },
+    AcceptRenew {
+        channel: ChannelId,
+        responder: Sender<Result<(RenewAccept, PublicKey), Error>>,
+    },
+    CloseChannel {
+        channel: ChannelId,
+        counter_payout: u64,
+        responder: Sender<Result<(CollaborativeCloseOffer, PublicKey), Error>>,
+    },
+    AcceptClose {
+        channel: ChannelId,
+        responder: Sender<Result<(), Error>>,
+    },
+    ForceCloseChannel {
+        channel: ChannelId,
+        responder: Sender<Result<(), Error>>,
     },
     PeriodicCheck,
 }
 
+/// Health of the manager task, see [`DlcDevKit::manager_health`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ManagerHealth {
+    /// The manager task is processing messages.
+    pub running: bool,
+    /// Times the supervisor restarted the manager task after it exited unexpectedly.
+    pub restarts: u64,
+    /// Periodic checks that returned an error.
+    pub periodic_check_failures: u64,
+    /// The last error of a periodic check or the reason of the last restart.
+    pub last_error: Option<String>,
+}
+
+/// Shared between the manager task, its supervisor and [`DlcDevKit`].
+#[derive(Debug, Default)]
+pub(crate) struct ManagerHealthTracker {
+    running: AtomicBool,
+    restarts: AtomicU64,
+    periodic_check_failures: AtomicU64,
+    last_error: Mutex<Option<String>>,
+}
+
+impl ManagerHealthTracker {
+    fn set_running(&self, running: bool) {
+        self.running.store(running, Ordering::SeqCst);
+    }
+
+    fn record_restart(&self, reason: String) {
+        self.restarts.fetch_add(1, Ordering::SeqCst);
+        *self.last_error.lock().unwrap() = Some(reason);
+    }
+
+    fn record_periodic_check_failure(&self, error: String) {
+        self.periodic_check_failures.fetch_add(1, Ordering::SeqCst);
+        *self.last_error.lock().unwrap() = Some(error);
+    }
+
+    fn snapshot(&self) -> ManagerHealth {
+        ManagerHealth {
+            running: self.running.load(Ordering::SeqCst),
+            restarts: self.restarts.load(Ordering::SeqCst),
+            periodic_check_failures: self.periodic_check_failures.load(Ordering::SeqCst),
+            last_error: self.last_error.lock().unwrap().clone(),
+        }
+    }
+}
+
+/// Answer a manager message. The caller may have stopped waiting, which is not an error
+/// of the manager.
+fn respond<R>(responder: Sender<R>, response: R) {
+    if responder.send(response).is_err() {
+        tracing::warn!("Caller dropped before the manager responded.");
+    }
+}
+
+/// Dropping the responders of queued messages fails them back to the callers.
+fn drop_queued_messages(receiver: &mut mpsc::UnboundedReceiver<DlcManagerMessage>) {
+    let mut dropped = 0;
+    while receiver.try_recv().is_ok() {
+        dropped += 1;
+    }
+    if dropped > 0 {
+        tracing::warn!(dropped, "Dropped unprocessed manager messages on shutdown.");
+    }
+}
+
+/// Numerical contracts sign with the contract secret key, which an
+/// [`crate::signer::ExternalSigner`] does not expose.
+fn is_numerical(descriptor: &ContractDescriptor) -> bool {
+    matches!(descriptor, ContractDescriptor::Numerical(_))
+}
+
 pub struct DlcDevKit<T: Transport, S: Storage, O: Oracle> {
     pub runtime: Arc<RwLock<Option<Runtime>>>,
     pub wallet: Arc<DlcDevKitWallet>,
     pub manager: Arc<DlcDevKitDlcManager<S, O>>,
-    pub sender: Arc<Sender<DlcManagerMessage>>,
-    pub receiver: Arc<Receiver<DlcManagerMessage>>,
+    pub sender: Arc<mpsc::UnboundedSender<DlcManagerMessage>>,
+    pub receiver: Arc<tokio::sync::Mutex<mpsc::UnboundedReceiver<DlcManagerMessage>>>,
     pub transport: Arc<T>,
     pub storage: Arc<S>,
     pub oracle: Arc<O>,
+    /// Every oracle the manager can use, including [`DlcDevKit::oracle`].
+    pub oracles: Vec<Arc<O>>,
     pub network: Network,
     pub stop_signal: watch::Receiver<bool>,
     pub stop_signal_sender: watch::Sender<bool>,
+    pub events: broadcast::Sender<DdkEvent>,
+    pub peers: Arc<PeerConnections<T, S>>,
+    pub(crate) tasks: Arc<Mutex<Vec<JoinHandle<()>>>>,
+    pub(crate) health: Arc<ManagerHealthTracker>,
+    pub config: DdkConfig,
+    /// Periodic backups of the storage, if set.
+    #[cfg(feature = "backup")]
+    pub backup_schedule: Option<BackupSchedule>,
+    /// Emergency refund records of signed contracts, if kept.
+    pub emergency_records: Option<EmergencyRecords>,
 }
 
 impl<T, S, O> DlcDevKit<T, S, O>
@@ -68,6 +224,10 @@ where
     O: Oracle,
 {
     pub fn start(&self) -> anyhow::Result<()> {
+        if self.runtime.read().unwrap().is_some() {
+            return Err(anyhow!("DDK is still running."));
+        }
+
         let runtime = tokio::runtime::Builder::new_multi_thread()
             .enable_all()
             .build()
@@ -82,75 +242,274 @@ where
             return Err(anyhow!("DDK is still running."));
         }
 
+        self.stop_signal_sender.send_replace(false);
+        let mut tasks = self.tasks.lock().unwrap();
+
         let manager_clone = self.manager.clone();
         let receiver_clone = self.receiver.clone();
-        runtime.spawn(async move { Self::run_manager(manager_clone, receiver_clone).await });
+        let health = self.health.clone();
+        let stop_signal = self.stop_signal_sender.subscribe();
+        tasks.push(runtime.spawn(async move {
+            Self::supervise_manager(manager_clone, receiver_clone, health, stop_signal).await
+        }));
 
         let transport_clone = self.transport.clone();
         let manager_clone = self.manager.clone();
-        let stop_signal = self.stop_signal.clone();
-        runtime.spawn(async move {
+        let stop_signal = self.stop_signal_sender.subscribe();
+        tasks.push(runtime.spawn(async move {
             if let Err(e) = transport_clone.start(stop_signal, manager_clone).await {
                 tracing::error!(error = e.to_string(), "Error in transport listeners.");
             }
-        });
+        }));
 
         let wallet_clone = self.wallet.clone();
-        runtime.spawn(async move {
-            let mut timer = tokio::time::interval(Duration::from_secs(60));
+        let events = self.events.clone();
+        let sync_interval = self.config.wallet_sync_interval;
+        let mut stop_signal = self.stop_signal_sender.subscribe();
+        tasks.push(runtime.spawn(async move {
+            let mut timer = tokio::time::interval(sync_interval);
             loop {
-                timer.tick().await;
-                if let Err(e) = wallet_clone.sync().await {
-                    tracing::warn!(error=?e, "Did not sync wallet.");
-                };
+                tokio::select! {
+                    _ = stop_signal.changed() => break,
+                    _ = timer.tick() => match wallet_clone.sync().await {
+                        Ok(()) => {
+                            let _ = events.send(DdkEvent::WalletSynced);
+                        }
+                        Err(e) => tracing::warn!(error=?e, "Did not sync wallet."),
+                    },
+                }
             }
-        });
+        }));
+
+        if let Some(deadline) = self.config.fee_bump_deadline {
+            let mut monitor = FeeBumpMonitor::new(self.wallet.clone(), deadline);
+            let events = self.events.clone();
+            let mut stop_signal = self.stop_signal_sender.subscribe();
+            tasks.push(runtime.spawn(async move {
+                let mut timer = tokio::time::interval(sync_interval);
+                loop {
+                    tokio::select! {
+                        _ = stop_signal.changed() => break,
+                        _ = timer.tick() => match monitor.check().await {
+                            Ok(bumped) => {
+                                for (txid, child_txid) in bumped {
+                                    let _ = events.send(DdkEvent::FeeBumped { txid, child_txid });
+                                }
+                            }
+                            Err(e) => tracing::warn!(error=?e, "Did not check for stuck transactions."),
+                        },
+                    }
+                }
+            }));
+        }
+
+        let fee_estimates = self.wallet.fee_estimates();
+        let mut stop_signal = self.stop_signal_sender.subscribe();
+        tasks.push(runtime.spawn(async move {
+            let mut timer = tokio::time::interval(fee_estimates.refresh_interval());
+            loop {
+                tokio::select! {
+                    _ = stop_signal.changed() => break,
+                    _ = timer.tick() => {
+                        if let Err(e) = fee_estimates.update().await {
+                            tracing::warn!(error=?e, "Did not update fee estimates.");
+                        }
+                    }
+                }
+            }
+        }));
 
         let processor = self.sender.clone();
-        runtime.spawn(async move {
-            let mut timer = tokio::time::interval(Duration::from_secs(5));
+        let check_interval = self.config.periodic_check_interval;
+        let mut stop_signal = self.stop_signal_sender.subscribe();
+        tasks.push(runtime.spawn(async move {
+            let mut timer = tokio::time::interval(check_interval);
             loop {
-                timer.tick().await;
-                processor
-                    .send(DlcManagerMessage::PeriodicCheck)
-                    .expect("couldn't send periodic check");
+                tokio::select! {
+                    _ = stop_signal.changed() => break,
+                    _ = timer.tick() => {
+                        if let Err(e) = processor.send(DlcManagerMessage::PeriodicCheck) {
+                            tracing::error!(error=?e, "Manager is gone, stopping periodic checks.");
+                            break;
+                        }
+                    }
+                }
             }
-        });
+        }));
 
         #[cfg(feature = "marketplace")]
         {
             let storage_clone = self.storage.clone();
-            runtime.spawn(async move {
+            let stop_signal = self.stop_signal_sender.subscribe();
+            tasks.push(runtime.spawn(async move {
                 tracing::info!("Starting marketplace listener.");
-                marketplace_listener(&storage_clone, vec![DEFAULT_NOSTR_RELAY])
-                    .await
-                    .unwrap();
-            });
+                if let Err(e) =
+                    marketplace_listener(&storage_clone, vec![DEFAULT_NOSTR_RELAY], stop_signal)
+                        .await
+                {
+                    tracing::error!(error = e.to_string(), "Error in marketplace listener.");
+                }
+            }));
         }
 
-        // TODO: connect stored peers.
+        let peers = self.peers.clone();
+        let mut stop_signal = self.stop_signal_sender.subscribe();
+        tasks.push(runtime.spawn(async move {
+            let mut timer = tokio::time::interval(Duration::from_secs(10));
+            loop {
+                tokio::select! {
+                    _ = stop_signal.changed() => break,
+                    _ = timer.tick() => {
+                        if let Err(e) = peers.reconnect().await {
+                            tracing::warn!(error=?e, "Could not reconnect to stored peers.");
+                        }
+                    }
+                }
+            }
+        }));
+
+        #[cfg(feature = "backup")]
+        if let Some(schedule) = self.backup_schedule.clone() {
+            let storage = self.storage.clone();
+            let events = self.events.clone();
+            let mut stop_signal = self.stop_signal_sender.subscribe();
+            tasks.push(runtime.spawn(async move {
+                let mut timer = tokio::time::interval(schedule.interval);
+                loop {
+                    tokio::select! {
+                        _ = stop_signal.changed() => break,
+                        _ = timer.tick() => {
+                            // Exporting and writing the archive blocks on storage and disk.
+                            let schedule = schedule.clone();
+                            let storage = storage.clone();
+                            let written = tokio::task::spawn_blocking(move || {
+                                schedule.write_backup(storage.as_ref())
+                            })
+                            .await;
+                            match written {
+                                Ok(Ok(path)) => {
+                                    let _ = events.send(DdkEvent::BackupWritten { path });
+                                }
+                                Ok(Err(e)) => tracing::error!(error=?e, "Could not write backup."),
+                                Err(e) => tracing::error!(error=?e, "Backup task failed."),
+                            }
+                        }
+                    }
+                }
+            }));
+        }
 
+        drop(tasks);
         *runtime_lock = Some(runtime);
         Ok(())
     }
 
-    pub fn stop(&self) -> anyhow::Result<()> {
+    /// Stop DDK, waiting up to [`DEFAULT_SHUTDOWN_TIMEOUT`] for the running tasks to
+    /// finish. Storage is flushed afterwards.
+    pub async fn stop(&self) -> anyhow::Result<()> {
+        self.stop_with_timeout(DEFAULT_SHUTDOWN_TIMEOUT).await
+    }
+
+    /// Signal every task to stop and wait for them to finish. Messages that are being
+    /// processed by the manager complete, queued messages are failed back to the caller.
+    /// The runtime is shut down when the tasks are done or `timeout` elapses, storage is
+    /// flushed afterwards in both cases.
+    pub async fn stop_with_timeout(&self, timeout: Duration) -> anyhow::Result<()> {
+        if self.runtime.read().unwrap().is_none() {
+            return Err(anyhow!("Runtime is not running."));
+        }
+
         tracing::warn!("Shutting down DDK runtime and listeners.");
-        self.stop_signal_sender.send(true)?;
-        let mut runtime_lock = self.runtime.write().unwrap();
-        if let Some(rt) = runtime_lock.take() {
+        // New messages are refused from here on, see `send_to_manager`.
+        self.stop_signal_sender.send_replace(true);
+
+        let tasks = std::mem::take(&mut *self.tasks.lock().unwrap());
+        let wait = async {
+            for task in tasks {
+                if let Err(e) = task.await {
+                    tracing::error!(error = e.to_string(), "DDK task did not stop cleanly.");
+                }
+            }
+        };
+        let stopped = tokio::time::timeout(timeout, wait).await;
+
+        if let Some(rt) = self.runtime.write().unwrap().take() {
             rt.shutdown_background();
-            Ok(())
-        } else {
-            Err(anyhow!("Runtime is not running."))
+        }
+        // A message sent while the manager task was exiting is failed back here.
+        match self.receiver.try_lock() {
+            Ok(mut receiver) => drop_queued_messages(&mut receiver),
+            Err(_) => tracing::warn!("Manager task still holds the message queue."),
+        }
+
+        let flushed = self.storage.flush().await;
+        tracing::info!("DDK stopped.");
+
+        match (stopped, flushed) {
+            (Ok(()), flushed) => flushed,
+            (Err(_), Ok(())) => Err(anyhow!("Timed out waiting for DDK to stop.")),
+            (Err(_), Err(e)) => Err(anyhow!(
+                "Timed out waiting for DDK to stop. Failed to flush storage: {}",
+                e
+            )),
+        }
+    }
+
+    /// Health of the manager task.
+    pub fn manager_health(&self) -> ManagerHealth {
+        self.health.snapshot()
+    }
+
+    /// Run the manager task and restart it when it exits before DDK is stopped. A
+    /// panic while handling a message fails that message back to its caller.
+    async fn supervise_manager(
+        manager: Arc<DlcDevKitDlcManager<S, O>>,
+        receiver: Arc<tokio::sync::Mutex<mpsc::UnboundedReceiver<DlcManagerMessage>>>,
+        health: Arc<ManagerHealthTracker>,
+        mut stop_signal: watch::Receiver<bool>,
+    ) {
+        loop {
+            let task = tokio::spawn(Self::run_manager(
+                manager.clone(),
+                receiver.clone(),
+                health.clone(),
+                stop_signal.clone(),
+            ));
+            let reason = match task.await {
+                Ok(()) => "Manager task exited.".to_string(),
+                Err(e) => e.to_string(),
+            };
+            health.set_running(false);
+
+            if *stop_signal.borrow() {
+                break;
+            }
+            tracing::error!(reason, "Manager task stopped unexpectedly. Restarting.");
+            health.record_restart(reason);
+
+            tokio::select! {
+                _ = stop_signal.changed() => break,
+                _ = tokio::time::sleep(MANAGER_RESTART_DELAY) => {}
+            }
         }
     }
 
     async fn run_manager(
         manager: Arc<DlcDevKitDlcManager<S, O>>,
-        receiver: Arc<Receiver<DlcManagerMessage>>,
+        receiver: Arc<tokio::sync::Mutex<mpsc::UnboundedReceiver<DlcManagerMessage>>>,
+        health: Arc<ManagerHealthTracker>,
+        mut stop_signal: watch::Receiver<bool>,
     ) {
-        while let Ok(msg) = receiver.recv() {
+        let mut receiver = receiver.lock().await;
+        health.set_running(true);
+        while !*stop_signal.borrow() {
+            let msg = tokio::select! {
+                _ = stop_signal.changed() => break,
+                msg = receiver.recv() => match msg {
+                    Some(msg) => msg,
+                    None => break,
+                },
+            };
             match msg {
                 DlcManagerMessage::OfferDlc {
                     contract_input,
@@ -158,12 +517,9 @@ where
                     oracle_announcements: _,
                     responder,
                 } => {
-                    let offer = manager
-                        .send_offer(&contract_input, counter_party)
-                        .await
-                        .expect("can't create offerdlc");
+                    let offer = manager.send_offer(&contract_input, counter_party).await;
 
-                    responder.send(offer).expect("send offer error")
+                    respond(responder, offer)
                 }
                 DlcManagerMessage::AcceptDlc {
                     contract,
@@ -171,21 +527,159 @@ where
                 } => {
                     let accept_dlc = manager.accept_contract_offer(&contract).await;
 
-                    responder.send(accept_dlc).expect("can't send")
+                    respond(responder, accept_dlc)
+                }
+                DlcManagerMessage::RejectOffer {
+                    contract,
+                    responder,
+                } => {
+                    let reject = manager.reject_contract_offer(&contract);
+
+                    respond(responder, reject)
+                }
+                DlcManagerMessage::CancelOffer {
+                    contract,
+                    responder,
+                } => {
+                    let cancel = manager.cancel_offer(&contract);
+
+                    respond(responder, cancel)
+                }
+                DlcManagerMessage::OfferChannel {
+                    contract_input,
+                    counter_party,
+                    responder,
+                } => {
+                    let offer = manager.offer_channel(&contract_input, counter_party).await;
+
+                    respond(responder, offer)
+                }
+                DlcManagerMessage::AcceptChannel { channel, responder } => {
+                    let accept = manager.accept_channel(&channel).await;
+
+                    respond(responder, accept)
+                }
+                DlcManagerMessage::SettleChannel {
+                    channel,
+                    counter_payout,
+                    responder,
+                } => {
+                    let settle = manager.settle_offer(&channel, counter_payout);
+
+                    respond(responder, settle)
+                }
+                DlcManagerMessage::AcceptSettle { channel, responder } => {
+                    let accept = manager.accept_settle_offer(&channel);
+
+                    respond(responder, accept)
+                }
+                DlcManagerMessage::RenewChannel {
+                    channel,
+                    counter_payout,
+                    contract_input,
+                    responder,
+                } => {
+                    let renew = manager
+                        .renew_offer(&channel, counter_payout, &contract_input)
+                        .await;
+
+                    respond(responder, renew)
+                }
+                DlcManagerMessage::AcceptRenew { channel, responder } => {
+                    let accept = manager.accept_renew_offer(&channel);
+
+                    respond(responder, accept)
+                }
+                DlcManagerMessage::CloseChannel {
+                    channel,
+                    counter_payout,
+                    responder,
+                } => {
+                    let close = manager
+                        .offer_collaborative_close(&channel, counter_payout)
+                        .and_then(|offer| {
+                            let counter_party = manager
+                                .get_store()
+                                .get_channel(&channel)?
+                                .ok_or_else(|| Error::InvalidParameters("Unknown channel.".into()))?
+                                .get_counter_party_id();
+                            Ok((offer, counter_party))
+                        });
+
+                    respond(responder, close)
+                }
+                DlcManagerMessage::AcceptClose { channel, responder } => {
+                    let close = manager.accept_collaborative_close(&channel).await;
+
+                    respond(responder, close)
+                }
+                DlcManagerMessage::ForceCloseChannel { channel, responder } => {
+                    let close = manager.force_close_channel(&channel).await;
+
+                    respond(responder, close)
                 }
                 DlcManagerMessage::PeriodicCheck => {
-                    manager.periodic_check(false).await.unwrap();
+                    if let Err(e) = manager.periodic_check(true).await {
+                        tracing::error!(error=?e, "Error in periodic check.");
+                        health.record_periodic_check_failure(e.to_string());
+                    }
                 }
             }
         }
+
+        health.set_running(false);
+        drop_queued_messages(&mut receiver);
     }
 
-    pub fn connect_if_necessary(&self) -> anyhow::Result<()> {
-        let _known_peers = self.storage.list_peers()?;
+    /// Send a message to the manager task. Fails if DDK is not running or stopping, the
+    /// message would never be answered.
+    fn send_to_manager(&self, message: DlcManagerMessage) -> anyhow::Result<()> {
+        // Holding the runtime lock while sending means `stop_with_timeout` drains the
+        // queue only after the message is in it.
+        let runtime = self.runtime.read().unwrap();
+        if runtime.is_none() || *self.stop_signal_sender.borrow() {
+            return Err(anyhow!("DDK is not running."));
+        }
+        Ok(self.sender.send(message)?)
+    }
 
-        // check from already connected
+    /// Archive of the full node state, encrypted if a secret is given. Restore it into any
+    /// storage with [`backup::import_backup`].
+    #[cfg(feature = "backup")]
+    pub fn export_backup(&self, secret: Option<&StorageSecret>) -> anyhow::Result<Vec<u8>> {
+        backup::export_backup(self.storage.as_ref(), secret)
+    }
 
-        Ok(())
+    /// Broadcast the refunds of the emergency records whose refund locktime has passed, see
+    /// [`recovery::recover_refunds`]. The storage is not used.
+    pub async fn recover_refunds(&self) -> anyhow::Result<Vec<RefundOutcome>> {
+        let records = self
+            .emergency_records
+            .as_ref()
+            .ok_or_else(|| anyhow!("Emergency records are not kept."))?;
+        recovery::recover_refunds(records, self.wallet.chain().as_ref()).await
+    }
+
+    /// Subscribe to the events of the node. Events are only delivered to subscribers
+    /// that exist when they are emitted.
+    pub fn subscribe(&self) -> broadcast::Receiver<DdkEvent> {
+        self.events.subscribe()
+    }
+
+    /// Connect to a peer with the transport. The peer is stored and reconnected
+    /// when the connection drops or DDK restarts.
+    pub async fn connect_peer(&self, pubkey: PublicKey, host: &str) -> anyhow::Result<()> {
+        self.peers.connect(pubkey, host).await
+    }
+
+    /// Reconnect to stored peers that are not connected.
+    pub async fn connect_if_necessary(&self) -> anyhow::Result<()> {
+        self.peers.reconnect().await
+    }
+
+    /// Stored and connected peers with their live connection status.
+    pub async fn list_peers(&self) -> anyhow::Result<Vec<PeerStatus>> {
+        self.peers.list_peers().await
     }
 
     pub fn network(&self) -> Network {
@@ -198,14 +692,27 @@ where
         counter_party: PublicKey,
         oracle_announcements: Vec<OracleAnnouncement>,
     ) -> anyhow::Result<OfferDlc> {
+        if self.wallet.has_external_signer()
+            && contract_input
+                .contract_infos
+                .iter()
+                .any(|info| is_numerical(&info.contract_descriptor))
+        {
+            return Err(anyhow!(
+                "Numerical contracts are not supported with an external signer."
+            ));
+        }
         let (responder, receiver) = unbounded();
-        self.sender.send(DlcManagerMessage::OfferDlc {
+        self.send_to_manager(DlcManagerMessage::OfferDlc {
             contract_input: contract_input.to_owned(),
             counter_party,
             oracle_announcements,
             responder,
         })?;
-        let offer = receiver.recv()?;
+        let offer = receiver.recv()?.map_err(|e| {
+            tracing::error!(error=?e, "Could not create offer.");
+            anyhow!("Could not create dlc offer.")
+        })?;
 
         let contract_id = hex::encode(offer.temporary_contract_id);
         self.transport
@@ -224,8 +731,21 @@ where
         &self,
         contract: [u8; 32],
     ) -> anyhow::Result<(String, String, AcceptDlc)> {
+        if self.wallet.has_external_signer() {
+            if let Some(Contract::Offered(offered)) = self.storage.get_contract(&contract)? {
+                if offered
+                    .contract_info
+                    .iter()
+                    .any(|info| is_numerical(&info.contract_descriptor))
+                {
+                    return Err(anyhow!(
+                        "Numerical contracts are not supported with an external signer."
+                    ));
+                }
+            }
+        }
         let (responder, receiver) = unbounded();
-        self.sender.send(DlcManagerMessage::AcceptDlc {
+        self.send_to_manager(DlcManagerMessage::AcceptDlc {
             contract,
             responder,
         })?;
@@ -246,9 +766,298 @@ where
         Ok((contract_id, counter_party, accept_dlc))
     }
 
+    /// Reject a DLC offer received from a counterparty. The counterparty is sent a
+    /// [`Reject`] message so it can release the funds reserved for the offer.
+    pub async fn reject_dlc_offer(&self, contract: [u8; 32]) -> anyhow::Result<Reject> {
+        let (responder, receiver) = unbounded();
+        self.send_to_manager(DlcManagerMessage::RejectOffer {
+            contract,
+            responder,
+        })?;
+
+        let (reject, public_key) = receiver.recv()?.map_err(|e| {
+            tracing::error!(error=?e, "Could not reject offer.");
+            anyhow!("Could not reject dlc offer.")
+        })?;
+
+        self.transport
+            .send_message(public_key, Message::Reject(reject.clone()))
+            .await;
+
+        let contract_id = hex::encode(contract);
+        let counter_party = public_key.to_string();
+        tracing::info!(counter_party, contract_id, "Rejected DLC offer.");
+
+        Ok(reject)
+    }
+
+    /// Cancel a DLC offer that has not been accepted yet, releasing the UTXOs reserved
+    /// to fund it. The counterparty is sent a [`Reject`] message so it drops the offer.
+    pub async fn cancel_dlc_offer(&self, contract: [u8; 32]) -> anyhow::Result<Reject> {
+        let (responder, receiver) = unbounded();
+        self.send_to_manager(DlcManagerMessage::CancelOffer {
+            contract,
+            responder,
+        })?;
+
+        let (reject, public_key) = receiver.recv()?.map_err(|e| {
+            tracing::error!(error=?e, "Could not cancel offer.");
+            anyhow!("Could not cancel dlc offer.")
+        })?;
+
+        self.transport
+            .send_message(public_key, Message::Reject(reject.clone()))
+            .await;
+
+        let contract_id = hex::encode(contract);
+        let counter_party = public_key.to_string();
+        tracing::info!(counter_party, contract_id, "Cancelled DLC offer.");
+
+        Ok(reject)
+    }
+
+    /// Offer a DLC channel funded with the collateral of the contract input.
+    pub async fn offer_channel(
+        &self,
+        contract_input: &ContractInput,
+        counter_party: PublicKey,
+    ) -> anyhow::Result<OfferChannel> {
+        if self.wallet.has_external_signer() {
+            return Err(anyhow!(
+                "Channels are not supported with an external signer."
+            ));
+        }
+        let (responder, receiver) = unbounded();
+        self.send_to_manager(DlcManagerMessage::OfferChannel {
+            contract_input: contract_input.to_owned(),
+            counter_party,
+            responder,
+        })?;
+
+        let offer = receiver.recv()?.map_err(|e| {
+            tracing::error!(error=?e, "Could not offer channel.");
+            anyhow!("Could not offer channel.")
+        })?;
+
+        self.transport
+            .send_message(counter_party, Message::OfferChannel(offer.clone()))
+            .await;
+
+        let channel_id = hex::encode(offer.temporary_channel_id);
+        let counter_party = counter_party.to_string();
+        tracing::info!(
+            counter_party,
+            channel_id,
+            "Sent channel offer to counterparty."
+        );
+
+        Ok(offer)
+    }
+
+    /// Accept a channel offered by a counterparty. Returns the channel id and the
+    /// contract id of the first contract in the channel.
+    pub async fn accept_channel(
+        &self,
+        channel: ChannelId,
+    ) -> anyhow::Result<(ChannelId, ContractId, AcceptChannel)> {
+        if self.wallet.has_external_signer() {
+            return Err(anyhow!(
+                "Channels are not supported with an external signer."
+            ));
+        }
+        let (responder, receiver) = unbounded();
+        self.send_to_manager(DlcManagerMessage::AcceptChannel { channel, responder })?;
+
+        let (accept, channel_id, contract_id, public_key) = receiver.recv()?.map_err(|e| {
+            tracing::error!(error=?e, "Could not accept channel.");
+            anyhow!("Could not accept channel.")
+        })?;
+
+        self.transport
+            .send_message(public_key, Message::AcceptChannel(accept.clone()))
+            .await;
+
+        let counter_party = public_key.to_string();
+        tracing::info!(
+            counter_party,
+            channel_id = hex::encode(channel_id),
+            "Accepted channel."
+        );
+
+        Ok((channel_id, contract_id, accept))
+    }
+
+    /// Offer to settle the channel so that the counterparty gets `counter_payout`.
+    pub async fn settle_channel(
+        &self,
+        channel: ChannelId,
+        counter_payout: Amount,
+    ) -> anyhow::Result<SettleOffer> {
+        let (responder, receiver) = unbounded();
+        self.send_to_manager(DlcManagerMessage::SettleChannel {
+            channel,
+            counter_payout: counter_payout.to_sat(),
+            responder,
+        })?;
+
+        let (settle, public_key) = receiver.recv()?.map_err(|e| {
+            tracing::error!(error=?e, "Could not settle channel.");
+            anyhow!("Could not offer to settle channel.")
+        })?;
+
+        self.transport
+            .send_message(public_key, Message::SettleOffer(settle.clone()))
+            .await;
+
+        let channel_id = hex::encode(channel);
+        tracing::info!(channel_id, "Sent settle offer to counterparty.");
+
+        Ok(settle)
+    }
+
+    /// Accept a settle offer received from the counterparty.
+    pub async fn accept_channel_settle(&self, channel: ChannelId) -> anyhow::Result<SettleAccept> {
+        let (responder, receiver) = unbounded();
+        self.send_to_manager(DlcManagerMessage::AcceptSettle { channel, responder })?;
+
+        let (accept, public_key) = receiver.recv()?.map_err(|e| {
+            tracing::error!(error=?e, "Could not accept settle offer.");
+            anyhow!("Could not accept settle offer.")
+        })?;
+
+        self.transport
+            .send_message(public_key, Message::SettleAccept(accept.clone()))
+            .await;
+
+        let channel_id = hex::encode(channel);
+        tracing::info!(channel_id, "Accepted settle offer.");
+
+        Ok(accept)
+    }
+
+    /// Offer to replace the contract in the channel with a new contract, paying
+    /// `counter_payout` to the counterparty for the current contract.
+    pub async fn renew_channel(
+        &self,
+        channel: ChannelId,
+        counter_payout: Amount,
+        contract_input: &ContractInput,
+    ) -> anyhow::Result<RenewOffer> {
+        let (responder, receiver) = unbounded();
+        self.send_to_manager(DlcManagerMessage::RenewChannel {
+            channel,
+            counter_payout: counter_payout.to_sat(),
+            contract_input: contract_input.to_owned(),
+            responder,
+        })?;
+
+        let (renew, public_key) = receiver.recv()?.map_err(|e| {
+            tracing::error!(error=?e, "Could not renew channel.");
+            anyhow!("Could not offer to renew channel.")
+        })?;
+
+        self.transport
+            .send_message(public_key, Message::RenewOffer(renew.clone()))
+            .await;
+
+        let channel_id = hex::encode(channel);
+        tracing::info!(channel_id, "Sent renew offer to counterparty.");
+
+        Ok(renew)
+    }
+
+    /// Accept a renew offer received from the counterparty.
+    pub async fn accept_channel_renew(&self, channel: ChannelId) -> anyhow::Result<RenewAccept> {
+        let (responder, receiver) = unbounded();
+        self.send_to_manager(DlcManagerMessage::AcceptRenew { channel, responder })?;
+
+        let (accept, public_key) = receiver.recv()?.map_err(|e| {
+            tracing::error!(error=?e, "Could not accept renew offer.");
+            anyhow!("Could not accept renew offer.")
+        })?;
+
+        self.transport
+            .send_message(public_key, Message::RenewAccept(accept.clone()))
+            .await;
+
+        let channel_id = hex::encode(channel);
+        tracing::info!(channel_id, "Accepted renew offer.");
+
+        Ok(accept)
+    }
+
+    /// Offer to collaboratively close the channel so that the counterparty gets
+    /// `counter_payout`. The counterparty closes the channel on chain by accepting.
+    pub async fn close_channel(
+        &self,
+        channel: ChannelId,
+        counter_payout: Amount,
+    ) -> anyhow::Result<CollaborativeCloseOffer> {
+        let (responder, receiver) = unbounded();
+        self.send_to_manager(DlcManagerMessage::CloseChannel {
+            channel,
+            counter_payout: counter_payout.to_sat(),
+            responder,
+        })?;
+
+        let (close, public_key) = receiver.recv()?.map_err(|e| {
+            tracing::error!(error=?e, "Could not close channel.");
+            anyhow!("Could not offer to close channel.")
+        })?;
+
+        self.transport
+            .send_message(public_key, Message::CollaborativeCloseOffer(close.clone()))
+            .await;
+
+        let channel_id = hex::encode(channel);
+        tracing::info!(
+            channel_id,
+            "Sent collaborative close offer to counterparty."
+        );
+
+        Ok(close)
+    }
+
+    /// Accept a collaborative close offer and broadcast the close transaction.
+    pub async fn accept_channel_close(&self, channel: ChannelId) -> anyhow::Result<()> {
+        let (responder, receiver) = unbounded();
+        self.send_to_manager(DlcManagerMessage::AcceptClose { channel, responder })?;
+
+        receiver.recv()?.map_err(|e| {
+            tracing::error!(error=?e, "Could not accept close offer.");
+            anyhow!("Could not accept collaborative close offer.")
+        })?;
+
+        let channel_id = hex::encode(channel);
+        tracing::info!(channel_id, "Collaboratively closed channel.");
+
+        Ok(())
+    }
+
+    /// Unilaterally close the channel by broadcasting the latest buffer transaction.
+    pub async fn force_close_channel(&self, channel: ChannelId) -> anyhow::Result<()> {
+        let (responder, receiver) = unbounded();
+        self.send_to_manager(DlcManagerMessage::ForceCloseChannel { channel, responder })?;
+
+        receiver.recv()?.map_err(|e| {
+            tracing::error!(error=?e, "Could not force close channel.");
+            anyhow!("Could not force close channel.")
+        })?;
+
+        let channel_id = hex::encode(channel);
+        tracing::info!(channel_id, "Force closed channel.");
+
+        Ok(())
+    }
+
     pub fn balance(&self) -> anyhow::Result<crate::Balance> {
         let wallet_balance = self.wallet.get_balance()?;
-        let contracts = self.storage.get_contracts()?;
+        let contracts = self
+            .storage
+            .query_contracts(
+                &ContractQuery::new().states([ContractState::Confirmed, ContractState::PreClosed]),
+            )?
+            .contracts;
 
         let contract = &contracts
             .iter()
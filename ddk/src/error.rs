@@ -5,6 +5,16 @@ pub fn esplora_err_to_manager_err(e: EsploraError) -> ManagerError {
     ManagerError::BlockchainError(e.to_string())
 }
 
+#[cfg(feature = "bitcoind")]
+pub fn bitcoind_err_to_manager_err(e: bitcoincore_rpc::Error) -> ManagerError {
+    ManagerError::BlockchainError(e.to_string())
+}
+
+#[cfg(feature = "cbf")]
+pub fn cbf_err_to_manager_err(e: kyoto::ClientError) -> ManagerError {
+    ManagerError::BlockchainError(e.to_string())
+}
+
 pub fn wallet_err_to_manager_err(e: WalletError) -> ManagerError {
     ManagerError::WalletError(Box::new(e))
 }
@@ -28,10 +38,20 @@ pub enum WalletError {
     Esplora(#[from] Box<bdk_esplora::esplora_client::Error>),
     #[error("Broadcast to esplora: {0}")]
     Broadcast(#[from] bdk_esplora::esplora_client::Error),
+    #[cfg(feature = "bitcoind")]
+    #[error("Wallet call to bitcoind: {0}")]
+    Bitcoind(#[from] bitcoincore_rpc::Error),
+    #[cfg(feature = "cbf")]
+    #[error("Wallet call to the compact block filter node: {0}")]
+    Cbf(#[from] kyoto::ClientError),
+    #[error("Chain source error: {0}")]
+    Blockchain(String),
     #[error("Could not extract txn from psbt. {0}")]
     ExtractTx(#[from] bitcoin::psbt::ExtractTxError),
     #[error("Applying an update to the wallet.")]
     UtxoUpdate(#[from] bdk_chain::local_chain::CannotConnectError),
+    #[error("Applying a block to the wallet.")]
+    BlockUpdate(#[from] bdk_chain::local_chain::ApplyHeaderError),
     #[error("Error signing PSBT: {0}")]
     Signing(#[from] bdk_wallet::signer::SignerError),
     #[error("Receive error from wallet channel: {0}")]
@@ -42,4 +62,10 @@ pub enum WalletError {
     Serde(#[from] serde_json::Error),
     #[error("Error converting to descriptor.")]
     Descriptor(#[from] bdk_wallet::descriptor::DescriptorError),
+    #[error("Invalid wallet config: {0}")]
+    InvalidConfig(String),
+    #[error("Could not build transaction: {0}")]
+    BuildTx(String),
+    #[error("Could not bump fee: {0}")]
+    FeeBump(String),
 }
@@ -0,0 +1,63 @@
+use bitcoin::secp256k1::PublicKey;
+use bitcoin::Txid;
+pub use ddk_manager::event::Event;
+use ddk_manager::event::EventHandler;
+use ddk_manager::ContractId;
+#[cfg(feature = "backup")]
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::broadcast;
+
+/// Capacity of the event channel. Slow subscribers miss the oldest events.
+pub(crate) const EVENT_CHANNEL_CAPACITY: usize = 1024;
+
+/// State changes of a [`crate::DlcDevKit`] node. Subscribe with [`crate::DlcDevKit::subscribe`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DdkEvent {
+    /// A contract or channel event emitted by the DLC manager.
+    Manager(Event),
+    /// The wallet finished syncing with the chain.
+    WalletSynced,
+    /// Connected to a peer.
+    PeerConnected { pubkey: PublicKey, host: String },
+    /// The fee of an unconfirmed contract transaction was bumped by a child transaction.
+    FeeBumped { txid: Txid, child_txid: Txid },
+    /// A scheduled backup was written.
+    #[cfg(feature = "backup")]
+    BackupWritten { path: PathBuf },
+    /// The emergency record of a contract could not be written or removed. The funding
+    /// transaction of a signed contract may be broadcast without a record, so the refund
+    /// can not be recovered from [`crate::recovery::EmergencyRecords`] alone.
+    EmergencyRecordFailed {
+        contract_id: ContractId,
+        error: String,
+    },
+}
+
+impl From<Event> for DdkEvent {
+    fn from(event: Event) -> Self {
+        DdkEvent::Manager(event)
+    }
+}
+
+/// Forwards [`ddk_manager::manager::Manager`] events to the DDK event channel.
+pub(crate) struct EventForwarder(pub broadcast::Sender<DdkEvent>);
+
+impl EventHandler for EventForwarder {
+    fn handle_event(&self, event: Event) {
+        // Sending only fails when there are no subscribers.
+        let _ = self.0.send(event.into());
+    }
+}
+
+/// Passes manager events to each handler in turn.
+pub(crate) struct EventHandlers(pub Vec<Arc<dyn EventHandler>>);
+
+impl EventHandler for EventHandlers {
+    fn handle_event(&self, event: Event) {
+        for handler in &self.0 {
+            handler.handle_event(event.clone());
+        }
+    }
+}
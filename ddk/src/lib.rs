@@ -2,13 +2,20 @@
 // #![doc = include_str!("../README.md")]
 #![allow(dead_code)]
 // #![allow(unused_imports)]
+/// Export and import of the full node state.
+#[cfg(feature = "backup")]
+pub mod backup;
 /// Build a DDK application.
 pub mod builder;
 /// Working with the bitcoin chain.
 pub mod chain;
+/// Runtime intervals and manager settings.
+pub mod config;
 mod ddk;
 /// DDK error types
 pub mod error;
+/// Events emitted by a running DDK node.
+pub mod event;
 /// JSON structs
 pub mod json;
 /// Nostr related functions.
@@ -16,6 +23,10 @@ pub mod json;
 pub(crate) mod nostr;
 /// Oracle clients.
 pub mod oracle;
+/// Emergency refunds of signed contracts without the storage.
+pub mod recovery;
+/// External signers for wallet and contract keys.
+pub mod signer;
 /// Storage implementations.
 pub mod storage;
 /// Transport services.
@@ -25,10 +36,13 @@ pub mod util;
 /// The internal [`bdk_wallet::PersistedWallet`].
 pub mod wallet;
 
+pub use config::DdkConfig;
 /// DDK object with all services
 pub use ddk::DlcDevKit;
 pub use ddk::DlcManagerMessage;
+pub use ddk::ManagerHealth;
 pub use ddk_manager;
+pub use event::DdkEvent;
 
 /// Default nostr relay.
 pub const DEFAULT_NOSTR_RELAY: &str = "wss://nostr.dlcdevkit.com";
@@ -36,14 +50,19 @@ pub const DEFAULT_NOSTR_RELAY: &str = "wss://nostr.dlcdevkit.com";
 use async_trait::async_trait;
 use bdk_wallet::ChangeSet;
 use bitcoin::secp256k1::{PublicKey, SecretKey};
-use bitcoin::Amount;
+use bitcoin::{Amount, OutPoint};
 use ddk::DlcDevKitDlcManager;
+use ddk_manager::channel::Channel;
 use dlc_messages::oracle_msgs::OracleAnnouncement;
 use dlc_messages::Message;
 use error::WalletError;
+use std::collections::HashMap;
 use std::sync::Arc;
+use storage::query::{ContractPage, ContractQuery, ContractSummary};
 use tokio::sync::watch;
 use transport::PeerInformation;
+use wallet::labels::Label;
+use wallet::UtxoReservation;
 
 #[async_trait]
 /// Allows ddk to open a listening connection and send/receive dlc messages functionality.
@@ -60,30 +79,97 @@ pub trait Transport: Send + Sync + 'static {
     ) -> Result<(), anyhow::Error>;
     /// Send a message to a specific counterparty.
     async fn send_message(&self, counterparty: PublicKey, message: Message);
-    /// Connect to another peer
-    async fn connect_outbound(&self, pubkey: PublicKey, host: &str);
+    /// Connect to another peer. Returns the error of a failed connection, peers are
+    /// reconnected with backoff when this fails.
+    async fn connect_outbound(&self, pubkey: PublicKey, host: &str) -> anyhow::Result<()>;
+    /// Whether the transport is currently connected to the peer.
+    async fn is_connected(&self, pubkey: &PublicKey) -> bool;
+    /// Peers with an open connection, inbound or outbound. Transports without
+    /// connections to individual peers return none.
+    async fn connected_peers(&self) -> Vec<PeerInformation> {
+        Vec::new()
+    }
 }
 
 /// Storage for DLC contracts.
-pub trait Storage: ddk_manager::Storage + Send + Sync + 'static {
+#[async_trait]
+pub trait Storage: ddk_manager::Storage + KeyStorage + Send + Sync + 'static {
     ///// Instantiate the storage for the BDK wallet.
     fn initialize_bdk(&self) -> Result<ChangeSet, WalletError>;
     /// Save changeset to the wallet storage.
     fn persist_bdk(&self, changeset: &ChangeSet) -> Result<(), WalletError>;
     /// Connected counterparties.
     fn list_peers(&self) -> anyhow::Result<Vec<PeerInformation>>;
-    /// Persis counterparty.
+    /// Persist counterparty. Replaces the stored information of a peer with the same pubkey.
     fn save_peer(&self, peer: PeerInformation) -> anyhow::Result<()>;
     // #[cfg(feature = "marketplace")]
     fn save_announcement(&self, announcement: OracleAnnouncement) -> anyhow::Result<()>;
     // #[cfg(feature = "marketplace")]
     fn get_marketplace_announcements(&self) -> anyhow::Result<Vec<OracleAnnouncement>>;
+    /// Save the last fee rate estimates (sats/vbyte keyed by block target).
+    fn persist_fee_estimates(&self, estimates: &HashMap<u16, f64>) -> anyhow::Result<()>;
+    /// Last saved fee rate estimates.
+    fn get_fee_estimates(&self) -> anyhow::Result<Option<HashMap<u16, f64>>>;
+    /// Save coin reservations, replacing stored reservations of the same outpoints.
+    fn reserve_utxos(&self, reservations: &[UtxoReservation]) -> anyhow::Result<()>;
+    /// Coins reserved to fund contracts.
+    fn list_utxo_reservations(&self) -> anyhow::Result<Vec<UtxoReservation>>;
+    /// Release reserved coins. Outpoints that are not reserved are ignored.
+    fn unreserve_utxos(&self, outpoints: &[OutPoint]) -> anyhow::Result<()>;
+    /// Save BIP329 labels, replacing stored labels with the same type and reference.
+    fn save_labels(&self, labels: &[Label]) -> anyhow::Result<()>;
+    /// Labels of wallet transactions, addresses and outputs.
+    fn list_labels(&self) -> anyhow::Result<Vec<Label>>;
+    /// Contracts matching the query, one page at a time.
+    ///
+    /// The default implementation filters every contract of
+    /// [`ddk_manager::Storage::get_contracts`]. It does not know when contracts were stored,
+    /// so creation times are 0 and contracts are ordered by id. Storages that keep the
+    /// creation time, like the ones of this crate, override it.
+    fn query_contracts(&self, query: &ContractQuery) -> anyhow::Result<ContractPage> {
+        let mut contracts = self
+            .get_contracts()?
+            .into_iter()
+            .map(|contract| (contract.get_id(), contract))
+            .collect::<HashMap<_, _>>();
+        let summaries = contracts
+            .values()
+            .map(|contract| ContractSummary {
+                created_at: 0,
+                ..ContractSummary::new(contract, None)
+            })
+            .collect::<Vec<_>>();
+        let (page, next) = query.paginate(summaries);
+        Ok(ContractPage {
+            contracts: page
+                .iter()
+                .filter_map(|summary| contracts.remove(&summary.id))
+                .collect(),
+            next,
+        })
+    }
+    /// Every channel, whatever its state.
+    fn list_channels(&self) -> anyhow::Result<Vec<Channel>>;
+    /// Write buffered data to disk. Called when DDK stops.
+    async fn flush(&self) -> anyhow::Result<()> {
+        Ok(())
+    }
 }
 
 /// Retrieval of key material for signing DLC transactions
 pub trait KeyStorage {
     fn get_secret_key(&self, key_id: [u8; 32]) -> Result<SecretKey, WalletError>;
     fn store_secret_key(&self, key_id: [u8; 32], secret_key: SecretKey) -> Result<(), WalletError>;
+    /// Return the next unused derivation index of a key account and increment it. Indexes
+    /// must never be handed out twice, the counter has to survive restarts.
+    fn next_key_index(&self, account: u32) -> anyhow::Result<u32>;
+    /// Every stored secret key by its key id.
+    fn list_secret_keys(&self) -> Result<Vec<([u8; 32], SecretKey)>, WalletError>;
+    /// Next unused derivation index of every key account that handed out an index.
+    fn key_indexes(&self) -> anyhow::Result<HashMap<u32, u32>>;
+    /// Raise the next unused index of a key account to `next`. A higher index is kept,
+    /// indexes never go back.
+    fn advance_key_index(&self, account: u32, next: u32) -> anyhow::Result<()>;
 }
 
 /// Oracle client
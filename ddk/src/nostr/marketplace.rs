@@ -2,6 +2,7 @@ use crate::Storage;
 use nostr_rs::Timestamp;
 use nostr_sdk::{client::builder::ClientBuilder, Event, Kind, RelayPoolNotification};
 use std::ops::Deref;
+use tokio::sync::watch;
 
 /// NIP-88 compliant oracle announcement listener.
 ///
@@ -11,7 +12,11 @@ use std::ops::Deref;
 ///
 /// The marketplace listener can be paired with `ddk::transport::NostDlc` to fetch announcements
 /// and attestations from storage.
-pub async fn marketplace_listener<S: Deref>(storage: &S, relays: Vec<&str>) -> anyhow::Result<()>
+pub async fn marketplace_listener<S: Deref>(
+    storage: &S,
+    relays: Vec<&str>,
+    mut stop_signal: watch::Receiver<bool>,
+) -> anyhow::Result<()>
 where
     S::Target: Storage,
 {
@@ -25,20 +30,26 @@ where
 
     client.subscribe(vec![oracle_filter], None).await?;
 
-    while let Ok(notification) = client.notifications().recv().await {
-        match notification {
-            RelayPoolNotification::Event {
-                relay_url: _,
-                subscription_id: _,
-                event,
-            } => handle_oracle_event(storage, *event),
-            RelayPoolNotification::Shutdown => {
-                tracing::error!("Relay disconnected.")
-            }
-            _ => (),
+    let mut notifications = client.notifications();
+    loop {
+        tokio::select! {
+            _ = stop_signal.changed() => break,
+            notification = notifications.recv() => match notification {
+                Ok(RelayPoolNotification::Event {
+                    relay_url: _,
+                    subscription_id: _,
+                    event,
+                }) => handle_oracle_event(storage, *event),
+                Ok(RelayPoolNotification::Shutdown) => {
+                    tracing::error!("Relay disconnected.")
+                }
+                Ok(_) => (),
+                Err(_) => break,
+            },
         }
     }
 
+    client.disconnect().await?;
     Ok(())
 }
 
@@ -3,3 +3,100 @@ pub mod kormir;
 pub mod memory;
 #[cfg(feature = "p2pderivatives")]
 pub mod p2p_derivatives;
+
+use crate::Oracle;
+use bitcoin::XOnlyPublicKey;
+use ddk_manager::error::Error;
+use dlc_messages::oracle_msgs::{OracleAnnouncement, OracleAttestation};
+
+/// An oracle of any implementation. Use it as the oracle type of
+/// [`crate::DlcDevKit`] to combine different oracle clients with
+/// [`crate::builder::Builder::add_oracle`].
+pub type DynOracle = Box<dyn Oracle>;
+
+impl Oracle for DynOracle {
+    fn name(&self) -> String {
+        self.as_ref().name()
+    }
+}
+
+#[async_trait::async_trait]
+impl ddk_manager::Oracle for DynOracle {
+    fn get_public_key(&self) -> XOnlyPublicKey {
+        self.as_ref().get_public_key()
+    }
+
+    async fn get_announcement(&self, event_id: &str) -> Result<OracleAnnouncement, Error> {
+        self.as_ref().get_announcement(event_id).await
+    }
+
+    async fn get_attestation(&self, event_id: &str) -> Result<OracleAttestation, Error> {
+        self.as_ref().get_attestation(event_id).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::memory::MemoryOracle;
+    use super::*;
+    use crate::builder::Builder;
+    use crate::chain::ChainSource;
+    use crate::storage::memory::MemoryStorage;
+    use crate::transport::memory::MemoryTransport;
+    use crate::DlcDevKit;
+    use bitcoin::key::rand::Fill;
+    use bitcoin::key::Secp256k1;
+    use bitcoin::Network;
+    use std::sync::Arc;
+
+    fn builder() -> Builder<MemoryTransport, MemoryStorage, DynOracle> {
+        let mut seed = [0u8; 32];
+        seed.try_fill(&mut bitcoin::key::rand::thread_rng())
+            .unwrap();
+        let mut builder = Builder::new();
+        builder
+            .set_network(Network::Regtest)
+            .set_chain_source(ChainSource::esplora("http://127.0.0.1:30000"))
+            .set_seed_bytes(seed)
+            .set_transport(Arc::new(MemoryTransport::new(&Secp256k1::new())))
+            .set_storage(Arc::new(MemoryStorage::new()));
+        builder
+    }
+
+    #[tokio::test]
+    async fn builder_registers_every_oracle() {
+        let first: DynOracle = Box::<MemoryOracle>::default();
+        let second: DynOracle = Box::<MemoryOracle>::default();
+        let pubkeys = vec![first.get_public_key(), second.get_public_key()];
+
+        let ddk: DlcDevKit<_, _, DynOracle> = builder()
+            .set_oracle(Arc::new(first))
+            .add_oracle(Arc::new(second))
+            .finish()
+            .await
+            .unwrap();
+
+        assert_eq!(ddk.oracle.get_public_key(), pubkeys[0]);
+        let registered = ddk
+            .oracles
+            .iter()
+            .map(|o| o.get_public_key())
+            .collect::<Vec<_>>();
+        assert_eq!(registered, pubkeys);
+    }
+
+    #[tokio::test]
+    async fn builder_rejects_duplicate_oracles() {
+        let oracle: Arc<DynOracle> = Arc::new(Box::<MemoryOracle>::default());
+
+        let err = builder()
+            .set_oracle(oracle.clone())
+            .add_oracle(oracle)
+            .finish()
+            .await
+            .err()
+            .unwrap();
+
+        assert!(err.to_string().contains("was added more than once"));
+    }
+}
@@ -0,0 +1,546 @@
+//! Emergency refunds of signed contracts.
+//!
+//! A signed contract can only be closed with the full contract from storage. If the storage is
+//! lost or corrupted, the collateral is stuck. For every signed or confirmed contract a compact
+//! [`EmergencyRecord`] is kept in a directory of its own, apart from the storage. It holds the
+//! refund transaction with the refund signatures of both parties, so the collateral comes back
+//! after the refund locktime with nothing but the records and a chain source, see
+//! [`recover_refunds`].
+//!
+//! Records hold no keys, only the transactions of the contract and the counterparty.
+
+use crate::event::DdkEvent;
+use crate::Storage;
+use bitcoin::absolute::LockTime;
+use bitcoin::secp256k1::PublicKey;
+use bitcoin::{OutPoint, ScriptBuf, Transaction, Txid};
+use ddk_manager::contract::signed_contract::SignedContract;
+use ddk_manager::contract::Contract;
+use ddk_manager::contract_updater::get_refund_with_stored_signatures;
+use ddk_manager::event::{Event, EventHandler};
+use ddk_manager::{Blockchain, ContractId};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::broadcast;
+
+const RECORD_EXTENSION: &str = "json";
+/// Number of blocks whose median time is the median time past of a block, see BIP113.
+const MEDIAN_TIME_SPAN: u64 = 11;
+
+/// What is needed to refund a signed contract without its storage.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EmergencyRecord {
+    pub contract_id: ContractId,
+    pub counterparty: PublicKey,
+    /// Id of the local keys of the contract.
+    pub key_id: [u8; 32],
+    pub funding_outpoint: OutPoint,
+    /// The 2-of-2 script of the funding output.
+    pub funding_script: ScriptBuf,
+    /// The refund transaction with the signatures of both parties.
+    pub refund: Transaction,
+}
+
+impl EmergencyRecord {
+    pub fn new(contract: &SignedContract) -> EmergencyRecord {
+        let accepted_contract = &contract.accepted_contract;
+        let offered_contract = &accepted_contract.offered_contract;
+        EmergencyRecord {
+            contract_id: accepted_contract.get_contract_id(),
+            counterparty: offered_contract.counter_party,
+            key_id: offered_contract.keys_id(),
+            funding_outpoint: accepted_contract.dlc_transactions.get_fund_outpoint(),
+            funding_script: accepted_contract
+                .dlc_transactions
+                .funding_script_pubkey
+                .clone(),
+            refund: get_refund_with_stored_signatures(contract),
+        }
+    }
+
+    /// The record of a signed or confirmed contract. Contracts of channels are refunded
+    /// through the channel and have none.
+    pub fn from_contract(contract: &Contract) -> Option<EmergencyRecord> {
+        match contract {
+            Contract::Signed(c) | Contract::Confirmed(c) if c.channel_id.is_none() => {
+                Some(EmergencyRecord::new(c))
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether the refund transaction can be mined in the block after the tip at `height`,
+    /// whose median time past is `median_time_past`. Time locktimes are checked against the
+    /// median time past of the chain, not the wall clock, see BIP113.
+    pub fn is_refundable(&self, height: u64, median_time_past: u64) -> bool {
+        match self.refund.lock_time {
+            LockTime::Blocks(locktime) => u64::from(locktime.to_consensus_u32()) <= height,
+            LockTime::Seconds(locktime) => {
+                u64::from(locktime.to_consensus_u32()) < median_time_past
+            }
+        }
+    }
+}
+
+/// Directory of [`EmergencyRecord`]s, one file per contract. Keep it apart from the storage,
+/// ideally on another disk.
+#[derive(Clone, Debug)]
+pub struct EmergencyRecords {
+    directory: PathBuf,
+}
+
+impl EmergencyRecords {
+    pub fn new(directory: impl Into<PathBuf>) -> EmergencyRecords {
+        EmergencyRecords {
+            directory: directory.into(),
+        }
+    }
+
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    fn path(&self, contract_id: &ContractId) -> PathBuf {
+        self.directory
+            .join(hex::encode(contract_id))
+            .with_extension(RECORD_EXTENSION)
+    }
+
+    /// Write the record, replacing the record of the same contract.
+    pub fn write(&self, record: &EmergencyRecord) -> anyhow::Result<()> {
+        std::fs::create_dir_all(&self.directory)?;
+        let path = self.path(&record.contract_id);
+        let partial = path.with_extension("partial");
+        std::fs::write(&partial, serde_json::to_vec_pretty(record)?)?;
+        std::fs::rename(&partial, &path)?;
+        Ok(())
+    }
+
+    /// Remove the record of a contract that was closed or refunded.
+    pub fn remove(&self, contract_id: &ContractId) -> anyhow::Result<()> {
+        match std::fs::remove_file(self.path(contract_id)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Every readable record. Records that can not be read are skipped with a warning, one
+    /// damaged file must not keep the other contracts from being refunded.
+    pub fn list(&self) -> anyhow::Result<Vec<EmergencyRecord>> {
+        let entries = match std::fs::read_dir(&self.directory) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        let mut records = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some(RECORD_EXTENSION) {
+                continue;
+            }
+            match std::fs::read(&path)
+                .map_err(anyhow::Error::from)
+                .and_then(|bytes| Ok(serde_json::from_slice(&bytes)?))
+            {
+                Ok(record) => records.push(record),
+                Err(e) => {
+                    tracing::warn!(path = ?path, error = ?e, "Could not read emergency record.")
+                }
+            }
+        }
+        Ok(records)
+    }
+
+    /// Write the record of every signed and confirmed contract of the storage, for contracts
+    /// signed before the records were kept.
+    pub fn sync<S: Storage>(&self, storage: &S) -> anyhow::Result<()> {
+        let contracts = storage
+            .get_signed_contracts()?
+            .into_iter()
+            .chain(storage.get_confirmed_contracts()?);
+        for contract in contracts.filter(|c| c.channel_id.is_none()) {
+            self.write(&EmergencyRecord::new(&contract))?;
+        }
+        Ok(())
+    }
+}
+
+/// Keeps the [`EmergencyRecords`] in step with the contracts handled by the manager.
+///
+/// The manager emits [`Event::Signed`] before it broadcasts the funding transaction, so the
+/// record is on disk first. Writes that fail are reported with
+/// [`DdkEvent::EmergencyRecordFailed`].
+pub(crate) struct EmergencyRecorder<S> {
+    pub storage: Arc<S>,
+    pub records: EmergencyRecords,
+    pub events: broadcast::Sender<DdkEvent>,
+}
+
+impl<S: Storage> EmergencyRecorder<S> {
+    fn record(&self, contract_id: &ContractId) -> anyhow::Result<()> {
+        let contract = self.storage.get_contract(contract_id)?;
+        match contract.as_ref().and_then(EmergencyRecord::from_contract) {
+            Some(record) => self.records.write(&record),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<S: Storage> EventHandler for EmergencyRecorder<S> {
+    fn handle_event(&self, event: Event) {
+        let (contract_id, result) = match &event {
+            Event::Signed { contract_id } | Event::FundingConfirmed { contract_id } => {
+                (contract_id, self.record(contract_id))
+            }
+            Event::Closed { contract_id } | Event::Refunded { contract_id } => {
+                (contract_id, self.records.remove(contract_id))
+            }
+            _ => return,
+        };
+        if let Err(e) = result {
+            tracing::error!(event = ?event, error = ?e, "Could not update emergency record.");
+            // Sending only fails when there are no subscribers.
+            let _ = self.events.send(DdkEvent::EmergencyRecordFailed {
+                contract_id: *contract_id,
+                error: e.to_string(),
+            });
+        }
+    }
+}
+
+/// What [`recover_refunds`] did with the refund of a record.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RefundStatus {
+    /// The refund locktime has not passed yet.
+    Locked,
+    /// The refund transaction is confirmed.
+    Confirmed,
+    /// The refund transaction was broadcast.
+    Broadcast,
+    /// Broadcasting failed, e.g. because a CET already spent the funding output.
+    Failed(String),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RefundOutcome {
+    pub contract_id: ContractId,
+    pub refund_txid: Txid,
+    pub status: RefundStatus,
+}
+
+/// Broadcast the refund of every record whose refund locktime has passed and whose refund is
+/// not confirmed yet.
+pub async fn recover_refunds<B: Blockchain>(
+    records: &EmergencyRecords,
+    blockchain: &B,
+) -> anyhow::Result<Vec<RefundOutcome>> {
+    let records = records.list()?;
+    let height = blockchain.get_blockchain_height().await?;
+    // Fetching the blocks for the median time past is only worth it for time locktimes.
+    let median_time_past = if records.iter().any(|r| r.refund.lock_time.is_block_time()) {
+        median_time_past(blockchain, height).await?
+    } else {
+        0
+    };
+    let mut outcomes = Vec::new();
+    for record in records {
+        let refund_txid = record.refund.compute_txid();
+        let status = if !record.is_refundable(height, median_time_past) {
+            RefundStatus::Locked
+        } else if blockchain
+            .get_transaction_confirmations(&refund_txid)
+            .await
+            .is_ok_and(|confirmations| confirmations > 0)
+        {
+            RefundStatus::Confirmed
+        } else {
+            match blockchain.send_transaction(&record.refund).await {
+                Ok(()) => RefundStatus::Broadcast,
+                Err(e) => RefundStatus::Failed(e.to_string()),
+            }
+        };
+        tracing::info!(
+            contract_id = hex::encode(record.contract_id),
+            refund_txid = refund_txid.to_string(),
+            status = ?status,
+            "Checked emergency refund."
+        );
+        outcomes.push(RefundOutcome {
+            contract_id: record.contract_id,
+            refund_txid,
+            status,
+        });
+    }
+    Ok(outcomes)
+}
+
+/// Median of the timestamps of the block at `height` and the ten blocks before it.
+async fn median_time_past<B: Blockchain>(blockchain: &B, height: u64) -> anyhow::Result<u64> {
+    let mut times = Vec::new();
+    for height in height.saturating_sub(MEDIAN_TIME_SPAN - 1)..=height {
+        let block = blockchain.get_block_at_height(height).await?;
+        times.push(u64::from(block.header.time));
+    }
+    times.sort_unstable();
+    Ok(times[times.len() / 2])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::memory::MemoryStorage;
+    use bitcoin::secp256k1::Secp256k1;
+    use bitcoin::{absolute, Block, Network};
+    use ddk_manager::contract::ser::Serializable;
+    use ddk_manager::error::Error;
+    use ddk_manager::Storage as ManagerStorage;
+    use std::sync::Mutex;
+
+    fn confirmed_contract() -> SignedContract {
+        let serialized = include_bytes!("../tests/data/dlc_storage/Confirmed");
+        SignedContract::deserialize(&mut lightning::io::Cursor::new(&serialized)).unwrap()
+    }
+
+    fn temp_records(name: &str) -> EmergencyRecords {
+        let directory =
+            std::env::temp_dir().join(format!("ddk-emergency-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+        EmergencyRecords::new(directory)
+    }
+
+    /// Blocks are ten minutes apart.
+    fn block_time(height: u64) -> u32 {
+        (1_000_000_000 + height * 600) as u32
+    }
+
+    struct MockChain {
+        height: u64,
+        confirmed: Vec<Txid>,
+        broadcast: Mutex<Vec<Txid>>,
+    }
+
+    #[async_trait::async_trait]
+    impl Blockchain for MockChain {
+        async fn send_transaction(&self, transaction: &Transaction) -> Result<(), Error> {
+            self.broadcast
+                .lock()
+                .unwrap()
+                .push(transaction.compute_txid());
+            Ok(())
+        }
+
+        fn get_network(&self) -> Result<Network, Error> {
+            Ok(Network::Regtest)
+        }
+
+        async fn get_blockchain_height(&self) -> Result<u64, Error> {
+            Ok(self.height)
+        }
+
+        async fn get_block_at_height(&self, height: u64) -> Result<Block, Error> {
+            use bitcoin::hashes::Hash;
+            if height > self.height {
+                return Err(Error::BlockchainError("No block.".to_string()));
+            }
+            Ok(Block {
+                header: bitcoin::block::Header {
+                    version: bitcoin::block::Version::ONE,
+                    prev_blockhash: bitcoin::BlockHash::all_zeros(),
+                    merkle_root: bitcoin::TxMerkleNode::all_zeros(),
+                    time: block_time(height),
+                    bits: bitcoin::CompactTarget::from_consensus(0),
+                    nonce: 0,
+                },
+                txdata: Vec::new(),
+            })
+        }
+
+        async fn get_transaction(&self, _tx_id: &Txid) -> Result<Transaction, Error> {
+            Err(Error::BlockchainError("No transactions.".to_string()))
+        }
+
+        async fn get_transaction_confirmations(&self, tx_id: &Txid) -> Result<u32, Error> {
+            Ok(if self.confirmed.contains(tx_id) { 6 } else { 0 })
+        }
+    }
+
+    #[test]
+    fn records_hold_a_fully_signed_refund() {
+        let contract = confirmed_contract();
+        let record =
+            EmergencyRecord::from_contract(&Contract::Confirmed(contract.clone())).unwrap();
+        let accepted = &contract.accepted_contract;
+        assert_eq!(record.contract_id, accepted.get_contract_id());
+        assert_eq!(record.counterparty, accepted.offered_contract.counter_party);
+        assert_eq!(
+            record.funding_outpoint,
+            accepted.dlc_transactions.get_fund_outpoint()
+        );
+
+        let witness = &record.refund.input[0].witness;
+        assert_eq!(witness.len(), 4);
+        assert_eq!(witness.last().unwrap(), record.funding_script.as_bytes());
+        let secp = Secp256k1::verification_only();
+        let value = accepted.dlc_transactions.get_fund_output().value.to_sat();
+        for (signature, pubkey) in [
+            (
+                &contract.offer_refund_signature,
+                &accepted.offered_contract.offer_params.fund_pubkey,
+            ),
+            (
+                &accepted.accept_refund_signature,
+                &accepted.accept_params.fund_pubkey,
+            ),
+        ] {
+            dlc::verify_tx_input_sig(
+                &secp,
+                signature,
+                &record.refund,
+                0,
+                &record.funding_script,
+                value,
+                pubkey,
+            )
+            .unwrap();
+            let mut der = signature.serialize_der().to_vec();
+            der.push(bitcoin::EcdsaSighashType::All as u8);
+            assert!(witness.iter().any(|element| element == der.as_slice()));
+        }
+    }
+
+    #[test]
+    fn recorder_follows_the_contract_lifecycle() {
+        let storage = Arc::new(MemoryStorage::new());
+        let contract = Contract::Confirmed(confirmed_contract());
+        storage.update_contract(&contract).unwrap();
+        let records = temp_records("recorder");
+        let (events, _) = broadcast::channel(16);
+        let recorder = EmergencyRecorder {
+            storage,
+            records: records.clone(),
+            events,
+        };
+
+        let contract_id = contract.get_id();
+        recorder.handle_event(Event::FundingConfirmed { contract_id });
+        assert_eq!(
+            records.list().unwrap(),
+            vec![EmergencyRecord::from_contract(&contract).unwrap()]
+        );
+
+        std::fs::write(records.directory().join("damaged.json"), b"{").unwrap();
+        assert_eq!(records.list().unwrap().len(), 1);
+
+        recorder.handle_event(Event::Closed { contract_id });
+        assert!(records.list().unwrap().is_empty());
+        std::fs::remove_dir_all(records.directory()).unwrap();
+    }
+
+    #[test]
+    fn recorder_reports_failed_writes() {
+        let storage = Arc::new(MemoryStorage::new());
+        let contract = Contract::Signed(confirmed_contract());
+        storage.update_contract(&contract).unwrap();
+        // A file where the records directory should be makes every write fail.
+        let records = temp_records("recorder-failed");
+        std::fs::write(records.directory(), b"").unwrap();
+        let (events, mut receiver) = broadcast::channel(16);
+        let recorder = EmergencyRecorder {
+            storage,
+            records: records.clone(),
+            events,
+        };
+
+        let contract_id = contract.get_id();
+        recorder.handle_event(Event::Signed { contract_id });
+        match receiver.try_recv().unwrap() {
+            DdkEvent::EmergencyRecordFailed {
+                contract_id: failed,
+                ..
+            } => assert_eq!(failed, contract_id),
+            event => panic!("unexpected event {:?}", event),
+        }
+        std::fs::remove_file(records.directory()).unwrap();
+    }
+
+    #[test]
+    fn refunds_follow_the_consensus_locktime_rules() {
+        let mut record = EmergencyRecord::new(&confirmed_contract());
+        let (height, median_time_past) = (800_000, 1_700_000_000);
+
+        record.refund.lock_time = absolute::LockTime::from_height(height as u32).unwrap();
+        assert!(record.is_refundable(height, median_time_past));
+        record.refund.lock_time = absolute::LockTime::from_height(height as u32 + 1).unwrap();
+        assert!(!record.is_refundable(height, median_time_past));
+
+        record.refund.lock_time =
+            absolute::LockTime::from_time(median_time_past as u32 - 1).unwrap();
+        assert!(record.is_refundable(height, median_time_past));
+        record.refund.lock_time = absolute::LockTime::from_time(median_time_past as u32).unwrap();
+        assert!(!record.is_refundable(height, median_time_past));
+    }
+
+    #[tokio::test]
+    async fn median_time_past_is_the_median_of_the_last_eleven_blocks() {
+        let chain = MockChain {
+            height: 800_000,
+            confirmed: Vec::new(),
+            broadcast: Mutex::new(Vec::new()),
+        };
+        assert_eq!(
+            median_time_past(&chain, 800_000).await.unwrap(),
+            u64::from(block_time(799_995))
+        );
+        assert_eq!(
+            median_time_past(&chain, 2).await.unwrap(),
+            u64::from(block_time(1))
+        );
+    }
+
+    #[tokio::test]
+    async fn refunds_are_broadcast_after_the_locktime() {
+        let records = temp_records("recover");
+        let height = 800_000;
+        let record = EmergencyRecord::new(&confirmed_contract());
+
+        let mut refundable = record.clone();
+        refundable.contract_id = [1; 32];
+        refundable.refund.lock_time =
+            absolute::LockTime::from_time(block_time(799_995) - 1).unwrap();
+        let mut locked = record.clone();
+        locked.contract_id = [2; 32];
+        locked.refund.lock_time = absolute::LockTime::from_time(block_time(799_995)).unwrap();
+        let mut refunded = record.clone();
+        refunded.contract_id = [3; 32];
+        refunded.refund.lock_time = absolute::LockTime::from_height(height as u32).unwrap();
+        for record in [&refundable, &locked, &refunded] {
+            records.write(record).unwrap();
+        }
+
+        let chain = MockChain {
+            height,
+            confirmed: vec![refunded.refund.compute_txid()],
+            broadcast: Mutex::new(Vec::new()),
+        };
+        let mut outcomes = recover_refunds(&records, &chain).await.unwrap();
+        outcomes.sort_by_key(|outcome| outcome.contract_id);
+        let statuses = outcomes
+            .iter()
+            .map(|outcome| (outcome.contract_id, outcome.status.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            statuses,
+            vec![
+                ([1; 32], RefundStatus::Broadcast),
+                ([2; 32], RefundStatus::Locked),
+                ([3; 32], RefundStatus::Confirmed),
+            ]
+        );
+        assert_eq!(
+            *chain.broadcast.lock().unwrap(),
+            vec![refundable.refund.compute_txid()]
+        );
+        std::fs::remove_dir_all(records.directory()).unwrap();
+    }
+}
@@ -0,0 +1,84 @@
+use super::{AdaptorSignatureRequest, ExternalSigner, FundingInputRequest, KeyId};
+use bitcoin::bip32::Xpriv;
+use bitcoin::hashes::{sha256, Hash};
+use bitcoin::psbt::Psbt;
+use bitcoin::secp256k1::{ecdsa::Signature, All, PublicKey, Secp256k1, SecretKey};
+use dlc::secp256k1_zkp::EcdsaAdaptorSignature;
+
+/// [`ExternalSigner`] that keeps its keys in memory. Used to test external signing and
+/// as the signer of [`super::unix::serve`] daemons.
+///
+/// Wallet inputs are signed with the BIP32 derivation paths of the PSBT, so the
+/// descriptors of a watch-only wallet need the key origin of this signer's key.
+#[derive(Debug)]
+pub struct MemorySigner {
+    xprv: Xpriv,
+    secp: Secp256k1<All>,
+}
+
+impl MemorySigner {
+    pub fn new(xprv: Xpriv) -> MemorySigner {
+        MemorySigner {
+            xprv,
+            secp: Secp256k1::new(),
+        }
+    }
+
+    /// Secret key of the contract key `key_id`.
+    pub fn contract_secret_key(&self, key_id: &KeyId) -> anyhow::Result<SecretKey> {
+        let mut preimage = self.xprv.private_key.secret_bytes().to_vec();
+        preimage.extend_from_slice(key_id);
+        Ok(SecretKey::from_slice(
+            sha256::Hash::hash(&preimage).as_byte_array(),
+        )?)
+    }
+}
+
+#[async_trait::async_trait]
+impl ExternalSigner for MemorySigner {
+    async fn get_public_key(&self, key_id: KeyId) -> anyhow::Result<PublicKey> {
+        Ok(self.contract_secret_key(&key_id)?.public_key(&self.secp))
+    }
+
+    async fn sign_psbt(&self, mut psbt: Psbt) -> anyhow::Result<Psbt> {
+        // Inputs of the counterparty can't be signed and are left as they are.
+        if let Err((_, errors)) = psbt.sign(&self.xprv, &self.secp) {
+            tracing::warn!(?errors, "Could not sign every psbt input.");
+        }
+        Ok(psbt)
+    }
+
+    async fn create_adaptor_signatures(
+        &self,
+        key_id: KeyId,
+        request: AdaptorSignatureRequest,
+    ) -> anyhow::Result<Vec<EcdsaAdaptorSignature>> {
+        let cets = request
+            .cets
+            .iter()
+            .map(|(cet, adaptor_point)| (cet, adaptor_point))
+            .collect::<Vec<_>>();
+        Ok(dlc::create_cet_adaptor_sigs_from_points(
+            &self.secp,
+            &cets,
+            &self.contract_secret_key(&key_id)?,
+            &request.funding_script_pubkey,
+            request.fund_output_value,
+        )?)
+    }
+
+    async fn sign_funding_input(
+        &self,
+        key_id: KeyId,
+        request: FundingInputRequest,
+    ) -> anyhow::Result<Signature> {
+        Ok(dlc::util::get_raw_sig_for_tx_input(
+            &self.secp,
+            &request.tx,
+            request.input_index,
+            &request.funding_script_pubkey,
+            request.fund_output_value,
+            &self.contract_secret_key(&key_id)?,
+        )?)
+    }
+}
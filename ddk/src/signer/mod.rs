@@ -0,0 +1,294 @@
+pub mod memory;
+#[cfg(unix)]
+pub mod unix;
+
+use crate::wallet::PsbtSigner;
+use bitcoin::psbt::Psbt;
+use bitcoin::secp256k1::{ecdsa::Signature, PublicKey, Secp256k1, SecretKey, Signing};
+use bitcoin::{Script, ScriptBuf, Transaction};
+use ddk_manager::error::Error as ManagerError;
+use ddk_manager::{ContractSigner, SimpleSigner};
+use dlc::secp256k1_zkp::EcdsaAdaptorSignature;
+use std::fmt::Debug;
+use std::future::Future;
+use std::sync::Arc;
+use tokio::runtime::{Handle, RuntimeFlavor};
+
+/// Contract key id passed to an [`ExternalSigner`]. Created by
+/// [`ddk_manager::ContractSignerProvider::derive_signer_key_id`].
+pub type KeyId = [u8; 32];
+
+/// Signs funding transactions, CETs and refund transactions with keys kept outside of
+/// DDK, e.g. by a hardware wallet or a signing daemon. Set with
+/// [`crate::builder::Builder::set_external_signer`].
+///
+/// Enumeration contracts are fully supported. Numerical contracts and channels still
+/// need the contract secret key and can not be used with an external signer; the
+/// [`crate::DlcDevKit`] offer and accept methods reject them up front.
+#[async_trait::async_trait]
+pub trait ExternalSigner: Debug + Send + Sync + 'static {
+    /// Public key of the contract key `key_id`, used in the 2-of-2 funding output.
+    async fn get_public_key(&self, key_id: KeyId) -> anyhow::Result<PublicKey>;
+    /// Sign the wallet inputs of a funding or wallet transaction.
+    async fn sign_psbt(&self, psbt: Psbt) -> anyhow::Result<Psbt>;
+    /// Adaptor signatures of the CETs of a contract, in the order of the request.
+    async fn create_adaptor_signatures(
+        &self,
+        key_id: KeyId,
+        request: AdaptorSignatureRequest,
+    ) -> anyhow::Result<Vec<EcdsaAdaptorSignature>>;
+    /// Signature of a transaction spending the funding output, e.g. the refund
+    /// transaction or the CET of the attested outcome.
+    async fn sign_funding_input(
+        &self,
+        key_id: KeyId,
+        request: FundingInputRequest,
+    ) -> anyhow::Result<Signature>;
+}
+
+/// CETs to create adaptor signatures for, each with the adaptor point of its outcome.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct AdaptorSignatureRequest {
+    pub cets: Vec<(Transaction, PublicKey)>,
+    pub funding_script_pubkey: ScriptBuf,
+    pub fund_output_value: u64,
+}
+
+/// Input of a transaction spending the funding output.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct FundingInputRequest {
+    pub tx: Transaction,
+    pub input_index: usize,
+    pub funding_script_pubkey: ScriptBuf,
+    pub fund_output_value: u64,
+}
+
+/// [`ContractSigner`] of the DDK wallet. Contract keys are derived from the wallet seed
+/// unless an [`ExternalSigner`] is set.
+#[derive(Clone, Debug)]
+pub enum DdkContractSigner {
+    Local(SimpleSigner),
+    External {
+        signer: Arc<dyn ExternalSigner>,
+        key_id: KeyId,
+        public_key: PublicKey,
+    },
+}
+
+impl DdkContractSigner {
+    pub(crate) fn external(
+        signer: Arc<dyn ExternalSigner>,
+        key_id: KeyId,
+    ) -> Result<DdkContractSigner, ManagerError> {
+        let public_key = block_on(signer.get_public_key(key_id))?;
+        Ok(DdkContractSigner::External {
+            signer,
+            key_id,
+            public_key,
+        })
+    }
+}
+
+impl From<SecretKey> for DdkContractSigner {
+    fn from(secret_key: SecretKey) -> Self {
+        DdkContractSigner::Local(SimpleSigner::new(secret_key))
+    }
+}
+
+impl ContractSigner for DdkContractSigner {
+    fn get_public_key<C: Signing>(&self, secp: &Secp256k1<C>) -> Result<PublicKey, ManagerError> {
+        match self {
+            DdkContractSigner::Local(signer) => signer.get_public_key(secp),
+            DdkContractSigner::External { public_key, .. } => Ok(*public_key),
+        }
+    }
+
+    fn get_secret_key(&self) -> Result<SecretKey, ManagerError> {
+        match self {
+            DdkContractSigner::Local(signer) => signer.get_secret_key(),
+            DdkContractSigner::External { .. } => Err(ManagerError::InvalidState(
+                "The contract key is held by an external signer.".to_string(),
+            )),
+        }
+    }
+
+    fn create_cet_adaptor_signatures<C: Signing>(
+        &self,
+        secp: &Secp256k1<C>,
+        cets: &[(&Transaction, &PublicKey)],
+        funding_script_pubkey: &Script,
+        fund_output_value: u64,
+    ) -> Result<Vec<EcdsaAdaptorSignature>, ManagerError> {
+        let DdkContractSigner::External { signer, key_id, .. } = self else {
+            return Ok(dlc::create_cet_adaptor_sigs_from_points(
+                secp,
+                cets,
+                &self.get_secret_key()?,
+                funding_script_pubkey,
+                fund_output_value,
+            )?);
+        };
+        let request = AdaptorSignatureRequest {
+            cets: cets
+                .iter()
+                .map(|(cet, adaptor_point)| ((*cet).clone(), **adaptor_point))
+                .collect(),
+            funding_script_pubkey: funding_script_pubkey.to_owned(),
+            fund_output_value,
+        };
+        let signatures = block_on(signer.create_adaptor_signatures(*key_id, request))?;
+        if signatures.len() != cets.len() {
+            return Err(ManagerError::InvalidState(format!(
+                "External signer returned {} adaptor signatures for {} CETs.",
+                signatures.len(),
+                cets.len()
+            )));
+        }
+        Ok(signatures)
+    }
+
+    fn sign_funding_input<C: Signing>(
+        &self,
+        secp: &Secp256k1<C>,
+        tx: &Transaction,
+        input_index: usize,
+        funding_script_pubkey: &Script,
+        fund_output_value: u64,
+    ) -> Result<Signature, ManagerError> {
+        let DdkContractSigner::External { signer, key_id, .. } = self else {
+            return Ok(dlc::util::get_raw_sig_for_tx_input(
+                secp,
+                tx,
+                input_index,
+                funding_script_pubkey,
+                fund_output_value,
+                &self.get_secret_key()?,
+            )?);
+        };
+        let request = FundingInputRequest {
+            tx: tx.clone(),
+            input_index,
+            funding_script_pubkey: funding_script_pubkey.to_owned(),
+            fund_output_value,
+        };
+        block_on(signer.sign_funding_input(*key_id, request))
+    }
+}
+
+/// Signs wallet inputs with an [`ExternalSigner`].
+#[derive(Debug)]
+pub(crate) struct ExternalPsbtSigner(pub Arc<dyn ExternalSigner>);
+
+impl PsbtSigner for ExternalPsbtSigner {
+    fn sign_psbt(&self, psbt: &mut Psbt) -> anyhow::Result<()> {
+        *psbt =
+            block_on(self.0.sign_psbt(psbt.clone())).map_err(|e| anyhow::anyhow!(e.to_string()))?;
+        Ok(())
+    }
+}
+
+/// Wait for an external signer from the synchronous signing functions of the manager.
+/// The manager runs on the multi-threaded runtime of [`crate::DlcDevKit`].
+fn block_on<T, F>(future: F) -> Result<T, ManagerError>
+where
+    F: Future<Output = anyhow::Result<T>>,
+{
+    let handle = Handle::try_current().map_err(|e| ManagerError::SignerError(e.to_string()))?;
+    if handle.runtime_flavor() == RuntimeFlavor::CurrentThread {
+        return Err(ManagerError::SignerError(
+            "External signers need a multi-threaded tokio runtime.".to_string(),
+        ));
+    }
+    tokio::task::block_in_place(|| handle.block_on(future))
+        .map_err(|e| ManagerError::SignerError(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::memory::MemorySigner;
+    use super::*;
+    use bitcoin::absolute::LockTime;
+    use bitcoin::bip32::Xpriv;
+    use bitcoin::transaction::Version;
+    use bitcoin::{Amount, Network, OutPoint, TxIn, TxOut};
+
+    pub(crate) fn spending_tx() -> Transaction {
+        Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: OutPoint::null(),
+                ..Default::default()
+            }],
+            output: vec![TxOut {
+                value: Amount::from_sat(99_000),
+                script_pubkey: ScriptBuf::new(),
+            }],
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn external_contract_signer_signs_with_signer_key() {
+        let secp = Secp256k1::new();
+        let xprv = Xpriv::new_master(Network::Regtest, &[1u8; 32]).unwrap();
+        let signer = Arc::new(MemorySigner::new(xprv));
+        let contract_signer = DdkContractSigner::external(signer.clone(), [7u8; 32]).unwrap();
+
+        let public_key = contract_signer.get_public_key(&secp).unwrap();
+        assert_eq!(
+            public_key,
+            signer
+                .contract_secret_key(&[7u8; 32])
+                .unwrap()
+                .public_key(&secp)
+        );
+        assert!(contract_signer.get_secret_key().is_err());
+
+        let counterparty = SecretKey::from_slice(&[2u8; 32]).unwrap().public_key(&secp);
+        let funding_script = dlc::make_funding_redeemscript(&public_key, &counterparty);
+        let tx = spending_tx();
+
+        let signature = contract_signer
+            .sign_funding_input(&secp, &tx, 0, &funding_script, 100_000)
+            .unwrap();
+        dlc::verify_tx_input_sig(
+            &secp,
+            &signature,
+            &tx,
+            0,
+            &funding_script,
+            100_000,
+            &public_key,
+        )
+        .unwrap();
+
+        let adaptor_point = SecretKey::from_slice(&[3u8; 32]).unwrap().public_key(&secp);
+        let adaptor_signatures = contract_signer
+            .create_cet_adaptor_signatures(
+                &secp,
+                &[(&tx, &adaptor_point)],
+                &funding_script,
+                100_000,
+            )
+            .unwrap();
+        assert_eq!(adaptor_signatures.len(), 1);
+        dlc::verify_cet_adaptor_sig_from_point(
+            &secp,
+            &adaptor_signatures[0],
+            &tx,
+            &adaptor_point,
+            &public_key,
+            &funding_script,
+            100_000,
+        )
+        .unwrap();
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn external_contract_signer_needs_multi_threaded_runtime() {
+        let xprv = Xpriv::new_master(Network::Regtest, &[1u8; 32]).unwrap();
+        let error =
+            DdkContractSigner::external(Arc::new(MemorySigner::new(xprv)), [7u8; 32]).unwrap_err();
+        assert!(matches!(error, ManagerError::SignerError(_)));
+    }
+}
@@ -0,0 +1,267 @@
+//! [`ExternalSigner`] for a local signing daemon listening on a Unix socket.
+//!
+//! Every request is a JSON object on its own line, answered by a JSON
+//! [`SignerResponse`] line. [`serve`] runs a daemon for any [`ExternalSigner`].
+
+use super::{AdaptorSignatureRequest, ExternalSigner, FundingInputRequest, KeyId};
+use bitcoin::psbt::Psbt;
+use bitcoin::secp256k1::{ecdsa::Signature, PublicKey};
+use dlc::secp256k1_zkp::EcdsaAdaptorSignature;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{UnixListener, UnixStream};
+
+/// Request sent to the signing daemon. Key ids and PSBTs are hex encoded.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "method", content = "params", rename_all = "snake_case")]
+pub enum SignerRequest {
+    GetPublicKey {
+        key_id: String,
+    },
+    SignPsbt {
+        psbt: String,
+    },
+    CreateAdaptorSignatures {
+        key_id: String,
+        request: AdaptorSignatureRequest,
+    },
+    SignFundingInput {
+        key_id: String,
+        request: FundingInputRequest,
+    },
+}
+
+/// Response of the signing daemon.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SignerResponse {
+    PublicKey(PublicKey),
+    Psbt(String),
+    AdaptorSignatures(Vec<EcdsaAdaptorSignature>),
+    Signature(Signature),
+    Error(String),
+}
+
+/// Client of a signing daemon listening at a Unix socket.
+#[derive(Clone, Debug)]
+pub struct UnixSocketSigner {
+    path: PathBuf,
+}
+
+impl UnixSocketSigner {
+    pub fn new(path: impl AsRef<Path>) -> UnixSocketSigner {
+        UnixSocketSigner {
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+
+    async fn call(&self, request: &SignerRequest) -> anyhow::Result<SignerResponse> {
+        let mut stream = UnixStream::connect(&self.path).await?;
+        let mut line = serde_json::to_vec(request)?;
+        line.push(b'\n');
+        stream.write_all(&line).await?;
+
+        let mut response = String::new();
+        BufReader::new(stream).read_line(&mut response).await?;
+        match serde_json::from_str(&response)? {
+            SignerResponse::Error(e) => Err(anyhow::anyhow!("Signing daemon error: {e}")),
+            response => Ok(response),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl ExternalSigner for UnixSocketSigner {
+    async fn get_public_key(&self, key_id: KeyId) -> anyhow::Result<PublicKey> {
+        let request = SignerRequest::GetPublicKey {
+            key_id: hex::encode(key_id),
+        };
+        match self.call(&request).await? {
+            SignerResponse::PublicKey(public_key) => Ok(public_key),
+            response => Err(unexpected(response)),
+        }
+    }
+
+    async fn sign_psbt(&self, psbt: Psbt) -> anyhow::Result<Psbt> {
+        let request = SignerRequest::SignPsbt {
+            psbt: hex::encode(psbt.serialize()),
+        };
+        match self.call(&request).await? {
+            SignerResponse::Psbt(psbt) => Ok(Psbt::deserialize(&hex::decode(psbt)?)?),
+            response => Err(unexpected(response)),
+        }
+    }
+
+    async fn create_adaptor_signatures(
+        &self,
+        key_id: KeyId,
+        request: AdaptorSignatureRequest,
+    ) -> anyhow::Result<Vec<EcdsaAdaptorSignature>> {
+        let request = SignerRequest::CreateAdaptorSignatures {
+            key_id: hex::encode(key_id),
+            request,
+        };
+        match self.call(&request).await? {
+            SignerResponse::AdaptorSignatures(signatures) => Ok(signatures),
+            response => Err(unexpected(response)),
+        }
+    }
+
+    async fn sign_funding_input(
+        &self,
+        key_id: KeyId,
+        request: FundingInputRequest,
+    ) -> anyhow::Result<Signature> {
+        let request = SignerRequest::SignFundingInput {
+            key_id: hex::encode(key_id),
+            request,
+        };
+        match self.call(&request).await? {
+            SignerResponse::Signature(signature) => Ok(signature),
+            response => Err(unexpected(response)),
+        }
+    }
+}
+
+fn unexpected(response: SignerResponse) -> anyhow::Error {
+    anyhow::anyhow!("Unexpected signing daemon response: {response:?}")
+}
+
+/// Answer signing requests on `listener` with `signer` until the listener fails.
+pub async fn serve(listener: UnixListener, signer: Arc<dyn ExternalSigner>) -> anyhow::Result<()> {
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let signer = signer.clone();
+        tokio::spawn(async move {
+            if let Err(e) = handle_connection(stream, signer).await {
+                tracing::error!(error = e.to_string(), "Signing daemon connection failed.");
+            }
+        });
+    }
+}
+
+async fn handle_connection(
+    stream: UnixStream,
+    signer: Arc<dyn ExternalSigner>,
+) -> anyhow::Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+    while let Some(line) = lines.next_line().await? {
+        let response = match serde_json::from_str::<SignerRequest>(&line) {
+            Ok(request) => handle_request(request, signer.as_ref())
+                .await
+                .unwrap_or_else(|e| SignerResponse::Error(e.to_string())),
+            Err(e) => SignerResponse::Error(format!("Invalid request: {e}")),
+        };
+        let mut line = serde_json::to_vec(&response)?;
+        line.push(b'\n');
+        writer.write_all(&line).await?;
+    }
+    Ok(())
+}
+
+async fn handle_request(
+    request: SignerRequest,
+    signer: &dyn ExternalSigner,
+) -> anyhow::Result<SignerResponse> {
+    let response = match request {
+        SignerRequest::GetPublicKey { key_id } => {
+            SignerResponse::PublicKey(signer.get_public_key(parse_key_id(&key_id)?).await?)
+        }
+        SignerRequest::SignPsbt { psbt } => {
+            let psbt = Psbt::deserialize(&hex::decode(psbt)?)?;
+            SignerResponse::Psbt(hex::encode(signer.sign_psbt(psbt).await?.serialize()))
+        }
+        SignerRequest::CreateAdaptorSignatures { key_id, request } => {
+            SignerResponse::AdaptorSignatures(
+                signer
+                    .create_adaptor_signatures(parse_key_id(&key_id)?, request)
+                    .await?,
+            )
+        }
+        SignerRequest::SignFundingInput { key_id, request } => SignerResponse::Signature(
+            signer
+                .sign_funding_input(parse_key_id(&key_id)?, request)
+                .await?,
+        ),
+    };
+    Ok(response)
+}
+
+fn parse_key_id(key_id: &str) -> anyhow::Result<KeyId> {
+    hex::decode(key_id)?
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("Key id must be 32 bytes."))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signer::memory::MemorySigner;
+    use bitcoin::bip32::Xpriv;
+    use bitcoin::secp256k1::{Secp256k1, SecretKey};
+    use bitcoin::Network;
+
+    #[tokio::test]
+    async fn unix_socket_signer_round_trip() {
+        let secp = Secp256k1::new();
+        let path = std::env::temp_dir().join(format!("ddk-signer-{}.sock", uuid::Uuid::new_v4()));
+        let xprv = Xpriv::new_master(Network::Regtest, &[1u8; 32]).unwrap();
+        let memory_signer = Arc::new(MemorySigner::new(xprv));
+        let listener = UnixListener::bind(&path).unwrap();
+        let daemon = tokio::spawn(serve(listener, memory_signer.clone()));
+
+        let signer = UnixSocketSigner::new(&path);
+        let key_id = [9u8; 32];
+        let public_key = signer.get_public_key(key_id).await.unwrap();
+        assert_eq!(
+            public_key,
+            memory_signer.get_public_key(key_id).await.unwrap()
+        );
+
+        let counterparty = SecretKey::from_slice(&[2u8; 32]).unwrap().public_key(&secp);
+        let request = FundingInputRequest {
+            tx: crate::signer::tests::spending_tx(),
+            input_index: 0,
+            funding_script_pubkey: dlc::make_funding_redeemscript(&public_key, &counterparty),
+            fund_output_value: 100_000,
+        };
+        let signature = signer
+            .sign_funding_input(key_id, request.clone())
+            .await
+            .unwrap();
+        dlc::verify_tx_input_sig(
+            &secp,
+            &signature,
+            &request.tx,
+            0,
+            &request.funding_script_pubkey,
+            100_000,
+            &public_key,
+        )
+        .unwrap();
+
+        daemon.abort();
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[tokio::test]
+    async fn unix_socket_signer_reports_daemon_errors() {
+        let path = std::env::temp_dir().join(format!("ddk-signer-{}.sock", uuid::Uuid::new_v4()));
+        let xprv = Xpriv::new_master(Network::Regtest, &[1u8; 32]).unwrap();
+        let listener = UnixListener::bind(&path).unwrap();
+        let daemon = tokio::spawn(serve(listener, Arc::new(MemorySigner::new(xprv))));
+
+        let error = UnixSocketSigner::new(&path)
+            .call(&SignerRequest::GetPublicKey {
+                key_id: "00".to_string(),
+            })
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("32 bytes"));
+
+        daemon.abort();
+        let _ = std::fs::remove_file(path);
+    }
+}
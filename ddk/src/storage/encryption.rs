@@ -0,0 +1,245 @@
+//! Encryption of stored values.
+//!
+//! Values are sealed with XChaCha20-Poly1305 under a key derived from the wallet seed or a user
+//! passphrase. The derivation parameters and a sealed check value are stored in plaintext in an
+//! [`EncryptionHeader`] next to the data, so a wrong secret is rejected before anything is read.
+
+use bitcoin::key::rand::{thread_rng, RngCore};
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+/// Version of the sealed value format.
+const VERSION: u8 = 1;
+const NONCE_LEN: usize = 24;
+/// PBKDF2 rounds of new passphrase headers. Existing headers keep the rounds they were created
+/// with.
+const PASSPHRASE_ROUNDS: u32 = if cfg!(test) { 1_000 } else { 600_000 };
+const CHECK_AAD: &[u8] = b"ddk/storage/check";
+const CHECK_VALUE: &[u8] = b"ddk";
+
+#[derive(thiserror::Error, Debug)]
+pub enum EncryptionError {
+    #[error("The secret does not unlock the storage.")]
+    WrongSecret,
+    #[error("The storage is encrypted with a {expected}, not a {actual}.")]
+    SecretKind {
+        expected: &'static str,
+        actual: &'static str,
+    },
+    #[error("Unsupported encryption version {0}.")]
+    UnsupportedVersion(u8),
+    #[error("A stored value could not be decrypted.")]
+    Corrupted,
+}
+
+/// Secret the storage key is derived from.
+#[derive(Clone)]
+pub enum StorageSecret {
+    /// The wallet seed. It has full entropy and is used as is.
+    Seed(Vec<u8>),
+    /// A user passphrase, stretched with PBKDF2-HMAC-SHA256.
+    Passphrase(String),
+}
+
+impl StorageSecret {
+    fn kind(&self) -> &'static str {
+        match self {
+            StorageSecret::Seed(_) => "seed",
+            StorageSecret::Passphrase(_) => "passphrase",
+        }
+    }
+}
+
+impl std::fmt::Debug for StorageSecret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "StorageSecret::{}(..)", self.kind())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "kind")]
+pub enum KeyDerivation {
+    Seed,
+    Pbkdf2 { rounds: u32 },
+}
+
+/// Parameters to derive the storage key, stored in plaintext with the encrypted data.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EncryptionHeader {
+    pub version: u8,
+    pub derivation: KeyDerivation,
+    pub salt: [u8; 32],
+    /// A known value sealed with the key, to tell a wrong secret from corrupted data.
+    pub check: Vec<u8>,
+}
+
+impl EncryptionHeader {
+    /// A header with a random salt and the cipher it unlocks.
+    pub fn new(secret: &StorageSecret) -> (EncryptionHeader, StorageCipher) {
+        let mut salt = [0u8; 32];
+        thread_rng().fill_bytes(&mut salt);
+        let derivation = match secret {
+            StorageSecret::Seed(_) => KeyDerivation::Seed,
+            StorageSecret::Passphrase(_) => KeyDerivation::Pbkdf2 {
+                rounds: PASSPHRASE_ROUNDS,
+            },
+        };
+        let cipher = StorageCipher::derive(secret, &salt, derivation);
+        let header = EncryptionHeader {
+            version: VERSION,
+            derivation,
+            salt,
+            check: cipher.seal(CHECK_AAD, CHECK_VALUE),
+        };
+        (header, cipher)
+    }
+
+    /// Derive the cipher of the header and verify it against the check value.
+    pub fn unlock(&self, secret: &StorageSecret) -> Result<StorageCipher, EncryptionError> {
+        if self.version != VERSION {
+            return Err(EncryptionError::UnsupportedVersion(self.version));
+        }
+        let expected = match self.derivation {
+            KeyDerivation::Seed => "seed",
+            KeyDerivation::Pbkdf2 { .. } => "passphrase",
+        };
+        if expected != secret.kind() {
+            return Err(EncryptionError::SecretKind {
+                expected,
+                actual: secret.kind(),
+            });
+        }
+        let cipher = StorageCipher::derive(secret, &self.salt, self.derivation);
+        match cipher.open(CHECK_AAD, &self.check) {
+            Ok(check) if check == CHECK_VALUE => Ok(cipher),
+            _ => Err(EncryptionError::WrongSecret),
+        }
+    }
+}
+
+/// Seals values and blinds keys with the keys derived from a [`StorageSecret`].
+#[derive(Clone)]
+pub struct StorageCipher {
+    aead: XChaCha20Poly1305,
+    blinding_key: [u8; 32],
+}
+
+impl StorageCipher {
+    fn derive(secret: &StorageSecret, salt: &[u8; 32], derivation: KeyDerivation) -> Self {
+        let master = match secret {
+            StorageSecret::Seed(seed) => hmac(salt, &[b"ddk/storage/seed", seed]),
+            StorageSecret::Passphrase(passphrase) => {
+                let rounds = match derivation {
+                    KeyDerivation::Pbkdf2 { rounds } => rounds,
+                    KeyDerivation::Seed => PASSPHRASE_ROUNDS,
+                };
+                let mut master = [0u8; 32];
+                pbkdf2::pbkdf2_hmac::<Sha256>(passphrase.as_bytes(), salt, rounds, &mut master);
+                master
+            }
+        };
+        StorageCipher {
+            aead: XChaCha20Poly1305::new(&hmac(&master, &[b"encryption"]).into()),
+            blinding_key: hmac(&master, &[b"blinding"]),
+        }
+    }
+
+    /// Encrypt and authenticate `plaintext` together with `aad`.
+    pub fn seal(&self, aad: &[u8], plaintext: &[u8]) -> Vec<u8> {
+        let mut nonce = [0u8; NONCE_LEN];
+        thread_rng().fill_bytes(&mut nonce);
+        let ciphertext = self
+            .aead
+            .encrypt(
+                XNonce::from_slice(&nonce),
+                Payload {
+                    msg: plaintext,
+                    aad,
+                },
+            )
+            .expect("encryption of in-memory buffers does not fail");
+        [&[VERSION][..], &nonce, &ciphertext].concat()
+    }
+
+    /// Decrypt a value sealed with the same key and `aad`.
+    pub fn open(&self, aad: &[u8], sealed: &[u8]) -> Result<Vec<u8>, EncryptionError> {
+        let (version, sealed) = sealed.split_first().ok_or(EncryptionError::Corrupted)?;
+        if *version != VERSION {
+            return Err(EncryptionError::UnsupportedVersion(*version));
+        }
+        if sealed.len() < NONCE_LEN {
+            return Err(EncryptionError::Corrupted);
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        self.aead
+            .decrypt(
+                XNonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad,
+                },
+            )
+            .map_err(|_| EncryptionError::Corrupted)
+    }
+
+    /// Keyed hash of `data`, stored in place of keys that would reveal wallet data.
+    pub fn blind(&self, data: &[u8]) -> [u8; 32] {
+        hmac(&self.blinding_key, &[data])
+    }
+}
+
+impl std::fmt::Debug for StorageCipher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("StorageCipher(..)")
+    }
+}
+
+fn hmac(key: &[u8], parts: &[&[u8]]) -> [u8; 32] {
+    let mut mac =
+        <Hmac<Sha256> as Mac>::new_from_slice(key).expect("hmac takes keys of any length");
+    for part in parts {
+        mac.update(part);
+    }
+    mac.finalize().into_bytes().into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sealed_values_are_bound_to_their_key_and_aad() {
+        let (_, cipher) = EncryptionHeader::new(&StorageSecret::Seed(vec![1; 64]));
+        let (_, other) = EncryptionHeader::new(&StorageSecret::Seed(vec![1; 64]));
+
+        let sealed = cipher.seal(b"contract", b"secret");
+        assert_eq!(cipher.open(b"contract", &sealed).unwrap(), b"secret");
+        assert!(cipher.open(b"channel", &sealed).is_err());
+        assert!(other.open(b"contract", &sealed).is_err());
+
+        let mut tampered = sealed.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(cipher.open(b"contract", &tampered).is_err());
+    }
+
+    #[test]
+    fn headers_only_unlock_with_their_secret() {
+        let passphrase = StorageSecret::Passphrase("correct horse".to_string());
+        let (header, cipher) = EncryptionHeader::new(&passphrase);
+        let sealed = cipher.seal(b"", b"value");
+
+        let unlocked = header.unlock(&passphrase).unwrap();
+        assert_eq!(unlocked.open(b"", &sealed).unwrap(), b"value");
+        assert!(matches!(
+            header.unlock(&StorageSecret::Passphrase("battery staple".to_string())),
+            Err(EncryptionError::WrongSecret)
+        ));
+        assert!(matches!(
+            header.unlock(&StorageSecret::Seed(vec![1; 64])),
+            Err(EncryptionError::SecretKind { .. })
+        ));
+    }
+}
@@ -1,6 +1,12 @@
+use super::query::{ContractPage, ContractQuery, ContractSummary};
+use crate::error::WalletError;
 use crate::transport::PeerInformation;
-use crate::Storage;
+use crate::wallet::labels::{Label, LabelType};
+use crate::wallet::UtxoReservation;
+use crate::{KeyStorage, Storage};
 use bdk_chain::Merge;
+use bitcoin::secp256k1::SecretKey;
+use bitcoin::OutPoint;
 use ddk_manager::{channel::Channel, contract::Contract, ChannelId, ContractId};
 use dlc_messages::oracle_msgs::OracleAnnouncement;
 use std::collections::HashMap;
@@ -12,8 +18,14 @@ pub struct MemoryStorage {
     bdk_data: RwLock<Option<bdk_wallet::ChangeSet>>,
     announcements: RwLock<Vec<OracleAnnouncement>>,
     contracts: RwLock<HashMap<ContractId, Contract>>,
+    contract_summaries: RwLock<HashMap<ContractId, ContractSummary>>,
     channels: RwLock<HashMap<ChannelId, Channel>>,
     chain_monitor: RwLock<Option<ddk_manager::chain_monitor::ChainMonitor>>,
+    fee_estimates: RwLock<Option<HashMap<u16, f64>>>,
+    secret_keys: RwLock<HashMap<[u8; 32], SecretKey>>,
+    key_indexes: RwLock<HashMap<u32, u32>>,
+    utxo_reservations: RwLock<HashMap<OutPoint, UtxoReservation>>,
+    labels: RwLock<HashMap<(LabelType, String), Label>>,
 }
 
 impl MemoryStorage {
@@ -23,24 +35,42 @@ impl MemoryStorage {
             bdk_data: RwLock::new(None),
             announcements: RwLock::new(Vec::new()),
             contracts: RwLock::new(HashMap::new()),
+            contract_summaries: RwLock::new(HashMap::new()),
             channels: RwLock::new(HashMap::new()),
             chain_monitor: RwLock::new(None),
+            fee_estimates: RwLock::new(None),
+            secret_keys: RwLock::new(HashMap::new()),
+            key_indexes: RwLock::new(HashMap::new()),
+            utxo_reservations: RwLock::new(HashMap::new()),
+            labels: RwLock::new(HashMap::new()),
         }
     }
+
+    fn insert_contract(&self, contract: Contract) {
+        let mut summaries = self.contract_summaries.write().unwrap();
+        let previous = summaries
+            .get(&contract.get_id())
+            .or_else(|| summaries.get(&contract.get_temporary_id()));
+        let summary = ContractSummary::new(&contract, previous);
+        summaries.insert(summary.id, summary);
+        self.contracts
+            .write()
+            .unwrap()
+            .insert(contract.get_id(), contract);
+    }
 }
 
 impl Storage for MemoryStorage {
-    fn save_peer(&self, _peer: PeerInformation) -> anyhow::Result<()> {
-        // self.peers.write().unwrap().insert(peer.id.clone(), peer);
+    fn save_peer(&self, peer: PeerInformation) -> anyhow::Result<()> {
+        self.peers
+            .write()
+            .unwrap()
+            .insert(peer.pubkey.clone(), peer);
         Ok(())
     }
 
     fn list_peers(&self) -> anyhow::Result<Vec<PeerInformation>> {
-        // Ok(self.peers.read().unwrap().values().cloned().collect())
-        Ok(vec![PeerInformation {
-            pubkey: "".to_string(),
-            host: "".to_string(),
-        }])
+        Ok(self.peers.read().unwrap().values().cloned().collect())
     }
 
     fn persist_bdk(
@@ -65,6 +95,119 @@ impl Storage for MemoryStorage {
     fn get_marketplace_announcements(&self) -> anyhow::Result<Vec<kormir::OracleAnnouncement>> {
         Ok(self.announcements.read().unwrap().clone())
     }
+
+    fn persist_fee_estimates(&self, estimates: &HashMap<u16, f64>) -> anyhow::Result<()> {
+        *self.fee_estimates.write().unwrap() = Some(estimates.clone());
+        Ok(())
+    }
+
+    fn get_fee_estimates(&self) -> anyhow::Result<Option<HashMap<u16, f64>>> {
+        Ok(self.fee_estimates.read().unwrap().clone())
+    }
+
+    fn reserve_utxos(&self, reservations: &[UtxoReservation]) -> anyhow::Result<()> {
+        let mut stored = self.utxo_reservations.write().unwrap();
+        for reservation in reservations {
+            stored.insert(reservation.outpoint, reservation.clone());
+        }
+        Ok(())
+    }
+
+    fn list_utxo_reservations(&self) -> anyhow::Result<Vec<UtxoReservation>> {
+        Ok(self
+            .utxo_reservations
+            .read()
+            .unwrap()
+            .values()
+            .cloned()
+            .collect())
+    }
+
+    fn unreserve_utxos(&self, outpoints: &[OutPoint]) -> anyhow::Result<()> {
+        let mut stored = self.utxo_reservations.write().unwrap();
+        for outpoint in outpoints {
+            stored.remove(outpoint);
+        }
+        Ok(())
+    }
+
+    fn save_labels(&self, labels: &[Label]) -> anyhow::Result<()> {
+        let mut stored = self.labels.write().unwrap();
+        for label in labels {
+            stored.insert((label.label_type, label.reference.clone()), label.clone());
+        }
+        Ok(())
+    }
+
+    fn list_labels(&self) -> anyhow::Result<Vec<Label>> {
+        Ok(self.labels.read().unwrap().values().cloned().collect())
+    }
+
+    fn query_contracts(&self, query: &ContractQuery) -> anyhow::Result<ContractPage> {
+        let (summaries, next) =
+            query.paginate(self.contract_summaries.read().unwrap().values().cloned());
+        let contracts = self.contracts.read().unwrap();
+        Ok(ContractPage {
+            contracts: summaries
+                .iter()
+                .filter_map(|s| contracts.get(&s.id).cloned())
+                .collect(),
+            next,
+        })
+    }
+
+    fn list_channels(&self) -> anyhow::Result<Vec<Channel>> {
+        Ok(self.channels.read().unwrap().values().cloned().collect())
+    }
+}
+
+impl KeyStorage for MemoryStorage {
+    fn get_secret_key(&self, key_id: [u8; 32]) -> Result<SecretKey, WalletError> {
+        self.secret_keys
+            .read()
+            .unwrap()
+            .get(&key_id)
+            .cloned()
+            .ok_or_else(|| {
+                WalletError::StorageError(format!("No secret key for {}", hex::encode(key_id)))
+            })
+    }
+
+    fn store_secret_key(&self, key_id: [u8; 32], secret_key: SecretKey) -> Result<(), WalletError> {
+        self.secret_keys.write().unwrap().insert(key_id, secret_key);
+        Ok(())
+    }
+
+    fn next_key_index(&self, account: u32) -> anyhow::Result<u32> {
+        let mut indexes = self.key_indexes.write().unwrap();
+        let index = indexes.entry(account).or_insert(0);
+        let next = *index;
+        *index = next
+            .checked_add(1)
+            .ok_or_else(|| anyhow::anyhow!("Key indexes exhausted."))?;
+        Ok(next)
+    }
+
+    fn list_secret_keys(&self) -> Result<Vec<([u8; 32], SecretKey)>, WalletError> {
+        Ok(self
+            .secret_keys
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(key_id, secret_key)| (*key_id, *secret_key))
+            .collect())
+    }
+
+    fn key_indexes(&self) -> anyhow::Result<HashMap<u32, u32>> {
+        Ok(self.key_indexes.read().unwrap().clone())
+    }
+
+    fn advance_key_index(&self, account: u32, next: u32) -> anyhow::Result<()> {
+        let mut indexes = self.key_indexes.write().unwrap();
+        let index = indexes.entry(account).or_insert(0);
+        *index = (*index).max(next);
+        Ok(())
+    }
 }
 
 impl ddk_manager::Storage for MemoryStorage {
@@ -94,10 +237,7 @@ impl ddk_manager::Storage for MemoryStorage {
         contract: Option<ddk_manager::contract::Contract>,
     ) -> Result<(), ddk_manager::error::Error> {
         if let Some(contract) = contract {
-            self.contracts
-                .write()
-                .unwrap()
-                .insert(contract.get_id(), contract);
+            self.insert_contract(contract);
         }
         self.channels
             .write()
@@ -118,10 +258,7 @@ impl ddk_manager::Storage for MemoryStorage {
         &self,
         contract: &ddk_manager::contract::offered_contract::OfferedContract,
     ) -> Result<(), ddk_manager::error::Error> {
-        self.contracts
-            .write()
-            .unwrap()
-            .insert(contract.id, Contract::Offered(contract.clone()));
+        self.insert_contract(Contract::Offered(contract.clone()));
         Ok(())
     }
 
@@ -130,6 +267,7 @@ impl ddk_manager::Storage for MemoryStorage {
         id: &ddk_manager::ContractId,
     ) -> Result<(), ddk_manager::error::Error> {
         self.contracts.write().unwrap().remove(id);
+        self.contract_summaries.write().unwrap().remove(id);
         Ok(())
     }
 
@@ -137,10 +275,7 @@ impl ddk_manager::Storage for MemoryStorage {
         &self,
         contract: &ddk_manager::contract::Contract,
     ) -> Result<(), ddk_manager::error::Error> {
-        self.contracts
-            .write()
-            .unwrap()
-            .insert(contract.get_id(), contract.clone());
+        self.insert_contract(contract.clone());
         Ok(())
     }
 
@@ -168,6 +303,22 @@ impl ddk_manager::Storage for MemoryStorage {
         Ok(offers)
     }
 
+    fn get_accepted_contracts(
+        &self,
+    ) -> Result<
+        Vec<ddk_manager::contract::accepted_contract::AcceptedContract>,
+        ddk_manager::error::Error,
+    > {
+        let contracts = self.contracts.read().unwrap();
+        Ok(contracts
+            .values()
+            .filter_map(|c| match c {
+                Contract::Accepted(c) => Some(c.clone()),
+                _ => None,
+            })
+            .collect())
+    }
+
     fn get_signed_channels(
         &self,
         _channel_state: Option<ddk_manager::channel::signed_channel::SignedChannelStateType>,
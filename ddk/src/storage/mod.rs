@@ -1,3 +1,8 @@
+#[cfg(feature = "encryption")]
+pub mod encryption;
 pub mod memory;
+pub mod query;
 #[cfg(feature = "sled")]
 pub mod sled;
+#[cfg(any(feature = "sqlite", feature = "postgres"))]
+pub mod sql;
@@ -0,0 +1,507 @@
+//! Filtered and paginated contract queries.
+//!
+//! Storage implementations keep a [`ContractSummary`] next to every contract with the
+//! attributes a [`ContractQuery`] filters on, so matching contracts are found without
+//! deserializing the others. Results are ordered by creation time and returned in pages,
+//! the [`ContractCursor`] of a page continues the query where it stopped.
+
+use bitcoin::secp256k1::PublicKey;
+use bitcoin::Txid;
+use ddk_manager::contract::accepted_contract::AcceptedContract;
+use ddk_manager::contract::offered_contract::OfferedContract;
+use ddk_manager::contract::Contract;
+use ddk_manager::ContractId;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::ops::{Bound, RangeBounds};
+use std::str::FromStr;
+
+/// State of a contract. The order of the variants is part of the sled index format, new
+/// states go last.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContractState {
+    Offered,
+    Accepted,
+    Signed,
+    Confirmed,
+    PreClosed,
+    Closed,
+    Refunded,
+    FailedAccept,
+    FailedSign,
+    Rejected,
+}
+
+impl ContractState {
+    pub const ALL: [ContractState; 10] = [
+        ContractState::Offered,
+        ContractState::Accepted,
+        ContractState::Signed,
+        ContractState::Confirmed,
+        ContractState::PreClosed,
+        ContractState::Closed,
+        ContractState::Refunded,
+        ContractState::FailedAccept,
+        ContractState::FailedSign,
+        ContractState::Rejected,
+    ];
+
+    pub fn of(contract: &Contract) -> ContractState {
+        match contract {
+            Contract::Offered(_) => ContractState::Offered,
+            Contract::Accepted(_) => ContractState::Accepted,
+            Contract::Signed(_) => ContractState::Signed,
+            Contract::Confirmed(_) => ContractState::Confirmed,
+            Contract::PreClosed(_) => ContractState::PreClosed,
+            Contract::Closed(_) => ContractState::Closed,
+            Contract::Refunded(_) => ContractState::Refunded,
+            Contract::FailedAccept(_) => ContractState::FailedAccept,
+            Contract::FailedSign(_) => ContractState::FailedSign,
+            Contract::Rejected(_) => ContractState::Rejected,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ContractState::Offered => "offered",
+            ContractState::Accepted => "accepted",
+            ContractState::Signed => "signed",
+            ContractState::Confirmed => "confirmed",
+            ContractState::PreClosed => "preclosed",
+            ContractState::Closed => "closed",
+            ContractState::Refunded => "refunded",
+            ContractState::FailedAccept => "failed_accept",
+            ContractState::FailedSign => "failed_sign",
+            ContractState::Rejected => "rejected",
+        }
+    }
+}
+
+impl std::fmt::Display for ContractState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for ContractState {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ContractState::ALL
+            .into_iter()
+            .find(|state| state.as_str() == s)
+            .ok_or_else(|| anyhow::anyhow!("Unknown contract state {}", s))
+    }
+}
+
+/// Order of the contracts by creation time.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SortOrder {
+    /// Oldest contracts first.
+    #[default]
+    Ascending,
+    /// Newest contracts first.
+    Descending,
+}
+
+/// Position of a contract in the creation time order. Ties are broken by contract id.
+///
+/// Formats as 80 hex characters so it can be handed to clients.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ContractCursor {
+    pub created_at: u64,
+    pub id: ContractId,
+}
+
+impl ContractCursor {
+    pub fn to_bytes(&self) -> [u8; 40] {
+        let mut bytes = [0u8; 40];
+        bytes[..8].copy_from_slice(&self.created_at.to_be_bytes());
+        bytes[8..].copy_from_slice(&self.id);
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> anyhow::Result<ContractCursor> {
+        if bytes.len() != 40 {
+            return Err(anyhow::anyhow!("A contract cursor is 40 bytes."));
+        }
+        let mut created_at = [0u8; 8];
+        created_at.copy_from_slice(&bytes[..8]);
+        let mut id = [0u8; 32];
+        id.copy_from_slice(&bytes[8..]);
+        Ok(ContractCursor {
+            created_at: u64::from_be_bytes(created_at),
+            id,
+        })
+    }
+}
+
+impl std::fmt::Display for ContractCursor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&hex::encode(self.to_bytes()))
+    }
+}
+
+impl FromStr for ContractCursor {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ContractCursor::from_bytes(&hex::decode(s)?)
+    }
+}
+
+/// Attributes of a stored contract that queries filter on.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContractSummary {
+    pub id: ContractId,
+    pub state: ContractState,
+    pub counterparty: PublicKey,
+    /// Event of the first oracle announcement.
+    pub event_id: Option<String>,
+    /// Maturity of the oracle event.
+    pub maturity: Option<u32>,
+    pub funding_txid: Option<Txid>,
+    /// Unix time the contract was first stored.
+    pub created_at: u64,
+}
+
+impl ContractSummary {
+    /// Summary of a contract being written. `previous` is the stored summary of the contract
+    /// or of its temporary id, closed contracts do not carry the oracle event or funding
+    /// transaction and keep its values. The creation time of a new contract is now.
+    pub fn new(contract: &Contract, previous: Option<&ContractSummary>) -> ContractSummary {
+        let event = offered_contract(contract)
+            .and_then(|o| o.contract_info.first())
+            .and_then(|info| info.oracle_announcements.first())
+            .map(|a| &a.oracle_event);
+        let funding_txid =
+            accepted_contract(contract).map(|a| a.dlc_transactions.fund.compute_txid());
+        ContractSummary {
+            id: contract.get_id(),
+            state: ContractState::of(contract),
+            counterparty: contract.get_counter_party_id(),
+            event_id: event
+                .map(|e| e.event_id.clone())
+                .or_else(|| previous.and_then(|p| p.event_id.clone())),
+            maturity: event
+                .map(|e| e.event_maturity_epoch)
+                .or_else(|| previous.and_then(|p| p.maturity)),
+            funding_txid: funding_txid.or_else(|| previous.and_then(|p| p.funding_txid)),
+            created_at: previous.map_or_else(crate::util::unix_time_now, |p| p.created_at),
+        }
+    }
+
+    pub fn cursor(&self) -> ContractCursor {
+        ContractCursor {
+            created_at: self.created_at,
+            id: self.id,
+        }
+    }
+}
+
+fn offered_contract(contract: &Contract) -> Option<&OfferedContract> {
+    match contract {
+        Contract::Offered(o) | Contract::Rejected(o) => Some(o),
+        Contract::FailedAccept(f) => Some(&f.offered_contract),
+        Contract::Closed(_) => None,
+        _ => accepted_contract(contract).map(|a| &a.offered_contract),
+    }
+}
+
+fn accepted_contract(contract: &Contract) -> Option<&AcceptedContract> {
+    match contract {
+        Contract::Accepted(a) => Some(a),
+        Contract::Signed(s) | Contract::Confirmed(s) | Contract::Refunded(s) => {
+            Some(&s.accepted_contract)
+        }
+        Contract::PreClosed(p) => Some(&p.signed_contract.accepted_contract),
+        Contract::FailedSign(f) => Some(&f.accepted_contract),
+        _ => None,
+    }
+}
+
+/// Contract filters, pagination and order. Filters left unset match every contract.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ContractQuery {
+    /// Contracts in any of these states. Empty matches every state.
+    pub states: BTreeSet<ContractState>,
+    pub counterparty: Option<PublicKey>,
+    pub event_id: Option<String>,
+    /// Range of the oracle event maturity.
+    pub maturity: RangeFilter<u32>,
+    /// Range of the creation time.
+    pub created_at: RangeFilter<u64>,
+    pub order: SortOrder,
+    /// Maximum number of contracts in a page, `None` returns every match.
+    pub limit: Option<usize>,
+    /// Continue after this contract.
+    pub after: Option<ContractCursor>,
+}
+
+impl ContractQuery {
+    pub fn new() -> ContractQuery {
+        ContractQuery::default()
+    }
+
+    /// Only contracts in one of these states.
+    pub fn states<I: IntoIterator<Item = ContractState>>(mut self, states: I) -> ContractQuery {
+        self.states.extend(states);
+        self
+    }
+
+    pub fn counterparty(mut self, counterparty: PublicKey) -> ContractQuery {
+        self.counterparty = Some(counterparty);
+        self
+    }
+
+    /// Only contracts on this oracle event.
+    pub fn event_id(mut self, event_id: impl Into<String>) -> ContractQuery {
+        self.event_id = Some(event_id.into());
+        self
+    }
+
+    /// Only contracts with an oracle event maturing in the range, unix time.
+    pub fn maturity<R: RangeBounds<u32>>(mut self, range: R) -> ContractQuery {
+        self.maturity = RangeFilter::from_range(range, |m| m.checked_add(1), |m| m.checked_sub(1));
+        self
+    }
+
+    /// Only contracts created in the range, unix time.
+    pub fn created_at<R: RangeBounds<u64>>(mut self, range: R) -> ContractQuery {
+        self.created_at =
+            RangeFilter::from_range(range, |t| t.checked_add(1), |t| t.checked_sub(1));
+        self
+    }
+
+    pub fn order(mut self, order: SortOrder) -> ContractQuery {
+        self.order = order;
+        self
+    }
+
+    pub fn limit(mut self, limit: usize) -> ContractQuery {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Continue a query from the cursor of the previous page.
+    pub fn after(mut self, cursor: ContractCursor) -> ContractQuery {
+        self.after = Some(cursor);
+        self
+    }
+
+    /// Whether a contract passes the filters. Does not look at the cursor.
+    pub fn matches(&self, summary: &ContractSummary) -> bool {
+        (self.states.is_empty() || self.states.contains(&summary.state))
+            && self.counterparty.iter().all(|c| *c == summary.counterparty)
+            && self
+                .event_id
+                .iter()
+                .all(|e| summary.event_id.as_ref() == Some(e))
+            && self.maturity.contains(summary.maturity)
+            && self.created_at.contains(Some(summary.created_at))
+    }
+
+    /// Whether a contract comes after the cursor in the query order.
+    pub fn is_after_cursor(&self, cursor: &ContractCursor) -> bool {
+        match (&self.after, self.order) {
+            (None, _) => true,
+            (Some(after), SortOrder::Ascending) => cursor > after,
+            (Some(after), SortOrder::Descending) => cursor < after,
+        }
+    }
+
+    /// Page of the matching summaries. Summaries are filtered, ordered and cut to the limit.
+    pub fn paginate<I>(&self, summaries: I) -> (Vec<ContractSummary>, Option<ContractCursor>)
+    where
+        I: IntoIterator<Item = ContractSummary>,
+    {
+        let mut matching = summaries
+            .into_iter()
+            .filter(|s| self.matches(s) && self.is_after_cursor(&s.cursor()))
+            .collect::<Vec<_>>();
+        matching.sort_by_key(ContractSummary::cursor);
+        if self.order == SortOrder::Descending {
+            matching.reverse();
+        }
+        match self.limit {
+            Some(limit) if matching.len() > limit => {
+                matching.truncate(limit);
+                let next = matching.last().map(ContractSummary::cursor);
+                (matching, next)
+            }
+            _ => (matching, None),
+        }
+    }
+}
+
+/// Range filter of a [`ContractQuery`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RangeFilter<T> {
+    /// Values between the inclusive bounds, `None` leaves that side open. Contracts
+    /// without a value only match when both sides are open.
+    Bounds(Option<T>, Option<T>),
+    /// Matches no contract, e.g. for `..0` or a minimum above the maximum.
+    Empty,
+}
+
+impl<T> Default for RangeFilter<T> {
+    fn default() -> Self {
+        RangeFilter::Bounds(None, None)
+    }
+}
+
+impl<T: Copy + PartialOrd> RangeFilter<T> {
+    /// Range between the inclusive bounds, [`RangeFilter::Empty`] if `min` is above `max`.
+    pub fn between(min: Option<T>, max: Option<T>) -> RangeFilter<T> {
+        match (min, max) {
+            (Some(min), Some(max)) if min > max => RangeFilter::Empty,
+            _ => RangeFilter::Bounds(min, max),
+        }
+    }
+
+    /// Inclusive bounds of `range`. `next` and `previous` step past excluded bounds and
+    /// return `None` when there is no value past them, which leaves the range empty.
+    fn from_range<R: RangeBounds<T>>(
+        range: R,
+        next: impl Fn(T) -> Option<T>,
+        previous: impl Fn(T) -> Option<T>,
+    ) -> RangeFilter<T> {
+        let min = match range.start_bound() {
+            Bound::Included(v) => Some(*v),
+            Bound::Excluded(v) => match next(*v) {
+                Some(v) => Some(v),
+                None => return RangeFilter::Empty,
+            },
+            Bound::Unbounded => None,
+        };
+        let max = match range.end_bound() {
+            Bound::Included(v) => Some(*v),
+            Bound::Excluded(v) => match previous(*v) {
+                Some(v) => Some(v),
+                None => return RangeFilter::Empty,
+            },
+            Bound::Unbounded => None,
+        };
+        RangeFilter::between(min, max)
+    }
+
+    /// Whether both sides of the range are open.
+    pub fn is_unbounded(&self) -> bool {
+        matches!(self, RangeFilter::Bounds(None, None))
+    }
+
+    pub fn contains(&self, value: Option<T>) -> bool {
+        match (self, value) {
+            (RangeFilter::Empty, _) => false,
+            (RangeFilter::Bounds(None, None), _) => true,
+            (RangeFilter::Bounds(..), None) => false,
+            (RangeFilter::Bounds(min, max), Some(value)) => {
+                min.iter().all(|min| value >= *min) && max.iter().all(|max| value <= *max)
+            }
+        }
+    }
+}
+
+/// A page of contracts.
+#[derive(Debug, Clone)]
+pub struct ContractPage {
+    pub contracts: Vec<Contract>,
+    /// Cursor to fetch the next page, `None` on the last page.
+    pub next: Option<ContractCursor>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::secp256k1::{Secp256k1, SecretKey};
+
+    fn summary(id: u8, state: ContractState, created_at: u64) -> ContractSummary {
+        let secp = Secp256k1::new();
+        ContractSummary {
+            id: [id; 32],
+            state,
+            counterparty: PublicKey::from_secret_key(
+                &secp,
+                &SecretKey::from_slice(&[id % 2 + 1; 32]).unwrap(),
+            ),
+            event_id: Some(format!("event-{}", id % 3)),
+            maturity: Some(1000 + id as u32),
+            funding_txid: None,
+            created_at,
+        }
+    }
+
+    fn summaries() -> Vec<ContractSummary> {
+        (0..10)
+            .map(|i| {
+                let state = if i % 2 == 0 {
+                    ContractState::Closed
+                } else {
+                    ContractState::Confirmed
+                };
+                summary(i, state, 100 + (i as u64 / 2))
+            })
+            .collect()
+    }
+
+    fn ids(page: &[ContractSummary]) -> Vec<u8> {
+        page.iter().map(|s| s.id[0]).collect()
+    }
+
+    #[test]
+    fn filters_combine() {
+        let query = ContractQuery::new()
+            .states([ContractState::Confirmed])
+            .maturity(1002..=1007);
+        assert_eq!(ids(&query.paginate(summaries()).0), vec![3, 5, 7]);
+
+        let query = ContractQuery::new().event_id("event-0").created_at(..103);
+        assert_eq!(ids(&query.paginate(summaries()).0), vec![0, 3]);
+
+        for query in [
+            ContractQuery::new().created_at(..0),
+            ContractQuery::new().maturity(..0),
+            ContractQuery::new().maturity(1005..1002),
+            ContractQuery::new().maturity((Bound::Excluded(u32::MAX), Bound::Unbounded)),
+        ] {
+            assert!(query.paginate(summaries()).0.is_empty());
+        }
+
+        let counterparty = summaries()[1].counterparty;
+        let query = ContractQuery::new()
+            .counterparty(counterparty)
+            .states([ContractState::Closed]);
+        assert!(query.paginate(summaries()).0.is_empty());
+    }
+
+    #[test]
+    fn pages_follow_the_cursor() {
+        for (order, expected) in [
+            (SortOrder::Ascending, (0..10).collect::<Vec<u8>>()),
+            (SortOrder::Descending, (0..10).rev().collect()),
+        ] {
+            let mut query = ContractQuery::new().order(order).limit(3);
+            let mut seen = Vec::new();
+            loop {
+                let (page, next) = query.paginate(summaries());
+                seen.extend(ids(&page));
+                match next {
+                    Some(cursor) => query = query.after(cursor),
+                    None => break,
+                }
+            }
+            assert_eq!(seen, expected);
+        }
+    }
+
+    #[test]
+    fn cursor_round_trips() {
+        let cursor = summary(7, ContractState::Offered, 1_700_000_000).cursor();
+        assert_eq!(
+            cursor.to_string().parse::<ContractCursor>().unwrap(),
+            cursor
+        );
+        assert!("00".parse::<ContractCursor>().is_err());
+    }
+}
@@ -1,113 +1,20 @@
-use super::{SledStorage, CHAIN_MONITOR_KEY, CHAIN_MONITOR_TREE};
-use crate::util::{deserialize_contract, serialize_contract};
-use bitcoin::consensus::ReadExt;
+use super::query::{remove_contract, write_contract};
+use super::{SledStorage, CHAIN_MONITOR_KEY, CHAIN_MONITOR_TREE, CHANNEL_TREE, CONTRACT_TREE};
+use crate::util::{
+    deserialize_channel, deserialize_contract, serialize_channel, serialize_contract,
+    ChannelPrefix, ContractPrefix, SignedChannelPrefix,
+};
 use ddk_manager::chain_monitor::ChainMonitor;
-use ddk_manager::channel::accepted_channel::AcceptedChannel;
 use ddk_manager::channel::offered_channel::OfferedChannel;
 use ddk_manager::channel::signed_channel::{SignedChannel, SignedChannelStateType};
-use ddk_manager::channel::{
-    Channel, ClosedChannel, ClosedPunishedChannel, ClosingChannel, FailedAccept, FailedSign,
-};
+use ddk_manager::channel::Channel;
+use ddk_manager::contract::accepted_contract::AcceptedContract;
 use ddk_manager::contract::offered_contract::OfferedContract;
 use ddk_manager::contract::ser::Serializable;
 use ddk_manager::contract::signed_contract::SignedContract;
 use ddk_manager::contract::{Contract, PreClosedContract};
 use ddk_manager::{error::Error, ContractId, Storage};
-use sled::transaction::{ConflictableTransactionResult, UnabortableTransactionError};
 use sled::Transactional;
-use std::convert::TryInto;
-
-macro_rules! convertible_enum {
-    (enum $name:ident {
-        $($vname:ident $(= $val:expr)?,)*;
-        $($tname:ident $(= $tval:expr)?,)*
-    }, $input:ident) => {
-        #[derive(Debug)]
-        enum $name {
-            $($vname $(= $val)?,)*
-            $($tname $(= $tval)?,)*
-        }
-
-        impl From<$name> for u8 {
-            fn from(prefix: $name) -> u8 {
-                prefix as u8
-            }
-        }
-
-        impl std::convert::TryFrom<u8> for $name {
-            type Error = Error;
-
-            fn try_from(v: u8) -> Result<Self, Self::Error> {
-                match v {
-                    $(x if x == u8::from($name::$vname) => Ok($name::$vname),)*
-                    $(x if x == u8::from($name::$tname) => Ok($name::$tname),)*
-                    _ => Err(Error::StorageError("Unknown prefix".to_string())),
-                }
-            }
-        }
-
-        impl $name {
-            fn get_prefix(input: &$input) -> u8 {
-                let prefix = match input {
-                    $($input::$vname(_) => $name::$vname,)*
-                    $($input::$tname{..} => $name::$tname,)*
-                };
-                prefix.into()
-            }
-        }
-    }
-}
-
-convertible_enum!(
-    enum ContractPrefix {
-        Offered = 1,
-        Accepted,
-        Signed,
-        Confirmed,
-        PreClosed,
-        Closed,
-        FailedAccept,
-        FailedSign,
-        Refunded,
-        Rejected,;
-    },
-    Contract
-);
-
-convertible_enum!(
-    enum ChannelPrefix {
-        Offered = 100,
-        Accepted,
-        Signed,
-        FailedAccept,
-        FailedSign,
-        Closing,
-        Closed,
-        CounterClosed,
-        ClosedPunished,
-        CollaborativelyClosed,
-        Cancelled,;
-    },
-    Channel
-);
-
-convertible_enum!(
-    enum SignedChannelPrefix {;
-        Established = 1,
-        SettledOffered,
-        SettledReceived,
-        SettledAccepted,
-        SettledConfirmed,
-        Settled,
-        Closing,
-        CollaborativeCloseOffered,
-        RenewAccepted,
-        RenewOffered,
-        RenewFinalized,
-        RenewConfirmed,
-    },
-    SignedChannelStateType
-);
 
 fn to_storage_error<T>(e: T) -> Error
 where
@@ -118,35 +25,33 @@ where
 
 impl Storage for SledStorage {
     fn get_contract(&self, contract_id: &ContractId) -> Result<Option<Contract>, Error> {
-        match self
-            .contract_tree()?
-            .get(contract_id)
-            .map_err(to_storage_error)?
-        {
-            Some(res) => Ok(Some(deserialize_contract(&res.to_vec())?)),
+        match self.read(CONTRACT_TREE, contract_id)? {
+            Some(res) => Ok(Some(deserialize_contract(&res)?)),
             None => Ok(None),
         }
     }
 
     fn get_contracts(&self) -> Result<Vec<Contract>, Error> {
-        self.contract_tree()?
+        self.read_all(CONTRACT_TREE)?
             .iter()
-            .values()
-            .map(|x| deserialize_contract(&x.unwrap().to_vec()))
+            .map(deserialize_contract)
             .collect::<Result<Vec<Contract>, Error>>()
     }
 
     fn create_contract(&self, contract: &OfferedContract) -> Result<(), Error> {
-        let serialized = serialize_contract(&Contract::Offered(contract.clone()))?;
-        self.contract_tree()?
-            .insert(contract.id, serialized)
+        let contract = Contract::Offered(contract.clone());
+        let serialized = serialize_contract(&contract)?;
+        self.contract_trees()
+            .map_err(to_storage_error)?
+            .transaction(|trees| write_contract(trees, &self.vault, &contract, &serialized))
             .map_err(to_storage_error)?;
         Ok(())
     }
 
     fn delete_contract(&self, contract_id: &ContractId) -> Result<(), Error> {
-        self.contract_tree()?
-            .remove(contract_id)
+        self.contract_trees()
+            .map_err(to_storage_error)?
+            .transaction(|trees| remove_contract(trees, &self.vault, contract_id))
             .map_err(to_storage_error)?;
         Ok(())
     }
@@ -154,18 +59,9 @@ impl Storage for SledStorage {
     fn update_contract(&self, contract: &Contract) -> Result<(), Error> {
         tracing::info!("Updating contract. {:?}", contract);
         let serialized = serialize_contract(contract)?;
-        self.contract_tree()?
-            .transaction::<_, _, UnabortableTransactionError>(|db| {
-                match contract {
-                    a @ Contract::Accepted(_) | a @ Contract::Signed(_) => {
-                        db.remove(&a.get_temporary_id())?;
-                    }
-                    _ => {}
-                };
-
-                db.insert(&contract.get_id(), serialized.clone())?;
-                Ok(())
-            })
+        self.contract_trees()
+            .map_err(to_storage_error)?
+            .transaction(|trees| write_contract(trees, &self.vault, contract, &serialized))
             .map_err(|e| {
                 tracing::error!("Could not update contract: {:?}", e);
                 to_storage_error(e)
@@ -174,35 +70,23 @@ impl Storage for SledStorage {
     }
 
     fn get_contract_offers(&self) -> Result<Vec<OfferedContract>, Error> {
-        self.get_data_with_prefix(
-            &self.contract_tree()?,
-            &[ContractPrefix::Offered.into()],
-            None,
-        )
+        self.get_data_with_prefix(CONTRACT_TREE, &[ContractPrefix::Offered.into()], None)
+    }
+
+    fn get_accepted_contracts(&self) -> Result<Vec<AcceptedContract>, Error> {
+        self.get_data_with_prefix(CONTRACT_TREE, &[ContractPrefix::Accepted.into()], None)
     }
 
     fn get_signed_contracts(&self) -> Result<Vec<SignedContract>, Error> {
-        self.get_data_with_prefix(
-            &self.contract_tree()?,
-            &[ContractPrefix::Signed.into()],
-            None,
-        )
+        self.get_data_with_prefix(CONTRACT_TREE, &[ContractPrefix::Signed.into()], None)
     }
 
     fn get_confirmed_contracts(&self) -> Result<Vec<SignedContract>, Error> {
-        self.get_data_with_prefix(
-            &self.contract_tree()?,
-            &[ContractPrefix::Confirmed.into()],
-            None,
-        )
+        self.get_data_with_prefix(CONTRACT_TREE, &[ContractPrefix::Confirmed.into()], None)
     }
 
     fn get_preclosed_contracts(&self) -> Result<Vec<PreClosedContract>, Error> {
-        self.get_data_with_prefix(
-            &self.contract_tree()?,
-            &[ContractPrefix::PreClosed.into()],
-            None,
-        )
+        self.get_data_with_prefix(CONTRACT_TREE, &[ContractPrefix::PreClosed.into()], None)
     }
 
     fn upsert_channel(&self, channel: Channel, contract: Option<Contract>) -> Result<(), Error> {
@@ -211,33 +95,34 @@ impl Storage for SledStorage {
             Some(c) => Some(serialize_contract(c)?),
             None => None,
         };
-        let channel_tree = self.channel_tree()?;
-        let contract_tree = self.contract_tree()?;
-        (&channel_tree, &contract_tree)
-            .transaction::<_, ()>(
-                |(channel_db, contract_db)| -> ConflictableTransactionResult<(), UnabortableTransactionError> {
-                    match &channel {
-                        a @ Channel::Accepted(_) | a @ Channel::Signed(_) => {
-                            channel_db.remove(&a.get_temporary_id())?;
-                        }
-                        _ => {}
-                    };
-
-                    channel_db.insert(&channel.get_id(), serialized.clone())?;
-
-                    if let Some(c) = contract.as_ref() {
-                        insert_contract(
-                            contract_db,
-                            serialized_contract
-                                .clone()
-                                .expect("to have the serialized version"),
-                            c,
-                        )?;
+        let mut trees = self.contract_trees().map_err(to_storage_error)?;
+        trees.push(self.channel_tree()?);
+        trees
+            .transaction(|trees| {
+                let channel_db = trees.last().expect("to have the channel tree");
+                match &channel {
+                    a @ Channel::Accepted(_) | a @ Channel::Signed(_) => {
+                        channel_db.remove(&a.get_temporary_id())?;
                     }
-                    Ok(())
-                },
-            )
-        .map_err(to_storage_error)?;
+                    _ => {}
+                };
+
+                let id = channel.get_id();
+                channel_db.insert(&id, self.vault.seal(CHANNEL_TREE, &id, &serialized))?;
+
+                if let Some(c) = contract.as_ref() {
+                    write_contract(
+                        trees,
+                        &self.vault,
+                        c,
+                        serialized_contract
+                            .as_ref()
+                            .expect("to have the serialized version"),
+                    )?;
+                }
+                Ok(())
+            })
+            .map_err(to_storage_error)?;
         Ok(())
     }
 
@@ -249,11 +134,7 @@ impl Storage for SledStorage {
     }
 
     fn get_channel(&self, channel_id: &ddk_manager::ChannelId) -> Result<Option<Channel>, Error> {
-        match self
-            .channel_tree()?
-            .get(channel_id)
-            .map_err(to_storage_error)?
-        {
+        match self.read(CHANNEL_TREE, channel_id)? {
             Some(res) => Ok(Some(deserialize_channel(&res)?)),
             None => Ok(None),
         }
@@ -275,27 +156,25 @@ impl Storage for SledStorage {
             (vec![ChannelPrefix::Signed.into()], Some(1))
         };
 
-        self.get_data_with_prefix(&self.channel_tree()?, &prefix, consume)
+        self.get_data_with_prefix(CHANNEL_TREE, &prefix, consume)
     }
 
     fn get_offered_channels(&self) -> Result<Vec<OfferedChannel>, Error> {
-        self.get_data_with_prefix(
-            &self.channel_tree()?,
-            &[ChannelPrefix::Offered.into()],
-            None,
-        )
+        self.get_data_with_prefix(CHANNEL_TREE, &[ChannelPrefix::Offered.into()], None)
     }
 
     fn persist_chain_monitor(&self, monitor: &ChainMonitor) -> Result<(), Error> {
-        self.open_tree(&[CHAIN_MONITOR_TREE])?
-            .insert([CHAIN_MONITOR_KEY], monitor.serialize()?)
-            .map_err(|e| Error::StorageError(format!("Error writing chain monitor: {}", e)))?;
+        self.write(
+            CHAIN_MONITOR_TREE,
+            &[CHAIN_MONITOR_KEY],
+            &monitor.serialize()?,
+        )
+        .map_err(|e| Error::StorageError(format!("Error writing chain monitor: {}", e)))?;
         Ok(())
     }
     fn get_chain_monitor(&self) -> Result<Option<ChainMonitor>, ddk_manager::error::Error> {
         let serialized = self
-            .open_tree(&[CHAIN_MONITOR_TREE])?
-            .get([CHAIN_MONITOR_KEY])
+            .read(CHAIN_MONITOR_TREE, &[CHAIN_MONITOR_KEY])
             .map_err(|e| Error::StorageError(format!("Error reading chain monitor: {}", e)))?;
         let deserialized = match serialized {
             Some(s) => Some(
@@ -308,90 +187,6 @@ impl Storage for SledStorage {
     }
 }
 
-fn insert_contract(
-    db: &sled::transaction::TransactionalTree,
-    serialized: Vec<u8>,
-    contract: &Contract,
-) -> Result<Option<sled::IVec>, UnabortableTransactionError> {
-    match contract {
-        a @ Contract::Accepted(_) | a @ Contract::Signed(_) => {
-            db.remove(&a.get_temporary_id())?;
-        }
-        _ => {}
-    };
-
-    db.insert(&contract.get_id(), serialized)
-}
-
-fn serialize_channel(channel: &Channel) -> Result<Vec<u8>, ::lightning::io::Error> {
-    let serialized = match channel {
-        Channel::Offered(o) => o.serialize(),
-        Channel::Accepted(a) => a.serialize(),
-        Channel::Signed(s) => s.serialize(),
-        Channel::FailedAccept(f) => f.serialize(),
-        Channel::FailedSign(f) => f.serialize(),
-        Channel::Cancelled(o) => o.serialize(),
-        Channel::Closing(c) => c.serialize(),
-        Channel::Closed(c) => c.serialize(),
-        Channel::CollaborativelyClosed(c) => c.serialize(),
-        Channel::CounterClosed(c) => c.serialize(),
-        Channel::ClosedPunished(c) => c.serialize(),
-    };
-    let mut serialized = serialized?;
-    let mut res = Vec::with_capacity(serialized.len() + 1);
-    res.push(ChannelPrefix::get_prefix(channel));
-    if let Channel::Signed(s) = channel {
-        res.push(SignedChannelPrefix::get_prefix(&s.state.get_type()))
-    }
-    res.append(&mut serialized);
-    Ok(res)
-}
-
-fn deserialize_channel(buff: &sled::IVec) -> Result<Channel, Error> {
-    let mut cursor = lightning::io::Cursor::new(buff);
-    let mut prefix = [0u8; 1];
-    cursor.read_slice(&mut prefix)?;
-    let channel_prefix: ChannelPrefix = prefix[0].try_into()?;
-    let channel = match channel_prefix {
-        ChannelPrefix::Offered => {
-            Channel::Offered(OfferedChannel::deserialize(&mut cursor).map_err(to_storage_error)?)
-        }
-        ChannelPrefix::Accepted => {
-            Channel::Accepted(AcceptedChannel::deserialize(&mut cursor).map_err(to_storage_error)?)
-        }
-        ChannelPrefix::Signed => {
-            // Skip the channel state prefix.
-            cursor.set_position(cursor.position() + 1);
-            Channel::Signed(SignedChannel::deserialize(&mut cursor).map_err(to_storage_error)?)
-        }
-        ChannelPrefix::FailedAccept => {
-            Channel::FailedAccept(FailedAccept::deserialize(&mut cursor).map_err(to_storage_error)?)
-        }
-        ChannelPrefix::FailedSign => {
-            Channel::FailedSign(FailedSign::deserialize(&mut cursor).map_err(to_storage_error)?)
-        }
-        ChannelPrefix::Cancelled => {
-            Channel::Cancelled(OfferedChannel::deserialize(&mut cursor).map_err(to_storage_error)?)
-        }
-        ChannelPrefix::Closed => {
-            Channel::Closed(ClosedChannel::deserialize(&mut cursor).map_err(to_storage_error)?)
-        }
-        ChannelPrefix::Closing => {
-            Channel::Closing(ClosingChannel::deserialize(&mut cursor).map_err(to_storage_error)?)
-        }
-        ChannelPrefix::CounterClosed => Channel::CounterClosed(
-            ClosedChannel::deserialize(&mut cursor).map_err(to_storage_error)?,
-        ),
-        ChannelPrefix::ClosedPunished => Channel::ClosedPunished(
-            ClosedPunishedChannel::deserialize(&mut cursor).map_err(to_storage_error)?,
-        ),
-        ChannelPrefix::CollaborativelyClosed => Channel::CollaborativelyClosed(
-            ClosedChannel::deserialize(&mut cursor).map_err(to_storage_error)?,
-        ),
-    };
-    Ok(channel)
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -599,6 +394,63 @@ mod tests {
         assert_eq!(6, contracts.len());
     });
 
+    sled_test!(query_contracts_uses_indexes, |mut storage: SledStorage| {
+        use crate::storage::query::{ContractQuery, ContractState};
+        use crate::Storage as _;
+
+        insert_offered_signed_and_confirmed(&mut storage);
+
+        let query = ContractQuery::new().states([ContractState::Signed, ContractState::Confirmed]);
+        let page = storage.query_contracts(&query).unwrap();
+        assert_eq!(4, page.contracts.len());
+        assert!(page.next.is_none());
+
+        let query = ContractQuery::new().limit(4);
+        let first = storage.query_contracts(&query).unwrap();
+        assert_eq!(4, first.contracts.len());
+        let second = storage
+            .query_contracts(&query.clone().after(first.next.unwrap()))
+            .unwrap();
+        assert_eq!(2, second.contracts.len());
+        assert!(second.next.is_none());
+        assert!(second
+            .contracts
+            .iter()
+            .all(|c| first.contracts.iter().all(|f| f.get_id() != c.get_id())));
+
+        for query in [
+            ContractQuery::new().created_at(..0),
+            ContractQuery::new().maturity(..0),
+        ] {
+            assert!(storage
+                .query_contracts(&query)
+                .unwrap()
+                .contracts
+                .is_empty());
+        }
+    });
+
+    #[test]
+    fn contracts_of_old_databases_are_indexed() {
+        use crate::storage::query::ContractQuery;
+        use crate::Storage as _;
+
+        let path = "tests/data/dlc_storagedb/contracts_of_old_databases_are_indexed";
+        {
+            let mut storage = SledStorage::new(path).unwrap();
+            insert_offered_signed_and_confirmed(&mut storage);
+            for id in super::super::query::CONTRACT_TREE_IDS.iter().skip(1) {
+                storage.db.drop_tree([*id]).unwrap();
+            }
+        }
+        {
+            let storage = SledStorage::new(path).unwrap();
+            let page = storage.query_contracts(&ContractQuery::new()).unwrap();
+            assert_eq!(6, page.contracts.len());
+        }
+        std::fs::remove_dir_all(path).unwrap();
+    }
+
     sled_test!(persist_chain_monitor_test, |storage: SledStorage| {
         let chain_monitor = ChainMonitor::new(123);
 
@@ -613,4 +465,53 @@ mod tests {
 
         assert_eq!(chain_monitor, retrieved);
     });
+
+    sled_test!(key_indexes_are_never_reused, |storage: SledStorage| {
+        use crate::KeyStorage;
+
+        assert_eq!(storage.next_key_index(0).unwrap(), 0);
+        assert_eq!(storage.next_key_index(0).unwrap(), 1);
+        assert_eq!(storage.next_key_index(1).unwrap(), 0);
+        assert_eq!(storage.next_key_index(0).unwrap(), 2);
+    });
+
+    #[test]
+    fn utxo_reservations_survive_restart() {
+        use crate::wallet::UtxoReservation;
+        use crate::Storage as _;
+        use bitcoin::hashes::Hash;
+        use bitcoin::OutPoint;
+
+        let path = "tests/data/dlc_storagedb/utxo_reservations_survive_restart";
+        let reserved = UtxoReservation {
+            outpoint: OutPoint::new(bitcoin::Txid::all_zeros(), 0),
+            contract_id: Some([1u8; 32]),
+            reserved_at: 1,
+        };
+        let released = UtxoReservation {
+            outpoint: OutPoint::new(bitcoin::Txid::all_zeros(), 1),
+            contract_id: None,
+            reserved_at: 2,
+        };
+        {
+            let storage = SledStorage::new(path).unwrap();
+            storage
+                .reserve_utxos(&[reserved.clone(), released.clone()])
+                .unwrap();
+            storage.unreserve_utxos(&[released.outpoint]).unwrap();
+        }
+        {
+            // Sled may still hold the file lock of the dropped database for a moment.
+            let storage = (0..100)
+                .find_map(|_| {
+                    SledStorage::new(path).ok().or_else(|| {
+                        std::thread::sleep(std::time::Duration::from_millis(20));
+                        None
+                    })
+                })
+                .unwrap();
+            assert_eq!(storage.list_utxo_reservations().unwrap(), vec![reserved]);
+        }
+        std::fs::remove_dir_all(path).unwrap();
+    }
 }
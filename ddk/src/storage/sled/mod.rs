@@ -3,18 +3,28 @@
 //! Storage provider for dlc-manager using sled as underlying storage.
 
 mod contract;
+mod query;
+mod vault;
 mod wallet;
 
 use bdk_chain::Merge;
 use bdk_wallet::ChangeSet;
+use bitcoin::OutPoint;
+use ddk_manager::channel::Channel;
 use ddk_manager::contract::ser::Serializable;
 use ddk_manager::error::Error;
 use dlc_messages::oracle_msgs::OracleAnnouncement;
 use lightning::io::{Cursor, Read};
-use sled::{Db, Tree};
+use sled::{Batch, Db, Tree};
+use std::collections::HashMap;
+use vault::{Vault, DEFAULT_TREE};
 
 use crate::error::WalletError;
+use crate::storage::query::{ContractPage, ContractQuery};
 use crate::transport::PeerInformation;
+use crate::util::deserialize_channel;
+use crate::wallet::labels::Label;
+use crate::wallet::UtxoReservation;
 use crate::Storage;
 
 const CONTRACT_TREE: u8 = 1;
@@ -24,35 +34,43 @@ pub const CHAIN_MONITOR_KEY: u8 = 4;
 const SIGNER_TREE: u8 = 6;
 const WALLET_TREE: u8 = 7;
 const MARKETPLACE_TREE: u8 = 8;
+const UTXO_RESERVATION_TREE: u8 = 9;
+const LABEL_TREE: u8 = 10;
+const CONTRACT_SUMMARY_TREE: u8 = 11;
+const CONTRACT_STATE_INDEX_TREE: u8 = 12;
+const CONTRACT_COUNTERPARTY_INDEX_TREE: u8 = 13;
+const CONTRACT_EVENT_INDEX_TREE: u8 = 14;
+const CONTRACT_MATURITY_INDEX_TREE: u8 = 15;
+const CONTRACT_CREATED_INDEX_TREE: u8 = 16;
 
 const MARKETPLACE_KEY: &str = "marketplace";
 const CHANGESET_KEY: &str = "changeset";
 const PEERS_KEY: &str = "peers";
+const FEE_ESTIMATES_KEY: &str = "fee_estimates";
 
 /// Implementation of Storage interface using the sled DB backend.
 #[derive(Debug, Clone)]
 pub struct SledStorage {
     db: Db,
+    vault: Vault,
 }
 
 impl SledStorage {
-    /// Creates a new instance of a SledStorage.
+    /// Creates a new instance of a SledStorage. Fails for an encrypted database, see
+    /// [`SledStorage::new_encrypted`].
     pub fn new(path: &str) -> Result<Self, sled::Error> {
-        Ok(SledStorage {
-            db: sled::open(path)?,
-        })
+        Self::open(path, None)
     }
 
     fn get_data_with_prefix<T: Serializable>(
         &self,
-        tree: &Tree,
+        tree: u8,
         prefix: &[u8],
         consume: Option<u64>,
     ) -> Result<Vec<T>, Error> {
-        let iter = tree.iter();
-        iter.values()
-            .filter_map(|res| {
-                let value = res.unwrap();
+        self.read_all(tree)?
+            .into_iter()
+            .filter_map(|value| {
                 let mut cursor = Cursor::new(&value);
                 let mut pref = vec![0u8; prefix.len()];
                 cursor.read_exact(&mut pref).expect("Error reading prefix");
@@ -93,14 +111,22 @@ impl SledStorage {
     pub fn marketplace_tree(&self) -> Result<Tree, sled::Error> {
         self.db.open_tree([MARKETPLACE_TREE])
     }
+
+    fn utxo_reservation_tree(&self) -> Result<Tree, sled::Error> {
+        self.db.open_tree([UTXO_RESERVATION_TREE])
+    }
+
+    fn label_tree(&self) -> Result<Tree, sled::Error> {
+        self.db.open_tree([LABEL_TREE])
+    }
 }
 
+#[async_trait::async_trait]
 impl Storage for SledStorage {
     fn persist_bdk(&self, changeset: &ChangeSet) -> Result<(), WalletError> {
-        let wallet_tree = self.wallet_tree().map_err(sled_to_wallet_error)?;
-        let new_changeset = match wallet_tree
-            .get(CHANGESET_KEY)
-            .map_err(sled_to_wallet_error)?
+        let new_changeset = match self
+            .read(WALLET_TREE, CHANGESET_KEY.as_bytes())
+            .map_err(manager_to_wallet_error)?
         {
             Some(stored_changeset) => {
                 let mut stored_changeset = serde_json::from_slice::<ChangeSet>(&stored_changeset)?;
@@ -110,19 +136,20 @@ impl Storage for SledStorage {
             None => changeset.to_owned(),
         };
 
-        wallet_tree
-            .insert(CHANGESET_KEY, serde_json::to_vec(&new_changeset)?)
-            .map_err(sled_to_wallet_error)?;
+        self.write(
+            WALLET_TREE,
+            CHANGESET_KEY.as_bytes(),
+            &serde_json::to_vec(&new_changeset)?,
+        )
+        .map_err(manager_to_wallet_error)?;
         Ok(())
     }
 
     fn initialize_bdk(&self) -> Result<ChangeSet, WalletError> {
         tracing::info!("Initializing wallet persistance.");
         let changeset = match self
-            .wallet_tree()
-            .map_err(sled_to_wallet_error)?
-            .get(CHANGESET_KEY)
-            .map_err(sled_to_wallet_error)?
+            .read(WALLET_TREE, CHANGESET_KEY.as_bytes())
+            .map_err(manager_to_wallet_error)?
         {
             Some(changeset) => serde_json::from_slice(&changeset)?,
             None => ChangeSet::default(),
@@ -131,7 +158,7 @@ impl Storage for SledStorage {
     }
 
     fn list_peers(&self) -> anyhow::Result<Vec<PeerInformation>> {
-        if let Some(bytes) = self.db.get(PEERS_KEY)? {
+        if let Some(bytes) = self.read(DEFAULT_TREE, PEERS_KEY.as_bytes())? {
             let peers: Vec<_> = serde_json::from_slice(&bytes)?;
             Ok(peers)
         } else {
@@ -141,23 +168,18 @@ impl Storage for SledStorage {
 
     fn save_peer(&self, peer: PeerInformation) -> anyhow::Result<()> {
         let mut known_peers = self.list_peers()?;
-
-        if known_peers.contains(&peer) {
-            return Ok(());
-        }
-
+        known_peers.retain(|p| p.pubkey != peer.pubkey);
         known_peers.push(peer);
         let peer_vec = serde_json::to_vec(&known_peers)?;
 
-        self.db.insert("peers", peer_vec)?;
+        self.write(DEFAULT_TREE, PEERS_KEY.as_bytes(), &peer_vec)?;
 
         Ok(())
     }
 
     fn save_announcement(&self, announcement: OracleAnnouncement) -> anyhow::Result<()> {
-        let marketplace = self.marketplace_tree().map_err(sled_to_wallet_error)?;
         let stored_announcements: Vec<OracleAnnouncement> =
-            match marketplace.get(MARKETPLACE_KEY)? {
+            match self.read(MARKETPLACE_TREE, MARKETPLACE_KEY.as_bytes())? {
                 Some(o) => serde_json::from_slice(&o)?,
                 None => vec![],
             };
@@ -166,22 +188,123 @@ impl Storage for SledStorage {
         announcements.push(announcement);
 
         let serialize_announcements = serde_json::to_vec(&announcements)?;
-        marketplace.insert(MARKETPLACE_KEY, serialize_announcements)?;
+        self.write(
+            MARKETPLACE_TREE,
+            MARKETPLACE_KEY.as_bytes(),
+            &serialize_announcements,
+        )?;
 
         Ok(())
     }
 
     fn get_marketplace_announcements(&self) -> anyhow::Result<Vec<OracleAnnouncement>> {
-        let marketplace = self.marketplace_tree().map_err(sled_to_wallet_error)?;
-        let prev_announcements = match marketplace.get(MARKETPLACE_KEY)? {
-            Some(o) => o.to_vec(),
-            None => vec![],
-        };
-        let announcements: Vec<OracleAnnouncement> = serde_json::from_slice(&prev_announcements)?;
-        Ok(announcements)
+        match self.read(MARKETPLACE_TREE, MARKETPLACE_KEY.as_bytes())? {
+            Some(announcements) => Ok(serde_json::from_slice(&announcements)?),
+            None => Ok(vec![]),
+        }
+    }
+
+    fn persist_fee_estimates(&self, estimates: &HashMap<u16, f64>) -> anyhow::Result<()> {
+        self.write(
+            WALLET_TREE,
+            FEE_ESTIMATES_KEY.as_bytes(),
+            &serde_json::to_vec(estimates)?,
+        )?;
+        Ok(())
+    }
+
+    fn get_fee_estimates(&self) -> anyhow::Result<Option<HashMap<u16, f64>>> {
+        match self.read(WALLET_TREE, FEE_ESTIMATES_KEY.as_bytes())? {
+            Some(estimates) => Ok(Some(serde_json::from_slice(&estimates)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn reserve_utxos(&self, reservations: &[UtxoReservation]) -> anyhow::Result<()> {
+        let tree = self.utxo_reservation_tree()?;
+        let mut batch = Batch::default();
+        for reservation in reservations {
+            let key = reservation.outpoint.to_string();
+            batch.insert(
+                self.vault.key(UTXO_RESERVATION_TREE, key.as_bytes()),
+                self.vault.seal(
+                    UTXO_RESERVATION_TREE,
+                    key.as_bytes(),
+                    &serde_json::to_vec(reservation)?,
+                ),
+            );
+        }
+        tree.apply_batch(batch)?;
+        Ok(())
+    }
+
+    fn list_utxo_reservations(&self) -> anyhow::Result<Vec<UtxoReservation>> {
+        self.read_all(UTXO_RESERVATION_TREE)?
+            .iter()
+            .map(|value| Ok(serde_json::from_slice(value)?))
+            .collect()
+    }
+
+    fn unreserve_utxos(&self, outpoints: &[OutPoint]) -> anyhow::Result<()> {
+        let tree = self.utxo_reservation_tree()?;
+        let mut batch = Batch::default();
+        for outpoint in outpoints {
+            batch.remove(
+                self.vault
+                    .key(UTXO_RESERVATION_TREE, outpoint.to_string().as_bytes()),
+            );
+        }
+        tree.apply_batch(batch)?;
+        Ok(())
+    }
+
+    fn save_labels(&self, labels: &[Label]) -> anyhow::Result<()> {
+        let tree = self.label_tree()?;
+        let mut batch = Batch::default();
+        for label in labels {
+            let key = format!("{}:{}", label.label_type, label.reference);
+            batch.insert(
+                self.vault.key(LABEL_TREE, key.as_bytes()),
+                self.vault
+                    .seal(LABEL_TREE, key.as_bytes(), &serde_json::to_vec(label)?),
+            );
+        }
+        tree.apply_batch(batch)?;
+        Ok(())
+    }
+
+    fn list_labels(&self) -> anyhow::Result<Vec<Label>> {
+        self.read_all(LABEL_TREE)?
+            .iter()
+            .map(|value| Ok(serde_json::from_slice(value)?))
+            .collect()
+    }
+
+    fn query_contracts(&self, query: &ContractQuery) -> anyhow::Result<ContractPage> {
+        self.find_contracts(query)
+    }
+
+    fn list_channels(&self) -> anyhow::Result<Vec<Channel>> {
+        self.read_all(CHANNEL_TREE)?
+            .iter()
+            .map(|channel| Ok(deserialize_channel(channel)?))
+            .collect()
+    }
+
+    async fn flush(&self) -> anyhow::Result<()> {
+        let bytes = self.db.flush_async().await?;
+        tracing::info!(bytes, "Flushed sled storage.");
+        Ok(())
     }
 }
 
 fn sled_to_wallet_error(error: sled::Error) -> WalletError {
     WalletError::StorageError(error.to_string())
 }
+
+fn manager_to_wallet_error(error: Error) -> WalletError {
+    match error {
+        Error::StorageError(e) => WalletError::StorageError(e),
+        e => WalletError::StorageError(e.to_string()),
+    }
+}
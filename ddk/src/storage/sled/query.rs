@@ -0,0 +1,320 @@
+//! Secondary indexes of the contract tree.
+//!
+//! Every contract has a [`ContractSummary`] in the summary tree. Index trees map
+//! `<attribute><created_at><contract id>` keys to nothing, so a prefix scan of an index lists
+//! the contracts with that attribute in creation order without touching the contracts.
+
+use super::vault::Vault;
+use super::{
+    SledStorage, CONTRACT_COUNTERPARTY_INDEX_TREE, CONTRACT_CREATED_INDEX_TREE,
+    CONTRACT_EVENT_INDEX_TREE, CONTRACT_MATURITY_INDEX_TREE, CONTRACT_STATE_INDEX_TREE,
+    CONTRACT_SUMMARY_TREE, CONTRACT_TREE,
+};
+use crate::storage::query::{
+    ContractCursor, ContractPage, ContractQuery, ContractSummary, RangeFilter, SortOrder,
+};
+use crate::util::deserialize_contract;
+use bitcoin::hashes::{sha256, Hash};
+use ddk_manager::contract::Contract;
+use ddk_manager::error::Error;
+use sled::transaction::{ConflictableTransactionError, TransactionalTree};
+use sled::Tree;
+
+/// Position of the trees in [`SledStorage::contract_trees`].
+pub(super) const CONTRACTS: usize = 0;
+const SUMMARIES: usize = 1;
+const STATE_INDEX: usize = 2;
+const COUNTERPARTY_INDEX: usize = 3;
+const EVENT_INDEX: usize = 4;
+const MATURITY_INDEX: usize = 5;
+const CREATED_INDEX: usize = 6;
+
+pub(super) const CONTRACT_TREE_IDS: [u8; 7] = [
+    CONTRACT_TREE,
+    CONTRACT_SUMMARY_TREE,
+    CONTRACT_STATE_INDEX_TREE,
+    CONTRACT_COUNTERPARTY_INDEX_TREE,
+    CONTRACT_EVENT_INDEX_TREE,
+    CONTRACT_MATURITY_INDEX_TREE,
+    CONTRACT_CREATED_INDEX_TREE,
+];
+
+pub(super) const INDEX_TREES: [u8; 5] = [
+    CONTRACT_STATE_INDEX_TREE,
+    CONTRACT_COUNTERPARTY_INDEX_TREE,
+    CONTRACT_EVENT_INDEX_TREE,
+    CONTRACT_MATURITY_INDEX_TREE,
+    CONTRACT_CREATED_INDEX_TREE,
+];
+
+type TransactionResult<T> = Result<T, ConflictableTransactionError<Error>>;
+
+fn abort<T: std::fmt::Display>(e: T) -> ConflictableTransactionError<Error> {
+    ConflictableTransactionError::Abort(Error::StorageError(e.to_string()))
+}
+
+/// Keys of a contract in each index tree, by position.
+pub(super) fn index_keys(vault: &Vault, summary: &ContractSummary) -> Vec<(usize, Vec<u8>)> {
+    let cursor = summary.cursor().to_bytes();
+    let mut keys = vec![
+        (STATE_INDEX, [&[summary.state as u8][..], &cursor].concat()),
+        (
+            COUNTERPARTY_INDEX,
+            [&counterparty_key(vault, &summary.counterparty)[..], &cursor].concat(),
+        ),
+        (CREATED_INDEX, cursor.to_vec()),
+    ];
+    if let Some(event_id) = &summary.event_id {
+        keys.push((
+            EVENT_INDEX,
+            [&event_key(vault, event_id)[..], &cursor].concat(),
+        ));
+    }
+    if let Some(maturity) = summary.maturity {
+        keys.push((
+            MATURITY_INDEX,
+            [&maturity.to_be_bytes()[..], &cursor].concat(),
+        ));
+    }
+    keys
+}
+
+fn counterparty_key(vault: &Vault, counterparty: &bitcoin::secp256k1::PublicKey) -> Vec<u8> {
+    vault.blind(CONTRACT_COUNTERPARTY_INDEX_TREE, &counterparty.serialize())
+}
+
+/// Event ids have no length limit, the index uses their hash.
+fn event_key(vault: &Vault, event_id: &str) -> Vec<u8> {
+    let hash = sha256::Hash::hash(event_id.as_bytes());
+    vault.blind(CONTRACT_EVENT_INDEX_TREE, hash.as_byte_array())
+}
+
+fn read_summary(
+    vault: &Vault,
+    summaries: &TransactionalTree,
+    id: &[u8],
+) -> TransactionResult<Option<ContractSummary>> {
+    summaries
+        .get(id)?
+        .map(|s| {
+            let summary = vault
+                .open(CONTRACT_SUMMARY_TREE, id, &s)
+                .map_err(ConflictableTransactionError::Abort)?;
+            serde_json::from_slice(&summary).map_err(abort)
+        })
+        .transpose()
+}
+
+/// Remove a contract and its index entries.
+pub(super) fn remove_contract(
+    trees: &[TransactionalTree],
+    vault: &Vault,
+    id: &[u8],
+) -> TransactionResult<()> {
+    if let Some(summary) = read_summary(vault, &trees[SUMMARIES], id)? {
+        for (tree, key) in index_keys(vault, &summary) {
+            trees[tree].remove(key)?;
+        }
+        trees[SUMMARIES].remove(id)?;
+    }
+    trees[CONTRACTS].remove(id)?;
+    Ok(())
+}
+
+/// Write a contract and update its index entries. An accepted or signed contract replaces the
+/// contract stored under its temporary id and keeps its creation time.
+pub(super) fn write_contract(
+    trees: &[TransactionalTree],
+    vault: &Vault,
+    contract: &Contract,
+    serialized: &[u8],
+) -> TransactionResult<()> {
+    let id = contract.get_id();
+    let mut previous = read_summary(vault, &trees[SUMMARIES], &id)?;
+    if let Contract::Accepted(_) | Contract::Signed(_) = contract {
+        let temporary_id = contract.get_temporary_id();
+        if previous.is_none() {
+            previous = read_summary(vault, &trees[SUMMARIES], &temporary_id)?;
+        }
+        remove_contract(trees, vault, &temporary_id)?;
+    }
+    if let Some(previous) = &previous {
+        for (tree, key) in index_keys(vault, previous) {
+            trees[tree].remove(key)?;
+        }
+    }
+
+    let summary = ContractSummary::new(contract, previous.as_ref());
+    for (tree, key) in index_keys(vault, &summary) {
+        trees[tree].insert(key, vec![])?;
+    }
+    let serialized_summary = serde_json::to_vec(&summary).map_err(abort)?;
+    trees[SUMMARIES].insert(
+        &id,
+        vault.seal(CONTRACT_SUMMARY_TREE, &id, &serialized_summary),
+    )?;
+    trees[CONTRACTS].insert(&id, vault.seal(CONTRACT_TREE, &id, serialized))?;
+    Ok(())
+}
+
+impl SledStorage {
+    /// The contract tree and its index trees, in the order of the positions above.
+    pub(super) fn contract_trees(&self) -> Result<Vec<Tree>, sled::Error> {
+        CONTRACT_TREE_IDS
+            .iter()
+            .map(|id| self.db.open_tree([*id]))
+            .collect()
+    }
+
+    /// Index contracts written before the indexes existed. Their creation time is unknown and
+    /// set to zero, they come first in creation order.
+    pub(super) fn index_contracts(&self) -> Result<(), sled::Error> {
+        let trees = self.contract_trees()?;
+        for entry in trees[CONTRACTS].iter() {
+            let (id, serialized) = entry?;
+            if trees[SUMMARIES].contains_key(&id)? {
+                continue;
+            }
+            let contract = match self
+                .vault
+                .open(CONTRACT_TREE, &id, &serialized)
+                .and_then(|serialized| deserialize_contract(&serialized))
+            {
+                Ok(contract) => contract,
+                Err(e) => {
+                    tracing::warn!(id = hex::encode(&id), error = ?e, "Could not index contract.");
+                    continue;
+                }
+            };
+            let mut summary = ContractSummary::new(&contract, None);
+            summary.created_at = 0;
+            for (tree, key) in index_keys(&self.vault, &summary) {
+                trees[tree].insert(key, vec![])?;
+            }
+            let summary = serde_json::to_vec(&summary).expect("summary serializes");
+            trees[SUMMARIES].insert(&id, self.vault.seal(CONTRACT_SUMMARY_TREE, &id, &summary))?;
+        }
+        Ok(())
+    }
+
+    pub(super) fn find_contracts(&self, query: &ContractQuery) -> anyhow::Result<ContractPage> {
+        let trees = self.contract_trees()?;
+        let mut candidates = self.candidates(&trees, query)?;
+        candidates.sort();
+        candidates.dedup();
+        if query.order == SortOrder::Descending {
+            candidates.reverse();
+        }
+
+        let mut page = Vec::new();
+        let mut more = false;
+        for cursor in candidates
+            .into_iter()
+            .filter(|cursor| query.is_after_cursor(cursor))
+        {
+            let Some(summary) = trees[SUMMARIES].get(cursor.id)? else {
+                continue;
+            };
+            let summary = self
+                .vault
+                .open(CONTRACT_SUMMARY_TREE, &cursor.id, &summary)?;
+            let summary: ContractSummary = serde_json::from_slice(&summary)?;
+            if summary.cursor() != cursor || !query.matches(&summary) {
+                continue;
+            }
+            if query.limit.is_some_and(|limit| page.len() == limit) {
+                more = true;
+                break;
+            }
+            page.push(cursor);
+        }
+
+        let contracts = page
+            .iter()
+            .filter_map(|cursor| {
+                let serialized = trees[CONTRACTS].get(cursor.id).transpose()?;
+                Some((cursor, serialized))
+            })
+            .map(|(cursor, serialized)| {
+                let serialized = self.vault.open(CONTRACT_TREE, &cursor.id, &serialized?)?;
+                Ok(deserialize_contract(&serialized)?)
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(ContractPage {
+            contracts,
+            next: if more { page.last().copied() } else { None },
+        })
+    }
+
+    /// Contracts that may match, read from the most selective index of the query.
+    fn candidates(
+        &self,
+        trees: &[Tree],
+        query: &ContractQuery,
+    ) -> anyhow::Result<Vec<ContractCursor>> {
+        if query.maturity == RangeFilter::Empty || query.created_at == RangeFilter::Empty {
+            return Ok(Vec::new());
+        }
+        if let Some(event_id) = &query.event_id {
+            let prefix = event_key(&self.vault, event_id);
+            return scan(&trees[EVENT_INDEX], &[prefix], query);
+        }
+        if let Some(counterparty) = query.counterparty {
+            return scan(
+                &trees[COUNTERPARTY_INDEX],
+                &[counterparty_key(&self.vault, &counterparty)],
+                query,
+            );
+        }
+        if !query.states.is_empty() {
+            let prefixes = query
+                .states
+                .iter()
+                .map(|state| vec![*state as u8])
+                .collect::<Vec<_>>();
+            return scan(&trees[STATE_INDEX], &prefixes, query);
+        }
+        match query.maturity {
+            RangeFilter::Bounds(min, max) if min.is_some() || max.is_some() => {
+                let start = min.unwrap_or(0).to_be_bytes();
+                let end = [&max.unwrap_or(u32::MAX).to_be_bytes()[..], &[0xff; 40]].concat();
+                trees[MATURITY_INDEX]
+                    .range(&start[..]..=&end[..])
+                    .keys()
+                    .map(|key| ContractCursor::from_bytes(&key?[4..]))
+                    .collect()
+            }
+            _ => scan(&trees[CREATED_INDEX], &[vec![]], query),
+        }
+    }
+}
+
+/// Cursors of the index entries with one of the prefixes, restricted to the creation time
+/// range of the query.
+fn scan(
+    index: &Tree,
+    prefixes: &[Vec<u8>],
+    query: &ContractQuery,
+) -> anyhow::Result<Vec<ContractCursor>> {
+    let RangeFilter::Bounds(min, max) = query.created_at else {
+        return Ok(Vec::new());
+    };
+    let start = ContractCursor {
+        created_at: min.unwrap_or(0),
+        id: [0; 32],
+    }
+    .to_bytes();
+    let end = ContractCursor {
+        created_at: max.unwrap_or(u64::MAX),
+        id: [0xff; 32],
+    }
+    .to_bytes();
+    let mut cursors = Vec::new();
+    for prefix in prefixes {
+        let range = [&prefix[..], &start].concat()..=[&prefix[..], &end].concat();
+        for key in index.range(range).keys() {
+            cursors.push(ContractCursor::from_bytes(&key?[prefix.len()..])?);
+        }
+    }
+    Ok(cursors)
+}
@@ -0,0 +1,471 @@
+//! Encryption at rest of the sled database.
+//!
+//! An encrypted database stores an [`EncryptionHeader`] in the default tree and seals every value
+//! with the key it is stored under as associated data, so values cannot be swapped between keys.
+//! The sealed plaintext starts with the key itself, which lets a re-key restore keys that are
+//! blinded. Trees whose keys reveal wallet data store keyed hashes of the keys instead. The
+//! contract index keys keep their state, maturity and creation time in the clear, their
+//! counterparty and event prefixes are blinded. Index entries are only hints, every match is
+//! checked against the sealed contract summary.
+
+use super::query::{index_keys, CONTRACT_TREE_IDS, INDEX_TREES};
+use super::{SledStorage, CONTRACT_SUMMARY_TREE, LABEL_TREE, SIGNER_TREE, UTXO_RESERVATION_TREE};
+use crate::storage::encryption::{EncryptionError, EncryptionHeader, StorageCipher, StorageSecret};
+use crate::storage::query::ContractSummary;
+use ddk_manager::error::Error;
+use sled::{Batch, Db, Tree};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Id of the default tree in associated data. Named trees have ids starting at one.
+pub(super) const DEFAULT_TREE: u8 = 0;
+/// Key of the encryption header in the default tree.
+const HEADER_KEY: &str = "encryption";
+/// Trees keyed by key ids, outpoints and labelled addresses or transactions.
+const BLINDED_TREES: [u8; 3] = [SIGNER_TREE, UTXO_RESERVATION_TREE, LABEL_TREE];
+
+/// Seals the values of an encrypted database, a no-op for a plaintext one.
+#[derive(Debug, Clone, Default)]
+pub(super) struct Vault {
+    cipher: Option<Arc<StorageCipher>>,
+}
+
+impl Vault {
+    fn new(cipher: StorageCipher) -> Self {
+        Vault {
+            cipher: Some(Arc::new(cipher)),
+        }
+    }
+
+    pub fn is_encrypted(&self) -> bool {
+        self.cipher.is_some()
+    }
+
+    /// Key an entry of the tree is stored under.
+    pub fn key(&self, tree: u8, key: &[u8]) -> Vec<u8> {
+        if BLINDED_TREES.contains(&tree) {
+            self.blind(tree, key)
+        } else {
+            key.to_vec()
+        }
+    }
+
+    /// Keyed hash of `data` in an encrypted database, `data` itself otherwise.
+    pub fn blind(&self, tree: u8, data: &[u8]) -> Vec<u8> {
+        match &self.cipher {
+            Some(cipher) => cipher.blind(&[&[tree], data].concat()).to_vec(),
+            None => data.to_vec(),
+        }
+    }
+
+    /// Seal the value stored under `key`, the key before blinding.
+    pub fn seal(&self, tree: u8, key: &[u8], value: &[u8]) -> Vec<u8> {
+        let Some(cipher) = &self.cipher else {
+            return value.to_vec();
+        };
+        let plaintext = [&(key.len() as u32).to_be_bytes()[..], key, value].concat();
+        cipher.seal(&[&[tree], &self.key(tree, key)[..]].concat(), &plaintext)
+    }
+
+    /// Open the value stored under `stored_key`.
+    pub fn open(&self, tree: u8, stored_key: &[u8], value: &[u8]) -> Result<Vec<u8>, Error> {
+        Ok(self.open_entry(tree, stored_key, value)?.1)
+    }
+
+    /// Open an entry, returning its key before blinding and its value.
+    pub fn open_entry(
+        &self,
+        tree: u8,
+        stored_key: &[u8],
+        value: &[u8],
+    ) -> Result<(Vec<u8>, Vec<u8>), Error> {
+        let Some(cipher) = &self.cipher else {
+            return Ok((stored_key.to_vec(), value.to_vec()));
+        };
+        let plaintext = cipher
+            .open(&[&[tree], stored_key].concat(), value)
+            .map_err(to_storage_error)?;
+        let (length, rest) = plaintext
+            .split_first_chunk::<4>()
+            .ok_or_else(|| to_storage_error(EncryptionError::Corrupted))?;
+        let length = u32::from_be_bytes(*length) as usize;
+        if rest.len() < length || self.key(tree, &rest[..length]) != stored_key {
+            return Err(to_storage_error(EncryptionError::Corrupted));
+        }
+        Ok((rest[..length].to_vec(), rest[length..].to_vec()))
+    }
+}
+
+fn to_storage_error<T: std::fmt::Display>(e: T) -> Error {
+    Error::StorageError(e.to_string())
+}
+
+fn unsupported<T: std::fmt::Display>(e: T) -> sled::Error {
+    sled::Error::Unsupported(e.to_string())
+}
+
+fn read_header(db: &Db) -> Result<Option<EncryptionHeader>, sled::Error> {
+    db.get(HEADER_KEY)?
+        .map(|header| serde_json::from_slice(&header).map_err(unsupported))
+        .transpose()
+}
+
+impl SledStorage {
+    /// Opens an encrypted sled database, see [`SledStorage::new`]. A new database is encrypted
+    /// with a key derived from `secret`. An existing plaintext database is rewritten into a fresh
+    /// directory that replaces it, see [`SledStorage::rekey`].
+    pub fn new_encrypted(path: &str, secret: &StorageSecret) -> Result<Self, sled::Error> {
+        let storage = Self::open(path, Some(secret))?;
+        if storage.vault.is_encrypted() {
+            return Ok(storage);
+        }
+        tracing::info!("Encrypting the sled storage.");
+        storage.reseal(Path::new(path), Some(secret))
+    }
+
+    /// Re-encrypts the database at `path` with a key derived from `new`. `None` stands for a
+    /// plaintext database, so this also encrypts or decrypts a database. The database must not be
+    /// open.
+    ///
+    /// Sled keeps overwritten values in its log segments, so the values are rewritten into a
+    /// fresh directory that replaces the old one instead of in place. The old directory is
+    /// deleted, its files may still be recoverable from the disk itself.
+    pub fn rekey(
+        path: &str,
+        current: Option<&StorageSecret>,
+        new: Option<&StorageSecret>,
+    ) -> Result<(), sled::Error> {
+        let storage = Self::open(path, current)?;
+        if current.is_some() && !storage.vault.is_encrypted() {
+            return Err(unsupported("The storage is not encrypted."));
+        }
+        storage.reseal(Path::new(path), new)?;
+        Ok(())
+    }
+
+    /// Open the database and unlock it with `secret` if it is encrypted.
+    pub(super) fn open(path: &str, secret: Option<&StorageSecret>) -> Result<Self, sled::Error> {
+        let db = sled::open(path)?;
+        let vault = match (read_header(&db)?, secret) {
+            (Some(header), Some(secret)) => Vault::new(header.unlock(secret).map_err(unsupported)?),
+            (Some(_), None) => {
+                return Err(unsupported(
+                    "The storage is encrypted, open it with SledStorage::new_encrypted.",
+                ))
+            }
+            (None, _) => Vault::default(),
+        };
+        let storage = SledStorage { db, vault };
+        storage.index_contracts()?;
+        Ok(storage)
+    }
+
+    /// The tree with the id, the default tree for [`DEFAULT_TREE`].
+    pub(super) fn tree(&self, id: u8) -> Result<Tree, sled::Error> {
+        if id == DEFAULT_TREE {
+            Ok((*self.db).clone())
+        } else {
+            self.db.open_tree([id])
+        }
+    }
+
+    pub(super) fn read(&self, tree: u8, key: &[u8]) -> Result<Option<Vec<u8>>, Error> {
+        let stored_key = self.vault.key(tree, key);
+        self.tree(tree)
+            .and_then(|t| t.get(&stored_key))
+            .map_err(to_storage_error)?
+            .map(|value| self.vault.open(tree, &stored_key, &value))
+            .transpose()
+    }
+
+    /// Values of the tree in key order.
+    pub(super) fn read_all(&self, tree: u8) -> Result<Vec<Vec<u8>>, Error> {
+        self.tree(tree)
+            .map_err(to_storage_error)?
+            .iter()
+            .map(|entry| {
+                let (key, value) = entry.map_err(to_storage_error)?;
+                self.vault.open(tree, &key, &value)
+            })
+            .collect()
+    }
+
+    pub(super) fn write(&self, tree: u8, key: &[u8], value: &[u8]) -> Result<(), Error> {
+        self.tree(tree)
+            .and_then(|t| t.insert(self.vault.key(tree, key), self.vault.seal(tree, key, value)))
+            .map_err(to_storage_error)?;
+        Ok(())
+    }
+
+    /// Rewrite every entry for a new secret into a fresh database at `path`, the directory of
+    /// this one. The old directory is kept aside until the new one is written and restored if
+    /// writing fails.
+    fn reseal(self, path: &Path, secret: Option<&StorageSecret>) -> Result<Self, sled::Error> {
+        let (header, vault) = match secret {
+            Some(secret) => {
+                let (header, cipher) = EncryptionHeader::new(secret);
+                (Some(header), Vault::new(cipher))
+            }
+            None => (None, Vault::default()),
+        };
+
+        let mut ids = vec![DEFAULT_TREE];
+        ids.extend(
+            self.db
+                .tree_names()
+                .iter()
+                .filter(|name| name.len() == 1)
+                .map(|name| name[0]),
+        );
+
+        // Insertions of each tree. Index entries are rebuilt from the summaries.
+        let mut writes = vec![Batch::default(); ids.len()];
+        let position = |id: u8| ids.iter().position(|i| *i == id);
+        for (i, id) in ids.iter().enumerate() {
+            for entry in self.tree(*id)?.iter() {
+                let (stored_key, value) = entry?;
+                if (*id == DEFAULT_TREE && &*stored_key == HEADER_KEY.as_bytes())
+                    || INDEX_TREES.contains(id)
+                {
+                    continue;
+                }
+                let (key, value) = self
+                    .vault
+                    .open_entry(*id, &stored_key, &value)
+                    .map_err(unsupported)?;
+                if *id == CONTRACT_SUMMARY_TREE {
+                    let summary: ContractSummary =
+                        serde_json::from_slice(&value).map_err(unsupported)?;
+                    for (tree, index_key) in index_keys(&vault, &summary) {
+                        if let Some(p) = position(CONTRACT_TREE_IDS[tree]) {
+                            writes[p].insert(index_key, vec![]);
+                        }
+                    }
+                }
+                writes[i].insert(vault.key(*id, &key), vault.seal(*id, &key, &value));
+            }
+        }
+        if let Some(header) = &header {
+            let header = serde_json::to_vec(header).map_err(unsupported)?;
+            writes[0].insert(HEADER_KEY.as_bytes(), header);
+        }
+
+        let replaced = replaced_path(path)?;
+        if replaced.exists() {
+            return Err(unsupported(format!(
+                "{} is left from an interrupted re-key, restore or remove it first.",
+                replaced.display()
+            )));
+        }
+        self.db.flush()?;
+        drop(self);
+        std::fs::rename(path, &replaced)?;
+
+        let written = sled::open(path).and_then(|db| {
+            let storage = SledStorage { db, vault };
+            for (id, batch) in ids.iter().zip(writes) {
+                storage.tree(*id)?.apply_batch(batch)?;
+            }
+            storage.db.flush()?;
+            Ok(storage)
+        });
+        match written {
+            Ok(storage) => {
+                std::fs::remove_dir_all(&replaced)?;
+                Ok(storage)
+            }
+            Err(e) => {
+                let _ = std::fs::remove_dir_all(path);
+                std::fs::rename(&replaced, path)?;
+                Err(e)
+            }
+        }
+    }
+}
+
+/// Where the old directory of a database is kept while it is rewritten.
+fn replaced_path(path: &Path) -> Result<PathBuf, sled::Error> {
+    let mut name = path
+        .file_name()
+        .ok_or_else(|| unsupported("The storage path has no directory name."))?
+        .to_os_string();
+    name.push(".replaced");
+    Ok(path.with_file_name(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::query::ContractQuery;
+    use crate::{KeyStorage, Storage as _};
+    use bitcoin::secp256k1::SecretKey;
+    use ddk_manager::contract::offered_contract::OfferedContract;
+    use ddk_manager::contract::ser::Serializable;
+    use ddk_manager::Storage as _;
+    use std::path::Path;
+
+    const KEY_ID: [u8; 32] = [7; 32];
+
+    fn offered_contract() -> OfferedContract {
+        let serialized = include_bytes!("../../../tests/data/dlc_storage/Offered");
+        OfferedContract::deserialize(&mut lightning::io::Cursor::new(&serialized)).unwrap()
+    }
+
+    fn secret_key() -> SecretKey {
+        SecretKey::from_slice(&[3; 32]).unwrap()
+    }
+
+    fn populate(storage: &SledStorage) {
+        storage.create_contract(&offered_contract()).unwrap();
+        storage.store_secret_key(KEY_ID, secret_key()).unwrap();
+        storage.next_key_index(0).unwrap();
+        storage.db.flush().unwrap();
+    }
+
+    fn copy_dir(from: &Path, to: &Path) {
+        std::fs::create_dir_all(to).unwrap();
+        for entry in std::fs::read_dir(from).unwrap() {
+            let entry = entry.unwrap();
+            if entry.file_type().unwrap().is_dir() {
+                copy_dir(&entry.path(), &to.join(entry.file_name()));
+            } else {
+                std::fs::copy(entry.path(), to.join(entry.file_name())).unwrap();
+            }
+        }
+    }
+
+    /// Retry opening while sled still holds the lock of a dropped database, it releases the lock
+    /// from background threads.
+    fn reopen<T>(open: impl Fn() -> Result<T, sled::Error>) -> Result<T, sled::Error> {
+        for _ in 0..100 {
+            match open() {
+                Err(sled::Error::Io(e)) if e.to_string().contains("could not acquire lock") => {
+                    std::thread::sleep(std::time::Duration::from_millis(20))
+                }
+                result => return result,
+            }
+        }
+        open()
+    }
+
+    fn is_locked_out<T>(result: Result<T, sled::Error>) -> bool {
+        matches!(result, Err(sled::Error::Unsupported(_)))
+    }
+
+    /// Whether any file of the directory contains `needle`.
+    fn contains(dir: &Path, needle: &[u8]) -> bool {
+        std::fs::read_dir(dir).unwrap().any(|entry| {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                contains(&path, needle)
+            } else {
+                std::fs::read(path)
+                    .unwrap()
+                    .windows(needle.len())
+                    .any(|w| w == needle)
+            }
+        })
+    }
+
+    #[test]
+    fn copied_database_is_unreadable_without_the_secret() {
+        let root = Path::new("tests/data/dlc_storagedb/copied_database_is_unreadable");
+        let _ = std::fs::remove_dir_all(root);
+        let (plain, encrypted, copy) = (
+            root.join("plain"),
+            root.join("encrypted"),
+            root.join("copy"),
+        );
+        let seed = StorageSecret::Seed(vec![1; 64]);
+        let event_id = offered_contract().contract_info[0].oracle_announcements[0]
+            .oracle_event
+            .event_id
+            .clone();
+        let needles = [
+            event_id.as_bytes().to_vec(),
+            secret_key().display_secret().to_string().into_bytes(),
+            hex::encode(KEY_ID).into_bytes(),
+        ];
+
+        populate(&SledStorage::new(plain.to_str().unwrap()).unwrap());
+        assert!(needles.iter().all(|needle| contains(&plain, needle)));
+
+        populate(&SledStorage::new_encrypted(encrypted.to_str().unwrap(), &seed).unwrap());
+        copy_dir(&encrypted, &copy);
+        assert!(needles.iter().all(|needle| !contains(&copy, needle)));
+
+        let copy = copy.to_str().unwrap();
+        assert!(is_locked_out(SledStorage::new(copy)));
+        assert!(is_locked_out(reopen(|| {
+            SledStorage::new_encrypted(copy, &StorageSecret::Seed(vec![2; 64]))
+        })));
+        let storage = reopen(|| SledStorage::new_encrypted(copy, &seed)).unwrap();
+        assert_eq!(storage.get_contracts().unwrap().len(), 1);
+        assert_eq!(storage.get_secret_key(KEY_ID).unwrap(), secret_key());
+        drop(storage);
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn encrypting_a_plaintext_database_leaves_no_plaintext() {
+        let path = Path::new("tests/data/dlc_storagedb/encrypting_a_plaintext_database");
+        let _ = std::fs::remove_dir_all(path);
+        let seed = StorageSecret::Seed(vec![1; 64]);
+        let needles = [
+            secret_key().display_secret().to_string().into_bytes(),
+            hex::encode(KEY_ID).into_bytes(),
+        ];
+
+        populate(&SledStorage::new(path.to_str().unwrap()).unwrap());
+        assert!(needles.iter().all(|needle| contains(path, needle)));
+
+        let storage = reopen(|| SledStorage::new_encrypted(path.to_str().unwrap(), &seed)).unwrap();
+        assert_eq!(storage.get_contracts().unwrap().len(), 1);
+        assert_eq!(storage.get_secret_key(KEY_ID).unwrap(), secret_key());
+        assert!(needles.iter().all(|needle| !contains(path, needle)));
+        assert!(!replaced_path(path).unwrap().exists());
+        drop(storage);
+
+        assert!(is_locked_out(reopen(|| SledStorage::new(
+            path.to_str().unwrap()
+        ))));
+        std::fs::remove_dir_all(path).unwrap();
+    }
+
+    #[test]
+    fn rekey_rotates_the_secret() {
+        let path = "tests/data/dlc_storagedb/rekey_rotates_the_secret";
+        let seed = StorageSecret::Seed(vec![1; 64]);
+        let passphrase = StorageSecret::Passphrase("correct horse".to_string());
+
+        populate(&SledStorage::new(path).unwrap());
+        drop(reopen(|| SledStorage::new_encrypted(path, &seed)).unwrap());
+        assert!(is_locked_out(reopen(|| SledStorage::new(path))));
+
+        reopen(|| SledStorage::rekey(path, Some(&seed), Some(&passphrase))).unwrap();
+        assert!(is_locked_out(reopen(|| SledStorage::new_encrypted(
+            path, &seed
+        ))));
+        {
+            let storage = reopen(|| SledStorage::new_encrypted(path, &passphrase)).unwrap();
+            let page = storage.query_contracts(&ContractQuery::new()).unwrap();
+            assert_eq!(page.contracts.len(), 1);
+            assert_eq!(storage.get_secret_key(KEY_ID).unwrap(), secret_key());
+            assert_eq!(storage.next_key_index(0).unwrap(), 1);
+        }
+
+        reopen(|| SledStorage::rekey(path, Some(&passphrase), None)).unwrap();
+        {
+            let storage = reopen(|| SledStorage::new(path)).unwrap();
+            let counterparty = offered_contract().counter_party;
+            let page = storage
+                .query_contracts(&ContractQuery::new().counterparty(counterparty))
+                .unwrap();
+            assert_eq!(page.contracts.len(), 1);
+            assert_eq!(storage.next_key_index(0).unwrap(), 2);
+        }
+
+        std::fs::remove_dir_all(path).unwrap();
+    }
+}
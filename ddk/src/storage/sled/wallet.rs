@@ -1,18 +1,18 @@
-use super::sled_to_wallet_error;
-use super::SledStorage;
+use super::{manager_to_wallet_error, SledStorage, SIGNER_TREE};
 use crate::error::WalletError;
 use crate::KeyStorage;
 use bitcoin::secp256k1::SecretKey;
+use std::collections::HashMap;
+
+const KEY_INDEX_PREFIX: &str = "key_index_";
 
 impl KeyStorage for SledStorage {
     fn get_secret_key(&self, key_id: [u8; 32]) -> Result<SecretKey, WalletError> {
         let key = hex::encode(key_id);
         let info = self
-            .signer_tree()
-            .map_err(sled_to_wallet_error)?
-            .get(key)
-            .map_err(sled_to_wallet_error)?
-            .unwrap();
+            .read(SIGNER_TREE, key.as_bytes())
+            .map_err(manager_to_wallet_error)?
+            .ok_or_else(|| WalletError::StorageError(format!("No secret key for {}", key)))?;
         Ok(serde_json::from_slice::<SecretKey>(&info)?)
     }
 
@@ -23,10 +23,91 @@ impl KeyStorage for SledStorage {
         // Store the key id string instead of bytes.
         let key_id = hex::encode(key_id);
 
-        self.signer_tree()
-            .map_err(sled_to_wallet_error)?
-            .insert(key_id, serialized_signer_info)
-            .map_err(sled_to_wallet_error)?;
+        self.write(SIGNER_TREE, key_id.as_bytes(), &serialized_signer_info)
+            .map_err(manager_to_wallet_error)?;
         Ok(())
     }
+
+    fn next_key_index(&self, account: u32) -> anyhow::Result<u32> {
+        self.update_key_index(account, |index| {
+            if index == u32::MAX {
+                return Err(anyhow::anyhow!("Key indexes exhausted."));
+            }
+            Ok((index + 1, index))
+        })
+    }
+
+    fn list_secret_keys(&self) -> Result<Vec<([u8; 32], SecretKey)>, WalletError> {
+        let mut keys = Vec::new();
+        let tree = self
+            .signer_tree()
+            .map_err(|e| WalletError::StorageError(e.to_string()))?;
+        for entry in tree.iter() {
+            let (key, value) = entry.map_err(|e| WalletError::StorageError(e.to_string()))?;
+            let (key, value) = self
+                .vault
+                .open_entry(SIGNER_TREE, &key, &value)
+                .map_err(manager_to_wallet_error)?;
+            let Some(key_id) = hex::decode(&key)
+                .ok()
+                .and_then(|key_id| <[u8; 32]>::try_from(key_id).ok())
+            else {
+                continue;
+            };
+            keys.push((key_id, serde_json::from_slice(&value)?));
+        }
+        Ok(keys)
+    }
+
+    fn key_indexes(&self) -> anyhow::Result<HashMap<u32, u32>> {
+        let mut indexes = HashMap::new();
+        for entry in self.signer_tree()?.iter() {
+            let (key, value) = entry?;
+            let (key, value) = self.vault.open_entry(SIGNER_TREE, &key, &value)?;
+            let Some(account) = key
+                .strip_prefix(KEY_INDEX_PREFIX.as_bytes())
+                .and_then(|account| std::str::from_utf8(account).ok()?.parse().ok())
+            else {
+                continue;
+            };
+            indexes.insert(account, decode_index(&value));
+        }
+        Ok(indexes)
+    }
+
+    fn advance_key_index(&self, account: u32, next: u32) -> anyhow::Result<()> {
+        self.update_key_index(account, |index| Ok((index.max(next), ())))
+    }
+}
+
+impl SledStorage {
+    /// Replace the next key index of the account with the one returned by `update`, retrying
+    /// if another writer changed it in the meantime.
+    fn update_key_index<T>(
+        &self,
+        account: u32,
+        update: impl Fn(u32) -> anyhow::Result<(u32, T)>,
+    ) -> anyhow::Result<T> {
+        let tree = self.signer_tree()?;
+        let name = format!("{}{}", KEY_INDEX_PREFIX, account);
+        let key = self.vault.key(SIGNER_TREE, name.as_bytes());
+        loop {
+            let current = tree.get(&key)?;
+            let index = match &current {
+                Some(v) => decode_index(&self.vault.open(SIGNER_TREE, &key, v)?),
+                None => 0,
+            };
+            let (next, result) = update(index)?;
+            let next = self
+                .vault
+                .seal(SIGNER_TREE, name.as_bytes(), &next.to_be_bytes());
+            if tree.compare_and_swap(&key, current, Some(next))?.is_ok() {
+                return Ok(result);
+            }
+        }
+    }
+}
+
+fn decode_index(bytes: &[u8]) -> u32 {
+    bytes.try_into().map(u32::from_be_bytes).unwrap_or(u32::MAX)
 }
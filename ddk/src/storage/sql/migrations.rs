@@ -0,0 +1,131 @@
+//! Schema migrations. Migrations are applied in order and recorded in `schema_migrations`, a
+//! database is never migrated twice. Append new migrations, never edit a released one.
+
+use super::{Dialect, SqlConnection, SqlValue};
+
+pub(crate) struct Migration {
+    pub version: i64,
+    /// Statements of the migration. `BLOB` is replaced with `BYTEA` on Postgres.
+    pub sql: &'static str,
+}
+
+pub(crate) const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        sql: "
+        CREATE TABLE contracts (
+            id BLOB PRIMARY KEY,
+            state TEXT NOT NULL,
+            counterparty TEXT NOT NULL,
+            event_id TEXT,
+            maturity BIGINT,
+            funding_txid TEXT,
+            data BLOB NOT NULL
+        );
+        CREATE INDEX contracts_state ON contracts (state);
+        CREATE INDEX contracts_counterparty ON contracts (counterparty);
+        CREATE INDEX contracts_event_id ON contracts (event_id);
+        CREATE INDEX contracts_maturity ON contracts (maturity);
+        CREATE INDEX contracts_funding_txid ON contracts (funding_txid);
+
+        CREATE TABLE channels (
+            id BLOB PRIMARY KEY,
+            state TEXT NOT NULL,
+            signed_state TEXT,
+            counterparty TEXT NOT NULL,
+            data BLOB NOT NULL
+        );
+        CREATE INDEX channels_state ON channels (state, signed_state);
+
+        CREATE TABLE chain_monitor (
+            id BIGINT PRIMARY KEY,
+            data BLOB NOT NULL
+        );
+
+        CREATE TABLE wallet (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL
+        );
+
+        CREATE TABLE peers (
+            pubkey TEXT PRIMARY KEY,
+            data BLOB NOT NULL
+        );
+
+        CREATE TABLE announcements (
+            event_id TEXT PRIMARY KEY,
+            maturity BIGINT NOT NULL,
+            data BLOB NOT NULL
+        );
+
+        CREATE TABLE secret_keys (
+            key_id TEXT PRIMARY KEY,
+            secret_key BLOB NOT NULL
+        );
+
+        CREATE TABLE key_indexes (
+            account BIGINT PRIMARY KEY,
+            next_index BIGINT NOT NULL
+        );
+
+        CREATE TABLE utxo_reservations (
+            outpoint TEXT PRIMARY KEY,
+            contract_id TEXT,
+            reserved_at BIGINT NOT NULL,
+            data BLOB NOT NULL
+        );
+
+        CREATE TABLE labels (
+            label_type TEXT NOT NULL,
+            reference TEXT NOT NULL,
+            data BLOB NOT NULL,
+            PRIMARY KEY (label_type, reference)
+        );
+    ",
+    },
+    Migration {
+        version: 2,
+        sql: "
+        ALTER TABLE contracts ADD COLUMN created_at BIGINT NOT NULL DEFAULT 0;
+        CREATE INDEX contracts_created_at ON contracts (created_at, id);
+    ",
+    },
+];
+
+/// Apply the migrations the database has not seen yet.
+pub(crate) fn migrate(connection: &mut dyn SqlConnection) -> anyhow::Result<()> {
+    connection.execute_batch(
+        "CREATE TABLE IF NOT EXISTS schema_migrations (
+            version BIGINT PRIMARY KEY,
+            applied_at BIGINT NOT NULL
+        );",
+    )?;
+    let current = current_version(connection)?;
+    for migration in MIGRATIONS.iter().filter(|m| m.version > current) {
+        tracing::info!(version = migration.version, "Applying storage migration.");
+        let sql = match connection.dialect() {
+            Dialect::Sqlite => migration.sql.to_string(),
+            Dialect::Postgres => migration.sql.replace("BLOB", "BYTEA"),
+        };
+        let record = format!(
+            "INSERT INTO schema_migrations (version, applied_at) VALUES ({}, {});",
+            migration.version,
+            crate::util::unix_time_now()
+        );
+        connection.execute_batch(&format!("{}\n{}", sql, record))?;
+    }
+    Ok(())
+}
+
+/// Version of the last applied migration, `0` for an empty database.
+pub(crate) fn current_version(connection: &mut dyn SqlConnection) -> anyhow::Result<i64> {
+    match connection
+        .query("SELECT MAX(version) FROM schema_migrations", vec![])?
+        .into_iter()
+        .next()
+        .and_then(|row| row.into_iter().next())
+    {
+        Some(SqlValue::Integer(version)) => Ok(version),
+        _ => Ok(0),
+    }
+}
@@ -0,0 +1,156 @@
+use super::{Dialect, SqlConnection, SqlValue, Statement};
+use bytes::BytesMut;
+use postgres::types::{to_sql_checked, FromSql, IsNull, ToSql, Type};
+use postgres::{Client, NoTls};
+use std::sync::mpsc;
+
+type Job = Box<dyn FnOnce(&mut Client) + Send>;
+
+/// The blocking postgres client drives its own tokio runtime and panics when it is used from
+/// within another one. The client lives on a dedicated thread and statements are sent to it.
+pub(crate) struct PostgresConnection {
+    jobs: mpsc::Sender<Job>,
+}
+
+impl PostgresConnection {
+    pub fn connect(url: &str) -> anyhow::Result<Self> {
+        let (jobs, receiver) = mpsc::channel::<Job>();
+        let (connected, connection) = mpsc::sync_channel(1);
+        let url = url.to_string();
+        std::thread::Builder::new()
+            .name("ddk-postgres".to_string())
+            .spawn(move || {
+                let mut client = match Client::connect(&url, NoTls) {
+                    Ok(client) => {
+                        let _ = connected.send(Ok(()));
+                        client
+                    }
+                    Err(e) => {
+                        let _ = connected.send(Err(e));
+                        return;
+                    }
+                };
+                while let Ok(job) = receiver.recv() {
+                    job(&mut client);
+                }
+            })?;
+        connection.recv()??;
+        Ok(PostgresConnection { jobs })
+    }
+
+    fn run<T, F>(&self, f: F) -> anyhow::Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Client) -> anyhow::Result<T> + Send + 'static,
+    {
+        let (sender, receiver) = mpsc::sync_channel(1);
+        self.jobs
+            .send(Box::new(move |client| {
+                let _ = sender.send(f(client));
+            }))
+            .map_err(|_| anyhow::anyhow!("Postgres connection closed."))?;
+        receiver
+            .recv()
+            .map_err(|_| anyhow::anyhow!("Postgres connection closed."))?
+    }
+}
+
+impl SqlConnection for PostgresConnection {
+    fn dialect(&self) -> Dialect {
+        Dialect::Postgres
+    }
+
+    fn execute_batch(&mut self, sql: &str) -> anyhow::Result<()> {
+        let sql = sql.to_string();
+        self.run(move |client| {
+            let mut transaction = client.transaction()?;
+            transaction.batch_execute(&sql)?;
+            transaction.commit()?;
+            Ok(())
+        })
+    }
+
+    fn query(&mut self, sql: &str, params: Vec<SqlValue>) -> anyhow::Result<Vec<Vec<SqlValue>>> {
+        let sql = sql.to_string();
+        self.run(move |client| run(client, &sql, &params))
+    }
+
+    fn transaction(&mut self, statements: Vec<Statement>) -> anyhow::Result<()> {
+        self.run(move |client| {
+            let mut transaction = client.transaction()?;
+            for (sql, params) in statements {
+                run(&mut transaction, sql, &params)?;
+            }
+            transaction.commit()?;
+            Ok(())
+        })
+    }
+}
+
+fn run<C: postgres::GenericClient>(
+    client: &mut C,
+    sql: &str,
+    params: &[SqlValue],
+) -> anyhow::Result<Vec<Vec<SqlValue>>> {
+    let params = params
+        .iter()
+        .map(|p| p as &(dyn ToSql + Sync))
+        .collect::<Vec<_>>();
+    let rows = client.query(sql, &params)?;
+    Ok(rows
+        .iter()
+        .map(|row| {
+            (0..row.len())
+                .map(|i| row.try_get::<_, SqlValue>(i))
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .collect::<Result<Vec<_>, _>>()?)
+}
+
+impl ToSql for SqlValue {
+    fn to_sql(
+        &self,
+        ty: &Type,
+        out: &mut BytesMut,
+    ) -> Result<IsNull, Box<dyn std::error::Error + Sync + Send>> {
+        match self {
+            SqlValue::Null => Ok(IsNull::Yes),
+            SqlValue::Integer(i) => match *ty {
+                Type::INT4 => i32::try_from(*i)?.to_sql(ty, out),
+                _ => i.to_sql(ty, out),
+            },
+            SqlValue::Real(r) => r.to_sql(ty, out),
+            SqlValue::Text(t) => t.to_sql(ty, out),
+            SqlValue::Blob(b) => b.to_sql(ty, out),
+        }
+    }
+
+    fn accepts(_: &Type) -> bool {
+        true
+    }
+
+    to_sql_checked!();
+}
+
+impl<'a> FromSql<'a> for SqlValue {
+    fn from_sql(
+        ty: &Type,
+        raw: &'a [u8],
+    ) -> Result<Self, Box<dyn std::error::Error + Sync + Send>> {
+        Ok(match *ty {
+            Type::INT8 => SqlValue::Integer(i64::from_sql(ty, raw)?),
+            Type::INT4 => SqlValue::Integer(i32::from_sql(ty, raw)?.into()),
+            Type::FLOAT8 => SqlValue::Real(f64::from_sql(ty, raw)?),
+            Type::BYTEA => SqlValue::Blob(Vec::<u8>::from_sql(ty, raw)?),
+            _ => SqlValue::Text(String::from_sql(ty, raw)?),
+        })
+    }
+
+    fn from_sql_null(_: &Type) -> Result<Self, Box<dyn std::error::Error + Sync + Send>> {
+        Ok(SqlValue::Null)
+    }
+
+    fn accepts(_: &Type) -> bool {
+        true
+    }
+}
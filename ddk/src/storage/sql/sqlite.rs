@@ -0,0 +1,90 @@
+use super::{Dialect, SqlConnection, SqlValue, Statement};
+use rusqlite::types::{ToSqlOutput, Value, ValueRef};
+use rusqlite::{Connection, ToSql};
+
+pub(crate) struct SqliteConnection {
+    connection: Connection,
+}
+
+impl SqliteConnection {
+    pub fn open<P: AsRef<std::path::Path>>(path: P) -> anyhow::Result<Self> {
+        let connection = Connection::open(path)?;
+        connection.pragma_update(None, "journal_mode", "WAL")?;
+        Ok(SqliteConnection { connection })
+    }
+}
+
+impl SqlConnection for SqliteConnection {
+    fn dialect(&self) -> Dialect {
+        Dialect::Sqlite
+    }
+
+    fn execute_batch(&mut self, sql: &str) -> anyhow::Result<()> {
+        let transaction = self.connection.transaction()?;
+        transaction.execute_batch(sql)?;
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn query(&mut self, sql: &str, params: Vec<SqlValue>) -> anyhow::Result<Vec<Vec<SqlValue>>> {
+        run(&self.connection, sql, params)
+    }
+
+    fn transaction(&mut self, statements: Vec<Statement>) -> anyhow::Result<()> {
+        let transaction = self.connection.transaction()?;
+        for (sql, params) in statements {
+            run(&transaction, sql, params)?;
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+}
+
+fn run(
+    connection: &Connection,
+    sql: &str,
+    params: Vec<SqlValue>,
+) -> anyhow::Result<Vec<Vec<SqlValue>>> {
+    let mut statement = connection.prepare_cached(sql)?;
+    // `$n` placeholders are named parameters in SQLite, bind them by name so a parameter can
+    // be used more than once.
+    for (i, param) in params.iter().enumerate() {
+        if let Some(index) = statement.parameter_index(&format!("${}", i + 1))? {
+            statement.raw_bind_parameter(index, param)?;
+        }
+    }
+    let columns = statement.column_count();
+    let mut rows = statement.raw_query();
+    let mut result = Vec::new();
+    while let Some(row) = rows.next()? {
+        let values = (0..columns)
+            .map(|i| Ok(row.get::<_, Value>(i)?.into()))
+            .collect::<anyhow::Result<Vec<SqlValue>>>()?;
+        result.push(values);
+    }
+    Ok(result)
+}
+
+impl ToSql for SqlValue {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::Borrowed(match self {
+            SqlValue::Null => ValueRef::Null,
+            SqlValue::Integer(i) => ValueRef::Integer(*i),
+            SqlValue::Real(r) => ValueRef::Real(*r),
+            SqlValue::Text(t) => ValueRef::Text(t.as_bytes()),
+            SqlValue::Blob(b) => ValueRef::Blob(b),
+        }))
+    }
+}
+
+impl From<Value> for SqlValue {
+    fn from(value: Value) -> Self {
+        match value {
+            Value::Null => SqlValue::Null,
+            Value::Integer(i) => SqlValue::Integer(i),
+            Value::Real(r) => SqlValue::Real(r),
+            Value::Text(t) => SqlValue::Text(t),
+            Value::Blob(b) => SqlValue::Blob(b),
+        }
+    }
+}
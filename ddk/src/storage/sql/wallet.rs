@@ -0,0 +1,95 @@
+use super::{sql_to_wallet_error, SqlStorage, SqlValue};
+use crate::error::WalletError;
+use crate::KeyStorage;
+use bitcoin::secp256k1::SecretKey;
+use std::collections::HashMap;
+
+impl KeyStorage for SqlStorage {
+    fn get_secret_key(&self, key_id: [u8; 32]) -> Result<SecretKey, WalletError> {
+        let key = hex::encode(key_id);
+        let secret_key = self
+            .query_column(
+                "SELECT secret_key FROM secret_keys WHERE key_id = $1",
+                vec![key.as_str().into()],
+            )
+            .map_err(sql_to_wallet_error)?
+            .into_iter()
+            .next()
+            .ok_or_else(|| WalletError::StorageError(format!("No secret key for {}", key)))?
+            .into_blob()
+            .map_err(sql_to_wallet_error)?;
+        Ok(serde_json::from_slice::<SecretKey>(&secret_key)?)
+    }
+
+    fn store_secret_key(&self, key_id: [u8; 32], secret_key: SecretKey) -> Result<(), WalletError> {
+        self.execute(
+            "INSERT INTO secret_keys (key_id, secret_key) VALUES ($1, $2) \
+             ON CONFLICT (key_id) DO UPDATE SET secret_key = excluded.secret_key",
+            vec![
+                hex::encode(key_id).into(),
+                serde_json::to_vec(&secret_key)?.into(),
+            ],
+        )
+        .map_err(sql_to_wallet_error)
+    }
+
+    fn next_key_index(&self, account: u32) -> anyhow::Result<u32> {
+        let next = self
+            .query_column(
+                "INSERT INTO key_indexes (account, next_index) VALUES ($1, 1) \
+                 ON CONFLICT (account) DO UPDATE SET next_index = key_indexes.next_index + 1 \
+                 RETURNING next_index",
+                vec![i64::from(account).into()],
+            )?
+            .into_iter()
+            .next()
+            .map(SqlValue::into_integer)
+            .transpose()?
+            .ok_or_else(|| anyhow::anyhow!("No key index returned."))?;
+        match u32::try_from(next - 1) {
+            Ok(index) if index < u32::MAX => Ok(index),
+            _ => Err(anyhow::anyhow!("Key indexes exhausted.")),
+        }
+    }
+
+    fn list_secret_keys(&self) -> Result<Vec<([u8; 32], SecretKey)>, WalletError> {
+        self.query("SELECT key_id, secret_key FROM secret_keys", vec![])
+            .map_err(sql_to_wallet_error)?
+            .into_iter()
+            .map(|row| {
+                let [key_id, secret_key] = <[SqlValue; 2]>::try_from(row)
+                    .map_err(|_| WalletError::StorageError("Malformed secret key row.".into()))?;
+                let key_id = hex::decode(key_id.into_text().map_err(sql_to_wallet_error)?)
+                    .ok()
+                    .and_then(|key_id| <[u8; 32]>::try_from(key_id).ok())
+                    .ok_or_else(|| WalletError::StorageError("Malformed key id.".into()))?;
+                let secret_key = secret_key.into_blob().map_err(sql_to_wallet_error)?;
+                Ok((key_id, serde_json::from_slice(&secret_key)?))
+            })
+            .collect()
+    }
+
+    fn key_indexes(&self) -> anyhow::Result<HashMap<u32, u32>> {
+        self.query("SELECT account, next_index FROM key_indexes", vec![])?
+            .into_iter()
+            .map(|row| {
+                let [account, next] = <[SqlValue; 2]>::try_from(row)
+                    .map_err(|_| anyhow::anyhow!("Malformed key index row."))?;
+                Ok((
+                    u32::try_from(account.into_integer()?)?,
+                    u32::try_from(next.into_integer()?)?,
+                ))
+            })
+            .collect()
+    }
+
+    fn advance_key_index(&self, account: u32, next: u32) -> anyhow::Result<()> {
+        self.execute(
+            "INSERT INTO key_indexes (account, next_index) VALUES ($1, $2) \
+             ON CONFLICT (account) DO UPDATE SET next_index = CASE \
+             WHEN excluded.next_index > key_indexes.next_index THEN excluded.next_index \
+             ELSE key_indexes.next_index END",
+            vec![i64::from(account).into(), i64::from(next).into()],
+        )
+    }
+}
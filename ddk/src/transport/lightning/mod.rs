@@ -1,3 +1,4 @@
+use crate::transport::PeerInformation;
 use crate::{DlcDevKitDlcManager, Oracle, Storage, Transport};
 use async_trait::async_trait;
 use bitcoin::secp256k1::PublicKey;
@@ -51,7 +52,31 @@ impl Transport for LightningTransport {
         }
     }
 
-    async fn connect_outbound(&self, pubkey: PublicKey, host: &str) {
-        connect_outbound(self.peer_manager.clone(), pubkey, host.parse().unwrap()).await;
+    async fn connect_outbound(&self, pubkey: PublicKey, host: &str) -> anyhow::Result<()> {
+        let addr = host.parse()?;
+        match connect_outbound(self.peer_manager.clone(), pubkey, addr).await {
+            Some(_) => Ok(()),
+            None => Err(anyhow::anyhow!("Could not connect to {}@{}", pubkey, host)),
+        }
+    }
+
+    async fn is_connected(&self, pubkey: &PublicKey) -> bool {
+        self.peer_manager.peer_by_node_id(pubkey).is_some()
+    }
+
+    async fn connected_peers(&self) -> Vec<PeerInformation> {
+        self.peer_manager
+            .list_peers()
+            .into_iter()
+            .map(|peer| PeerInformation {
+                pubkey: peer.counterparty_node_id.to_string(),
+                host: peer
+                    .socket_address
+                    .map(|address| address.to_string())
+                    .unwrap_or_default(),
+                transport: self.name(),
+                last_seen: None,
+            })
+            .collect()
     }
 }
@@ -203,12 +203,13 @@ impl LightningTransport {
 #[cfg(test)]
 mod tests {
     use bitcoin::Network;
+    use ddk_manager::contract::contract_input::ContractInput;
     use ddk_manager::Storage;
     use dlc_messages::{Message, OfferDlc};
 
     use crate::{
-        builder::Builder, oracle::memory::MemoryOracle, storage::memory::MemoryStorage, DlcDevKit,
-        Transport,
+        builder::Builder, chain::ChainSource, oracle::memory::MemoryOracle,
+        storage::memory::MemoryStorage, DlcDevKit, Transport,
     };
 
     use super::*;
@@ -242,7 +243,7 @@ mod tests {
 
         let mut builder = Builder::new();
         builder.set_network(Network::Regtest);
-        builder.set_esplora_host("http://127.0.0.1:30000".to_string());
+        builder.set_chain_source(ChainSource::esplora("http://127.0.0.1:30000"));
         builder.set_seed_bytes(seed_bytes);
         builder.set_transport(transport.clone());
         builder.set_storage(storage.clone());
@@ -250,6 +251,57 @@ mod tests {
         builder.finish().await.unwrap()
     }
 
+    #[test_log::test(tokio::test)]
+    async fn stop_waits_for_tasks_and_allows_restart() {
+        let node = manager(1778).await;
+
+        assert!(node.stop().await.is_err());
+        assert!(!node.manager_health().running);
+        node.start().unwrap();
+        assert!(node.start().is_err());
+
+        node.stop().await.unwrap();
+        assert!(node.tasks.lock().unwrap().is_empty());
+        let err = node.reject_dlc_offer([0u8; 32]).await.unwrap_err();
+        assert_eq!(err.to_string(), "DDK is not running.");
+
+        node.start().unwrap();
+        let err = node.reject_dlc_offer([0u8; 32]).await.unwrap_err();
+        assert_eq!(err.to_string(), "Could not reject dlc offer.");
+
+        // Messages are refused as soon as the node is stopping.
+        node.stop_signal_sender.send_replace(true);
+        let err = node.reject_dlc_offer([0u8; 32]).await.unwrap_err();
+        assert_eq!(err.to_string(), "DDK is not running.");
+        node.stop().await.unwrap();
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn failed_offer_keeps_manager_running() {
+        let node = manager(1779).await;
+        node.start().unwrap();
+
+        let contract_input = ContractInput {
+            offer_collateral: 50_000,
+            accept_collateral: 50_000,
+            fee_rate: 1,
+            contract_infos: Vec::new(),
+        };
+        let counter_party = node.transport.public_key();
+        let err = node
+            .send_dlc_offer(&contract_input, counter_party, Vec::new())
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), "Could not create dlc offer.");
+
+        let err = node.reject_dlc_offer([0u8; 32]).await.unwrap_err();
+        assert_eq!(err.to_string(), "Could not reject dlc offer.");
+        let health = node.manager_health();
+        assert!(health.running);
+        assert_eq!(health.restarts, 0);
+        node.stop().await.unwrap();
+    }
+
     #[test_log::test(tokio::test)]
     async fn send_offer() {
         let alice = manager(1776).await;
@@ -262,15 +314,16 @@ mod tests {
 
         bob.transport
             .connect_outbound(alice_pk, "127.0.0.1:1776")
-            .await;
+            .await
+            .unwrap();
 
         let mut connected = false;
         let mut retries = 0;
 
         while !connected {
             if retries > 10 {
-                bob.stop().unwrap();
-                alice.stop().unwrap();
+                bob.stop().await.unwrap();
+                alice.stop().await.unwrap();
                 panic!("Bob could not connect to alice.")
             }
             if bob
@@ -295,8 +348,8 @@ mod tests {
 
         while !offer_received {
             if retries > 15 {
-                bob.stop().unwrap();
-                alice.stop().unwrap();
+                bob.stop().await.unwrap();
+                alice.stop().await.unwrap();
                 panic!("Contract was not offered to alice")
             }
             if alice
@@ -313,8 +366,8 @@ mod tests {
             tokio::time::sleep(Duration::from_secs(1)).await
         }
 
-        bob.stop().unwrap();
-        alice.stop().unwrap();
+        bob.stop().await.unwrap();
+        alice.stop().await.unwrap();
         assert!(true)
     }
 }
@@ -4,7 +4,7 @@ use std::{
     time::Duration,
 };
 
-use crate::{ddk::DlcDevKitDlcManager, Oracle, Storage, Transport};
+use crate::{ddk::DlcDevKitDlcManager, transport::PeerInformation, Oracle, Storage, Transport};
 use bitcoin::{
     key::{self, Keypair},
     secp256k1::{All, PublicKey, Secp256k1},
@@ -76,7 +76,7 @@ impl Transport for MemoryTransport {
                     }
                 },
                 _ = timer.tick() => {
-                    if let Ok(msg) = self.receiver.recv() {
+                    if let Ok(msg) = self.receiver.try_recv() {
                         match manager.on_dlc_message(&msg.0, msg.1).await {
                             Ok(s) => {
                                 if let Some(reply) = s {
@@ -97,7 +97,35 @@ impl Transport for MemoryTransport {
         Ok(())
     }
 
-    async fn connect_outbound(&self, _pubkey: PublicKey, _host: &str) {
-        unreachable!("no need to connect to counterparty")
+    /// Counterparties are added with [`MemoryTransport::add_counterparty`].
+    async fn connect_outbound(&self, pubkey: PublicKey, _host: &str) -> anyhow::Result<()> {
+        if self.is_connected(&pubkey).await {
+            Ok(())
+        } else {
+            Err(anyhow::anyhow!(
+                "Counterparty was not added to the transport."
+            ))
+        }
+    }
+
+    async fn is_connected(&self, pubkey: &PublicKey) -> bool {
+        self.counterparty_transport
+            .lock()
+            .unwrap()
+            .contains_key(pubkey)
+    }
+
+    async fn connected_peers(&self) -> Vec<PeerInformation> {
+        self.counterparty_transport
+            .lock()
+            .unwrap()
+            .keys()
+            .map(|pubkey| PeerInformation {
+                pubkey: pubkey.to_string(),
+                host: "memory".to_string(),
+                transport: self.name(),
+                last_seen: None,
+            })
+            .collect()
     }
 }
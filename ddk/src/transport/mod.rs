@@ -3,9 +3,18 @@ pub mod lightning;
 pub mod memory;
 #[cfg(feature = "nostr")]
 pub mod nostr;
+mod peers;
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, serde::Serialize, serde::Deserialize)]
+pub use peers::{PeerConnections, PeerStatus};
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, serde::Serialize, serde::Deserialize)]
 pub struct PeerInformation {
     pub pubkey: String,
     pub host: String,
+    /// Name of the transport used to connect to the peer.
+    #[serde(default)]
+    pub transport: String,
+    /// Unix time of the last successful connection.
+    #[serde(default)]
+    pub last_seen: Option<u64>,
 }
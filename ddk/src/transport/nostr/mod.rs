@@ -51,12 +51,27 @@ impl Transport for NostrDlc {
         }
     }
     /// Connect to a relay.
-    async fn connect_outbound(&self, _pubkey: BitcoinPublicKey, host: &str) {
+    async fn connect_outbound(&self, _pubkey: BitcoinPublicKey, host: &str) -> anyhow::Result<()> {
         match self.client.add_relay(host).await {
-            Ok(_) => tracing::info!(host, "Added relay."),
-            Err(e) => tracing::error!(host, error = e.to_string(), "Could not add relay."),
+            Ok(_) => {
+                tracing::info!(host, "Added relay.");
+                Ok(())
+            }
+            Err(e) => {
+                tracing::error!(host, error = e.to_string(), "Could not add relay.");
+                Err(e.into())
+            }
         }
     }
+
+    /// Messages reach any peer as long as a relay is connected.
+    async fn is_connected(&self, _pubkey: &BitcoinPublicKey) -> bool {
+        self.client
+            .relays()
+            .await
+            .values()
+            .any(|relay| relay.is_connected())
+    }
 }
 
 fn bitcoin_to_nostr_pubkey(bitcoin_pk: &BitcoinPublicKey) -> PublicKey {
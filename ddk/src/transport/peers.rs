@@ -0,0 +1,306 @@
+use super::PeerInformation;
+use crate::event::DdkEvent;
+use crate::util::unix_time_now;
+use crate::{Storage, Transport};
+use bitcoin::secp256k1::PublicKey;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+
+/// Delay before the first reconnection attempt to a peer. Doubles with every failure.
+const RECONNECT_BASE_DELAY: Duration = Duration::from_secs(5);
+/// Longest delay between reconnection attempts to a peer.
+const RECONNECT_MAX_DELAY: Duration = Duration::from_secs(600);
+/// How often the stored `last_seen` of a peer that stays connected is refreshed.
+const LAST_SEEN_REFRESH: Duration = Duration::from_secs(600);
+
+#[derive(Debug, Clone, Default)]
+struct PeerHealth {
+    failed_attempts: u32,
+    next_attempt: Option<Instant>,
+}
+
+/// A stored or connected peer with its live connection status.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct PeerStatus {
+    #[serde(flatten)]
+    pub peer: PeerInformation,
+    /// Whether the transport is connected to the peer right now.
+    pub connected: bool,
+    /// Reconnection attempts that failed since the last successful connection.
+    pub failed_attempts: u32,
+}
+
+/// Keeps track of the peers DDK connected to. Peers are stored on every outbound
+/// connection and reconnected with exponential backoff when the connection drops.
+pub struct PeerConnections<T: Transport, S: Storage> {
+    transport: Arc<T>,
+    storage: Arc<S>,
+    events: broadcast::Sender<DdkEvent>,
+    health: Mutex<HashMap<PublicKey, PeerHealth>>,
+}
+
+impl<T: Transport, S: Storage> PeerConnections<T, S> {
+    pub fn new(transport: Arc<T>, storage: Arc<S>, events: broadcast::Sender<DdkEvent>) -> Self {
+        Self {
+            transport,
+            storage,
+            events,
+            health: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Connect to a peer and store it so it is reconnected on restart.
+    pub async fn connect(&self, pubkey: PublicKey, host: &str) -> anyhow::Result<()> {
+        let result = self.transport.connect_outbound(pubkey, host).await;
+        self.record_attempt(pubkey, result.is_ok());
+        result?;
+
+        self.save_peer(pubkey, host)?;
+        tracing::info!(pubkey = pubkey.to_string(), host, "Connected to peer.");
+        let _ = self.events.send(DdkEvent::PeerConnected {
+            pubkey,
+            host: host.to_string(),
+        });
+        Ok(())
+    }
+
+    /// Try to connect to the stored peers that are not connected and whose backoff
+    /// delay has elapsed.
+    pub async fn reconnect(&self) -> anyhow::Result<()> {
+        for peer in self.storage.list_peers()? {
+            if !peer.transport.is_empty() && peer.transport != self.transport.name() {
+                continue;
+            }
+
+            let Ok(pubkey) = PublicKey::from_str(&peer.pubkey) else {
+                tracing::warn!(pubkey = peer.pubkey, "Stored peer has an invalid pubkey.");
+                continue;
+            };
+
+            if self.transport.is_connected(&pubkey).await {
+                self.record_attempt(pubkey, true);
+                if needs_refresh(peer.last_seen, unix_time_now()) {
+                    self.save_peer(pubkey, &peer.host)?;
+                }
+                continue;
+            }
+
+            if !self.should_attempt(&pubkey) {
+                continue;
+            }
+
+            if let Err(e) = self.connect(pubkey, &peer.host).await {
+                tracing::warn!(
+                    pubkey = peer.pubkey,
+                    host = peer.host,
+                    error = e.to_string(),
+                    "Could not reconnect to peer."
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// All stored peers and the peers connected to the transport, e.g. inbound
+    /// connections, with their live connection status.
+    pub async fn list_peers(&self) -> anyhow::Result<Vec<PeerStatus>> {
+        let mut peers = Vec::new();
+        for peer in self.storage.list_peers()? {
+            let (connected, failed_attempts) = match PublicKey::from_str(&peer.pubkey) {
+                Ok(pubkey) => {
+                    let failed_attempts = self
+                        .health
+                        .lock()
+                        .unwrap()
+                        .get(&pubkey)
+                        .map_or(0, |h| h.failed_attempts);
+                    (self.transport.is_connected(&pubkey).await, failed_attempts)
+                }
+                Err(_) => (false, 0),
+            };
+            peers.push(PeerStatus {
+                peer,
+                connected,
+                failed_attempts,
+            });
+        }
+
+        for peer in self.transport.connected_peers().await {
+            match peers.iter_mut().find(|p| p.peer.pubkey == peer.pubkey) {
+                Some(stored) => stored.connected = true,
+                None => peers.push(PeerStatus {
+                    peer,
+                    connected: true,
+                    failed_attempts: 0,
+                }),
+            }
+        }
+
+        Ok(peers)
+    }
+
+    fn save_peer(&self, pubkey: PublicKey, host: &str) -> anyhow::Result<()> {
+        self.storage.save_peer(PeerInformation {
+            pubkey: pubkey.to_string(),
+            host: host.to_string(),
+            transport: self.transport.name(),
+            last_seen: Some(unix_time_now()),
+        })
+    }
+
+    fn should_attempt(&self, pubkey: &PublicKey) -> bool {
+        let next_attempt = self
+            .health
+            .lock()
+            .unwrap()
+            .get(pubkey)
+            .and_then(|h| h.next_attempt);
+        match next_attempt {
+            Some(next) => Instant::now() >= next,
+            None => true,
+        }
+    }
+
+    fn record_attempt(&self, pubkey: PublicKey, success: bool) {
+        let mut health = self.health.lock().unwrap();
+        let peer = health.entry(pubkey).or_default();
+        if success {
+            peer.failed_attempts = 0;
+            peer.next_attempt = None;
+        } else {
+            peer.failed_attempts += 1;
+            peer.next_attempt = Some(Instant::now() + backoff_delay(peer.failed_attempts));
+        }
+    }
+}
+
+fn needs_refresh(last_seen: Option<u64>, now: u64) -> bool {
+    match last_seen {
+        Some(seen) => now.saturating_sub(seen) >= LAST_SEEN_REFRESH.as_secs(),
+        None => true,
+    }
+}
+
+fn backoff_delay(failed_attempts: u32) -> Duration {
+    let exponent = failed_attempts.saturating_sub(1).min(16);
+    (RECONNECT_BASE_DELAY * 2u32.pow(exponent)).min(RECONNECT_MAX_DELAY)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::memory::MemoryStorage;
+    use crate::transport::memory::MemoryTransport;
+    use bitcoin::secp256k1::Secp256k1;
+
+    #[test]
+    fn backoff_doubles_up_to_the_max_delay() {
+        assert_eq!(backoff_delay(1), RECONNECT_BASE_DELAY);
+        assert_eq!(backoff_delay(2), RECONNECT_BASE_DELAY * 2);
+        assert_eq!(backoff_delay(4), RECONNECT_BASE_DELAY * 8);
+        assert_eq!(backoff_delay(50), RECONNECT_MAX_DELAY);
+    }
+
+    #[test]
+    fn last_seen_is_refreshed_after_the_refresh_interval() {
+        let now = 1_700_000_000;
+        assert!(needs_refresh(None, now));
+        assert!(!needs_refresh(Some(now), now));
+        assert!(!needs_refresh(
+            Some(now - LAST_SEEN_REFRESH.as_secs() + 1),
+            now
+        ));
+        assert!(needs_refresh(Some(now - LAST_SEEN_REFRESH.as_secs()), now));
+    }
+
+    #[tokio::test]
+    async fn reconnect_does_not_rewrite_connected_peers() {
+        let secp = Secp256k1::new();
+        let transport = Arc::new(MemoryTransport::new(&secp));
+        let counterparty = MemoryTransport::new(&secp);
+        let pubkey = counterparty.keypair.public_key();
+        let (events, _) = broadcast::channel(8);
+        let storage = Arc::new(MemoryStorage::new());
+        let peers = PeerConnections::new(transport.clone(), storage.clone(), events);
+
+        transport.add_counterparty(pubkey, counterparty.sender.clone());
+        storage
+            .save_peer(PeerInformation {
+                pubkey: pubkey.to_string(),
+                host: "memory".to_string(),
+                transport: transport.name(),
+                last_seen: Some(unix_time_now()),
+            })
+            .unwrap();
+        let stored = storage.list_peers().unwrap();
+
+        peers.reconnect().await.unwrap();
+        assert_eq!(
+            storage.list_peers().unwrap()[0].last_seen,
+            stored[0].last_seen
+        );
+
+        storage
+            .save_peer(PeerInformation {
+                last_seen: Some(1),
+                ..stored[0].clone()
+            })
+            .unwrap();
+        peers.reconnect().await.unwrap();
+        assert!(storage.list_peers().unwrap()[0].last_seen > Some(1));
+    }
+
+    #[tokio::test]
+    async fn connected_peers_are_stored_with_status() {
+        let secp = Secp256k1::new();
+        let transport = Arc::new(MemoryTransport::new(&secp));
+        let counterparty = MemoryTransport::new(&secp);
+        let pubkey = counterparty.keypair.public_key();
+        let (events, mut receiver) = broadcast::channel(8);
+        let storage = Arc::new(MemoryStorage::new());
+        let peers = PeerConnections::new(transport.clone(), storage.clone(), events);
+
+        assert!(peers.connect(pubkey, "memory").await.is_err());
+        assert!(peers.list_peers().await.unwrap().is_empty());
+        assert!(!peers.should_attempt(&pubkey));
+
+        transport.add_counterparty(pubkey, counterparty.sender.clone());
+        peers.connect(pubkey, "memory").await.unwrap();
+        assert_eq!(
+            receiver.try_recv().unwrap(),
+            DdkEvent::PeerConnected {
+                pubkey,
+                host: "memory".to_string()
+            }
+        );
+
+        let statuses = peers.list_peers().await.unwrap();
+        assert_eq!(statuses.len(), 1);
+        assert_eq!(statuses[0].peer.pubkey, pubkey.to_string());
+        assert_eq!(statuses[0].peer.transport, transport.name());
+        assert!(statuses[0].peer.last_seen.is_some());
+        assert!(statuses[0].connected);
+        assert_eq!(statuses[0].failed_attempts, 0);
+    }
+
+    #[tokio::test]
+    async fn inbound_peers_are_listed() {
+        let secp = Secp256k1::new();
+        let transport = Arc::new(MemoryTransport::new(&secp));
+        let counterparty = MemoryTransport::new(&secp);
+        let pubkey = counterparty.keypair.public_key();
+        let (events, _) = broadcast::channel(8);
+        let peers = PeerConnections::new(transport.clone(), Arc::new(MemoryStorage::new()), events);
+
+        transport.add_counterparty(pubkey, counterparty.sender.clone());
+
+        let statuses = peers.list_peers().await.unwrap();
+        assert_eq!(statuses.len(), 1);
+        assert_eq!(statuses[0].peer.pubkey, pubkey.to_string());
+        assert!(statuses[0].connected);
+    }
+}
@@ -1,4 +1,10 @@
 use chrono::Utc;
+use ddk_manager::channel::accepted_channel::AcceptedChannel;
+use ddk_manager::channel::offered_channel::OfferedChannel;
+use ddk_manager::channel::signed_channel::{SignedChannel, SignedChannelStateType};
+use ddk_manager::channel::{
+    Channel, ClosedChannel, ClosedPunishedChannel, ClosingChannel, FailedAccept, FailedSign,
+};
 use ddk_manager::contract::accepted_contract::AcceptedContract;
 use ddk_manager::contract::offered_contract::OfferedContract;
 use ddk_manager::contract::ser::Serializable;
@@ -18,7 +24,7 @@ macro_rules! convertible_enum {
         $($tname:ident $(= $tval:expr)?,)*
     }, $input:ident) => {
         #[derive(Debug)]
-        enum $name {
+        pub(crate) enum $name {
             $($vname $(= $val)?,)*
             $($tname $(= $tval)?,)*
         }
@@ -42,7 +48,7 @@ macro_rules! convertible_enum {
         }
 
         impl $name {
-            fn get_prefix(input: &$input) -> u8 {
+            pub(crate) fn get_prefix(input: &$input) -> u8 {
                 let prefix = match input {
                     $($input::$vname(_) => $name::$vname,)*
                     $($input::$tname{..} => $name::$tname,)*
@@ -69,6 +75,41 @@ convertible_enum!(
     Contract
 );
 
+convertible_enum!(
+    enum ChannelPrefix {
+        Offered = 100,
+        Accepted,
+        Signed,
+        FailedAccept,
+        FailedSign,
+        Closing,
+        Closed,
+        CounterClosed,
+        ClosedPunished,
+        CollaborativelyClosed,
+        Cancelled,;
+    },
+    Channel
+);
+
+convertible_enum!(
+    enum SignedChannelPrefix {;
+        Established = 1,
+        SettledOffered,
+        SettledReceived,
+        SettledAccepted,
+        SettledConfirmed,
+        Settled,
+        Closing,
+        CollaborativeCloseOffered,
+        RenewAccepted,
+        RenewOffered,
+        RenewFinalized,
+        RenewConfirmed,
+    },
+    SignedChannelStateType
+);
+
 fn to_storage_error<T>(e: T) -> Error
 where
     T: std::fmt::Display,
@@ -133,6 +174,75 @@ pub fn deserialize_contract(buff: &Vec<u8>) -> Result<Contract, Error> {
     Ok(contract)
 }
 
+pub fn serialize_channel(channel: &Channel) -> Result<Vec<u8>, ::lightning::io::Error> {
+    let serialized = match channel {
+        Channel::Offered(o) => o.serialize(),
+        Channel::Accepted(a) => a.serialize(),
+        Channel::Signed(s) => s.serialize(),
+        Channel::FailedAccept(f) => f.serialize(),
+        Channel::FailedSign(f) => f.serialize(),
+        Channel::Cancelled(o) => o.serialize(),
+        Channel::Closing(c) => c.serialize(),
+        Channel::Closed(c) => c.serialize(),
+        Channel::CollaborativelyClosed(c) => c.serialize(),
+        Channel::CounterClosed(c) => c.serialize(),
+        Channel::ClosedPunished(c) => c.serialize(),
+    };
+    let mut serialized = serialized?;
+    let mut res = Vec::with_capacity(serialized.len() + 1);
+    res.push(ChannelPrefix::get_prefix(channel));
+    if let Channel::Signed(s) = channel {
+        res.push(SignedChannelPrefix::get_prefix(&s.state.get_type()))
+    }
+    res.append(&mut serialized);
+    Ok(res)
+}
+
+pub fn deserialize_channel(buff: &[u8]) -> Result<Channel, Error> {
+    let mut cursor = ::lightning::io::Cursor::new(buff);
+    let mut prefix = [0u8; 1];
+    cursor.read_exact(&mut prefix)?;
+    let channel_prefix: ChannelPrefix = prefix[0].try_into()?;
+    let channel = match channel_prefix {
+        ChannelPrefix::Offered => {
+            Channel::Offered(OfferedChannel::deserialize(&mut cursor).map_err(to_storage_error)?)
+        }
+        ChannelPrefix::Accepted => {
+            Channel::Accepted(AcceptedChannel::deserialize(&mut cursor).map_err(to_storage_error)?)
+        }
+        ChannelPrefix::Signed => {
+            // Skip the channel state prefix.
+            cursor.set_position(cursor.position() + 1);
+            Channel::Signed(SignedChannel::deserialize(&mut cursor).map_err(to_storage_error)?)
+        }
+        ChannelPrefix::FailedAccept => {
+            Channel::FailedAccept(FailedAccept::deserialize(&mut cursor).map_err(to_storage_error)?)
+        }
+        ChannelPrefix::FailedSign => {
+            Channel::FailedSign(FailedSign::deserialize(&mut cursor).map_err(to_storage_error)?)
+        }
+        ChannelPrefix::Cancelled => {
+            Channel::Cancelled(OfferedChannel::deserialize(&mut cursor).map_err(to_storage_error)?)
+        }
+        ChannelPrefix::Closed => {
+            Channel::Closed(ClosedChannel::deserialize(&mut cursor).map_err(to_storage_error)?)
+        }
+        ChannelPrefix::Closing => {
+            Channel::Closing(ClosingChannel::deserialize(&mut cursor).map_err(to_storage_error)?)
+        }
+        ChannelPrefix::CounterClosed => Channel::CounterClosed(
+            ClosedChannel::deserialize(&mut cursor).map_err(to_storage_error)?,
+        ),
+        ChannelPrefix::ClosedPunished => Channel::ClosedPunished(
+            ClosedPunishedChannel::deserialize(&mut cursor).map_err(to_storage_error)?,
+        ),
+        ChannelPrefix::CollaborativelyClosed => Channel::CollaborativelyClosed(
+            ClosedChannel::deserialize(&mut cursor).map_err(to_storage_error)?,
+        ),
+    };
+    Ok(channel)
+}
+
 /// Filter stored oracle announcements if the event maturity is expired.
 pub(crate) fn filter_expired_oracle_announcements(
     announcements: Vec<OracleAnnouncement>,
@@ -157,6 +267,11 @@ pub fn message_variant_name(message: &Message) -> String {
     str.to_string()
 }
 
+/// Seconds since the unix epoch.
+pub(crate) fn unix_time_now() -> u64 {
+    Utc::now().timestamp().try_into().unwrap_or_default()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
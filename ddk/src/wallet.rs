@@ -1,16 +1,26 @@
+#[cfg(feature = "bitcoind")]
+use crate::chain::BitcoindClient;
+#[cfg(feature = "cbf")]
+use crate::chain::{CbfClient, CbfUpdate};
+use crate::chain::{ChainClient, ChainSource, EsploraClient, FeeEstimates, FeeSource};
 use crate::error::{wallet_err_to_manager_err, WalletError};
-use crate::{chain::EsploraClient, Storage};
-use bdk_chain::{spk_client::FullScanRequest, Balance};
+use crate::signer::{DdkContractSigner, ExternalPsbtSigner, ExternalSigner};
+use crate::storage::query::{ContractQuery, ContractState};
+use crate::Storage;
+#[cfg(feature = "bitcoind")]
+use bdk_chain::local_chain::CheckPoint;
+#[cfg(any(feature = "bitcoind", feature = "cbf"))]
+use bdk_chain::BlockId;
+use bdk_chain::{Balance, ChainPosition};
 use bdk_esplora::EsploraAsyncExt;
-use bdk_wallet::coin_selection::{
-    BranchAndBoundCoinSelection, CoinSelectionAlgorithm, SingleRandomDraw,
-};
-use bdk_wallet::descriptor::IntoWalletDescriptor;
+use bdk_wallet::descriptor::{ExtendedDescriptor, IntoWalletDescriptor};
+use bdk_wallet::keys::bip39::Mnemonic;
+use bdk_wallet::miniscript::descriptor::{Descriptor, ShInner};
 pub use bdk_wallet::LocalOutput;
 use bdk_wallet::WalletPersister;
 use bdk_wallet::{
     bitcoin::{
-        bip32::Xpriv,
+        bip32::{ChildNumber, DerivationPath, Xpriv},
         secp256k1::{All, PublicKey, Secp256k1},
         Address, Network, Txid,
     },
@@ -19,21 +29,33 @@ use bdk_wallet::{
 };
 use bdk_wallet::{Utxo, WeightedUtxo};
 use bitcoin::hashes::sha256::Hash as Sha256Hash;
-use bitcoin::hashes::sha256::HashEngine;
 use bitcoin::hashes::Hash;
-use bitcoin::key::rand::{thread_rng, Fill};
-use bitcoin::{secp256k1::SecretKey, Amount, FeeRate, ScriptBuf, Transaction};
-use ddk_manager::{error::Error as ManagerError, SimpleSigner};
+use bitcoin::script::PushBytesBuf;
+use bitcoin::{
+    psbt::Psbt, secp256k1::SecretKey, Amount, FeeRate, OutPoint, ScriptBuf, SignedAmount,
+    Transaction,
+};
+pub use coin_selection::CoinSelectionStrategy;
+use ddk_manager::contract::Contract;
+use ddk_manager::{error::Error as ManagerError, ContractId};
+use history::{ConfirmationTime, DlcLink, DlcTransactionIndex};
+pub use history::{TransactionKind, WalletTransaction};
+use labels::{Label, LabelType};
 use lightning::chain::chaininterface::{ConfirmationTarget, FeeEstimator};
-use std::collections::HashMap;
-use std::io::Write;
-use std::sync::atomic::AtomicU32;
+use send::MAX_OP_RETURN_SIZE;
+pub use send::{SendBuilder, SendResult};
 // use std::sync::RwLock;
-use std::{
-    collections::BTreeMap,
-    sync::{atomic::Ordering, Arc},
-};
-use tokio::sync::Mutex;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+use tokio::sync::watch;
+
+pub mod coin_selection;
+pub(crate) mod fee_bump;
+pub mod history;
+pub mod labels;
+pub mod send;
 
 /// Wrapper type to pass `crate::Storage` to a BDK wallet.
 #[derive(Clone)]
@@ -56,15 +78,114 @@ pub struct DlcDevKitWallet {
     /// BDK persisted wallet.
     pub wallet: Arc<Mutex<PersistedWallet<WalletStorage>>>,
     storage: WalletStorage,
-    blockchain: Arc<EsploraClient>,
+    blockchain: Arc<ChainClient>,
     network: Network,
     xprv: Xpriv,
     name: String,
-    fees: Arc<HashMap<ConfirmationTarget, AtomicU32>>,
+    fee_estimates: Arc<FeeEstimates>,
     secp: Secp256k1<All>,
+    reservation_timeout: Duration,
+    /// Held while coins are selected and reserved so two selections can't pick the
+    /// same coins.
+    reservation_lock: std::sync::Mutex<()>,
+    coin_selection: CoinSelectionStrategy,
+    psbt_signer: Option<Arc<dyn PsbtSigner>>,
+    external_signer: Option<Arc<dyn ExternalSigner>>,
+    watch_only: bool,
+    /// Held for a whole sync so syncs and full scans run one at a time.
+    sync_lock: tokio::sync::Mutex<()>,
+    sync_progress: Arc<watch::Sender<SyncProgress>>,
+}
+
+/// Source of the output descriptors of a [`DlcDevKitWallet`].
+#[derive(Clone, Default, PartialEq, Eq)]
+pub enum WalletConfig {
+    /// BIP84 descriptors derived from the seed bytes.
+    #[default]
+    Seed,
+    /// BIP84 descriptors derived from a BIP39 mnemonic and passphrase. Contract and
+    /// channel keys are derived from the mnemonic as well.
+    Mnemonic {
+        mnemonic: String,
+        passphrase: String,
+    },
+    /// Any descriptors BDK can spend from, e.g. BIP86 or BIP49 templates or miniscript.
+    /// Descriptors with public keys only make a watch-only wallet that needs a
+    /// [`PsbtSigner`]. Contract and channel keys are derived from the seed bytes, which
+    /// must be set.
+    Descriptors { external: String, internal: String },
+}
+
+// The mnemonic, the passphrase and descriptors, which may hold private keys, are never
+// printed.
+impl Debug for WalletConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WalletConfig::Seed => f.write_str("Seed"),
+            WalletConfig::Mnemonic { .. } => f
+                .debug_struct("Mnemonic")
+                .field("mnemonic", &"<redacted>")
+                .field("passphrase", &"<redacted>")
+                .finish(),
+            WalletConfig::Descriptors { .. } => f
+                .debug_struct("Descriptors")
+                .field("external", &"<redacted>")
+                .field("internal", &"<redacted>")
+                .finish(),
+        }
+    }
+}
+
+/// Signs the inputs of a watch-only [`DlcDevKitWallet`], e.g. with a hardware wallet.
+pub trait PsbtSigner: Debug + Send + Sync + 'static {
+    /// Add signatures for the wallet inputs of `psbt`. The wallet finalizes the inputs.
+    fn sign_psbt(&self, psbt: &mut Psbt) -> anyhow::Result<()>;
+}
+
+/// Time after which coins reserved for a contract that was never funded are released.
+pub const DEFAULT_UTXO_RESERVATION_TIMEOUT: Duration = Duration::from_secs(60 * 60 * 24);
+
+/// A coin reserved to fund a contract. Reserved coins are skipped by coin selection
+/// and the `send_*` functions of [`DlcDevKitWallet`] until they are released.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct UtxoReservation {
+    pub outpoint: OutPoint,
+    /// The contract funded by the coin. `None` until the contract is created.
+    pub contract_id: Option<ContractId>,
+    /// Unix time the coin was reserved.
+    pub reserved_at: u64,
+}
+
+/// Number of consecutive unused addresses after which a full scan stops.
+pub const DEFAULT_STOP_GAP: usize = 20;
+/// Number of parallel requests to the chain source while syncing.
+const SYNC_PARALLEL_REQUESTS: usize = 5;
+
+/// Progress of the running or last wallet sync, see
+/// [`DlcDevKitWallet::subscribe_sync_progress`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SyncProgress {
+    /// Whether all addresses are scanned up to the stop gap instead of the revealed ones.
+    pub full_scan: bool,
+    /// Addresses, transactions and outputs checked so far.
+    pub checked: usize,
+    /// Items left to check. Unknown for a full scan, which stops at the stop gap.
+    pub remaining: Option<usize>,
+    /// Whether the update was applied to the wallet.
+    pub finished: bool,
 }
 
-const MIN_FEERATE: u32 = 253;
+/// BIP32 purpose for keys used to sign DLC funding transactions and CETs.
+const CONTRACT_KEY_PURPOSE: u32 = 9999;
+const CONTRACT_KEY_ACCOUNT: u32 = 0;
+const CHANNEL_KEY_ACCOUNT: u32 = 1;
+/// Keys of contracts offered to us, counted separately from the keys of our own offers.
+const ACCEPT_CONTRACT_KEY_ACCOUNT: u32 = 2;
+/// Marks a contract key id that carries a derivation index. Key ids without it were
+/// created before keys were derived from the seed and are the secret key itself.
+const CONTRACT_KEY_ID_PREFIX: [u8; 8] = *b"ddk-kid1";
+/// Byte 12 of a contract key id for keys of the accepting party.
+const ACCEPT_PARTY_KEY_ID_FLAG: u8 = 1;
 
 impl DlcDevKitWallet {
     pub fn new(
@@ -73,15 +194,59 @@ impl DlcDevKitWallet {
         esplora_url: &str,
         network: Network,
         storage: Arc<dyn Storage>,
+    ) -> Result<DlcDevKitWallet, WalletError> {
+        DlcDevKitWallet::new_with_config(
+            name,
+            &WalletConfig::Seed,
+            seed_bytes,
+            &ChainSource::esplora(esplora_url),
+            network,
+            storage,
+        )
+    }
+
+    /// Create or load a wallet with the descriptors of `config` that syncs with `chain`.
+    /// `seed_bytes` are used for the contract and channel keys unless the config has a
+    /// mnemonic.
+    pub fn new_with_config(
+        name: &str,
+        config: &WalletConfig,
+        seed_bytes: &[u8; 32],
+        chain: &ChainSource,
+        network: Network,
+        storage: Arc<dyn Storage>,
     ) -> Result<DlcDevKitWallet, WalletError> {
         let secp = Secp256k1::new();
 
-        let xprv = Xpriv::new_master(network, seed_bytes)?;
+        let xprv = match config {
+            WalletConfig::Mnemonic {
+                mnemonic,
+                passphrase,
+            } => {
+                let mnemonic = Mnemonic::parse(mnemonic)
+                    .map_err(|e| WalletError::InvalidConfig(e.to_string()))?;
+                Xpriv::new_master(network, &mnemonic.to_seed(passphrase))?
+            }
+            WalletConfig::Descriptors { .. } if seed_bytes == &[0u8; 32] => {
+                return Err(WalletError::InvalidConfig(
+                    "Descriptor wallets need seed bytes to derive the contract keys.".to_string(),
+                ));
+            }
+            WalletConfig::Seed | WalletConfig::Descriptors { .. } => {
+                Xpriv::new_master(network, seed_bytes)?
+            }
+        };
 
-        let external_descriptor =
-            Bip84(xprv, KeychainKind::External).into_wallet_descriptor(&secp, network)?;
-        let internal_descriptor =
-            Bip84(xprv, KeychainKind::Internal).into_wallet_descriptor(&secp, network)?;
+        let (external_descriptor, internal_descriptor) = match config {
+            WalletConfig::Seed | WalletConfig::Mnemonic { .. } => (
+                Bip84(xprv, KeychainKind::External).into_wallet_descriptor(&secp, network)?,
+                Bip84(xprv, KeychainKind::Internal).into_wallet_descriptor(&secp, network)?,
+            ),
+            WalletConfig::Descriptors { external, internal } => (
+                external.as_str().into_wallet_descriptor(&secp, network)?,
+                internal.as_str().into_wallet_descriptor(&secp, network)?,
+            ),
+        };
 
         let mut storage = WalletStorage(storage);
 
@@ -101,41 +266,30 @@ impl DlcDevKitWallet {
                 .map_err(|_| WalletError::WalletPersistanceError)?,
         };
 
-        let wallet = Arc::new(Mutex::new(internal_wallet));
+        let watch_only = [KeychainKind::External, KeychainKind::Internal]
+            .iter()
+            .all(|keychain| internal_wallet.get_signers(*keychain).signers().is_empty());
 
         let blockchain = Arc::new(
-            EsploraClient::new(esplora_url, network)
-                .map_err(|_| WalletError::WalletPersistanceError)?,
+            ChainClient::new(chain, network).map_err(|_| WalletError::WalletPersistanceError)?,
         );
+        #[cfg(feature = "cbf")]
+        if let ChainClient::Cbf(client) = blockchain.as_ref() {
+            watch_with_cbf(client, &internal_wallet, storage.0.as_ref(), network)?;
+        }
 
-        // TODO: Actually get fees. I don't think it's used for regular DLCs though
-        let mut fees: HashMap<ConfirmationTarget, AtomicU32> = HashMap::new();
-        fees.insert(ConfirmationTarget::UrgentOnChainSweep, AtomicU32::new(5000));
-        fees.insert(
-            ConfirmationTarget::MinAllowedAnchorChannelRemoteFee,
-            AtomicU32::new(25 * 250),
-        );
-        fees.insert(
-            ConfirmationTarget::MinAllowedAnchorChannelRemoteFee,
-            AtomicU32::new(MIN_FEERATE),
-        );
-        fees.insert(
-            ConfirmationTarget::MinAllowedNonAnchorChannelRemoteFee,
-            AtomicU32::new(MIN_FEERATE),
-        );
-        fees.insert(
-            ConfirmationTarget::AnchorChannelFee,
-            AtomicU32::new(MIN_FEERATE),
-        );
-        fees.insert(
-            ConfirmationTarget::NonAnchorChannelFee,
-            AtomicU32::new(2000),
-        );
-        fees.insert(
-            ConfirmationTarget::ChannelCloseMinimum,
-            AtomicU32::new(MIN_FEERATE),
-        );
-        let fees = Arc::new(fees);
+        let wallet = Arc::new(Mutex::new(internal_wallet));
+
+        let fee_estimates = Arc::new(FeeEstimates::new(blockchain.clone(), storage.0.clone()));
+
+        let key_ids = storage
+            .0
+            .get_contracts()
+            .map_err(|e| WalletError::StorageError(e.to_string()))?
+            .iter()
+            .filter_map(|contract| contract.get_keys_id())
+            .collect::<Vec<_>>();
+        recover_contract_key_index(storage.0.as_ref(), key_ids)?;
 
         Ok(DlcDevKitWallet {
             wallet,
@@ -143,87 +297,350 @@ impl DlcDevKitWallet {
             blockchain,
             network,
             xprv,
-            fees,
+            fee_estimates,
             secp,
             name: name.to_string(),
+            reservation_timeout: DEFAULT_UTXO_RESERVATION_TIMEOUT,
+            reservation_lock: std::sync::Mutex::new(()),
+            coin_selection: CoinSelectionStrategy::default(),
+            psbt_signer: None,
+            external_signer: None,
+            watch_only,
+            sync_lock: tokio::sync::Mutex::new(()),
+            sync_progress: Arc::new(watch::Sender::new(SyncProgress::default())),
         })
     }
 
+    /// Replace the source the wallet fee estimates are refreshed from. The last stored
+    /// estimates are kept until the new source is polled.
+    pub fn with_fee_source(mut self, source: Arc<dyn FeeSource>) -> DlcDevKitWallet {
+        self.fee_estimates = Arc::new(FeeEstimates::new(source, self.storage.0.clone()));
+        self
+    }
+
+    /// Replace the fee estimates of the wallet, e.g. to change the fee rate limits.
+    pub fn with_fee_estimates(mut self, fee_estimates: FeeEstimates) -> DlcDevKitWallet {
+        self.fee_estimates = Arc::new(fee_estimates);
+        self
+    }
+
+    /// Set the time after which coins reserved for a contract that was never funded
+    /// are released.
+    pub fn with_reservation_timeout(mut self, timeout: Duration) -> DlcDevKitWallet {
+        self.reservation_timeout = timeout;
+        self
+    }
+
+    /// Set the algorithm that picks the coins funding contracts and channels.
+    pub fn with_coin_selection(mut self, strategy: CoinSelectionStrategy) -> DlcDevKitWallet {
+        self.coin_selection = strategy;
+        self
+    }
+
+    /// Sign wallet inputs with `signer` instead of the keys of the descriptors.
+    pub fn with_psbt_signer(mut self, signer: Arc<dyn PsbtSigner>) -> DlcDevKitWallet {
+        self.psbt_signer = Some(signer);
+        self
+    }
+
+    /// Sign wallet inputs, CETs and refund transactions with `signer`. Contract keys are
+    /// requested from the signer by their key id instead of derived from the seed.
+    pub fn with_external_signer(mut self, signer: Arc<dyn ExternalSigner>) -> DlcDevKitWallet {
+        self.psbt_signer = Some(Arc::new(ExternalPsbtSigner(signer.clone())));
+        self.external_signer = Some(signer);
+        self
+    }
+
+    /// Whether contract keys are held by an [`ExternalSigner`] set with
+    /// [`Self::with_external_signer`].
+    pub fn has_external_signer(&self) -> bool {
+        self.external_signer.is_some()
+    }
+
+    /// Whether the descriptors hold no private keys. Watch-only wallets sign with the
+    /// [`PsbtSigner`] set with [`Self::with_psbt_signer`].
+    pub fn is_watch_only(&self) -> bool {
+        self.watch_only
+    }
+
+    /// Fee rate estimates used by the wallet and the DLC manager.
+    pub fn fee_estimates(&self) -> Arc<FeeEstimates> {
+        self.fee_estimates.clone()
+    }
+
+    /// The client the wallet syncs with and broadcasts to.
+    pub fn chain(&self) -> Arc<ChainClient> {
+        self.blockchain.clone()
+    }
+
+    /// Sync the revealed addresses of the wallet with the chain. The chain is queried
+    /// without holding the wallet lock, so other wallet calls don't fail while syncing.
+    /// A wallet that never synced with esplora does a [`Self::full_scan`] with
+    /// [`DEFAULT_STOP_GAP`].
     pub async fn sync(&self) -> Result<(), WalletError> {
-        let mut wallet = match self.wallet.try_lock() {
-            Ok(w) => w,
-            Err(e) => {
-                tracing::error!(error =? e, "Could not get lock to sync wallet.");
-                return Err(WalletError::Lock);
-            }
-        };
+        let _sync = self.sync_lock.lock().await;
+        match self.blockchain.as_ref() {
+            ChainClient::Esplora(client) => self.sync_esplora(client).await,
+            #[cfg(feature = "bitcoind")]
+            ChainClient::Bitcoind(client) => self.sync_bitcoind(client, false).await,
+            #[cfg(feature = "cbf")]
+            ChainClient::Cbf(client) => self.sync_cbf(client, false).await,
+        }
+    }
 
-        let mut storage = self.storage.clone();
+    /// Scan every address of the wallet until `stop_gap` consecutive addresses have no
+    /// transactions, e.g. to recover a wallet restored from its seed.
+    ///
+    /// With bitcoind every block is scanned again from genesis, with compact block
+    /// filters every filter after the start height is checked again. Blocks are checked
+    /// for the revealed addresses and the lookahead of the wallet, `stop_gap` is not used.
+    pub async fn full_scan(&self, stop_gap: usize) -> Result<(), WalletError> {
+        let _sync = self.sync_lock.lock().await;
+        match self.blockchain.as_ref() {
+            ChainClient::Esplora(client) => self.scan_esplora(client, stop_gap).await,
+            #[cfg(feature = "bitcoind")]
+            ChainClient::Bitcoind(client) => self.sync_bitcoind(client, true).await,
+            #[cfg(feature = "cbf")]
+            ChainClient::Cbf(client) => self.sync_cbf(client, true).await,
+        }
+    }
 
-        let prev_tip = wallet.latest_checkpoint();
+    /// Subscribe to the progress of wallet syncs and full scans.
+    pub fn subscribe_sync_progress(&self) -> watch::Receiver<SyncProgress> {
+        self.sync_progress.subscribe()
+    }
+
+    async fn sync_esplora(&self, client: &EsploraClient) -> Result<(), WalletError> {
+        let (request, prev_height) = {
+            let wallet = self.lock_wallet()?;
+            let progress = self.sync_progress.clone();
+            let request = wallet
+                .start_sync_with_revealed_spks()
+                .inspect(move |_, sync| {
+                    progress.send_replace(SyncProgress {
+                        full_scan: false,
+                        checked: sync.consumed(),
+                        remaining: Some(sync.remaining()),
+                        finished: false,
+                    });
+                })
+                .build();
+            (request, wallet.latest_checkpoint().height())
+        };
+        if prev_height == 0 {
+            return self.scan_esplora(client, DEFAULT_STOP_GAP).await;
+        }
         tracing::debug!(
-            height = prev_tip.height(),
+            height = prev_height,
             "Syncing wallet with latest known height."
         );
-        let sync_result = if prev_tip.height() == 0 {
-            tracing::info!("Performing a full chain scan.");
-            let spks = wallet
-                .all_unbounded_spk_iters()
-                .get(&KeychainKind::External)
-                .unwrap()
-                .to_owned();
-            let chain = FullScanRequest::builder()
-                .spks_for_keychain(KeychainKind::External, spks.clone())
-                .chain_tip(prev_tip)
-                .build();
-            let sync = self.blockchain.async_client.full_scan(chain, 10, 1).await?;
-            Update {
-                last_active_indices: sync.last_active_indices,
-                tx_update: sync.tx_update,
-                chain: sync.chain_update,
-            }
-        } else {
-            let spks = wallet
-                .start_sync_with_revealed_spks()
-                .chain_tip(prev_tip)
-                .build();
-            let sync = self.blockchain.async_client.sync(spks, 1).await?;
-            let indices = wallet.derivation_index(KeychainKind::External).unwrap_or(0);
-            let internal_index = wallet.derivation_index(KeychainKind::Internal).unwrap_or(0);
-            let mut last_active_indices = BTreeMap::new();
-            last_active_indices.insert(KeychainKind::External, indices);
-            last_active_indices.insert(KeychainKind::Internal, internal_index);
-            Update {
-                last_active_indices,
-                tx_update: sync.tx_update,
-                chain: sync.chain_update,
+
+        let response = client
+            .async_client
+            .sync(request, SYNC_PARALLEL_REQUESTS)
+            .await?;
+        self.lock_wallet()?.apply_update(Update::from(response))?;
+        self.finish_sync(false)
+    }
+
+    async fn scan_esplora(
+        &self,
+        client: &EsploraClient,
+        stop_gap: usize,
+    ) -> Result<(), WalletError> {
+        tracing::info!(stop_gap, "Performing a full chain scan.");
+        let request = {
+            let wallet = self.lock_wallet()?;
+            let progress = self.sync_progress.clone();
+            let mut checked = 0;
+            wallet
+                .start_full_scan()
+                .inspect(move |_, _, _| {
+                    checked += 1;
+                    progress.send_replace(SyncProgress {
+                        full_scan: true,
+                        checked,
+                        remaining: None,
+                        finished: false,
+                    });
+                })
+                .build()
+        };
+        let response = client
+            .async_client
+            .full_scan(request, stop_gap, SYNC_PARALLEL_REQUESTS)
+            .await?;
+        self.lock_wallet()?.apply_update(Update::from(response))?;
+        self.finish_sync(true)
+    }
+
+    /// Apply the blocks after the wallet tip, or after genesis for a rescan, and the
+    /// mempool. The wallet is locked for each block only.
+    #[cfg(feature = "bitcoind")]
+    async fn sync_bitcoind(
+        &self,
+        client: &BitcoindClient,
+        rescan: bool,
+    ) -> Result<(), WalletError> {
+        use bitcoincore_rpc::RpcApi;
+
+        let last_checkpoint = {
+            let wallet = self.lock_wallet()?;
+            if rescan {
+                CheckPoint::new(BlockId {
+                    height: 0,
+                    hash: wallet.local_chain().genesis_hash(),
+                })
+            } else {
+                wallet.latest_checkpoint()
             }
         };
-        wallet.apply_update(sync_result)?;
-        wallet
-            .persist(&mut storage)
+        tracing::debug!(
+            height = last_checkpoint.height(),
+            rescan,
+            "Syncing wallet with bitcoind."
+        );
+
+        let rpc = client.client.clone();
+        let wallet = self.wallet.clone();
+        let progress = self.sync_progress.clone();
+        tokio::task::spawn_blocking(move || -> Result<(), WalletError> {
+            let tip = rpc.get_block_count()? as u32;
+            let mut emitter = bdk_bitcoind_rpc::Emitter::new(rpc.as_ref(), last_checkpoint, 0);
+            let mut checked = 0;
+            while let Some(event) = emitter.next_block()? {
+                let height = event.block_height();
+                wallet
+                    .lock()
+                    .map_err(|_| WalletError::Lock)?
+                    .apply_block_connected_to(&event.block, height, event.connected_to())?;
+                checked += 1;
+                progress.send_replace(SyncProgress {
+                    full_scan: rescan,
+                    checked,
+                    remaining: Some(tip.saturating_sub(height) as usize),
+                    finished: false,
+                });
+            }
+            let mempool = emitter.mempool()?;
+            wallet
+                .lock()
+                .map_err(|_| WalletError::Lock)?
+                .apply_unconfirmed_txs(mempool);
+            Ok(())
+        })
+        .await
+        .map_err(|_| WalletError::SyncError)??;
+        self.finish_sync(rescan)
+    }
+
+    /// Apply the blocks the compact block filter node matched since the last sync and
+    /// the transactions broadcast through it. The first sync, and a rescan, wait for the
+    /// node to reach the tip.
+    #[cfg(feature = "cbf")]
+    async fn sync_cbf(&self, client: &CbfClient, rescan: bool) -> Result<(), WalletError> {
+        let scripts = wallet_scripts(&*self.lock_wallet()?);
+        client.add_scripts(scripts)?;
+        let syncs = client.syncs();
+        if rescan {
+            client.rescan()?;
+        }
+        if rescan || syncs == 0 {
+            tracing::info!(rescan, "Waiting for the compact block filter node to sync.");
+            client
+                .wait_for_sync(syncs)
+                .await
+                .map_err(|e| WalletError::Blockchain(e.to_string()))?;
+        }
+
+        let updates = client.take_updates();
+        let total = updates.len();
+        for (checked, update) in updates.into_iter().enumerate() {
+            let mut wallet = self.lock_wallet()?;
+            match update {
+                CbfUpdate::Block { height, block } => {
+                    // Connect the parent first, the wallet may not know the blocks
+                    // between its tip and this block.
+                    if let Some(parent_height) = height.checked_sub(1) {
+                        let chain = wallet.latest_checkpoint().insert(BlockId {
+                            height: parent_height,
+                            hash: block.header.prev_blockhash,
+                        });
+                        wallet.apply_update(Update {
+                            chain: Some(chain),
+                            ..Default::default()
+                        })?;
+                    }
+                    wallet.apply_block(&block, height)?;
+                }
+                CbfUpdate::Headers(headers) => {
+                    let chain = headers
+                        .into_iter()
+                        .fold(wallet.latest_checkpoint(), |chain, block| {
+                            chain.insert(block)
+                        });
+                    wallet.apply_update(Update {
+                        chain: Some(chain),
+                        ..Default::default()
+                    })?;
+                }
+            }
+            self.sync_progress.send_replace(SyncProgress {
+                full_scan: rescan,
+                checked: checked + 1,
+                remaining: Some(total - checked - 1),
+                finished: false,
+            });
+        }
+
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        self.lock_wallet()?.apply_unconfirmed_txs(
+            client
+                .unconfirmed_transactions()
+                .into_iter()
+                .map(|tx| (tx, now)),
+        );
+        // Blocks paying to the lookahead reveal more addresses.
+        let scripts = wallet_scripts(&*self.lock_wallet()?);
+        client.add_scripts(scripts)?;
+        self.finish_sync(rescan)
+    }
+
+    /// Persist the wallet after a sync or full scan.
+    fn finish_sync(&self, full_scan: bool) -> Result<(), WalletError> {
+        self.lock_wallet()?
+            .persist(&mut self.storage.clone())
             .map_err(|_| WalletError::WalletPersistanceError)?;
+        self.sync_progress.send_modify(|progress| {
+            progress.full_scan = full_scan;
+            progress.remaining = progress.remaining.map(|_| 0);
+            progress.finished = true;
+        });
         Ok(())
     }
 
+    fn lock_wallet(&self) -> Result<MutexGuard<'_, PersistedWallet<WalletStorage>>, WalletError> {
+        self.wallet.lock().map_err(|e| {
+            tracing::error!(error = e.to_string(), "Wallet lock is poisoned.");
+            WalletError::Lock
+        })
+    }
+
     pub fn get_pubkey(&self) -> PublicKey {
         tracing::info!("Getting wallet public key.");
         PublicKey::from_secret_key(&self.secp, &self.xprv.private_key)
     }
 
     pub fn get_balance(&self) -> Result<Balance, WalletError> {
-        let Ok(wallet) = self.wallet.try_lock() else {
-            tracing::error!("Could not get lock to sync wallet.");
-            return Err(WalletError::Lock);
-        };
+        let wallet = self.lock_wallet()?;
         Ok(wallet.balance())
     }
 
     pub fn new_external_address(&self) -> Result<AddressInfo, WalletError> {
-        let Ok(mut wallet) = self.wallet.try_lock() else {
-            tracing::error!("Could not get lock to sync wallet.");
-            return Err(WalletError::Lock);
-        };
+        let mut wallet = self.lock_wallet()?;
         let mut storage = self.storage.clone();
         let address = wallet.next_unused_address(KeychainKind::External);
         let _ = wallet.persist(&mut storage);
@@ -231,10 +648,7 @@ impl DlcDevKitWallet {
     }
 
     pub fn new_change_address(&self) -> Result<AddressInfo, WalletError> {
-        let Ok(mut wallet) = self.wallet.try_lock() else {
-            tracing::error!("Could not get lock to sync wallet.");
-            return Err(WalletError::Lock);
-        };
+        let mut wallet = self.lock_wallet()?;
         let mut storage = self.storage.clone();
         let address = wallet.next_unused_address(KeychainKind::Internal);
         wallet
@@ -249,258 +663,1165 @@ impl DlcDevKitWallet {
         amount: Amount,
         fee_rate: FeeRate,
     ) -> Result<Txid, WalletError> {
-        let Ok(mut wallet) = self.wallet.try_lock() else {
-            tracing::error!("Could not get lock to sync wallet.");
-            return Err(WalletError::Lock);
+        let payment = SendBuilder::new(fee_rate).add_recipient(address, amount);
+        Ok(self.send(payment).await?.txid)
+    }
+
+    /// Build, sign and broadcast the payment of `builder`. A dry run returns the unsigned
+    /// PSBT and its fee without broadcasting.
+    pub async fn send(&self, builder: SendBuilder) -> Result<SendResult, WalletError> {
+        let psbt = self.create_send_psbt(&builder)?;
+        let fee = psbt
+            .fee()
+            .map_err(|e| WalletError::BuildTx(e.to_string()))?;
+        let txid = psbt.unsigned_tx.compute_txid();
+        if builder.dry_run {
+            return Ok(SendResult {
+                txid,
+                psbt,
+                fee,
+                broadcast: false,
+            });
+        }
+
+        let txid = self.sign_and_broadcast_send(psbt.clone()).await?;
+        Ok(SendResult {
+            txid,
+            psbt,
+            fee,
+            broadcast: true,
+        })
+    }
+
+    fn create_send_psbt(&self, builder: &SendBuilder) -> Result<Psbt, WalletError> {
+        if builder.recipients.is_empty() && builder.op_return.is_none() {
+            return Err(WalletError::BuildTx("No recipients.".to_string()));
+        }
+        let op_return = match &builder.op_return {
+            Some(data) if data.len() > MAX_OP_RETURN_SIZE => {
+                return Err(WalletError::BuildTx(format!(
+                    "OP_RETURN data is larger than {} bytes.",
+                    MAX_OP_RETURN_SIZE
+                )));
+            }
+            Some(data) => Some(
+                PushBytesBuf::try_from(data.clone())
+                    .map_err(|e| WalletError::BuildTx(e.to_string()))?,
+            ),
+            None => None,
         };
+        let _reservation = self.reservation_lock.lock().unwrap();
+        let mut unspendable = self
+            .reserved_outpoints()
+            .map_err(|e| WalletError::StorageError(e.to_string()))?;
+        if let Some(outpoint) = builder.include.iter().find(|o| unspendable.contains(o)) {
+            return Err(WalletError::BuildTx(format!(
+                "{} is reserved for a contract.",
+                outpoint
+            )));
+        }
+        unspendable.extend(builder.exclude.iter().copied());
+
+        let mut wallet = self.lock_wallet()?;
         tracing::info!(
-            address = address.to_string(),
-            amount =? amount,
+            recipients = builder.recipients.len(),
+            amount =? builder
+                .recipients
+                .iter()
+                .map(|(_, amount)| *amount)
+                .try_fold(Amount::ZERO, Amount::checked_add),
+            include = builder.include.len(),
+            exclude = builder.exclude.len(),
+            dry_run = builder.dry_run,
             "Sending transaction."
         );
         let mut txn_builder = wallet.build_tx();
-
         txn_builder
-            .add_recipient(address.script_pubkey(), amount)
+            .set_recipients(builder.recipients.clone())
+            .unspendable(unspendable.into_iter().collect())
             .version(2)
-            .fee_rate(fee_rate);
-
-        let mut psbt = txn_builder.finish().unwrap();
+            .fee_rate(builder.fee_rate);
+        if !builder.include.is_empty() {
+            txn_builder
+                .add_utxos(&builder.include)
+                .map_err(|e| WalletError::BuildTx(e.to_string()))?;
+        }
+        if let Some(data) = &op_return {
+            txn_builder.add_data(data);
+        }
+        if let Some(change_address) = &builder.change_address {
+            txn_builder.drain_to(change_address.script_pubkey());
+        }
 
-        wallet.sign(&mut psbt, SignOptions::default())?;
+        let psbt = txn_builder
+            .finish()
+            .map_err(|e| WalletError::BuildTx(e.to_string()))?;
+        drop(wallet);
+        if !builder.dry_run {
+            self.reserve_send_inputs(&psbt)?;
+        }
+        Ok(psbt)
+    }
 
-        let tx = psbt.extract_tx()?;
+    pub async fn send_all(&self, address: Address, fee_rate: FeeRate) -> Result<Txid, WalletError> {
+        let psbt = {
+            let _reservation = self.reservation_lock.lock().unwrap();
+            let reserved = self
+                .reserved_outpoints()
+                .map_err(|e| WalletError::StorageError(e.to_string()))?;
+            let mut wallet = self.lock_wallet()?;
+
+            tracing::info!(
+                address = address.to_string(),
+                "Sending all UTXOs to address."
+            );
 
-        self.blockchain.async_client.broadcast(&tx).await?;
+            let mut tx_builder = wallet.build_tx();
+            tx_builder.fee_rate(fee_rate);
+            tx_builder.unspendable(reserved.into_iter().collect());
+            tx_builder.drain_wallet();
+            tx_builder.drain_to(address.script_pubkey());
+            let psbt = tx_builder
+                .finish()
+                .map_err(|e| WalletError::BuildTx(e.to_string()))?;
+            drop(wallet);
+            self.reserve_send_inputs(&psbt)?;
+            psbt
+        };
+        self.sign_and_broadcast_send(psbt).await
+    }
 
-        Ok(tx.compute_txid())
+    /// Reserve the inputs of a payment until it is broadcast, so that contracts offered or
+    /// accepted in the meantime do not select them. Called with the reservation lock held.
+    fn reserve_send_inputs(&self, psbt: &Psbt) -> Result<(), WalletError> {
+        let reservations = psbt
+            .unsigned_tx
+            .input
+            .iter()
+            .map(|input| UtxoReservation {
+                outpoint: input.previous_output,
+                contract_id: None,
+                reserved_at: crate::util::unix_time_now(),
+            })
+            .collect::<Vec<_>>();
+        self.storage
+            .0
+            .reserve_utxos(&reservations)
+            .map_err(|e| WalletError::StorageError(e.to_string()))
     }
 
-    pub async fn send_all(&self, address: Address, fee_rate: FeeRate) -> Result<Txid, WalletError> {
-        let Ok(mut wallet) = self.wallet.try_lock() else {
-            tracing::error!("Could not get lock to sync wallet.");
-            return Err(WalletError::Lock);
+    /// Sign and broadcast a payment built by the wallet, releasing its inputs if it
+    /// could not be broadcast.
+    async fn sign_and_broadcast_send(&self, mut psbt: Psbt) -> Result<Txid, WalletError> {
+        let outpoints = psbt
+            .unsigned_tx
+            .input
+            .iter()
+            .map(|input| input.previous_output)
+            .collect::<Vec<_>>();
+        let result = match self.sign_psbt(&mut psbt) {
+            Ok(()) => self.broadcast_psbt(psbt).await,
+            Err(e) => Err(e),
         };
+        if result.is_err() {
+            if let Err(e) = self.storage.0.unreserve_utxos(&outpoints) {
+                tracing::error!(
+                    error = e.to_string(),
+                    "Could not release the inputs of a failed payment."
+                );
+            }
+        }
+        result
+    }
 
-        tracing::info!(
-            address = address.to_string(),
-            "Sending all UTXOs to address."
-        );
+    /// Build a transaction paying `amount` to `address` without signing it. Watch-only
+    /// wallets hand the PSBT to an external signer and broadcast it with
+    /// [`Self::broadcast_psbt`].
+    pub fn create_unsigned_psbt(
+        &self,
+        address: Address,
+        amount: Amount,
+        fee_rate: FeeRate,
+    ) -> Result<Psbt, WalletError> {
+        let payment = SendBuilder::new(fee_rate)
+            .add_recipient(address, amount)
+            .dry_run();
+        self.create_send_psbt(&payment)
+    }
+
+    /// Sign the wallet inputs of `psbt` with the [`PsbtSigner`] or the descriptor keys.
+    pub fn sign_psbt(&self, psbt: &mut Psbt) -> Result<(), WalletError> {
+        let wallet = self.lock_wallet()?;
+        self.sign_with_wallet(&wallet, psbt, SignOptions::default())
+    }
 
-        let mut tx_builder = wallet.build_tx();
-        tx_builder.fee_rate(fee_rate);
-        tx_builder.drain_wallet();
-        tx_builder.drain_to(address.script_pubkey());
-        let mut psbt = tx_builder.finish().unwrap();
-        wallet.sign(&mut psbt, SignOptions::default()).unwrap();
+    /// Finalize a signed PSBT and broadcast its transaction.
+    pub async fn broadcast_psbt(&self, mut psbt: Psbt) -> Result<Txid, WalletError> {
+        {
+            let wallet = self.lock_wallet()?;
+            wallet.finalize_psbt(&mut psbt, SignOptions::default())?;
+        }
         let tx = psbt.extract_tx()?;
-        self.blockchain.async_client.broadcast(&tx).await?;
+
+        self.blockchain.broadcast(&tx).await?;
+
+        // Spend the inputs in the wallet now instead of at the next sync, so that coin
+        // selection does not pick them again.
+        let outpoints = tx
+            .input
+            .iter()
+            .map(|input| input.previous_output)
+            .collect::<Vec<_>>();
+        {
+            let mut wallet = self.lock_wallet()?;
+            wallet.apply_unconfirmed_txs([(tx.clone(), crate::util::unix_time_now())]);
+            wallet
+                .persist(&mut self.storage.clone())
+                .map_err(|_| WalletError::WalletPersistanceError)?;
+        }
+        self.storage
+            .0
+            .unreserve_utxos(&outpoints)
+            .map_err(|e| WalletError::StorageError(e.to_string()))?;
 
         Ok(tx.compute_txid())
     }
 
+    fn sign_with_wallet(
+        &self,
+        wallet: &PersistedWallet<WalletStorage>,
+        psbt: &mut Psbt,
+        sign_options: SignOptions,
+    ) -> Result<(), WalletError> {
+        match &self.psbt_signer {
+            Some(signer) => {
+                signer
+                    .sign_psbt(psbt)
+                    .map_err(|e| WalletError::SignerError(e.to_string()))?;
+                wallet.finalize_psbt(psbt, sign_options)?;
+            }
+            None if self.watch_only => {
+                return Err(WalletError::SignerError(
+                    "Watch-only wallet has no psbt signer.".to_string(),
+                ));
+            }
+            None => {
+                wallet.sign(psbt, sign_options)?;
+            }
+        }
+        Ok(())
+    }
+
     pub fn get_transactions(&self) -> Result<Vec<Arc<Transaction>>, WalletError> {
-        let Ok(wallet) = self.wallet.try_lock() else {
-            tracing::error!("Could not get lock to sync wallet.");
-            return Err(WalletError::Lock);
-        };
+        let wallet = self.lock_wallet()?;
         Ok(wallet
             .transactions()
             .map(|t| t.tx_node.tx)
             .collect::<Vec<Arc<Transaction>>>())
     }
 
-    pub fn list_utxos(&self) -> Result<Vec<LocalOutput>, WalletError> {
-        let Ok(wallet) = self.wallet.try_lock() else {
-            tracing::error!("Could not get lock to sync wallet.");
-            return Err(WalletError::Lock);
-        };
-        Ok(wallet.list_unspent().map(|utxo| utxo.to_owned()).collect())
-    }
-
-    fn next_derivation_index(&self) -> Result<u32, WalletError> {
-        let Ok(wallet) = self.wallet.try_lock() else {
-            tracing::error!("Could not get lock to sync wallet.");
-            return Err(WalletError::Lock);
-        };
-
-        Ok(wallet.next_derivation_index(KeychainKind::External))
-    }
-}
-
-impl FeeEstimator for DlcDevKitWallet {
-    fn get_est_sat_per_1000_weight(&self, confirmation_target: ConfirmationTarget) -> u32 {
-        self.fees
-            .get(&confirmation_target)
-            .unwrap()
-            .load(Ordering::Acquire)
-    }
-}
-
-impl ddk_manager::ContractSignerProvider for DlcDevKitWallet {
-    type Signer = SimpleSigner;
-
-    // Using the data deterministically generate a key id. From a child key.
-    fn derive_signer_key_id(&self, _is_offer_party: bool, temp_id: [u8; 32]) -> [u8; 32] {
-        let mut random_bytes = [0u8; 32];
-        let _ = random_bytes.try_fill(&mut thread_rng()).map_err(|e| {
-            tracing::error!(
-                "Did not create random bytes while generating key id. {:?}",
-                e
-            );
+    /// Wallet transactions with their amounts, fee, confirmation and the contract or
+    /// channel they belong to. Unconfirmed transactions come first, then the most recently
+    /// confirmed.
+    pub fn transaction_history(&self) -> Result<Vec<WalletTransaction>, WalletError> {
+        let mut index = self.dlc_transaction_index()?;
+        let labels = self
+            .storage
+            .0
+            .list_labels()
+            .map_err(|e| WalletError::StorageError(e.to_string()))?
+            .into_iter()
+            .filter(|label| label.label_type == LabelType::Tx)
+            .filter_map(|label| Some((label.reference, label.label?)))
+            .collect::<HashMap<_, _>>();
+
+        let wallet = self.lock_wallet()?;
+        let wallet_txs = wallet.transactions().collect::<Vec<_>>();
+        index.match_closed_contracts(wallet_txs.iter().map(|t| t.tx_node.tx.as_ref()));
+
+        let mut history = wallet_txs
+            .into_iter()
+            .map(|wallet_tx| {
+                let tx = wallet_tx.tx_node.tx;
+                let txid = wallet_tx.tx_node.txid;
+                let (sent, received) = wallet.sent_and_received(&tx);
+                let net = SignedAmount::from_sat(received.to_sat() as i64 - sent.to_sat() as i64);
+                let (confirmation, last_seen) = match wallet_tx.chain_position {
+                    ChainPosition::Confirmed { anchor, .. } => (
+                        Some(ConfirmationTime {
+                            height: anchor.block_id.height,
+                            time: anchor.confirmation_time,
+                        }),
+                        None,
+                    ),
+                    ChainPosition::Unconfirmed { last_seen } => (None, last_seen),
+                };
+                let (kind, link) = index.classify(&tx, net);
+                WalletTransaction {
+                    txid,
+                    kind,
+                    sent,
+                    received,
+                    net,
+                    fee: wallet.calculate_fee(&tx).ok(),
+                    confirmation,
+                    last_seen,
+                    contract_id: match link {
+                        Some(DlcLink::Contract(id)) => Some(id),
+                        _ => None,
+                    },
+                    channel_id: match link {
+                        Some(DlcLink::Channel(id)) => Some(id),
+                        _ => None,
+                    },
+                    label: labels.get(&txid.to_string()).cloned(),
+                    transaction: tx.as_ref().clone(),
+                }
+            })
+            .collect::<Vec<_>>();
+        history.sort_by_key(|tx| {
+            std::cmp::Reverse(tx.confirmation.map(|c| c.height).unwrap_or(u32::MAX))
         });
-        let mut hasher = HashEngine::default();
-        hasher.write_all(&temp_id).unwrap();
-        hasher.write_all(&random_bytes).unwrap();
-        let hash: Sha256Hash = Hash::from_engine(hasher);
-
-        // Might want to store this for safe backups.
-        hash.to_byte_array()
+        Ok(history)
     }
 
-    fn derive_contract_signer(&self, key_id: [u8; 32]) -> Result<Self::Signer, ManagerError> {
-        let child_key = SecretKey::from_slice(&key_id).expect("correct size");
-        tracing::info!(
-            key_id = hex::encode(key_id),
-            "Derived secret key for contract."
-        );
-        Ok(SimpleSigner::new(child_key))
+    fn dlc_transaction_index(&self) -> Result<DlcTransactionIndex, WalletError> {
+        let storage = &self.storage.0;
+        let contracts = storage
+            .get_contracts()
+            .map_err(|e| WalletError::StorageError(e.to_string()))?;
+        let channels = storage
+            .get_signed_channels(None)
+            .map_err(|e| WalletError::StorageError(e.to_string()))?;
+        Ok(DlcTransactionIndex::new(&contracts, &channels))
     }
 
-    fn get_secret_key_for_pubkey(&self, _pubkey: &PublicKey) -> Result<SecretKey, ManagerError> {
-        unreachable!("get_secret_key_for_pubkey is only used in channels.")
-    }
+    /// Bump the fee of an unconfirmed transaction to `fee_rate`. Returns the txid of the
+    /// new transaction.
+    ///
+    /// Transactions sent by the wallet are replaced (RBF). Transactions with inputs of
+    /// other parties, like DLC funding transactions, CETs and refunds, can't be replaced
+    /// and get a child spending their wallet output (CPFP) with a fee that raises the rate
+    /// of parent and child to `fee_rate`.
+    pub async fn bump_fee(&self, txid: Txid, fee_rate: FeeRate) -> Result<Txid, WalletError> {
+        let parent = self.unconfirmed_transaction(txid).await?;
+        let index = self.dlc_transaction_index()?;
+        let replaceable = {
+            let wallet = self.lock_wallet()?;
+            let (sent, received) = wallet.sent_and_received(&parent);
+            let net = SignedAmount::from_sat(received.to_sat() as i64 - sent.to_sat() as i64);
+            let (kind, _) = index.classify(&parent, net);
+            let all_inputs_owned = parent.input.iter().all(|input| {
+                wallet
+                    .tx_graph()
+                    .get_txout(input.previous_output)
+                    .is_some_and(|txout| wallet.is_mine(txout.script_pubkey.clone()))
+            });
+            matches!(kind, TransactionKind::Deposit | TransactionKind::Withdrawal)
+                && all_inputs_owned
+        };
 
-    fn get_new_secret_key(&self) -> Result<SecretKey, ManagerError> {
-        unreachable!("get_new_secret_key is only used for channels")
+        if replaceable {
+            self.replace_by_fee(txid, fee_rate).await
+        } else {
+            self.child_pays_for_parent(&parent, fee_rate).await
+        }
     }
-}
 
-impl ddk_manager::Wallet for DlcDevKitWallet {
-    fn get_new_address(&self) -> Result<bitcoin::Address, ManagerError> {
-        let address = self
-            .new_external_address()
-            .map_err(wallet_err_to_manager_err)?;
-        tracing::info!(
-            address = address.address.to_string(),
-            "Revealed new address for contract."
-        );
-        Ok(address.address)
+    /// The unconfirmed transaction `txid`. Transactions the wallet has not synced yet are
+    /// fetched from the chain source and added to the wallet.
+    async fn unconfirmed_transaction(&self, txid: Txid) -> Result<Arc<Transaction>, WalletError> {
+        {
+            let wallet = self.lock_wallet()?;
+            if let Some(wallet_tx) = wallet.get_tx(txid) {
+                if wallet_tx.chain_position.is_confirmed() {
+                    return Err(WalletError::FeeBump(format!(
+                        "Transaction {} is confirmed.",
+                        txid
+                    )));
+                }
+                return Ok(wallet_tx.tx_node.tx);
+            }
+        }
+
+        let tx = self
+            .blockchain
+            .get_tx(txid)
+            .await?
+            .ok_or_else(|| WalletError::FeeBump(format!("Transaction {} not found.", txid)))?;
+        let mut wallet = self.lock_wallet()?;
+        wallet.apply_unconfirmed_txs([(tx.clone(), crate::util::unix_time_now())]);
+        Ok(Arc::new(tx))
     }
 
-    fn get_new_change_address(&self) -> Result<bitcoin::Address, ManagerError> {
-        let address = self
-            .new_change_address()
-            .map_err(wallet_err_to_manager_err)?;
+    async fn replace_by_fee(&self, txid: Txid, fee_rate: FeeRate) -> Result<Txid, WalletError> {
+        let mut psbt = {
+            let mut wallet = self.lock_wallet()?;
+            let mut tx_builder = wallet
+                .build_fee_bump(txid)
+                .map_err(|e| WalletError::FeeBump(e.to_string()))?;
+            tx_builder.fee_rate(fee_rate);
+            tx_builder
+                .finish()
+                .map_err(|e| WalletError::FeeBump(e.to_string()))?
+        };
+        self.sign_psbt(&mut psbt)?;
+        let replacement = self.broadcast_psbt(psbt).await?;
         tracing::info!(
-            address = address.address.to_string(),
-            "Revealed new change address for contract."
+            txid = txid.to_string(),
+            replacement = replacement.to_string(),
+            fee_rate = fee_rate.to_sat_per_vb_ceil(),
+            "Replaced transaction with a higher fee."
         );
-        Ok(address.address)
+        Ok(replacement)
     }
 
-    fn sign_psbt_input(
+    async fn child_pays_for_parent(
         &self,
-        psbt: &mut bitcoin::psbt::Psbt,
-        input_index: usize,
-    ) -> Result<(), ManagerError> {
+        parent: &Transaction,
+        fee_rate: FeeRate,
+    ) -> Result<Txid, WalletError> {
+        let txid = parent.compute_txid();
+        self.insert_previous_outputs(parent).await?;
+        let drain_script = self.new_change_address()?.address.script_pubkey();
+        let reserved = self
+            .reserved_outpoints()
+            .map_err(|e| WalletError::StorageError(e.to_string()))?;
+
+        let mut psbt = {
+            let mut wallet = self.lock_wallet()?;
+            let parent_fee = wallet
+                .calculate_fee(parent)
+                .map_err(|e| WalletError::FeeBump(e.to_string()))?;
+            let parent_fee_at_rate = fee_rate.fee_vb(parent.vsize() as u64).ok_or_else(|| {
+                WalletError::FeeBump(format!("Fee rate {} is too high.", fee_rate))
+            })?;
+            if parent_fee >= parent_fee_at_rate {
+                return Err(WalletError::FeeBump(format!(
+                    "Transaction {} already pays the fee rate.",
+                    txid
+                )));
+            }
+            let outputs = (0..parent.output.len() as u32)
+                .map(|vout| OutPoint::new(txid, vout))
+                .filter(|outpoint| wallet.get_utxo(*outpoint).is_some())
+                .collect::<Vec<_>>();
+            if outputs.is_empty() {
+                return Err(WalletError::FeeBump(format!(
+                    "Transaction {} has no unspent wallet output.",
+                    txid
+                )));
+            }
+
+            let mut build_child = |fee: Option<Amount>| -> Result<Psbt, WalletError> {
+                let mut tx_builder = wallet.build_tx();
+                tx_builder
+                    .add_utxos(&outputs)
+                    .map_err(|e| WalletError::FeeBump(e.to_string()))?
+                    .unspendable(reserved.iter().copied().collect())
+                    .drain_to(drain_script.clone());
+                match fee {
+                    Some(fee) => tx_builder.fee_absolute(fee),
+                    None => tx_builder.fee_rate(fee_rate),
+                };
+                tx_builder
+                    .finish()
+                    .map_err(|e| WalletError::FeeBump(e.to_string()))
+            };
+            // Price the child on its own first, then add what the parent is missing.
+            let child_fee = build_child(None)?
+                .fee()
+                .map_err(|e| WalletError::FeeBump(e.to_string()))?;
+            let fee = parent_fee_at_rate
+                .checked_sub(parent_fee)
+                .and_then(|missing| missing.checked_add(child_fee))
+                .ok_or_else(|| {
+                    WalletError::FeeBump(format!("Fee rate {} is too high.", fee_rate))
+                })?;
+            build_child(Some(fee))?
+        };
+        self.sign_psbt(&mut psbt)?;
+        let child = self.broadcast_psbt(psbt).await?;
         tracing::info!(
-            input_index,
-            inputs = psbt.inputs.len(),
-            outputs = psbt.outputs.len(),
-            "Signing psbt input for dlc manager."
+            txid = txid.to_string(),
+            child = child.to_string(),
+            fee_rate = fee_rate.to_sat_per_vb_ceil(),
+            "Bumped transaction fee with a child transaction."
         );
-        let Ok(wallet) = self.wallet.try_lock() else {
-            tracing::error!("Could not get lock to sync wallet.");
-            return Err(ManagerError::WalletError(WalletError::Lock.into()));
-        };
-        let sign_opts = SignOptions {
-            trust_witness_utxo: true,
-            ..Default::default()
-        };
+        Ok(child)
+    }
 
-        let mut signed_psbt = psbt.clone();
-        if let Err(e) = wallet.sign(&mut signed_psbt, sign_opts) {
-            tracing::error!("Could not sign PSBT: {:?}", e);
-            return Err(ManagerError::WalletError(WalletError::Signing(e).into()));
+    /// Add the outputs spent by `tx` to the wallet so the fee of `tx` can be calculated.
+    async fn insert_previous_outputs(&self, tx: &Transaction) -> Result<(), WalletError> {
+        let missing = {
+            let wallet = self.lock_wallet()?;
+            tx.input
+                .iter()
+                .map(|input| input.previous_output)
+                .filter(|outpoint| wallet.tx_graph().get_txout(*outpoint).is_none())
+                .collect::<Vec<_>>()
         };
 
-        psbt.inputs[input_index] = signed_psbt.inputs[input_index].clone();
+        let mut txouts = Vec::new();
+        for outpoint in missing {
+            let prev_tx = self
+                .blockchain
+                .get_tx(outpoint.txid)
+                .await?
+                .ok_or_else(|| {
+                    WalletError::FeeBump(format!("Transaction {} not found.", outpoint.txid))
+                })?;
+            let txout = prev_tx
+                .output
+                .get(outpoint.vout as usize)
+                .cloned()
+                .ok_or_else(|| {
+                    WalletError::FeeBump(format!("Output {} does not exist.", outpoint))
+                })?;
+            txouts.push((outpoint, txout));
+        }
 
+        let mut wallet = self.lock_wallet()?;
+        for (outpoint, txout) in txouts {
+            wallet.insert_txout(outpoint, txout);
+        }
         Ok(())
     }
 
-    // BDK does not have reserving UTXOs nor need it.
-    fn unreserve_utxos(&self, _outpoints: &[bitcoin::OutPoint]) -> Result<(), ManagerError> {
-        Ok(())
+    /// Save a BIP329 label, replacing the label of the same record.
+    pub fn set_label(&self, label: Label) -> Result<(), WalletError> {
+        self.storage
+            .0
+            .save_labels(&[label])
+            .map_err(|e| WalletError::StorageError(e.to_string()))
     }
 
-    fn import_address(&self, _address: &bitcoin::Address) -> Result<(), ManagerError> {
-        Ok(())
+    /// All BIP329 labels of the wallet.
+    pub fn labels(&self) -> Result<Vec<Label>, WalletError> {
+        self.storage
+            .0
+            .list_labels()
+            .map_err(|e| WalletError::StorageError(e.to_string()))
+    }
+
+    /// Export the wallet labels as BIP329 JSON lines.
+    pub fn export_labels(&self) -> Result<String, WalletError> {
+        Ok(labels::export_labels(&self.labels()?)?)
+    }
+
+    /// Import BIP329 JSON lines, replacing existing labels of the same records. Returns
+    /// the number of labels imported.
+    pub fn import_labels(&self, jsonl: &str) -> Result<usize, WalletError> {
+        let labels = labels::import_labels(jsonl)?;
+        self.storage
+            .0
+            .save_labels(&labels)
+            .map_err(|e| WalletError::StorageError(e.to_string()))?;
+        tracing::info!(labels = labels.len(), "Imported wallet labels.");
+        Ok(labels.len())
+    }
+
+    pub fn list_utxos(&self) -> Result<Vec<LocalOutput>, WalletError> {
+        let wallet = self.lock_wallet()?;
+        Ok(wallet.list_unspent().map(|utxo| utxo.to_owned()).collect())
+    }
+
+    /// Coins reserved to fund contracts. Expired reservations are released first.
+    pub fn list_reserved_utxos(&self) -> anyhow::Result<Vec<UtxoReservation>> {
+        self.release_expired_reservations()?;
+        self.storage.0.list_utxo_reservations()
+    }
+
+    fn reserved_outpoints(&self) -> anyhow::Result<HashSet<OutPoint>> {
+        Ok(self
+            .list_reserved_utxos()?
+            .into_iter()
+            .map(|r| r.outpoint)
+            .collect())
+    }
+
+    /// Release reservations older than the reservation timeout, unless the coin funds
+    /// a contract that is being signed or waits for its funding transaction.
+    fn release_expired_reservations(&self) -> anyhow::Result<()> {
+        let storage = &self.storage.0;
+        let expires_before =
+            crate::util::unix_time_now().saturating_sub(self.reservation_timeout.as_secs());
+        let expired = storage
+            .list_utxo_reservations()?
+            .into_iter()
+            .filter(|r| r.reserved_at < expires_before)
+            .map(|r| r.outpoint)
+            .collect::<Vec<_>>();
+        if expired.is_empty() {
+            return Ok(());
+        }
+
+        let mut funding = HashSet::new();
+        let funding_contracts = storage.query_contracts(&ContractQuery::new().states([
+            ContractState::Accepted,
+            ContractState::Signed,
+            ContractState::Confirmed,
+        ]))?;
+        for contract in funding_contracts.contracts {
+            let inputs = match &contract {
+                Contract::Accepted(c) => [&c.offered_contract.funding_inputs, &c.funding_inputs],
+                Contract::Signed(c) | Contract::Confirmed(c) => [
+                    &c.accepted_contract.offered_contract.funding_inputs,
+                    &c.accepted_contract.funding_inputs,
+                ],
+                _ => continue,
+            };
+            for funding_inputs in inputs {
+                funding.extend(ddk_manager::manager::get_funding_outpoints(funding_inputs)?);
+            }
+        }
+
+        let released = expired
+            .into_iter()
+            .filter(|outpoint| !funding.contains(outpoint))
+            .collect::<Vec<_>>();
+        if !released.is_empty() {
+            tracing::info!(
+                released = released.len(),
+                "Released expired utxo reservations."
+            );
+            storage.unreserve_utxos(&released)?;
+        }
+        Ok(())
+    }
+
+    /// The BIP32 path of the contract key for a key id created by
+    /// [`ddk_manager::ContractSignerProvider::derive_signer_key_id`]. `None` for legacy
+    /// key ids that are not derived from the seed.
+    ///
+    /// Keys of contracts we offer are derived at `m/9999'/<coin_type>'/0'/<index>'` and
+    /// keys of contracts we accept at `m/9999'/<coin_type>'/2'/<index>'`, where the index
+    /// is the big endian `u32` following [`CONTRACT_KEY_ID_PREFIX`] in the key id and the
+    /// next byte marks the accepting party. A wallet restored from the seed can re-derive
+    /// the signer of any contract it knows the key id of.
+    pub fn contract_key_path(&self, key_id: &[u8; 32]) -> Option<DerivationPath> {
+        contract_key_index(key_id).map(|(account, index)| self.key_path(account, &[index]))
+    }
+
+    /// Raise the contract key counters past the index of every given key id, so that the
+    /// keys of known contracts are not handed out again.
+    ///
+    /// The wallet does this for the contracts in storage when it is created. Funding
+    /// outputs are 2-of-2 with the counterparty key and can not be found on chain from the
+    /// seed alone, and closed contracts do not keep their key id. A wallet restored from the
+    /// seed into empty storage, without a backup, must be given the key ids of its
+    /// contracts here, e.g. from [`crate::recovery::EmergencyRecords`], or have the counter
+    /// raised past the last used index with [`crate::KeyStorage::advance_key_index`].
+    pub fn recover_contract_key_index<I>(&self, key_ids: I) -> Result<(), WalletError>
+    where
+        I: IntoIterator<Item = [u8; 32]>,
+    {
+        recover_contract_key_index(self.storage.0.as_ref(), key_ids)
+    }
+
+    /// The BIP32 path of a channel key (basepoints and per update seeds).
+    ///
+    /// Channel keys are derived at `m/9999'/<coin_type>'/1'/<index>'` where the index is
+    /// a counter kept in [`crate::KeyStorage`].
+    pub fn channel_key_path(&self, index: u32) -> DerivationPath {
+        self.key_path(CHANNEL_KEY_ACCOUNT, &[index])
+    }
+
+    fn key_path(&self, account: u32, indexes: &[u32]) -> DerivationPath {
+        let coin_type = match self.network {
+            Network::Bitcoin => 0,
+            _ => 1,
+        };
+        let mut path = vec![
+            ChildNumber::Hardened {
+                index: CONTRACT_KEY_PURPOSE,
+            },
+            ChildNumber::Hardened { index: coin_type },
+            ChildNumber::Hardened { index: account },
+        ];
+        path.extend(
+            indexes
+                .iter()
+                .map(|index| ChildNumber::Hardened { index: *index }),
+        );
+        path.into()
+    }
+
+    fn next_derivation_index(&self) -> Result<u32, WalletError> {
+        let wallet = self.lock_wallet()?;
+
+        Ok(wallet.next_derivation_index(KeychainKind::External))
+    }
+}
+
+impl FeeEstimator for DlcDevKitWallet {
+    fn get_est_sat_per_1000_weight(&self, confirmation_target: ConfirmationTarget) -> u32 {
+        self.fee_estimates
+            .get_est_sat_per_1000_weight(confirmation_target)
+    }
+}
+
+impl ddk_manager::ContractSignerProvider for DlcDevKitWallet {
+    type Signer = DdkContractSigner;
+
+    /// Create a key id for the next unused contract key index. Indexes come from a counter
+    /// in [`crate::KeyStorage`], so a counterparty can not make us reuse a funding key.
+    /// Offering and accepting parties have separate counters. See
+    /// [`DlcDevKitWallet::contract_key_path`] for how the key is derived from the id and
+    /// [`DlcDevKitWallet::recover_contract_key_index`] for restoring the counters.
+    fn derive_signer_key_id(
+        &self,
+        is_offer_party: bool,
+        temp_id: [u8; 32],
+    ) -> Result<[u8; 32], ManagerError> {
+        let account = if is_offer_party {
+            CONTRACT_KEY_ACCOUNT
+        } else {
+            ACCEPT_CONTRACT_KEY_ACCOUNT
+        };
+        let index = self
+            .storage
+            .0
+            .next_key_index(account)
+            .map_err(|e| wallet_err_to_manager_err(WalletError::StorageError(e.to_string())))?;
+        if index >= 1 << 31 {
+            return Err(wallet_err_to_manager_err(WalletError::StorageError(
+                "Contract key indexes exhausted.".into(),
+            )));
+        }
+
+        let mut key_id = [0u8; 32];
+        key_id[..8].copy_from_slice(&CONTRACT_KEY_ID_PREFIX);
+        key_id[8..12].copy_from_slice(&index.to_be_bytes());
+        if !is_offer_party {
+            key_id[12] = ACCEPT_PARTY_KEY_ID_FLAG;
+        }
+        tracing::info!(
+            temp_id = hex::encode(temp_id),
+            is_offer_party,
+            index,
+            "Created key id for contract."
+        );
+        Ok(key_id)
+    }
+
+    fn derive_contract_signer(&self, key_id: [u8; 32]) -> Result<Self::Signer, ManagerError> {
+        if let (Some(signer), Some(_)) = (&self.external_signer, contract_key_index(&key_id)) {
+            return DdkContractSigner::external(signer.clone(), key_id);
+        }
+        let Some(path) = self.contract_key_path(&key_id) else {
+            // Contracts created before keys were derived from the seed.
+            let secret_key = SecretKey::from_slice(&key_id)
+                .map_err(|e| ManagerError::InvalidParameters(e.to_string()))?;
+            tracing::info!(
+                key_id = hex::encode(key_id),
+                "Using legacy secret key for contract."
+            );
+            return Ok(secret_key.into());
+        };
+
+        let child_key = self
+            .xprv
+            .derive_priv(&self.secp, &path)
+            .map_err(|e| wallet_err_to_manager_err(WalletError::Seed(e)))?;
+        tracing::info!(
+            key_id = hex::encode(key_id),
+            path = path.to_string(),
+            "Derived secret key for contract."
+        );
+        Ok(child_key.private_key.into())
+    }
+
+    /// Look up a channel key created with [`Self::get_new_secret_key`].
+    fn get_secret_key_for_pubkey(&self, pubkey: &PublicKey) -> Result<SecretKey, ManagerError> {
+        self.storage
+            .0
+            .get_secret_key(channel_key_id(pubkey))
+            .map_err(wallet_err_to_manager_err)
+    }
+
+    /// Derive the next channel key from the wallet seed and store it by its public key.
+    ///
+    /// The key can be re-derived from the seed at [`DlcDevKitWallet::channel_key_path`],
+    /// but it is looked up by public key, so a restored wallet has to derive the keys up to
+    /// the last used index and store them again.
+    fn get_new_secret_key(&self) -> Result<SecretKey, ManagerError> {
+        let index = self
+            .storage
+            .0
+            .next_key_index(CHANNEL_KEY_ACCOUNT)
+            .map_err(|e| wallet_err_to_manager_err(WalletError::StorageError(e.to_string())))?;
+        if index >= 1 << 31 {
+            return Err(wallet_err_to_manager_err(WalletError::StorageError(
+                "Channel key indexes exhausted.".into(),
+            )));
+        }
+        let secret_key = self
+            .xprv
+            .derive_priv(&self.secp, &self.channel_key_path(index))
+            .map_err(|e| wallet_err_to_manager_err(WalletError::Seed(e)))?
+            .private_key;
+
+        let pubkey = PublicKey::from_secret_key(&self.secp, &secret_key);
+        self.storage
+            .0
+            .store_secret_key(channel_key_id(&pubkey), secret_key)
+            .map_err(wallet_err_to_manager_err)?;
+        Ok(secret_key)
+    }
+}
+
+impl ddk_manager::Wallet for DlcDevKitWallet {
+    fn get_new_address(&self) -> Result<bitcoin::Address, ManagerError> {
+        let address = self
+            .new_external_address()
+            .map_err(wallet_err_to_manager_err)?;
+        tracing::info!(
+            address = address.address.to_string(),
+            "Revealed new address for contract."
+        );
+        Ok(address.address)
+    }
+
+    fn get_new_change_address(&self) -> Result<bitcoin::Address, ManagerError> {
+        let address = self
+            .new_change_address()
+            .map_err(wallet_err_to_manager_err)?;
+        tracing::info!(
+            address = address.address.to_string(),
+            "Revealed new change address for contract."
+        );
+        Ok(address.address)
+    }
+
+    fn sign_psbt_input(
+        &self,
+        psbt: &mut bitcoin::psbt::Psbt,
+        input_index: usize,
+    ) -> Result<(), ManagerError> {
+        tracing::info!(
+            input_index,
+            inputs = psbt.inputs.len(),
+            outputs = psbt.outputs.len(),
+            "Signing psbt input for dlc manager."
+        );
+        let wallet = self
+            .lock_wallet()
+            .map_err(|e| ManagerError::WalletError(e.into()))?;
+        let sign_opts = SignOptions {
+            trust_witness_utxo: true,
+            ..Default::default()
+        };
+
+        let mut signed_psbt = psbt.clone();
+        if let Err(e) = self.sign_with_wallet(&wallet, &mut signed_psbt, sign_opts) {
+            tracing::error!("Could not sign PSBT: {:?}", e);
+            return Err(wallet_err_to_manager_err(e));
+        };
+
+        psbt.inputs[input_index] = signed_psbt.inputs[input_index].clone();
+
+        Ok(())
+    }
+
+    fn unreserve_utxos(&self, outpoints: &[bitcoin::OutPoint]) -> Result<(), ManagerError> {
+        tracing::info!(utxos = outpoints.len(), "Releasing reserved utxos.");
+        self.storage
+            .0
+            .unreserve_utxos(outpoints)
+            .map_err(|e| ManagerError::StorageError(e.to_string()))
+    }
+
+    fn reserve_utxos(
+        &self,
+        outpoints: &[bitcoin::OutPoint],
+        contract_id: &ContractId,
+    ) -> Result<(), ManagerError> {
+        let reservations = outpoints
+            .iter()
+            .map(|outpoint| UtxoReservation {
+                outpoint: *outpoint,
+                contract_id: Some(*contract_id),
+                reserved_at: crate::util::unix_time_now(),
+            })
+            .collect::<Vec<_>>();
+        self.storage
+            .0
+            .reserve_utxos(&reservations)
+            .map_err(|e| ManagerError::StorageError(e.to_string()))
+    }
+
+    /// Watch the funding output of a contract. The compact block filter client finds the
+    /// funding transaction and the CET spending it with the script.
+    fn import_address(&self, address: &bitcoin::Address) -> Result<(), ManagerError> {
+        tracing::debug!(address = address.to_string(), "Importing contract address.");
+        #[cfg(feature = "cbf")]
+        if let ChainClient::Cbf(client) = self.blockchain.as_ref() {
+            client
+                .add_scripts([address.script_pubkey()])
+                .map_err(crate::error::cbf_err_to_manager_err)?;
+        }
+        Ok(())
     }
 
-    // return all utxos
     fn get_utxos_for_amount(
         &self,
         amount: u64,
         fee_rate: u64,
-        _lock_utxos: bool,
+        lock_utxos: bool,
     ) -> Result<Vec<ddk_manager::Utxo>, ManagerError> {
-        let local_utxos = self.list_utxos().map_err(wallet_err_to_manager_err)?;
-
-        let utxos = local_utxos
-            .iter()
+        let _reservation = self.reservation_lock.lock().unwrap();
+        let reserved = self
+            .reserved_outpoints()
+            .map_err(|e| ManagerError::StorageError(e.to_string()))?;
+        let wallet = self.lock_wallet().map_err(wallet_err_to_manager_err)?;
+
+        let mut satisfaction_weights = BTreeMap::new();
+        let mut descriptors = BTreeMap::new();
+        for keychain in [KeychainKind::External, KeychainKind::Internal] {
+            let descriptor = wallet.public_descriptor(keychain);
+            let weight = descriptor
+                .max_weight_to_satisfy()
+                .map_err(|e| ManagerError::WalletError(Box::new(e)))?;
+            satisfaction_weights.insert(keychain, weight);
+            descriptors.insert(keychain, descriptor.clone());
+        }
+        let utxos = wallet
+            .list_unspent()
+            .filter(|utxo| !reserved.contains(&utxo.outpoint))
             .map(|utxo| WeightedUtxo {
-                satisfaction_weight: utxo.txout.weight(),
-                utxo: Utxo::Local(utxo.clone()),
+                satisfaction_weight: satisfaction_weights[&utxo.keychain],
+                utxo: Utxo::Local(utxo),
             })
             .collect::<Vec<WeightedUtxo>>();
-
-        let selected_utxos =
-            BranchAndBoundCoinSelection::new(Amount::MAX_MONEY.to_sat(), SingleRandomDraw)
-                .coin_select(
-                    vec![],
-                    utxos,
-                    FeeRate::from_sat_per_vb(fee_rate).unwrap(),
-                    Amount::from_sat(amount),
-                    ScriptBuf::new().as_script(),
-                    &mut thread_rng(),
-                )
-                .map_err(|e| ManagerError::WalletError(Box::new(e)))?;
+        // The manager pays change to a fresh internal address. All of them have the
+        // same script length, so the first one prices the change output.
+        let drain_script = wallet
+            .peek_address(KeychainKind::Internal, 0)
+            .script_pubkey();
+        drop(wallet);
+
+        let fee_rate = FeeRate::from_sat_per_vb(fee_rate)
+            .ok_or_else(|| ManagerError::InvalidParameters("Invalid fee rate.".to_string()))?;
+        let selected_utxos = self
+            .coin_selection
+            .select(utxos, fee_rate, Amount::from_sat(amount), &drain_script)
+            .map_err(|e| ManagerError::WalletError(Box::new(e)))?;
 
         let dlc_utxos = selected_utxos
             .selected
             .iter()
             .map(|utxo| {
-                let address =
-                    Address::from_script(&utxo.txout().script_pubkey, self.network).unwrap();
-                ddk_manager::Utxo {
-                    tx_out: utxo.txout().clone(),
-                    outpoint: utxo.outpoint(),
-                    address,
-                    redeem_script: ScriptBuf::new(),
-                    reserved: false,
-                }
+                let Utxo::Local(local) = utxo else {
+                    return Err(ManagerError::InvalidState(
+                        "Coin selection returned a foreign utxo.".to_string(),
+                    ));
+                };
+                funding_utxo(
+                    &descriptors[&local.keychain],
+                    local,
+                    self.network,
+                    lock_utxos,
+                )
             })
-            .collect();
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if lock_utxos {
+            let reservations = dlc_utxos
+                .iter()
+                .map(|utxo| UtxoReservation {
+                    outpoint: utxo.outpoint,
+                    contract_id: None,
+                    reserved_at: crate::util::unix_time_now(),
+                })
+                .collect::<Vec<_>>();
+            self.storage
+                .0
+                .reserve_utxos(&reservations)
+                .map_err(|e| ManagerError::StorageError(e.to_string()))?;
+        }
 
         Ok(dlc_utxos)
     }
 }
 
+/// A wallet output as a [`ddk_manager::Utxo`] funding a contract, with the redeem script
+/// and witness size of its descriptor. DLC funding transactions only spend segwit outputs.
+fn funding_utxo(
+    descriptor: &ExtendedDescriptor,
+    output: &LocalOutput,
+    network: Network,
+    reserved: bool,
+) -> Result<ddk_manager::Utxo, ManagerError> {
+    let descriptor = descriptor
+        .at_derivation_index(output.derivation_index)
+        .map_err(|e| ManagerError::WalletError(Box::new(e)))?;
+    if descriptor.desc_type().segwit_version().is_none() {
+        return Err(ManagerError::InvalidParameters(format!(
+            "Output {} is not segwit and can not fund a contract.",
+            output.outpoint
+        )));
+    }
+    let redeem_script = match &descriptor {
+        Descriptor::Sh(sh) => match sh.as_inner() {
+            ShInner::Wpkh(wpkh) => wpkh.script_pubkey(),
+            ShInner::Wsh(wsh) => wsh.inner_script().to_p2wsh(),
+            ShInner::SortedMulti(_) | ShInner::Ms(_) => ScriptBuf::new(),
+        },
+        _ => ScriptBuf::new(),
+    };
+    // The satisfaction weight counts the script sig, which the manager adds on its own
+    // from the redeem script.
+    let script_sig_weight = descriptor.unsigned_script_sig().len() as u64 * 4;
+    let max_witness_len = descriptor
+        .max_weight_to_satisfy()
+        .map_err(|e| ManagerError::WalletError(Box::new(e)))?
+        .to_wu()
+        .checked_sub(script_sig_weight)
+        .and_then(|weight| u16::try_from(weight).ok())
+        .ok_or_else(|| {
+            ManagerError::InvalidParameters(format!(
+                "Witness of output {} is too large to fund a contract.",
+                output.outpoint
+            ))
+        })?;
+    let address = descriptor
+        .address(network)
+        .map_err(|e| ManagerError::WalletError(Box::new(e)))?;
+
+    Ok(ddk_manager::Utxo {
+        tx_out: output.txout.clone(),
+        outpoint: output.outpoint,
+        address,
+        redeem_script,
+        max_witness_len,
+        reserved,
+    })
+}
+
+/// The revealed scripts of the wallet and the lookahead after them.
+#[cfg(feature = "cbf")]
+fn wallet_scripts(wallet: &Wallet) -> Vec<ScriptBuf> {
+    let mut scripts = Vec::new();
+    for keychain in [KeychainKind::External, KeychainKind::Internal] {
+        let last_revealed = wallet
+            .spk_index()
+            .last_revealed_index(keychain)
+            .unwrap_or(0);
+        let lookahead = last_revealed + wallet.spk_index().lookahead();
+        for index in 0..=lookahead {
+            scripts.push(wallet.peek_address(keychain, index).script_pubkey());
+        }
+    }
+    scripts
+}
+
+/// Register the scripts of the wallet and of open contracts with the compact block
+/// filter client, and index the wallet transactions the contracts were funded with.
+#[cfg(feature = "cbf")]
+fn watch_with_cbf(
+    client: &CbfClient,
+    wallet: &Wallet,
+    storage: &dyn Storage,
+    network: Network,
+) -> Result<(), WalletError> {
+    client.add_scripts(wallet_scripts(wallet))?;
+    for tx in wallet.transactions() {
+        let height = match tx.chain_position {
+            ChainPosition::Confirmed { anchor, .. } => Some(anchor.block_id.height),
+            ChainPosition::Unconfirmed { .. } => None,
+        };
+        client.insert_transaction(tx.tx_node.tx.as_ref().clone(), height);
+    }
+
+    let storage_err = |e: ManagerError| WalletError::StorageError(e.to_string());
+    let mut contracts = storage.get_signed_contracts().map_err(storage_err)?;
+    contracts.extend(storage.get_confirmed_contracts().map_err(storage_err)?);
+    contracts.extend(
+        storage
+            .get_preclosed_contracts()
+            .map_err(storage_err)?
+            .into_iter()
+            .map(|contract| contract.signed_contract),
+    );
+    client.add_scripts(contracts.iter().map(|contract| {
+        let funding_script = &contract
+            .accepted_contract
+            .dlc_transactions
+            .funding_script_pubkey;
+        Address::p2wsh(funding_script, network).script_pubkey()
+    }))?;
+    Ok(())
+}
+
+/// Derivation account and index of a contract key id, `None` for legacy key ids.
+fn contract_key_index(key_id: &[u8; 32]) -> Option<(u32, u32)> {
+    if key_id[..8] != CONTRACT_KEY_ID_PREFIX {
+        return None;
+    }
+    let account = if key_id[12] == ACCEPT_PARTY_KEY_ID_FLAG {
+        ACCEPT_CONTRACT_KEY_ACCOUNT
+    } else {
+        CONTRACT_KEY_ACCOUNT
+    };
+    let index = u32::from_be_bytes([key_id[8], key_id[9], key_id[10], key_id[11]]);
+    Some((account, index))
+}
+
+fn recover_contract_key_index<I>(storage: &dyn Storage, key_ids: I) -> Result<(), WalletError>
+where
+    I: IntoIterator<Item = [u8; 32]>,
+{
+    let mut next = HashMap::<u32, u32>::new();
+    for (account, index) in key_ids
+        .into_iter()
+        .filter_map(|key_id| contract_key_index(&key_id))
+    {
+        if let Some(index) = index.checked_add(1) {
+            let entry = next.entry(account).or_default();
+            *entry = (*entry).max(index);
+        }
+    }
+    for (account, next) in next {
+        storage
+            .advance_key_index(account, next)
+            .map_err(|e| WalletError::StorageError(e.to_string()))?;
+    }
+    Ok(())
+}
+
+/// Key id that channel keys are stored under in [`crate::KeyStorage`].
+fn channel_key_id(pubkey: &PublicKey) -> [u8; 32] {
+    Sha256Hash::hash(&pubkey.serialize()).to_byte_array()
+}
+
 #[cfg(test)]
 mod tests {
-    use std::{str::FromStr, sync::Arc, time::Duration};
+    use std::{
+        str::FromStr,
+        sync::atomic::{AtomicUsize, Ordering},
+        sync::Arc,
+        time::Duration,
+    };
 
+    use crate::error::WalletError;
     use crate::storage::memory::MemoryStorage;
     use bitcoin::{
-        address::NetworkChecked, bip32::Xpriv, key::rand::Fill, Address, AddressType, Amount,
-        FeeRate, Network,
+        address::NetworkChecked,
+        bip32::{DerivationPath, Xpriv, Xpub},
+        key::rand::Fill,
+        psbt::Psbt,
+        secp256k1::{PublicKey, SecretKey},
+        Address, AddressType, Amount, FeeRate, Network, Transaction,
     };
     use bitcoincore_rpc::RpcApi;
-    use ddk_manager::{Blockchain, ContractSignerProvider};
+    use ddk_manager::{Blockchain, ContractSigner, ContractSignerProvider};
 
-    use super::DlcDevKitWallet;
+    use super::{
+        DlcDevKitWallet, PsbtSigner, SendBuilder, UtxoReservation, WalletConfig, DEFAULT_STOP_GAP,
+    };
+    use crate::chain::ChainSource;
+    use bdk_wallet::KeychainKind;
 
     fn create_wallet() -> DlcDevKitWallet {
         let storage = Arc::new(MemoryStorage::new());
@@ -535,6 +1856,11 @@ mod tests {
     }
 
     fn fund_address(address: &Address<NetworkChecked>) {
+        send_from_node(address);
+        generate_blocks(5)
+    }
+
+    fn send_from_node(address: &Address<NetworkChecked>) -> bitcoin::Txid {
         let auth = bitcoincore_rpc::Auth::UserPass("ddk".to_string(), "ddk".to_string());
         let client = bitcoincore_rpc::Client::new("http://127.0.0.1:18443", auth).unwrap();
         client
@@ -548,8 +1874,193 @@ mod tests {
                 None,
                 None,
             )
+            .unwrap()
+    }
+
+    fn create_wallet_with_config(config: WalletConfig, network: Network) -> DlcDevKitWallet {
+        DlcDevKitWallet::new_with_config(
+            "test",
+            &config,
+            &[1u8; 32],
+            &ChainSource::esplora("http://localhost:30000"),
+            network,
+            Arc::new(MemoryStorage::new()),
+        )
+        .unwrap()
+    }
+
+    fn watch_only_taproot_config() -> WalletConfig {
+        let secp = bitcoin::secp256k1::Secp256k1::new();
+        let xprv = Xpriv::new_master(Network::Regtest, &[2u8; 32]).unwrap();
+        let account = xprv
+            .derive_priv(&secp, &DerivationPath::from_str("m/86'/1'/0'").unwrap())
             .unwrap();
-        generate_blocks(5)
+        let xpub = Xpub::from_priv(&secp, &account);
+        WalletConfig::Descriptors {
+            external: format!("tr({xpub}/0/*)"),
+            internal: format!("tr({xpub}/1/*)"),
+        }
+    }
+
+    fn funding_utxo_of(descriptor: &str) -> Result<ddk_manager::Utxo, ddk_manager::error::Error> {
+        use bdk_wallet::descriptor::IntoWalletDescriptor;
+        use bitcoin::hashes::Hash;
+
+        let secp = bitcoin::secp256k1::Secp256k1::new();
+        let (descriptor, _) = descriptor
+            .into_wallet_descriptor(&secp, Network::Regtest)
+            .unwrap();
+        let script_pubkey = descriptor.at_derivation_index(0).unwrap().script_pubkey();
+        let output = super::LocalOutput {
+            outpoint: bitcoin::OutPoint::new(bitcoin::Txid::all_zeros(), 0),
+            txout: bitcoin::TxOut {
+                value: Amount::from_sat(100_000),
+                script_pubkey,
+            },
+            keychain: KeychainKind::External,
+            is_spent: false,
+            derivation_index: 0,
+            chain_position: bdk_chain::ChainPosition::Unconfirmed { last_seen: None },
+        };
+        super::funding_utxo(&descriptor, &output, Network::Regtest, true)
+    }
+
+    #[test]
+    fn funding_utxos_follow_the_wallet_descriptor() {
+        let secp = bitcoin::secp256k1::Secp256k1::new();
+        let xprv = Xpriv::new_master(Network::Regtest, &[3u8; 32]).unwrap();
+        let xpub = Xpub::from_priv(&secp, &xprv);
+
+        let wpkh = funding_utxo_of(&format!("wpkh({xpub}/0/*)")).unwrap();
+        assert!(wpkh.redeem_script.is_empty());
+        assert_eq!(wpkh.address.address_type(), Some(AddressType::P2wpkh));
+
+        let sh_wpkh = funding_utxo_of(&format!("sh(wpkh({xpub}/0/*))")).unwrap();
+        assert!(sh_wpkh.redeem_script.is_p2wpkh());
+        assert_eq!(sh_wpkh.address.address_type(), Some(AddressType::P2sh));
+        assert_eq!(sh_wpkh.max_witness_len, wpkh.max_witness_len);
+
+        let tr = funding_utxo_of(&format!("tr({xpub}/0/*)")).unwrap();
+        assert!(tr.redeem_script.is_empty());
+        assert!(tr.max_witness_len < wpkh.max_witness_len);
+
+        assert!(funding_utxo_of(&format!("pkh({xpub}/0/*)")).is_err());
+    }
+
+    #[test]
+    fn wallet_config_debug_hides_secrets() {
+        let config = WalletConfig::Mnemonic {
+            mnemonic: "abandon ability".to_string(),
+            passphrase: "hunter2".to_string(),
+        };
+        let debug = format!("{:?}", config);
+        assert!(!debug.contains("abandon"));
+        assert!(!debug.contains("hunter2"));
+    }
+
+    #[test]
+    fn descriptor_wallet_needs_seed_bytes() {
+        let result = DlcDevKitWallet::new_with_config(
+            "descriptors",
+            &watch_only_taproot_config(),
+            &[0u8; 32],
+            &ChainSource::esplora("http://localhost:30000"),
+            Network::Regtest,
+            Arc::new(MemoryStorage::new()),
+        );
+        assert!(matches!(result, Err(WalletError::InvalidConfig(_))));
+    }
+
+    #[derive(Debug, Default)]
+    struct CountingSigner(AtomicUsize);
+
+    impl PsbtSigner for CountingSigner {
+        fn sign_psbt(&self, _psbt: &mut Psbt) -> anyhow::Result<()> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    fn empty_psbt() -> Psbt {
+        Psbt::from_unsigned_tx(Transaction {
+            version: bitcoin::transaction::Version::TWO,
+            lock_time: bitcoin::absolute::LockTime::ZERO,
+            input: vec![],
+            output: vec![],
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn mnemonic_wallet_derives_bip84_addresses() {
+        let mnemonic = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+        let wallet = create_wallet_with_config(
+            WalletConfig::Mnemonic {
+                mnemonic: mnemonic.to_string(),
+                passphrase: String::new(),
+            },
+            Network::Bitcoin,
+        );
+        // First receive address of the BIP84 test vectors.
+        assert_eq!(
+            wallet.new_external_address().unwrap().address.to_string(),
+            "bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu"
+        );
+
+        let with_passphrase = create_wallet_with_config(
+            WalletConfig::Mnemonic {
+                mnemonic: mnemonic.to_string(),
+                passphrase: "ddk".to_string(),
+            },
+            Network::Bitcoin,
+        );
+        assert_ne!(with_passphrase.get_pubkey(), wallet.get_pubkey());
+    }
+
+    #[test]
+    fn invalid_mnemonic_is_rejected() {
+        let result = DlcDevKitWallet::new_with_config(
+            "test",
+            &WalletConfig::Mnemonic {
+                mnemonic: "not a mnemonic".to_string(),
+                passphrase: String::new(),
+            },
+            &[1u8; 32],
+            &ChainSource::esplora("http://localhost:30000"),
+            Network::Regtest,
+            Arc::new(MemoryStorage::new()),
+        );
+        assert!(matches!(result, Err(WalletError::InvalidConfig(_))));
+    }
+
+    #[test]
+    fn public_descriptors_make_watch_only_wallet() {
+        let wallet = create_wallet_with_config(watch_only_taproot_config(), Network::Regtest);
+        assert!(wallet.is_watch_only());
+        assert_eq!(
+            wallet
+                .new_external_address()
+                .unwrap()
+                .address
+                .address_type()
+                .unwrap(),
+            AddressType::P2tr
+        );
+        assert!(matches!(
+            wallet.sign_psbt(&mut empty_psbt()),
+            Err(WalletError::SignerError(_))
+        ));
+
+        let signer = Arc::new(CountingSigner::default());
+        let wallet = create_wallet_with_config(watch_only_taproot_config(), Network::Regtest)
+            .with_psbt_signer(signer.clone());
+        wallet.sign_psbt(&mut empty_psbt()).unwrap();
+        assert_eq!(signer.0.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn seed_wallet_is_not_watch_only() {
+        assert!(!create_wallet().is_watch_only());
     }
 
     #[test]
@@ -566,11 +2077,404 @@ mod tests {
         temp_key_id
             .try_fill(&mut bitcoin::key::rand::thread_rng())
             .unwrap();
-        let gen_key_id = test.derive_signer_key_id(true, temp_key_id);
+        let gen_key_id = test.derive_signer_key_id(true, temp_key_id).unwrap();
         let key_info = test.derive_contract_signer(gen_key_id);
         assert!(key_info.is_ok())
     }
 
+    #[test]
+    fn contract_signer_is_recoverable_from_seed() {
+        let mut seed = [0u8; 32];
+        seed.try_fill(&mut bitcoin::key::rand::thread_rng())
+            .unwrap();
+        let wallet = |name: &str| {
+            DlcDevKitWallet::new(
+                name,
+                &seed,
+                "http://localhost:30000",
+                Network::Regtest,
+                Arc::new(MemoryStorage::new()),
+            )
+            .unwrap()
+        };
+        let original = wallet("original");
+        let restored = wallet("restored");
+
+        let temp_id = [7u8; 32];
+        let key_id = original.derive_signer_key_id(true, temp_id).unwrap();
+        let next_key_id = original.derive_signer_key_id(true, temp_id).unwrap();
+        assert_ne!(key_id, next_key_id);
+        assert_eq!(
+            original.contract_key_path(&key_id).unwrap().to_string(),
+            "9999'/1'/0'/0'"
+        );
+        assert_eq!(
+            original
+                .contract_key_path(&next_key_id)
+                .unwrap()
+                .to_string(),
+            "9999'/1'/0'/1'"
+        );
+
+        let signer = original.derive_contract_signer(key_id).unwrap();
+        let restored_signer = restored.derive_contract_signer(key_id).unwrap();
+        assert_eq!(
+            signer.get_secret_key().unwrap(),
+            restored_signer.get_secret_key().unwrap()
+        );
+    }
+
+    #[test]
+    fn restored_wallet_skips_the_indexes_of_known_key_ids() {
+        let original = create_wallet();
+        let key_ids = (0..3)
+            .map(|_| original.derive_signer_key_id(true, [7u8; 32]).unwrap())
+            .collect::<Vec<_>>();
+
+        let restored = create_wallet();
+        restored
+            .recover_contract_key_index([key_ids[2], key_ids[0], [3u8; 32]])
+            .unwrap();
+        // Known indexes never lower the counter.
+        restored.recover_contract_key_index([key_ids[1]]).unwrap();
+        let next_key_id = restored.derive_signer_key_id(true, [7u8; 32]).unwrap();
+        assert_eq!(
+            restored
+                .contract_key_path(&next_key_id)
+                .unwrap()
+                .to_string(),
+            "9999'/1'/0'/3'"
+        );
+    }
+
+    #[test]
+    fn accepting_party_keys_have_their_own_counter() {
+        let original = create_wallet();
+        let offer_key_id = original.derive_signer_key_id(true, [7u8; 32]).unwrap();
+        let accept_key_ids = (0..2)
+            .map(|_| original.derive_signer_key_id(false, [8u8; 32]).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            original
+                .contract_key_path(&offer_key_id)
+                .unwrap()
+                .to_string(),
+            "9999'/1'/0'/0'"
+        );
+        assert_eq!(
+            original
+                .contract_key_path(&accept_key_ids[1])
+                .unwrap()
+                .to_string(),
+            "9999'/1'/2'/1'"
+        );
+
+        let restored = create_wallet();
+        restored
+            .recover_contract_key_index([offer_key_id, accept_key_ids[1]])
+            .unwrap();
+        let next_offer = restored.derive_signer_key_id(true, [7u8; 32]).unwrap();
+        let next_accept = restored.derive_signer_key_id(false, [8u8; 32]).unwrap();
+        assert_eq!(
+            restored.contract_key_path(&next_offer).unwrap().to_string(),
+            "9999'/1'/0'/1'"
+        );
+        assert_eq!(
+            restored
+                .contract_key_path(&next_accept)
+                .unwrap()
+                .to_string(),
+            "9999'/1'/2'/2'"
+        );
+    }
+
+    #[test]
+    fn legacy_key_id_is_the_secret_key() {
+        let wallet = create_wallet();
+        let key_id = [3u8; 32];
+        assert!(wallet.contract_key_path(&key_id).is_none());
+
+        let signer = wallet.derive_contract_signer(key_id).unwrap();
+        assert_eq!(
+            signer.get_secret_key().unwrap(),
+            SecretKey::from_slice(&key_id).unwrap()
+        );
+    }
+
+    #[test]
+    fn expired_reservations_are_released_unless_funding_a_contract() {
+        use bitcoin::hashes::Hash;
+        use ddk_manager::contract::ser::Serializable;
+        use ddk_manager::contract::{accepted_contract::AcceptedContract, Contract};
+
+        let serialized = include_bytes!("../tests/data/dlc_storage/Accepted");
+        let accepted =
+            AcceptedContract::deserialize(&mut lightning::io::Cursor::new(&serialized)).unwrap();
+        let funding =
+            ddk_manager::manager::get_funding_outpoints(&accepted.funding_inputs).unwrap()[0];
+
+        let wallet = create_wallet().with_reservation_timeout(Duration::from_secs(60));
+        let storage = wallet.storage.0.clone();
+        storage
+            .update_contract(&Contract::Accepted(accepted))
+            .unwrap();
+
+        let now = crate::util::unix_time_now();
+        let reservation = |vout, reserved_at| UtxoReservation {
+            outpoint: bitcoin::OutPoint::new(bitcoin::Txid::all_zeros(), vout),
+            contract_id: None,
+            reserved_at,
+        };
+        let fresh = reservation(0, now);
+        let funding = UtxoReservation {
+            outpoint: funding,
+            ..reservation(0, now - 120)
+        };
+        storage
+            .reserve_utxos(&[fresh.clone(), funding.clone(), reservation(1, now - 120)])
+            .unwrap();
+
+        let mut reserved = wallet.list_reserved_utxos().unwrap();
+        reserved.sort_by_key(|r| r.reserved_at);
+        assert_eq!(reserved, vec![funding, fresh]);
+    }
+
+    #[test]
+    fn channel_keys_are_stored_by_pubkey() {
+        let wallet = create_wallet();
+        let secret_key = wallet.get_new_secret_key().unwrap();
+        assert_ne!(secret_key, wallet.get_new_secret_key().unwrap());
+
+        let pubkey = PublicKey::from_secret_key(&wallet.secp, &secret_key);
+        assert_eq!(
+            wallet.get_secret_key_for_pubkey(&pubkey).unwrap(),
+            secret_key
+        );
+
+        let unknown =
+            PublicKey::from_secret_key(&wallet.secp, &SecretKey::from_slice(&[1u8; 32]).unwrap());
+        assert!(wallet.get_secret_key_for_pubkey(&unknown).is_err());
+
+        let derived = wallet
+            .xprv
+            .derive_priv(&wallet.secp, &wallet.channel_key_path(0))
+            .unwrap();
+        assert_eq!(derived.private_key, secret_key);
+    }
+
+    #[test]
+    fn send_rejects_invalid_payments() {
+        let wallet = create_wallet();
+        let fee_rate = FeeRate::from_sat_per_vb(1).unwrap();
+        let create = |builder: SendBuilder| wallet.create_send_psbt(&builder);
+
+        assert!(matches!(
+            create(SendBuilder::new(fee_rate)),
+            Err(WalletError::BuildTx(_))
+        ));
+        assert!(matches!(
+            create(SendBuilder::new(fee_rate).op_return(vec![0u8; 81])),
+            Err(WalletError::BuildTx(_))
+        ));
+
+        use bitcoin::hashes::Hash;
+        let reserved = bitcoin::OutPoint::new(bitcoin::Txid::all_zeros(), 0);
+        wallet
+            .storage
+            .0
+            .reserve_utxos(&[UtxoReservation {
+                outpoint: reserved,
+                contract_id: None,
+                reserved_at: crate::util::unix_time_now(),
+            }])
+            .unwrap();
+        let address = wallet.new_external_address().unwrap().address;
+        assert!(matches!(
+            create(
+                SendBuilder::new(fee_rate)
+                    .add_recipient(address, Amount::from_sat(10_000))
+                    .include_utxos(&[reserved])
+            ),
+            Err(WalletError::BuildTx(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn send_with_coin_control() {
+        let wallet = create_wallet();
+        fund_address(&wallet.new_external_address().unwrap().address);
+        fund_address(&wallet.new_external_address().unwrap().address);
+        wallet.sync().await.unwrap();
+        let utxos = wallet.list_utxos().unwrap();
+        let (include, exclude) = (utxos[0].outpoint, utxos[1].outpoint);
+
+        let recipient = create_wallet();
+        let change = recipient.new_external_address().unwrap().address;
+        let payment = SendBuilder::new(FeeRate::from_sat_per_vb(2).unwrap())
+            .add_recipient(
+                recipient.new_external_address().unwrap().address,
+                Amount::from_sat(50_000),
+            )
+            .add_recipient(
+                recipient.new_external_address().unwrap().address,
+                Amount::from_sat(60_000),
+            )
+            .include_utxos(&[include])
+            .exclude_utxos(&[exclude])
+            .op_return(b"ddk".to_vec())
+            .change_address(change.clone());
+
+        let dry_run = wallet.send(payment.clone().dry_run()).await.unwrap();
+        assert!(!dry_run.broadcast);
+        let tx = &dry_run.psbt.unsigned_tx;
+        assert_eq!(tx.input.len(), 1);
+        assert_eq!(tx.input[0].previous_output, include);
+        assert_eq!(tx.output.len(), 4);
+        assert!(tx.output.iter().any(|o| o.script_pubkey.is_op_return()));
+        assert!(tx
+            .output
+            .iter()
+            .any(|o| o.script_pubkey == change.script_pubkey()));
+        assert!(dry_run.fee > Amount::ZERO);
+
+        let sent = wallet.send(payment).await.unwrap();
+        assert!(sent.broadcast);
+        assert_eq!(sent.psbt.unsigned_tx.input[0].previous_output, include);
+    }
+
+    #[tokio::test]
+    async fn full_scan_recovers_wallet_from_seed() {
+        let seed = [7u8; 32];
+        let restore = |storage: Arc<MemoryStorage>| {
+            DlcDevKitWallet::new(
+                "test",
+                &seed,
+                "http://localhost:30000",
+                Network::Regtest,
+                storage,
+            )
+            .unwrap()
+        };
+        let wallet = restore(Arc::new(MemoryStorage::new()));
+        let address = (0..10)
+            .map(|_| wallet.new_external_address().unwrap().address)
+            .last()
+            .unwrap();
+        fund_address(&address);
+
+        let restored = restore(Arc::new(MemoryStorage::new()));
+        let progress = restored.subscribe_sync_progress();
+        restored.full_scan(DEFAULT_STOP_GAP).await.unwrap();
+        assert!(restored.get_balance().unwrap().confirmed >= Amount::ONE_BTC);
+        assert_eq!(
+            restored
+                .lock_wallet()
+                .unwrap()
+                .derivation_index(KeychainKind::External),
+            Some(9)
+        );
+        let progress = progress.borrow().clone();
+        assert!(progress.full_scan && progress.finished);
+        assert!(progress.checked > 10);
+    }
+
+    #[cfg(feature = "bitcoind")]
+    #[tokio::test]
+    async fn bitcoind_wallet_syncs_blocks_and_mempool() {
+        let chain = ChainSource::Bitcoind {
+            url: "http://127.0.0.1:18443".to_string(),
+            auth: crate::chain::BitcoindAuth::UserPass("ddk".to_string(), "ddk".to_string()),
+        };
+        let mut seed = [0u8; 32];
+        seed.try_fill(&mut bitcoin::key::rand::thread_rng())
+            .unwrap();
+        let wallet = DlcDevKitWallet::new_with_config(
+            "test",
+            &WalletConfig::Seed,
+            &seed,
+            &chain,
+            Network::Regtest,
+            Arc::new(MemoryStorage::new()),
+        )
+        .unwrap();
+        let progress = wallet.subscribe_sync_progress();
+        wallet.sync().await.unwrap();
+        assert!(progress.borrow().finished);
+
+        let address = wallet.new_external_address().unwrap().address;
+        send_from_node(&address);
+        wallet.sync().await.unwrap();
+        assert_eq!(
+            wallet.get_balance().unwrap().untrusted_pending,
+            Amount::ONE_BTC
+        );
+
+        generate_blocks(1);
+        wallet.sync().await.unwrap();
+        assert_eq!(wallet.get_balance().unwrap().confirmed, Amount::ONE_BTC);
+        assert_eq!(progress.borrow().remaining, Some(0));
+    }
+
+    #[cfg(feature = "cbf")]
+    #[tokio::test]
+    async fn cbf_wallet_syncs_blocks_and_finds_imported_scripts() {
+        use crate::chain::ChainClient;
+        use ddk_manager::Wallet as _;
+
+        let chain = ChainSource::Cbf {
+            peers: vec!["127.0.0.1:18444".parse().unwrap()],
+            data_dir: std::env::temp_dir().join(format!("ddk-cbf-{}", uuid::Uuid::new_v4())),
+            start_height: None,
+        };
+        let mut seed = [0u8; 32];
+        seed.try_fill(&mut bitcoin::key::rand::thread_rng())
+            .unwrap();
+        let wallet = DlcDevKitWallet::new_with_config(
+            "test",
+            &WalletConfig::Seed,
+            &seed,
+            &chain,
+            Network::Regtest,
+            Arc::new(MemoryStorage::new()),
+        )
+        .unwrap();
+        wallet.sync().await.unwrap();
+
+        let address = wallet.new_external_address().unwrap().address;
+        send_from_node(&address);
+        let watched = Address::from_str("bcrt1qt0yrvs7qx8guvpqsx8u9mypz6t4zr3pxthsjkm")
+            .unwrap()
+            .assume_checked();
+        wallet.import_address(&watched).unwrap();
+        let txid = send_from_node(&watched);
+        generate_blocks(1);
+
+        let ChainClient::Cbf(client) = wallet.blockchain.as_ref() else {
+            panic!("Wallet is not synced with compact block filters.");
+        };
+        let syncs = client.syncs();
+        client.wait_for_sync(syncs).await.unwrap();
+        wallet.sync().await.unwrap();
+        assert_eq!(wallet.get_balance().unwrap().confirmed, Amount::ONE_BTC);
+        assert_eq!(
+            wallet
+                .blockchain
+                .get_transaction(&txid)
+                .await
+                .unwrap()
+                .compute_txid(),
+            txid
+        );
+        assert_eq!(
+            wallet
+                .blockchain
+                .get_transaction_confirmations(&txid)
+                .await
+                .unwrap(),
+            1
+        );
+    }
+
     #[tokio::test]
     async fn send_all() {
         let wallet = create_wallet();
@@ -597,4 +2501,137 @@ mod tests {
         wallet.sync().await.unwrap();
         assert!(wallet.get_balance().unwrap().confirmed == Amount::ZERO)
     }
+
+    #[tokio::test]
+    async fn send_all_fails_when_every_utxo_is_reserved() {
+        use bitcoin::hashes::Hash;
+        let wallet = create_wallet();
+        let deposit = Transaction {
+            version: bitcoin::transaction::Version::TWO,
+            lock_time: bitcoin::absolute::LockTime::ZERO,
+            input: vec![bitcoin::TxIn {
+                previous_output: bitcoin::OutPoint::new(bitcoin::Txid::all_zeros(), 0),
+                ..Default::default()
+            }],
+            output: vec![bitcoin::TxOut {
+                value: Amount::from_sat(100_000),
+                script_pubkey: wallet
+                    .new_external_address()
+                    .unwrap()
+                    .address
+                    .script_pubkey(),
+            }],
+        };
+        wallet
+            .lock_wallet()
+            .unwrap()
+            .apply_unconfirmed_txs([(deposit.clone(), crate::util::unix_time_now())]);
+        wallet
+            .storage
+            .0
+            .reserve_utxos(&[UtxoReservation {
+                outpoint: bitcoin::OutPoint::new(deposit.compute_txid(), 0),
+                contract_id: None,
+                reserved_at: crate::util::unix_time_now(),
+            }])
+            .unwrap();
+
+        let address = wallet.new_external_address().unwrap().address;
+        assert!(matches!(
+            wallet
+                .send_all(address, FeeRate::from_sat_per_vb(1).unwrap())
+                .await,
+            Err(WalletError::BuildTx(_))
+        ));
+        // The failed send must not poison the wallet lock.
+        assert_eq!(wallet.list_utxos().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn send_reserves_its_inputs_until_broadcast() {
+        use bitcoin::hashes::Hash;
+        let wallet = create_wallet();
+        let deposit = Transaction {
+            version: bitcoin::transaction::Version::TWO,
+            lock_time: bitcoin::absolute::LockTime::ZERO,
+            input: vec![bitcoin::TxIn {
+                previous_output: bitcoin::OutPoint::new(bitcoin::Txid::all_zeros(), 0),
+                ..Default::default()
+            }],
+            output: vec![bitcoin::TxOut {
+                value: Amount::from_sat(100_000),
+                script_pubkey: wallet
+                    .new_external_address()
+                    .unwrap()
+                    .address
+                    .script_pubkey(),
+            }],
+        };
+        wallet
+            .lock_wallet()
+            .unwrap()
+            .apply_unconfirmed_txs([(deposit.clone(), crate::util::unix_time_now())]);
+        let outpoint = bitcoin::OutPoint::new(deposit.compute_txid(), 0);
+        let address = wallet.new_external_address().unwrap().address;
+        let payment = SendBuilder::new(FeeRate::from_sat_per_vb(1).unwrap())
+            .add_recipient(address, Amount::from_sat(50_000));
+
+        wallet.create_send_psbt(&payment).unwrap();
+        let reserved = wallet.list_reserved_utxos().unwrap();
+        assert_eq!(reserved.len(), 1);
+        assert_eq!(reserved[0].outpoint, outpoint);
+        assert!(ddk_manager::Wallet::get_utxos_for_amount(&wallet, 10_000, 1, true).is_err());
+
+        // The deposit is not on chain, so the broadcast fails and the input is released.
+        wallet.storage.0.unreserve_utxos(&[outpoint]).unwrap();
+        assert!(wallet.send(payment).await.is_err());
+        assert!(wallet.list_reserved_utxos().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn bump_fee_replaces_wallet_send() {
+        let wallet = create_wallet();
+        fund_address(&wallet.new_external_address().unwrap().address);
+        wallet.sync().await.unwrap();
+        let recipient = create_wallet().new_external_address().unwrap().address;
+        let txid = wallet
+            .send_to_address(
+                recipient,
+                Amount::from_sat(50_000),
+                FeeRate::from_sat_per_vb(1).unwrap(),
+            )
+            .await
+            .unwrap();
+        wallet.sync().await.unwrap();
+
+        let replacement = wallet
+            .bump_fee(txid, FeeRate::from_sat_per_vb(10).unwrap())
+            .await
+            .unwrap();
+        assert_ne!(replacement, txid);
+        generate_blocks(1);
+        wallet.sync().await.unwrap();
+        assert!(matches!(
+            wallet
+                .bump_fee(replacement, FeeRate::from_sat_per_vb(20).unwrap())
+                .await,
+            Err(WalletError::FeeBump(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn bump_fee_of_deposit_spends_the_received_output() {
+        let wallet = create_wallet();
+        let txid = send_from_node(&wallet.new_external_address().unwrap().address);
+        wallet.sync().await.unwrap();
+
+        let child = wallet
+            .bump_fee(txid, FeeRate::from_sat_per_vb(50).unwrap())
+            .await
+            .unwrap();
+        wallet.sync().await.unwrap();
+        let history = wallet.transaction_history().unwrap();
+        let child = history.iter().find(|tx| tx.txid == child).unwrap();
+        assert_eq!(child.transaction.input[0].previous_output.txid, txid);
+    }
 }
@@ -0,0 +1,248 @@
+//! Coin selection for the inputs of DLC funding transactions.
+
+use bdk_wallet::coin_selection::{
+    decide_change, BranchAndBoundCoinSelection, CoinSelectionAlgorithm, CoinSelectionResult,
+    InsufficientFunds, LargestFirstCoinSelection, SingleRandomDraw,
+};
+use bdk_wallet::WeightedUtxo;
+use bitcoin::key::rand::{thread_rng, RngCore};
+use bitcoin::{Amount, FeeRate, Script, TxIn, TxOut};
+
+/// Algorithm used to pick the coins that fund a contract.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum CoinSelectionStrategy {
+    /// Search for a set of coins that needs no change output. Falls back to a random
+    /// draw when there is none.
+    #[default]
+    BranchAndBound,
+    /// Spend the largest coins first, keeping the funding transaction small.
+    LargestFirst,
+    /// Draw coins at random so the selection reveals less about the wallet.
+    PrivacyPreserving,
+    /// Spend the smallest coins first to consolidate them into the contract and change.
+    ConsolidateSmallCoins,
+}
+
+impl CoinSelectionStrategy {
+    /// Select coins worth `target` plus the fee of spending them at `fee_rate`. Change
+    /// is sent to `drain_script` when it is worth more than the cost of the output.
+    pub fn select(
+        &self,
+        utxos: Vec<WeightedUtxo>,
+        fee_rate: FeeRate,
+        target: Amount,
+        drain_script: &Script,
+    ) -> Result<CoinSelectionResult, InsufficientFunds> {
+        let mut rng = thread_rng();
+        match self {
+            CoinSelectionStrategy::BranchAndBound => {
+                let size_of_change = TxOut {
+                    value: Amount::ZERO,
+                    script_pubkey: drain_script.to_owned(),
+                }
+                .size() as u64;
+                BranchAndBoundCoinSelection::new(size_of_change, SingleRandomDraw).coin_select(
+                    vec![],
+                    utxos,
+                    fee_rate,
+                    target,
+                    drain_script,
+                    &mut rng,
+                )
+            }
+            CoinSelectionStrategy::LargestFirst => LargestFirstCoinSelection.coin_select(
+                vec![],
+                utxos,
+                fee_rate,
+                target,
+                drain_script,
+                &mut rng,
+            ),
+            CoinSelectionStrategy::PrivacyPreserving => SingleRandomDraw.coin_select(
+                vec![],
+                utxos,
+                fee_rate,
+                target,
+                drain_script,
+                &mut rng,
+            ),
+            CoinSelectionStrategy::ConsolidateSmallCoins => SmallestFirstCoinSelection.coin_select(
+                vec![],
+                utxos,
+                fee_rate,
+                target,
+                drain_script,
+                &mut rng,
+            ),
+        }
+    }
+}
+
+/// Spends the smallest coins first. Coins that cost more to spend than they are worth
+/// are skipped.
+#[derive(Debug, Default, Clone, Copy)]
+struct SmallestFirstCoinSelection;
+
+impl CoinSelectionAlgorithm for SmallestFirstCoinSelection {
+    fn coin_select<R: RngCore>(
+        &self,
+        required_utxos: Vec<WeightedUtxo>,
+        mut optional_utxos: Vec<WeightedUtxo>,
+        fee_rate: FeeRate,
+        target_amount: Amount,
+        drain_script: &Script,
+        _: &mut R,
+    ) -> Result<CoinSelectionResult, InsufficientFunds> {
+        optional_utxos.sort_unstable_by_key(|wu| wu.utxo.txout().value);
+
+        let mut selected = Vec::new();
+        let mut selected_amount = Amount::ZERO;
+        let mut fee_amount = Amount::ZERO;
+        let utxos = required_utxos
+            .into_iter()
+            .map(|wu| (true, wu))
+            .chain(optional_utxos.into_iter().map(|wu| (false, wu)));
+        for (must_use, wu) in utxos {
+            if !must_use && selected_amount >= target_amount + fee_amount {
+                break;
+            }
+            let input_fee = fee_rate * (TxIn::default().segwit_weight() + wu.satisfaction_weight);
+            if !must_use && wu.utxo.txout().value <= input_fee {
+                continue;
+            }
+            fee_amount += input_fee;
+            selected_amount += wu.utxo.txout().value;
+            selected.push(wu.utxo);
+        }
+
+        let needed = target_amount + fee_amount;
+        if selected_amount < needed {
+            return Err(InsufficientFunds {
+                needed,
+                available: selected_amount,
+            });
+        }
+
+        Ok(CoinSelectionResult {
+            selected,
+            fee_amount,
+            excess: decide_change(selected_amount - needed, fee_rate, drain_script),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bdk_chain::ChainPosition;
+    use bdk_wallet::{KeychainKind, LocalOutput, Utxo};
+    use bitcoin::hashes::Hash;
+    use bitcoin::{OutPoint, ScriptBuf, Txid, WPubkeyHash, Weight};
+
+    /// Witness of a P2WPKH input: item count, signature and public key.
+    const P2WPKH_SATISFACTION_WEIGHT: u64 = 108;
+
+    fn utxos(values: &[u64]) -> Vec<WeightedUtxo> {
+        values
+            .iter()
+            .enumerate()
+            .map(|(vout, value)| WeightedUtxo {
+                satisfaction_weight: Weight::from_wu(P2WPKH_SATISFACTION_WEIGHT),
+                utxo: Utxo::Local(LocalOutput {
+                    outpoint: OutPoint::new(Txid::all_zeros(), vout as u32),
+                    txout: TxOut {
+                        value: Amount::from_sat(*value),
+                        script_pubkey: change_script(),
+                    },
+                    keychain: KeychainKind::External,
+                    is_spent: false,
+                    derivation_index: vout as u32,
+                    chain_position: ChainPosition::Unconfirmed { last_seen: Some(0) },
+                }),
+            })
+            .collect()
+    }
+
+    fn change_script() -> ScriptBuf {
+        ScriptBuf::new_p2wpkh(&WPubkeyHash::all_zeros())
+    }
+
+    fn selected_values(result: &CoinSelectionResult) -> Vec<u64> {
+        let mut values = result
+            .selected
+            .iter()
+            .map(|utxo| utxo.txout().value.to_sat())
+            .collect::<Vec<_>>();
+        values.sort();
+        values
+    }
+
+    #[test]
+    fn fee_covers_input_satisfaction_weight() {
+        let fee_rate = FeeRate::from_sat_per_vb(10).unwrap();
+        let result = CoinSelectionStrategy::LargestFirst
+            .select(
+                utxos(&[10_000, 200_000]),
+                fee_rate,
+                Amount::from_sat(100_000),
+                &change_script(),
+            )
+            .unwrap();
+
+        // 41 byte txin, witness count and the P2WPKH witness: 273 weight units.
+        assert_eq!(result.fee_amount, Amount::from_sat(683));
+        assert_eq!(selected_values(&result), vec![200_000]);
+    }
+
+    #[test]
+    fn consolidate_spends_smallest_coins_first() {
+        let fee_rate = FeeRate::from_sat_per_vb(1).unwrap();
+        let result = CoinSelectionStrategy::ConsolidateSmallCoins
+            .select(
+                utxos(&[300, 60_000, 500_000, 30_000, 20_000]),
+                fee_rate,
+                Amount::from_sat(70_000),
+                &change_script(),
+            )
+            .unwrap();
+
+        // The 300 sat coin is worth more than its fee at 1 sat/vb.
+        assert_eq!(selected_values(&result), vec![300, 20_000, 30_000, 60_000]);
+    }
+
+    #[test]
+    fn consolidate_skips_uneconomical_coins() {
+        let fee_rate = FeeRate::from_sat_per_vb(50).unwrap();
+        let result = CoinSelectionStrategy::ConsolidateSmallCoins
+            .select(
+                utxos(&[2_000, 100_000]),
+                fee_rate,
+                Amount::from_sat(50_000),
+                &change_script(),
+            )
+            .unwrap();
+
+        assert_eq!(selected_values(&result), vec![100_000]);
+    }
+
+    #[test]
+    fn insufficient_funds_reports_needed_amount() {
+        let fee_rate = FeeRate::from_sat_per_vb(1).unwrap();
+        for strategy in [
+            CoinSelectionStrategy::BranchAndBound,
+            CoinSelectionStrategy::LargestFirst,
+            CoinSelectionStrategy::PrivacyPreserving,
+            CoinSelectionStrategy::ConsolidateSmallCoins,
+        ] {
+            let error = strategy
+                .select(
+                    utxos(&[10_000, 20_000]),
+                    fee_rate,
+                    Amount::from_sat(100_000),
+                    &change_script(),
+                )
+                .unwrap_err();
+            assert!(error.needed > Amount::from_sat(100_000));
+        }
+    }
+}
@@ -0,0 +1,124 @@
+//! Automatic fee bumping of contract transactions stuck in the mempool.
+
+use super::DlcDevKitWallet;
+use crate::error::WalletError;
+use crate::storage::query::{ContractQuery, ContractState};
+use bitcoin::{FeeRate, Txid};
+use ddk_manager::contract::Contract;
+use lightning::chain::chaininterface::{ConfirmationTarget, FeeEstimator};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Bumps the fee of DLC funding transactions, CETs and refunds that stay unconfirmed
+/// for longer than a deadline.
+///
+/// Transactions are bumped once with CPFP at the urgent fee estimate. The time a
+/// transaction was first seen unconfirmed is not persisted, so the deadline restarts
+/// with DDK.
+pub(crate) struct FeeBumpMonitor {
+    wallet: Arc<DlcDevKitWallet>,
+    deadline: Duration,
+    unconfirmed_since: HashMap<Txid, Instant>,
+    bumped: HashSet<Txid>,
+}
+
+impl FeeBumpMonitor {
+    pub(crate) fn new(wallet: Arc<DlcDevKitWallet>, deadline: Duration) -> Self {
+        Self {
+            wallet,
+            deadline,
+            unconfirmed_since: HashMap::new(),
+            bumped: HashSet::new(),
+        }
+    }
+
+    /// Bump the contract transactions that passed the deadline. Returns the bumped txids
+    /// with the txid of their child.
+    pub(crate) async fn check(&mut self) -> Result<Vec<(Txid, Txid)>, WalletError> {
+        let unconfirmed = self.unconfirmed_contract_transactions()?;
+        let now = Instant::now();
+        self.unconfirmed_since
+            .retain(|txid, _| unconfirmed.contains(txid));
+        self.bumped.retain(|txid| unconfirmed.contains(txid));
+
+        let mut stuck = Vec::new();
+        for txid in unconfirmed {
+            let since = self.unconfirmed_since.entry(txid).or_insert(now);
+            if now.duration_since(*since) >= self.deadline && !self.bumped.contains(&txid) {
+                stuck.push(txid);
+            }
+        }
+
+        let fee_rate = FeeRate::from_sat_per_kwu(
+            self.wallet
+                .get_est_sat_per_1000_weight(ConfirmationTarget::UrgentOnChainSweep)
+                as u64,
+        );
+        let mut bumped = Vec::new();
+        for txid in stuck {
+            match self.wallet.bump_fee(txid, fee_rate).await {
+                Ok(child) => {
+                    self.bumped.insert(txid);
+                    bumped.push((txid, child));
+                }
+                Err(WalletError::Lock) => {
+                    // The bumps done so far are kept, so their events are still emitted.
+                    tracing::error!("Wallet lock is poisoned, stopped bumping fees.");
+                    break;
+                }
+                Err(e) => {
+                    // Don't retry every check, the user can still bump the fee manually.
+                    tracing::warn!(
+                        txid = txid.to_string(),
+                        error = e.to_string(),
+                        "Could not bump fee of unconfirmed contract transaction."
+                    );
+                    self.bumped.insert(txid);
+                }
+            }
+        }
+        Ok(bumped)
+    }
+
+    /// Funding transactions, CETs and refunds of contracts that the wallet knows about
+    /// and are unconfirmed. Contracts in channels are skipped.
+    fn unconfirmed_contract_transactions(&self) -> Result<HashSet<Txid>, WalletError> {
+        let contracts = self
+            .wallet
+            .storage
+            .0
+            .query_contracts(&ContractQuery::new().states([
+                ContractState::Signed,
+                ContractState::PreClosed,
+                ContractState::Refunded,
+            ]))
+            .map_err(|e| WalletError::StorageError(e.to_string()))?
+            .contracts;
+        let txids = contracts
+            .iter()
+            .filter_map(|contract| match contract {
+                Contract::Signed(c) if c.channel_id.is_none() => {
+                    Some(c.accepted_contract.dlc_transactions.fund.compute_txid())
+                }
+                Contract::PreClosed(c) if c.signed_contract.channel_id.is_none() => {
+                    Some(c.signed_cet.compute_txid())
+                }
+                Contract::Refunded(c) if c.channel_id.is_none() => {
+                    Some(c.accepted_contract.dlc_transactions.refund.compute_txid())
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        let wallet = self.wallet.lock_wallet()?;
+        Ok(txids
+            .into_iter()
+            .filter(|txid| {
+                wallet
+                    .get_tx(*txid)
+                    .is_some_and(|tx| !tx.chain_position.is_confirmed())
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,351 @@
+//! Transaction history of the wallet, attributed to the contracts and channels in storage.
+
+use bitcoin::{Amount, OutPoint, SignedAmount, Transaction, Txid};
+use ddk_manager::channel::signed_channel::{SignedChannel, SignedChannelState};
+use ddk_manager::contract::accepted_contract::AcceptedContract;
+use ddk_manager::contract::Contract;
+use ddk_manager::{ChannelId, ContractId};
+use std::collections::HashMap;
+
+/// What a wallet transaction did.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum TransactionKind {
+    /// Received coins from outside the wallet.
+    Deposit,
+    /// Sent coins out of the wallet.
+    Withdrawal,
+    /// Locked the collateral of a contract.
+    DlcFunding,
+    /// Paid out a contract according to the oracle attestation.
+    Cet,
+    /// Returned the collateral of a contract that was not attested to.
+    Refund,
+    /// Funded, updated or closed a DLC channel.
+    ChannelUpdate,
+}
+
+/// The block a transaction was confirmed in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ConfirmationTime {
+    pub height: u32,
+    /// Unix time of the block.
+    pub time: u64,
+}
+
+/// A transaction of the wallet with its amounts and what it was used for.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct WalletTransaction {
+    pub txid: Txid,
+    pub kind: TransactionKind,
+    /// Value of the wallet outputs spent by the transaction.
+    pub sent: Amount,
+    /// Value of the transaction outputs paying to the wallet.
+    pub received: Amount,
+    /// Received minus sent. Includes the fee when the wallet paid it.
+    #[serde(with = "bitcoin::amount::serde::as_sat")]
+    pub net: SignedAmount,
+    /// `None` when the previous output of an input is not known to the wallet.
+    pub fee: Option<Amount>,
+    /// `None` while the transaction is unconfirmed.
+    pub confirmation: Option<ConfirmationTime>,
+    /// Unix time the transaction was last seen in the mempool.
+    pub last_seen: Option<u64>,
+    /// The contract the transaction funded, executed or refunded.
+    pub contract_id: Option<ContractId>,
+    /// The channel the transaction belongs to.
+    pub channel_id: Option<ChannelId>,
+    /// The BIP329 label of the transaction.
+    pub label: Option<String>,
+    pub transaction: Transaction,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum DlcLink {
+    Contract(ContractId),
+    Channel(ChannelId),
+}
+
+/// Transactions and outputs of the contracts and channels in storage.
+#[derive(Debug, Default)]
+pub(crate) struct DlcTransactionIndex {
+    transactions: HashMap<Txid, (TransactionKind, DlcLink)>,
+    /// Outputs whose spending transaction is a CET or a channel update.
+    outputs: HashMap<OutPoint, DlcLink>,
+    /// Closed contracts only keep their id, the funding transaction is matched by
+    /// recomputing the id from the wallet transactions.
+    closed: Vec<(ContractId, ContractId)>,
+}
+
+impl DlcTransactionIndex {
+    pub(crate) fn new(contracts: &[Contract], channels: &[SignedChannel]) -> Self {
+        let mut index = DlcTransactionIndex::default();
+        for contract in contracts {
+            match contract {
+                Contract::Accepted(c) => index.add_accepted(c, None),
+                Contract::Signed(c) | Contract::Confirmed(c) | Contract::Refunded(c) => {
+                    index.add_accepted(&c.accepted_contract, c.channel_id)
+                }
+                Contract::PreClosed(c) => {
+                    let signed = &c.signed_contract;
+                    index.add_accepted(&signed.accepted_contract, signed.channel_id);
+                    let link = match signed.channel_id {
+                        Some(channel_id) => DlcLink::Channel(channel_id),
+                        None => DlcLink::Contract(signed.accepted_contract.get_contract_id()),
+                    };
+                    index.add_transaction(&c.signed_cet, TransactionKind::Cet, link);
+                }
+                Contract::Closed(c) => {
+                    if let Some(cet) = &c.signed_cet {
+                        let link = DlcLink::Contract(c.contract_id);
+                        index.add_transaction(cet, TransactionKind::Cet, link);
+                    }
+                    index.closed.push((c.contract_id, c.temporary_contract_id));
+                }
+                _ => {}
+            }
+        }
+        for channel in channels {
+            index.add_channel(channel);
+        }
+        index
+    }
+
+    fn add_accepted(&mut self, contract: &AcceptedContract, channel_id: Option<ChannelId>) {
+        let dlc_transactions = &contract.dlc_transactions;
+        // Contracts in a channel are funded by the buffer transaction of the channel.
+        let (funding_kind, link) = match channel_id {
+            Some(channel_id) => (TransactionKind::ChannelUpdate, DlcLink::Channel(channel_id)),
+            None => (
+                TransactionKind::DlcFunding,
+                DlcLink::Contract(contract.get_contract_id()),
+            ),
+        };
+        self.add_transaction(&dlc_transactions.fund, funding_kind, link);
+        self.add_transaction(&dlc_transactions.refund, TransactionKind::Refund, link);
+        self.outputs
+            .insert(dlc_transactions.get_fund_outpoint(), link);
+    }
+
+    fn add_channel(&mut self, channel: &SignedChannel) {
+        let link = DlcLink::Channel(channel.channel_id);
+        self.add_transaction(&channel.fund_tx, TransactionKind::ChannelUpdate, link);
+        self.outputs.insert(
+            OutPoint::new(
+                channel.fund_tx.compute_txid(),
+                channel.fund_output_index as u32,
+            ),
+            link,
+        );
+        for state in std::iter::once(&channel.state).chain(&channel.roll_back_state) {
+            let (buffer, other) = channel_state_transactions(state);
+            if let Some(buffer) = buffer {
+                self.add_transaction(buffer, TransactionKind::ChannelUpdate, link);
+                let txid = buffer.compute_txid();
+                for vout in 0..buffer.output.len() {
+                    self.outputs.insert(OutPoint::new(txid, vout as u32), link);
+                }
+            }
+            if let Some(tx) = other {
+                self.add_transaction(tx, TransactionKind::ChannelUpdate, link);
+            }
+        }
+    }
+
+    fn add_transaction(&mut self, tx: &Transaction, kind: TransactionKind, link: DlcLink) {
+        self.transactions
+            .entry(tx.compute_txid())
+            .or_insert((kind, link));
+    }
+
+    /// Find the funding transactions of closed contracts among `transactions`.
+    pub(crate) fn match_closed_contracts<'a>(
+        &mut self,
+        transactions: impl IntoIterator<Item = &'a Transaction>,
+    ) {
+        if self.closed.is_empty() {
+            return;
+        }
+        for tx in transactions {
+            let txid = tx.compute_txid();
+            for vout in 0..tx.output.len() {
+                let matched = self.closed.iter().find(|(contract_id, temporary_id)| {
+                    compute_contract_id(txid, vout as u16, temporary_id) == *contract_id
+                });
+                if let Some((contract_id, _)) = matched {
+                    let link = DlcLink::Contract(*contract_id);
+                    self.add_transaction(tx, TransactionKind::DlcFunding, link);
+                    self.outputs.insert(OutPoint::new(txid, vout as u32), link);
+                }
+            }
+        }
+    }
+
+    /// Classify a wallet transaction. Transactions not linked to a contract or channel
+    /// are deposits or withdrawals depending on the direction of `net`.
+    pub(crate) fn classify(
+        &self,
+        tx: &Transaction,
+        net: SignedAmount,
+    ) -> (TransactionKind, Option<DlcLink>) {
+        if let Some((kind, link)) = self.transactions.get(&tx.compute_txid()) {
+            return (*kind, Some(*link));
+        }
+        let spent = tx
+            .input
+            .iter()
+            .find_map(|input| self.outputs.get(&input.previous_output));
+        match spent {
+            Some(link @ DlcLink::Contract(_)) => (TransactionKind::Cet, Some(*link)),
+            Some(link @ DlcLink::Channel(_)) => (TransactionKind::ChannelUpdate, Some(*link)),
+            None if net.is_negative() => (TransactionKind::Withdrawal, None),
+            None => (TransactionKind::Deposit, None),
+        }
+    }
+}
+
+/// The buffer transaction and the settle or close transaction of a channel state.
+fn channel_state_transactions(
+    state: &SignedChannelState,
+) -> (Option<&Transaction>, Option<&Transaction>) {
+    match state {
+        SignedChannelState::Established {
+            buffer_transaction, ..
+        }
+        | SignedChannelState::RenewAccepted {
+            buffer_transaction, ..
+        }
+        | SignedChannelState::RenewConfirmed {
+            buffer_transaction, ..
+        }
+        | SignedChannelState::RenewFinalized {
+            buffer_transaction, ..
+        }
+        | SignedChannelState::Closing {
+            buffer_transaction, ..
+        } => (Some(buffer_transaction), None),
+        SignedChannelState::SettledAccepted { settle_tx, .. }
+        | SignedChannelState::SettledConfirmed { settle_tx, .. }
+        | SignedChannelState::Settled { settle_tx, .. } => (None, Some(settle_tx)),
+        SignedChannelState::CollaborativeCloseOffered { close_tx, .. } => (None, Some(close_tx)),
+        _ => (None, None),
+    }
+}
+
+/// Contract id of a funding output, see `ddk_manager::utils::compute_id`.
+fn compute_contract_id(
+    fund_txid: Txid,
+    fund_output_index: u16,
+    temporary_id: &[u8; 32],
+) -> [u8; 32] {
+    let txid = bitcoin::hashes::Hash::as_byte_array(&fund_txid);
+    let mut id = [0u8; 32];
+    for i in 0..32 {
+        id[i] = txid[31 - i] ^ temporary_id[i];
+    }
+    id[30] ^= ((fund_output_index >> 8) & 0xff) as u8;
+    id[31] ^= (fund_output_index & 0xff) as u8;
+    id
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::hashes::Hash;
+    use bitcoin::{absolute::LockTime, transaction::Version, Sequence, TxIn, TxOut, Witness};
+    use ddk_manager::contract::ser::Serializable;
+    use ddk_manager::contract::ClosedContract;
+
+    fn accepted_contract() -> AcceptedContract {
+        let serialized = include_bytes!("../../tests/data/dlc_storage/Accepted");
+        AcceptedContract::deserialize(&mut lightning::io::Cursor::new(&serialized)).unwrap()
+    }
+
+    fn spend(outpoint: OutPoint, value: u64) -> Transaction {
+        Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: outpoint,
+                script_sig: Default::default(),
+                sequence: Sequence::MAX,
+                witness: Witness::new(),
+            }],
+            output: vec![TxOut {
+                value: Amount::from_sat(value),
+                script_pubkey: Default::default(),
+            }],
+        }
+    }
+
+    #[test]
+    fn contract_transactions_are_classified() {
+        let accepted = accepted_contract();
+        let contract_id = accepted.get_contract_id();
+        let dlc_transactions = accepted.dlc_transactions.clone();
+        let index = DlcTransactionIndex::new(&[Contract::Accepted(accepted)], &[]);
+        let link = Some(DlcLink::Contract(contract_id));
+        let loss = SignedAmount::from_sat(-1000);
+
+        assert_eq!(
+            index.classify(&dlc_transactions.fund, loss),
+            (TransactionKind::DlcFunding, link)
+        );
+        assert_eq!(
+            index.classify(&dlc_transactions.refund, SignedAmount::ZERO),
+            (TransactionKind::Refund, link)
+        );
+        let cet = spend(dlc_transactions.get_fund_outpoint(), 1000);
+        assert_eq!(
+            index.classify(&cet, SignedAmount::ZERO),
+            (TransactionKind::Cet, link)
+        );
+
+        let other = spend(OutPoint::new(Txid::all_zeros(), 0), 1000);
+        assert_eq!(
+            index.classify(&other, SignedAmount::from_sat(1000)),
+            (TransactionKind::Deposit, None)
+        );
+        assert_eq!(
+            index.classify(&other, loss),
+            (TransactionKind::Withdrawal, None)
+        );
+    }
+
+    #[test]
+    fn closed_contract_funding_is_matched_by_contract_id() {
+        let accepted = accepted_contract();
+        let dlc_transactions = accepted.dlc_transactions.clone();
+        let fund_outpoint = dlc_transactions.get_fund_outpoint();
+        let temporary_contract_id = [9u8; 32];
+        let contract_id = compute_contract_id(
+            fund_outpoint.txid,
+            fund_outpoint.vout as u16,
+            &temporary_contract_id,
+        );
+        let closed = Contract::Closed(ClosedContract {
+            attestations: None,
+            signed_cet: None,
+            contract_id,
+            temporary_contract_id,
+            counter_party_id: accepted.offered_contract.counter_party,
+            pnl: 0,
+        });
+        let mut index = DlcTransactionIndex::new(&[closed], &[]);
+        let cet = spend(fund_outpoint, 1000);
+        assert_eq!(
+            index.classify(&cet, SignedAmount::ZERO).0,
+            TransactionKind::Deposit
+        );
+
+        index.match_closed_contracts([&dlc_transactions.fund, &cet]);
+        let link = Some(DlcLink::Contract(contract_id));
+        assert_eq!(
+            index.classify(&dlc_transactions.fund, SignedAmount::ZERO),
+            (TransactionKind::DlcFunding, link)
+        );
+        assert_eq!(
+            index.classify(&cet, SignedAmount::ZERO),
+            (TransactionKind::Cet, link)
+        );
+    }
+}
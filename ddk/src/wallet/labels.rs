@@ -0,0 +1,162 @@
+//! Wallet labels in the BIP329 format <https://github.com/bitcoin/bips/blob/master/bip-0329.mediawiki>.
+
+use std::fmt;
+use std::str::FromStr;
+
+/// The kind of record a [`Label`] refers to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LabelType {
+    /// A transaction, referenced by its txid.
+    Tx,
+    /// An address.
+    Addr,
+    /// A hex encoded public key.
+    Pubkey,
+    /// A transaction input, referenced by the outpoint it spends.
+    Input,
+    /// A transaction output, referenced by its outpoint.
+    Output,
+    /// An extended public key.
+    Xpub,
+}
+
+impl fmt::Display for LabelType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label_type = match self {
+            LabelType::Tx => "tx",
+            LabelType::Addr => "addr",
+            LabelType::Pubkey => "pubkey",
+            LabelType::Input => "input",
+            LabelType::Output => "output",
+            LabelType::Xpub => "xpub",
+        };
+        f.write_str(label_type)
+    }
+}
+
+impl FromStr for LabelType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "tx" => Ok(LabelType::Tx),
+            "addr" => Ok(LabelType::Addr),
+            "pubkey" => Ok(LabelType::Pubkey),
+            "input" => Ok(LabelType::Input),
+            "output" => Ok(LabelType::Output),
+            "xpub" => Ok(LabelType::Xpub),
+            _ => Err(format!("Unknown label type: {}", s)),
+        }
+    }
+}
+
+/// A BIP329 label record. Labels are unique by their type and reference.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Label {
+    #[serde(rename = "type")]
+    pub label_type: LabelType,
+    #[serde(rename = "ref")]
+    pub reference: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// Key origin of the descriptor the record belongs to, e.g. `wpkh([d34db33f/84'/0'/0'])`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub origin: Option<String>,
+    /// Whether an output may be spent. Only used by [`LabelType::Output`] records.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spendable: Option<bool>,
+}
+
+impl Label {
+    /// Label a transaction.
+    pub fn tx(txid: bitcoin::Txid, label: &str) -> Label {
+        Label::new(LabelType::Tx, txid.to_string(), label)
+    }
+
+    /// Label a record of any type.
+    pub fn new(label_type: LabelType, reference: String, label: &str) -> Label {
+        Label {
+            label_type,
+            reference,
+            label: Some(label.to_string()),
+            origin: None,
+            spendable: None,
+        }
+    }
+}
+
+/// Encode labels as JSON lines, one record per line.
+pub fn export_labels(labels: &[Label]) -> Result<String, serde_json::Error> {
+    let mut jsonl = String::new();
+    for label in labels {
+        jsonl.push_str(&serde_json::to_string(label)?);
+        jsonl.push('\n');
+    }
+    Ok(jsonl)
+}
+
+/// Decode labels from JSON lines. Blank lines and records of unknown types are skipped
+/// as BIP329 requires.
+pub fn import_labels(jsonl: &str) -> Result<Vec<Label>, serde_json::Error> {
+    let mut labels = Vec::new();
+    for line in jsonl.lines().filter(|line| !line.trim().is_empty()) {
+        let record: serde_json::Value = serde_json::from_str(line)?;
+        let known_type = record
+            .get("type")
+            .and_then(|t| t.as_str())
+            .is_some_and(|t| LabelType::from_str(t).is_ok());
+        if !known_type {
+            tracing::warn!(record = line, "Skipping label of unknown type.");
+            continue;
+        }
+        labels.push(serde_json::from_value(record)?);
+    }
+    Ok(labels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn labels_roundtrip_as_json_lines() {
+        let labels = vec![
+            Label::tx(
+                bitcoin::Txid::from_str(
+                    "f91d0a8a78462bc59398f2c5d7a84fcff491c26ba54c4833478b202796c8aafd",
+                )
+                .unwrap(),
+                "Transaction",
+            ),
+            Label {
+                origin: Some("wpkh([d34db33f/84'/0'/0'])".to_string()),
+                spendable: Some(false),
+                ..Label::new(
+                    LabelType::Output,
+                    "f91d0a8a78462bc59398f2c5d7a84fcff491c26ba54c4833478b202796c8aafd:1"
+                        .to_string(),
+                    "Output",
+                )
+            },
+        ];
+        let jsonl = export_labels(&labels).unwrap();
+        assert_eq!(jsonl.lines().count(), 2);
+        assert!(jsonl.starts_with(r#"{"type":"tx","ref":"f91d0a8a"#));
+        assert_eq!(import_labels(&jsonl).unwrap(), labels);
+    }
+
+    #[test]
+    fn unknown_label_types_are_skipped() {
+        let jsonl = r#"{"type":"addr","ref":"bc1q34aq5drpuwy3wgl9lhup9892qp6svr8ldzyy7c","label":"Address"}
+
+{"type":"psbt","ref":"cHNidP8BAHECAAAAAQ==","label":"Unknown"}
+{"type":"xpub","ref":"xpub661MyMwAqRbcFtXgS5sYJABqqG9YLmC4Q1Rdap9gSE8NqtwybGhePY2gZ29ESFjqJoCu1Rupje8YtGqsefD265TMg7usUDFdp6W1EGMcet8"}"#;
+        let labels = import_labels(jsonl).unwrap();
+        assert_eq!(labels.len(), 2);
+        assert_eq!(labels[0].label_type, LabelType::Addr);
+        assert_eq!(labels[1].label, None);
+
+        assert!(import_labels("not json").is_err());
+    }
+}
@@ -0,0 +1,80 @@
+//! Payments from the wallet with coin control, see [`super::DlcDevKitWallet::send`].
+
+use bitcoin::psbt::Psbt;
+use bitcoin::{Address, Amount, FeeRate, OutPoint, ScriptBuf, Txid};
+
+/// Largest OP_RETURN payload relayed by default.
+pub const MAX_OP_RETURN_SIZE: usize = 80;
+
+/// A payment to any number of recipients, spent with [`super::DlcDevKitWallet::send`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SendBuilder {
+    pub(crate) fee_rate: FeeRate,
+    pub(crate) recipients: Vec<(ScriptBuf, Amount)>,
+    pub(crate) include: Vec<OutPoint>,
+    pub(crate) exclude: Vec<OutPoint>,
+    pub(crate) op_return: Option<Vec<u8>>,
+    pub(crate) change_address: Option<Address>,
+    pub(crate) dry_run: bool,
+}
+
+impl SendBuilder {
+    pub fn new(fee_rate: FeeRate) -> SendBuilder {
+        SendBuilder {
+            fee_rate,
+            recipients: Vec::new(),
+            include: Vec::new(),
+            exclude: Vec::new(),
+            op_return: None,
+            change_address: None,
+            dry_run: false,
+        }
+    }
+
+    /// Pay `amount` to `address`.
+    pub fn add_recipient(mut self, address: Address, amount: Amount) -> SendBuilder {
+        self.recipients.push((address.script_pubkey(), amount));
+        self
+    }
+
+    /// Spend these coins. More coins are selected when they don't cover the payment.
+    pub fn include_utxos(mut self, outpoints: &[OutPoint]) -> SendBuilder {
+        self.include.extend_from_slice(outpoints);
+        self
+    }
+
+    /// Never spend these coins. Coins reserved for contracts are always excluded.
+    pub fn exclude_utxos(mut self, outpoints: &[OutPoint]) -> SendBuilder {
+        self.exclude.extend_from_slice(outpoints);
+        self
+    }
+
+    /// Add an OP_RETURN output with `data`, at most [`MAX_OP_RETURN_SIZE`] bytes.
+    pub fn op_return(mut self, data: Vec<u8>) -> SendBuilder {
+        self.op_return = Some(data);
+        self
+    }
+
+    /// Send the change to `address` instead of a new wallet change address.
+    pub fn change_address(mut self, address: Address) -> SendBuilder {
+        self.change_address = Some(address);
+        self
+    }
+
+    /// Build the transaction without signing or broadcasting it.
+    pub fn dry_run(mut self) -> SendBuilder {
+        self.dry_run = true;
+        self
+    }
+}
+
+/// The transaction built by [`super::DlcDevKitWallet::send`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct SendResult {
+    pub txid: Txid,
+    /// The signed PSBT. Unsigned for a dry run.
+    pub psbt: Psbt,
+    pub fee: Amount,
+    /// Whether the transaction was broadcast. `false` for a dry run.
+    pub broadcast: bool,
+}
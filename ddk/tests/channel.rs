@@ -0,0 +1,153 @@
+mod test_util;
+
+use bitcoin::Amount;
+use chrono::{Local, TimeDelta};
+use ddk::Transport;
+use ddk_manager::channel::signed_channel::SignedChannelState;
+use ddk_manager::channel::Channel;
+use ddk_manager::{ChannelId, Storage};
+use dlc::EnumerationPayout;
+use std::time::Duration;
+use test_util::{generate_blocks, test_ddk, TestSuite};
+use tokio::time::sleep;
+
+async fn wait_for_channel<F>(node: &TestSuite, channel_id: &ChannelId, state: F)
+where
+    F: Fn(&Channel) -> bool,
+{
+    for _ in 0..30 {
+        if let Some(channel) = node.ddk.storage.get_channel(channel_id).unwrap() {
+            if state(&channel) {
+                return;
+            }
+        }
+        sleep(Duration::from_secs(1)).await;
+    }
+    panic!("Channel did not reach the expected state.");
+}
+
+fn is_signed_in(channel: &Channel, state: fn(&SignedChannelState) -> bool) -> bool {
+    match channel {
+        Channel::Signed(signed) => state(&signed.state),
+        _ => false,
+    }
+}
+
+#[test_log::test(tokio::test)]
+async fn channel_offer_settle_and_close() {
+    let (alice, bob, oracle) = test_ddk().await;
+    let alice_pubkey = alice.ddk.transport.public_key();
+    let bob_pubkey = bob.ddk.transport.public_key();
+    alice
+        .ddk
+        .transport
+        .add_counterparty(bob_pubkey, bob.ddk.transport.sender.clone());
+    bob.ddk
+        .transport
+        .add_counterparty(alice_pubkey, alice.ddk.transport.sender.clone());
+
+    alice.ddk.start().unwrap();
+    bob.ddk.start().unwrap();
+
+    let timestamp: u32 = Local::now()
+        .checked_add_signed(TimeDelta::days(1))
+        .unwrap()
+        .timestamp()
+        .try_into()
+        .unwrap();
+    let announcement = oracle
+        .oracle
+        .create_enum_event(
+            uuid::Uuid::new_v4().to_string(),
+            vec!["rust".to_string(), "go".to_string()],
+            timestamp,
+        )
+        .await
+        .unwrap();
+    let contract_input = ddk_payouts::enumeration::create_contract_input(
+        vec![
+            EnumerationPayout {
+                outcome: "rust".to_string(),
+                payout: dlc::Payout {
+                    offer: 100_000,
+                    accept: 0,
+                },
+            },
+            EnumerationPayout {
+                outcome: "go".to_string(),
+                payout: dlc::Payout {
+                    offer: 0,
+                    accept: 100_000,
+                },
+            },
+        ],
+        50_000,
+        50_000,
+        1,
+        announcement.oracle_public_key.to_string(),
+        announcement.oracle_event.event_id.clone(),
+    );
+
+    let offer = alice
+        .ddk
+        .offer_channel(&contract_input, bob_pubkey)
+        .await
+        .unwrap();
+    wait_for_channel(&bob, &offer.temporary_channel_id, |c| {
+        matches!(c, Channel::Offered(_))
+    })
+    .await;
+
+    let (channel_id, _contract_id, _accept) = bob
+        .ddk
+        .accept_channel(offer.temporary_channel_id)
+        .await
+        .unwrap();
+    wait_for_channel(&bob, &channel_id, |c| {
+        is_signed_in(c, |s| matches!(s, SignedChannelState::Established { .. }))
+    })
+    .await;
+
+    generate_blocks(6);
+
+    alice
+        .ddk
+        .settle_channel(channel_id, Amount::from_sat(60_000))
+        .await
+        .unwrap();
+    wait_for_channel(&bob, &channel_id, |c| {
+        is_signed_in(c, |s| {
+            matches!(s, SignedChannelState::SettledReceived { .. })
+        })
+    })
+    .await;
+
+    bob.ddk.accept_channel_settle(channel_id).await.unwrap();
+    for node in [&alice, &bob] {
+        wait_for_channel(node, &channel_id, |c| {
+            is_signed_in(c, |s| matches!(s, SignedChannelState::Settled { .. }))
+        })
+        .await;
+    }
+
+    alice
+        .ddk
+        .close_channel(channel_id, Amount::from_sat(60_000))
+        .await
+        .unwrap();
+    wait_for_channel(&bob, &channel_id, |c| {
+        is_signed_in(c, |s| {
+            matches!(s, SignedChannelState::CollaborativeCloseOffered { .. })
+        })
+    })
+    .await;
+
+    bob.ddk.accept_channel_close(channel_id).await.unwrap();
+    wait_for_channel(&bob, &channel_id, |c| {
+        matches!(c, Channel::CollaborativelyClosed(_))
+    })
+    .await;
+
+    alice.ddk.stop().await.unwrap();
+    bob.ddk.stop().await.unwrap();
+}
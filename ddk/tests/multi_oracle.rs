@@ -0,0 +1,151 @@
+mod test_util;
+
+use bitcoin::key::Secp256k1;
+use chrono::{Local, TimeDelta};
+use ddk::oracle::memory::MemoryOracle;
+use ddk::Transport;
+use ddk_manager::contract::Contract;
+use ddk_manager::{ContractId, Storage};
+use ddk_payouts::enumeration::create_contract_input;
+use dlc::EnumerationPayout;
+use std::sync::Arc;
+use std::time::Duration;
+use test_util::{fund_addresses, generate_blocks, TestSuite};
+use tokio::time::sleep;
+
+async fn wait_for_contract<F>(node: &TestSuite, contract_id: &ContractId, state: F)
+where
+    F: Fn(&Contract) -> bool,
+{
+    for _ in 0..60 {
+        if let Some(contract) = node.ddk.storage.get_contract(contract_id).unwrap() {
+            if state(&contract) {
+                return;
+            }
+        }
+        sleep(Duration::from_secs(1)).await;
+    }
+    panic!("Contract did not reach the expected state.");
+}
+
+#[test_log::test(tokio::test)]
+async fn two_of_two_oracle_contract() {
+    let secp = Secp256k1::new();
+    let oracles = vec![
+        Arc::new(MemoryOracle::default()),
+        Arc::new(MemoryOracle::default()),
+    ];
+    let alice = TestSuite::new_with_oracles(&secp, "multi_oracle_alice", oracles.clone()).await;
+    let bob = TestSuite::new_with_oracles(&secp, "multi_oracle_bob", oracles.clone()).await;
+
+    fund_addresses(
+        &alice.ddk.wallet.new_external_address().unwrap().address,
+        &bob.ddk.wallet.new_external_address().unwrap().address,
+    );
+    alice.ddk.wallet.sync().await.unwrap();
+    bob.ddk.wallet.sync().await.unwrap();
+
+    let alice_pubkey = alice.ddk.transport.public_key();
+    let bob_pubkey = bob.ddk.transport.public_key();
+    alice
+        .ddk
+        .transport
+        .add_counterparty(bob_pubkey, bob.ddk.transport.sender.clone());
+    bob.ddk
+        .transport
+        .add_counterparty(alice_pubkey, alice.ddk.transport.sender.clone());
+    alice.ddk.start().unwrap();
+    bob.ddk.start().unwrap();
+
+    let event_id = uuid::Uuid::new_v4().to_string();
+    let timestamp: u32 = Local::now()
+        .checked_add_signed(TimeDelta::seconds(15))
+        .unwrap()
+        .timestamp()
+        .try_into()
+        .unwrap();
+    let mut announcements = Vec::new();
+    for oracle in &oracles {
+        let announcement = oracle
+            .oracle
+            .create_enum_event(
+                event_id.clone(),
+                vec!["rust".to_string(), "go".to_string()],
+                timestamp,
+            )
+            .await
+            .unwrap();
+        announcements.push(announcement);
+    }
+
+    let mut contract_input = create_contract_input(
+        vec![
+            EnumerationPayout {
+                outcome: "rust".to_string(),
+                payout: dlc::Payout {
+                    offer: 100_000,
+                    accept: 0,
+                },
+            },
+            EnumerationPayout {
+                outcome: "go".to_string(),
+                payout: dlc::Payout {
+                    offer: 0,
+                    accept: 100_000,
+                },
+            },
+        ],
+        50_000,
+        50_000,
+        1,
+        announcements[0].oracle_public_key.to_string(),
+        event_id.clone(),
+    );
+    let oracle_input = &mut contract_input.contract_infos[0].oracles;
+    oracle_input
+        .public_keys
+        .push(announcements[1].oracle_public_key);
+    oracle_input.threshold = 2;
+
+    let offer = alice
+        .ddk
+        .send_dlc_offer(&contract_input, bob_pubkey, announcements.clone())
+        .await
+        .unwrap();
+    wait_for_contract(&bob, &offer.temporary_contract_id, |c| {
+        matches!(c, Contract::Offered(_))
+    })
+    .await;
+
+    let (contract_id, _, _) = bob
+        .ddk
+        .accept_dlc_offer(offer.temporary_contract_id)
+        .await
+        .unwrap();
+    let contract_id: ContractId = hex::decode(contract_id).unwrap().try_into().unwrap();
+    wait_for_contract(&bob, &contract_id, |c| matches!(c, Contract::Signed(_))).await;
+
+    generate_blocks(10);
+    wait_for_contract(&alice, &contract_id, |c| {
+        matches!(c, Contract::Confirmed(_))
+    })
+    .await;
+
+    for oracle in &oracles {
+        oracle
+            .oracle
+            .sign_enum_event(event_id.clone(), "rust".to_string())
+            .await
+            .unwrap();
+    }
+    sleep(Duration::from_secs(15)).await;
+    generate_blocks(10);
+
+    wait_for_contract(&alice, &contract_id, |c| {
+        matches!(c, Contract::PreClosed(_) | Contract::Closed(_))
+    })
+    .await;
+
+    alice.ddk.stop().await.unwrap();
+    bob.ddk.stop().await.unwrap();
+}
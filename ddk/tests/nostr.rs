@@ -6,6 +6,7 @@ mod nostr_test {
     use bitcoin::{key::rand::Fill, Network};
     use chrono::{Local, TimeDelta};
     use ddk::builder::Builder;
+    use ddk::chain::ChainSource;
     use ddk::oracle::memory::MemoryOracle;
     use ddk::storage::memory::MemoryStorage;
     use ddk::transport::nostr::NostrDlc;
@@ -19,7 +20,7 @@ mod nostr_test {
         let mut seed = [0u8; 32];
         seed.try_fill(&mut bitcoin::key::rand::thread_rng())
             .unwrap();
-        let esplora_host = "http://127.0.0.1:30000".to_string();
+        let chain_source = ChainSource::esplora("http://127.0.0.1:30000");
 
         let transport = Arc::new(
             NostrDlc::new(&seed, "wss://nostr.dlcdevkit.com", Network::Regtest)
@@ -31,7 +32,7 @@ mod nostr_test {
         let ddk: NostrDlcDevKit = Builder::new()
             .set_network(Network::Regtest)
             .set_seed_bytes(seed)
-            .set_esplora_host(esplora_host)
+            .set_chain_source(chain_source)
             .set_name(name)
             .set_oracle(oracle)
             .set_transport(transport)
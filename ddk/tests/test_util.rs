@@ -18,8 +18,8 @@ use std::{
 
 use bitcoincore_rpc::RpcApi;
 use ddk::{
-    builder::Builder, oracle::memory::MemoryOracle, storage::memory::MemoryStorage,
-    transport::memory::MemoryTransport, DlcDevKit,
+    builder::Builder, chain::ChainSource, oracle::memory::MemoryOracle,
+    storage::memory::MemoryStorage, transport::memory::MemoryTransport, DlcDevKit,
 };
 
 type TestDlcDevKit = DlcDevKit<MemoryTransport, MemoryStorage, MemoryOracle>;
@@ -97,25 +97,35 @@ pub struct TestSuite {
 
 impl TestSuite {
     pub async fn new(secp: &Secp256k1<All>, name: &str, oracle: Arc<MemoryOracle>) -> TestSuite {
+        Self::new_with_oracles(secp, name, vec![oracle]).await
+    }
+
+    pub async fn new_with_oracles(
+        secp: &Secp256k1<All>,
+        name: &str,
+        oracles: Vec<Arc<MemoryOracle>>,
+    ) -> TestSuite {
         let mut seed = [0u8; 32];
         seed.try_fill(&mut bitcoin::key::rand::thread_rng())
             .unwrap();
-        let esplora_host = "http://127.0.0.1:30000".to_string();
+        let chain_source = ChainSource::esplora("http://127.0.0.1:30000");
 
         let transport = Arc::new(MemoryTransport::new(secp));
         let storage = Arc::new(MemoryStorage::new());
 
-        let ddk: TestDlcDevKit = Builder::new()
+        let mut builder = Builder::new();
+        builder
             .set_network(Network::Regtest)
             .set_seed_bytes(seed)
-            .set_esplora_host(esplora_host)
+            .set_chain_source(chain_source)
             .set_name(name)
-            .set_oracle(oracle)
+            .set_oracle(oracles[0].clone())
             .set_transport(transport)
-            .set_storage(storage)
-            .finish()
-            .await
-            .unwrap();
+            .set_storage(storage);
+        for oracle in &oracles[1..] {
+            builder.add_oracle(oracle.clone());
+        }
+        let ddk: TestDlcDevKit = builder.finish().await.unwrap();
 
         TestSuite { ddk }
     }